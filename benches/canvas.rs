@@ -0,0 +1,196 @@
+//! Benchmarks of the CPU side of drawing: path tessellation, text shaping, glyph atlas churn and
+//! image decoding.
+//!
+//! They draw into the `Void` renderer, so they measure the work femtovg does before handing the
+//! commands to a backend. Run them with `cargo bench`, a name given after `--` only runs the
+//! benchmarks whose name contains it.
+
+use std::hint::black_box;
+
+use criterion::{
+    criterion_group,
+    criterion_main,
+    Criterion,
+};
+use femtovg::{
+    renderer::Void,
+    Canvas,
+    Color,
+    ImageFlags,
+    Paint,
+    Path,
+};
+
+const PARAGRAPH: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+    incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco \
+    laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit \
+    esse cillum dolore eu fugiat nulla pariatur.";
+
+fn tessellation(c: &mut Criterion) {
+    let mut canvas = canvas();
+
+    let fill = Paint::color(Color::rgb(200, 50, 50));
+    let mut stroke = Paint::color(Color::black());
+    stroke.set_line_width(3.0);
+
+    let mut rect = Path::new();
+    rect.rect(100.0, 100.0, 300.0, 200.0);
+
+    c.bench_function("fill_rect", |b| {
+        let mut iteration = 0;
+        b.iter(|| fill_uncached(&mut canvas, &mut iteration, &mut rect, fill));
+    });
+
+    let mut rounded_rect = Path::new();
+    rounded_rect.rounded_rect(100.0, 100.0, 300.0, 200.0, 20.0);
+
+    c.bench_function("fill_rounded_rect", |b| {
+        let mut iteration = 0;
+        b.iter(|| fill_uncached(&mut canvas, &mut iteration, &mut rounded_rect, fill));
+    });
+
+    let mut star = star(500.0, 500.0, 400.0, 100);
+
+    c.bench_function("fill_curved_star", |b| {
+        let mut iteration = 0;
+        b.iter(|| fill_uncached(&mut canvas, &mut iteration, &mut star, fill));
+    });
+
+    // The same path under the same transform every frame, as in a UI that doesn't change
+    canvas.reset();
+
+    c.bench_function("fill_unchanged_star", |b| {
+        b.iter(|| {
+            canvas.fill_path(&mut star, fill);
+            canvas.flush();
+        });
+    });
+
+    c.bench_function("stroke_curved_star", |b| {
+        let mut iteration = 0;
+        b.iter(|| stroke_uncached(&mut canvas, &mut iteration, &mut star, stroke));
+    });
+
+    let mut polyline = Path::new();
+    polyline.move_to(0.0, 500.0);
+
+    for i in 1..10_000 {
+        let x = i as f32 * 0.1;
+        polyline.line_to(x, 500.0 + (x * 0.1).sin() * 300.0);
+    }
+
+    c.bench_function("stroke_polyline_10k", |b| {
+        let mut iteration = 0;
+        b.iter(|| stroke_uncached(&mut canvas, &mut iteration, &mut polyline, stroke));
+    });
+}
+
+fn text(c: &mut Criterion) {
+    let mut canvas = canvas();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut text_paint = Paint::color(Color::black());
+    text_paint.set_font(&[font]);
+
+    c.bench_function("shape_paragraph", |b| {
+        b.iter(|| {
+            canvas.clear_shaping_caches();
+            black_box(canvas.measure_text(0.0, 0.0, PARAGRAPH, text_paint).unwrap());
+        });
+    });
+
+    c.bench_function("measure_fast_paragraph", |b| {
+        b.iter(|| black_box(canvas.measure_text_fast(PARAGRAPH, text_paint).unwrap()));
+    });
+
+    c.bench_function("fill_cached_paragraph", |b| {
+        b.iter(|| {
+            canvas.fill_text(10.0, 50.0, PARAGRAPH, text_paint).unwrap();
+            canvas.flush();
+        });
+    });
+
+    // Every iteration renders the glyphs at a size that isn't in the atlases yet
+    c.bench_function("glyph_atlas_churn", |b| {
+        let mut iteration = 0;
+
+        b.iter(|| {
+            let mut paint = text_paint;
+            paint.set_font_size(8.0 + (iteration % 64) as f32);
+
+            canvas.fill_text(10.0, 50.0, "The quick brown fox", paint).unwrap();
+            canvas.flush();
+
+            if iteration % 64 == 63 {
+                canvas.clear_glyph_atlases();
+            }
+
+            iteration += 1;
+        });
+    });
+}
+
+// Decoding is the part of an image upload that happens before the backend is involved
+fn images(c: &mut Criterion) {
+    let mut canvas = canvas();
+    let png = std::fs::read("examples/assets/rust-logo.png").unwrap();
+
+    c.bench_function("load_image_png", |b| {
+        b.iter(|| {
+            let id = canvas.load_image_mem(&png, ImageFlags::empty()).unwrap();
+            canvas.delete_image(id);
+        });
+    });
+}
+
+fn canvas() -> Canvas<Void> {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(1000, 1000, 1.0);
+    canvas
+}
+
+// Alternating between two transforms keeps the path from reusing its last tessellation
+fn fill_uncached(canvas: &mut Canvas<Void>, iteration: &mut usize, path: &mut Path, paint: Paint) {
+    canvas.reset_transform();
+    canvas.translate((*iteration % 2) as f32 * 0.5, 0.0);
+    canvas.fill_path(path, paint);
+    canvas.flush();
+    *iteration += 1;
+}
+
+fn stroke_uncached(canvas: &mut Canvas<Void>, iteration: &mut usize, path: &mut Path, paint: Paint) {
+    canvas.reset_transform();
+    canvas.translate((*iteration % 2) as f32 * 0.5, 0.0);
+    canvas.stroke_path(path, paint);
+    canvas.flush();
+    *iteration += 1;
+}
+
+fn star(cx: f32, cy: f32, radius: f32, points: usize) -> Path {
+    let mut path = Path::new();
+    let step = std::f32::consts::PI / points as f32;
+
+    path.move_to(cx + radius, cy);
+
+    for i in 1..=points * 2 {
+        let r = if i % 2 == 0 { radius } else { radius * 0.6 };
+        let (sin, cos) = (i as f32 * step).sin_cos();
+        let (ctrl_sin, ctrl_cos) = ((i as f32 - 0.5) * step).sin_cos();
+
+        path.quad_to(
+            cx + ctrl_cos * radius * 0.9,
+            cy + ctrl_sin * radius * 0.9,
+            cx + cos * r,
+            cy + sin * r,
+        );
+    }
+
+    path.close();
+    path
+}
+
+criterion_group!(benches, tessellation, text, images);
+criterion_main!(benches);
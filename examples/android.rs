@@ -0,0 +1,124 @@
+//! Android NativeActivity example.
+//!
+//! Build and run it on a device with [cargo-apk](https://crates.io/crates/cargo-apk):
+//! `cargo apk run --example android`
+//!
+//! Android destroys the native window, and with it the EGL surface, when the app is paused and
+//! creates a new one when it is resumed. The GL context is kept alive in between, so all fonts and
+//! images stay valid and femtovg only has to be told about the new surface with `Canvas::reset_surface`.
+
+#[cfg(target_os = "android")]
+#[cfg_attr(target_os = "android", ndk_glue::main(backtrace = "on", ndk_glue = "ndk_glue"))]
+fn main() {
+    use std::ffi::c_void;
+    use std::time::Instant;
+
+    use khronos_egl as egl;
+    use ndk_glue::Event;
+
+    use femtovg::{
+        renderer::OpenGl,
+        Canvas,
+        Color,
+        Paint,
+        Path,
+    };
+
+    let egl = &egl::API;
+
+    let display = egl.get_display(egl::DEFAULT_DISPLAY).expect("Cannot get EGL display");
+    egl.initialize(display).expect("Cannot initialize EGL");
+
+    #[rustfmt::skip]
+    let config_attributes = [
+        egl::RENDERABLE_TYPE, egl::OPENGL_ES2_BIT,
+        egl::SURFACE_TYPE, egl::WINDOW_BIT,
+        egl::RED_SIZE, 8,
+        egl::GREEN_SIZE, 8,
+        egl::BLUE_SIZE, 8,
+        egl::STENCIL_SIZE, 8,
+        egl::NONE,
+    ];
+
+    let config = egl
+        .choose_first_config(display, &config_attributes)
+        .expect("Cannot choose EGL config")
+        .expect("No matching EGL config");
+
+    egl.bind_api(egl::OPENGL_ES_API).expect("Cannot bind OpenGL ES");
+
+    let context = egl
+        .create_context(display, config, None, &[egl::CONTEXT_CLIENT_VERSION, 2, egl::NONE])
+        .expect("Cannot create EGL context");
+
+    let mut surface = None;
+    let mut canvas: Option<Canvas<OpenGl>> = None;
+
+    let start = Instant::now();
+
+    loop {
+        while let Some(event) = ndk_glue::poll_events() {
+            match event {
+                Event::WindowCreated => {
+                    let window = ndk_glue::native_window();
+                    let window = window.as_ref().expect("No native window");
+
+                    let new_surface = unsafe {
+                        egl.create_window_surface(display, config, window.ptr().as_ptr() as egl::NativeWindowType, None)
+                            .expect("Cannot create EGL surface")
+                    };
+
+                    egl.make_current(display, Some(new_surface), Some(new_surface), Some(context))
+                        .expect("Cannot make context current");
+
+                    let (width, height) = (window.width() as u32, window.height() as u32);
+
+                    match &mut canvas {
+                        // The app was resumed, the context survived but the surface is new
+                        Some(canvas) => canvas.reset_surface(width, height, 1.0),
+                        None => {
+                            let renderer = OpenGl::new(|s| {
+                                egl.get_proc_address(s).map_or(std::ptr::null(), |f| f as *const c_void)
+                            })
+                            .expect("Cannot create renderer");
+
+                            canvas = Some(Canvas::new(renderer).expect("Cannot create canvas"));
+                        }
+                    }
+
+                    surface = Some(new_surface);
+                }
+                Event::WindowDestroyed => {
+                    if let Some(old_surface) = surface.take() {
+                        egl.make_current(display, None, None, Some(context))
+                            .expect("Cannot release surface");
+                        egl.destroy_surface(display, old_surface)
+                            .expect("Cannot destroy EGL surface");
+                    }
+                }
+                Event::Destroy => return,
+                _ => (),
+            }
+        }
+
+        if let (Some(surface), Some(canvas)) = (surface, &mut canvas) {
+            let width = egl.query_surface(display, surface, egl::WIDTH).unwrap_or(0) as u32;
+            let height = egl.query_surface(display, surface, egl::HEIGHT).unwrap_or(0) as u32;
+
+            canvas.set_size(width, height, 1.0);
+            canvas.clear_rect(0, 0, width, height, Color::rgbf(0.3, 0.3, 0.32));
+
+            let t = start.elapsed().as_secs_f32();
+            let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+
+            let mut path = Path::new();
+            path.circle(cx + t.cos() * cx / 2.0, cy + t.sin() * cy / 2.0, cx.min(cy) / 4.0);
+            canvas.fill_path(&mut path, Paint::color(Color::rgb(200, 60, 60)));
+
+            canvas.flush();
+            egl.swap_buffers(display, surface).expect("Cannot swap buffers");
+        } else {
+            std::thread::sleep(std::time::Duration::from_millis(16));
+        }
+    }
+}
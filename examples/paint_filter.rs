@@ -97,11 +97,13 @@ fn main() {
                     let t = (now - start).as_secs_f32();
                     let sigma = 2.5 + 2.5 * t.cos();
 
-                    canvas.filter_image(
-                        filtered_image.unwrap(),
-                        femtovg::ImageFilter::GaussianBlur { sigma },
-                        image_id,
-                    );
+                    canvas
+                        .filter_image(
+                            filtered_image.unwrap(),
+                            femtovg::ImageFilter::GaussianBlur { sigma },
+                            image_id,
+                        )
+                        .unwrap();
 
                     let width = size.0 as f32;
                     let height = size.1 as f32;
@@ -0,0 +1,26 @@
+use std::time::Instant;
+
+use femtovg::{
+    Color,
+    Paint,
+    Path,
+};
+
+fn main() {
+    let start = Instant::now();
+
+    femtovg::helpers::run_window("femtovg run_window", (800, 600), move |canvas| {
+        let t = start.elapsed().as_secs_f32();
+        let (width, height) = (canvas.width(), canvas.height());
+
+        canvas.clear_rect(0, 0, width as u32, height as u32, Color::rgbf(0.3, 0.3, 0.32));
+
+        let mut path = Path::new();
+        path.circle(
+            width / 2.0 + t.cos() * width / 4.0,
+            height / 2.0 + t.sin() * height / 4.0,
+            50.0,
+        );
+        canvas.fill_path(&mut path, Paint::color(Color::rgb(200, 60, 60)));
+    });
+}
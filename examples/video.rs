@@ -0,0 +1,230 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
+
+use glow::HasContext;
+use glutin::{
+    event::{
+        Event,
+        WindowEvent,
+    },
+    event_loop::{
+        ControlFlow,
+        EventLoop,
+    },
+    window::WindowBuilder,
+    ContextBuilder,
+};
+
+use femtovg::{
+    renderer::{
+        ExternalImageProvider,
+        OpenGl,
+    },
+    Align,
+    Canvas,
+    Color,
+    ImageId,
+    Paint,
+    Path,
+};
+
+const VIDEO_WIDTH: usize = 320;
+const VIDEO_HEIGHT: usize = 180;
+
+type Texture = <glow::Context as HasContext>::Texture;
+
+// Stands in for a GStreamer appsink or an ffmpeg hardware decoder. It owns the texture the frames
+// are decoded into, femtovg only ever samples it.
+struct Decoder {
+    context: glow::Context,
+    texture: Texture,
+    frame: Vec<u8>,
+}
+
+impl Decoder {
+    fn new(context: glow::Context) -> Self {
+        unsafe {
+            let texture = context.create_texture().unwrap();
+            context.bind_texture(glow::TEXTURE_2D, Some(texture));
+            context.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA as i32,
+                VIDEO_WIDTH as i32,
+                VIDEO_HEIGHT as i32,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                None,
+            );
+            context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
+            context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+            context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+            context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+            context.bind_texture(glow::TEXTURE_2D, None);
+
+            Self {
+                context,
+                texture,
+                frame: vec![0; VIDEO_WIDTH * VIDEO_HEIGHT * 4],
+            }
+        }
+    }
+
+    // Produces the frame at time `t` directly in the decoder's texture.
+    fn decode(&mut self, t: f32) {
+        for (i, pixel) in self.frame.chunks_exact_mut(4).enumerate() {
+            let x = (i % VIDEO_WIDTH) as f32 / VIDEO_WIDTH as f32;
+            let y = (i / VIDEO_WIDTH) as f32 / VIDEO_HEIGHT as f32;
+
+            let v = (x * 10.0 + t).sin() + (y * 8.0 - t * 1.3).sin() + ((x + y) * 6.0 + t * 0.7).sin();
+
+            pixel[0] = (128.0 + 127.0 * (v * 1.1).sin()) as u8;
+            pixel[1] = (128.0 + 127.0 * (v * 1.1 + 2.0).sin()) as u8;
+            pixel[2] = (128.0 + 127.0 * (v * 1.1 + 4.0).sin()) as u8;
+            pixel[3] = 255;
+        }
+
+        unsafe {
+            self.context.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            self.context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+            self.context.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                0,
+                0,
+                VIDEO_WIDTH as i32,
+                VIDEO_HEIGHT as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(&self.frame),
+            );
+            self.context.bind_texture(glow::TEXTURE_2D, None);
+        }
+    }
+}
+
+// Hands the decoder's current texture to femtovg whenever the video image is drawn.
+struct VideoSink {
+    image: Rc<Cell<Option<ImageId>>>,
+    texture: Rc<Cell<Option<Texture>>>,
+}
+
+impl ExternalImageProvider for VideoSink {
+    fn texture(&mut self, image: ImageId) -> Option<Texture> {
+        if Some(image) == self.image.get() {
+            self.texture.get()
+        } else {
+            None
+        }
+    }
+}
+
+fn main() {
+    let window_size = glutin::dpi::PhysicalSize::new(1000, 600);
+    let el = EventLoop::new();
+    let wb = WindowBuilder::new()
+        .with_inner_size(window_size)
+        .with_title("femtovg video");
+
+    let windowed_context = ContextBuilder::new().build_windowed(wb, &el).unwrap();
+    let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+
+    let mut renderer =
+        OpenGl::new(|s| windowed_context.get_proc_address(s) as *const _).expect("Cannot create renderer");
+
+    let decoder_context =
+        unsafe { glow::Context::from_loader_function(|s| windowed_context.get_proc_address(s) as *const _) };
+    let mut decoder = Decoder::new(decoder_context);
+
+    let video_image = Rc::new(Cell::new(None));
+    let current_frame = Rc::new(Cell::new(None));
+
+    renderer.set_external_image_provider(Some(Box::new(VideoSink {
+        image: video_image.clone(),
+        texture: current_frame.clone(),
+    })));
+
+    let mut canvas = Canvas::new(renderer).expect("Cannot create canvas");
+
+    // The placeholder only provides an id with the video's size, its pixels are never used.
+    let video = canvas
+        .create_video_image(VIDEO_WIDTH, VIDEO_HEIGHT)
+        .expect("Cannot create video image");
+    video_image.set(Some(video));
+
+    canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Cannot add font");
+
+    let start = Instant::now();
+
+    el.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        match event {
+            Event::LoopDestroyed => return,
+            Event::WindowEvent { ref event, .. } => match event {
+                WindowEvent::Resized(physical_size) => {
+                    windowed_context.resize(*physical_size);
+                }
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                _ => (),
+            },
+            Event::RedrawRequested(_) => {
+                let dpi_factor = windowed_context.window().scale_factor();
+                let size = windowed_context.window().inner_size();
+                canvas.set_size(size.width as u32, size.height as u32, dpi_factor as f32);
+                canvas.clear_rect(0, 0, size.width as u32, size.height as u32, Color::rgbf(0.1, 0.1, 0.1));
+
+                let t = start.elapsed().as_secs_f32();
+
+                decoder.decode(t);
+                current_frame.set(Some(decoder.texture));
+
+                draw_player(&mut canvas, video, size.width as f32, t);
+
+                canvas.flush();
+                windowed_context.swap_buffers().unwrap();
+            }
+            Event::MainEventsCleared => windowed_context.window().request_redraw(),
+            _ => (),
+        }
+    });
+}
+
+fn draw_player(canvas: &mut Canvas<OpenGl>, video: ImageId, width: f32, t: f32) {
+    let margin = 40.0;
+    let video_width = width - margin * 2.0;
+    let video_height = video_width * VIDEO_HEIGHT as f32 / VIDEO_WIDTH as f32;
+
+    let mut path = Path::new();
+    path.rounded_rect(margin, margin, video_width, video_height, 8.0);
+    canvas.fill_path(
+        &mut path,
+        Paint::image(video, margin, margin, video_width, video_height, 0.0, 1.0),
+    );
+
+    // Overlays drawn on top of the video
+    let bar_y = margin + video_height - 30.0;
+    let progress = (t / 30.0).fract();
+
+    let mut path = Path::new();
+    path.rounded_rect(margin + 20.0, bar_y, video_width - 40.0, 6.0, 3.0);
+    canvas.fill_path(&mut path, Paint::color(Color::rgba(255, 255, 255, 80)));
+
+    let mut path = Path::new();
+    path.rounded_rect(margin + 20.0, bar_y, (video_width - 40.0) * progress, 6.0, 3.0);
+    canvas.fill_path(&mut path, Paint::color(Color::rgb(230, 60, 60)));
+
+    let mut text_paint = Paint::color(Color::white());
+    text_paint.set_font_size(18.0);
+    text_paint.set_text_align(Align::Right);
+    let _ = canvas.fill_text(
+        margin + video_width - 20.0,
+        margin + 30.0,
+        format!("{:.1}s", t % 30.0),
+        text_paint,
+    );
+}
@@ -0,0 +1,133 @@
+#ifndef FEMTOVG_H
+#define FEMTOVG_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct FVGcontext FVGcontext;
+typedef struct FVGpath FVGpath;
+typedef struct FVGpaint FVGpaint;
+
+/* Straight alpha color with components in the 0.0 - 1.0 range. */
+typedef struct FVGcolor {
+    float r, g, b, a;
+} FVGcolor;
+
+typedef const void* (*FVGloadfn)(const char* name, void* user);
+
+enum FVGsolidity {
+    FVG_SOLID = 1,
+    FVG_HOLE = 2,
+};
+
+enum FVGlineCap {
+    FVG_BUTT = 0,
+    FVG_ROUND = 1,
+    FVG_SQUARE = 2,
+    FVG_BEVEL = 3,
+    FVG_MITER = 4,
+};
+
+enum FVGfillRule {
+    FVG_NONZERO = 0,
+    FVG_EVENODD = 1,
+};
+
+enum FVGalign {
+    FVG_ALIGN_LEFT = 0,
+    FVG_ALIGN_CENTER = 1,
+    FVG_ALIGN_RIGHT = 2,
+};
+
+enum FVGbaseline {
+    FVG_BASELINE_TOP = 0,
+    FVG_BASELINE_MIDDLE = 1,
+    FVG_BASELINE_ALPHABETIC = 2,
+    FVG_BASELINE_BOTTOM = 3,
+};
+
+enum FVGimageFlags {
+    FVG_IMAGE_GENERATE_MIPMAPS = 1 << 0,
+    FVG_IMAGE_REPEAT_X = 1 << 1,
+    FVG_IMAGE_REPEAT_Y = 1 << 2,
+    FVG_IMAGE_FLIP_Y = 1 << 3,
+    FVG_IMAGE_PREMULTIPLIED = 1 << 4,
+    FVG_IMAGE_NEAREST = 1 << 5,
+};
+
+/* Context. Requires the OpenGL context to be current on the calling thread. Returns NULL on failure. */
+FVGcontext* fvg_create_gl(FVGloadfn load, void* user);
+void fvg_delete(FVGcontext* ctx);
+
+void fvg_set_size(FVGcontext* ctx, uint32_t width, uint32_t height, float dpi);
+void fvg_clear_rect(FVGcontext* ctx, uint32_t x, uint32_t y, uint32_t width, uint32_t height, FVGcolor color);
+void fvg_flush(FVGcontext* ctx);
+
+void fvg_save(FVGcontext* ctx);
+void fvg_restore(FVGcontext* ctx);
+void fvg_reset(FVGcontext* ctx);
+void fvg_translate(FVGcontext* ctx, float x, float y);
+void fvg_rotate(FVGcontext* ctx, float angle);
+void fvg_scale(FVGcontext* ctx, float x, float y);
+void fvg_set_global_alpha(FVGcontext* ctx, float alpha);
+void fvg_scissor(FVGcontext* ctx, float x, float y, float width, float height);
+void fvg_reset_scissor(FVGcontext* ctx);
+
+void fvg_fill_path(FVGcontext* ctx, FVGpath* path, const FVGpaint* paint);
+void fvg_stroke_path(FVGcontext* ctx, FVGpath* path, const FVGpaint* paint);
+
+/* Text. Fonts and images are integer handles owned by the context, -1 signals an error. */
+int fvg_add_font_mem(FVGcontext* ctx, const uint8_t* data, size_t len);
+int fvg_fill_text(FVGcontext* ctx, float x, float y, const char* text, const FVGpaint* paint);
+int fvg_stroke_text(FVGcontext* ctx, float x, float y, const char* text, const FVGpaint* paint);
+
+/* Images. `data` holds width * height tightly packed RGBA8 pixels. */
+int fvg_create_image_rgba(FVGcontext* ctx, uint32_t width, uint32_t height, uint32_t flags, const uint8_t* data);
+void fvg_delete_image(FVGcontext* ctx, int image);
+
+/* Paths */
+FVGpath* fvg_path_create(void);
+void fvg_path_delete(FVGpath* path);
+void fvg_path_move_to(FVGpath* path, float x, float y);
+void fvg_path_line_to(FVGpath* path, float x, float y);
+void fvg_path_bezier_to(FVGpath* path, float c1x, float c1y, float c2x, float c2y, float x, float y);
+void fvg_path_quad_to(FVGpath* path, float cx, float cy, float x, float y);
+void fvg_path_arc_to(FVGpath* path, float x1, float y1, float x2, float y2, float radius);
+void fvg_path_arc(FVGpath* path, float cx, float cy, float r, float a0, float a1, int dir);
+void fvg_path_rect(FVGpath* path, float x, float y, float width, float height);
+void fvg_path_rounded_rect(FVGpath* path, float x, float y, float width, float height, float r);
+void fvg_path_ellipse(FVGpath* path, float cx, float cy, float rx, float ry);
+void fvg_path_circle(FVGpath* path, float cx, float cy, float r);
+void fvg_path_solidity(FVGpath* path, int solidity);
+void fvg_path_close(FVGpath* path);
+
+/* Paints */
+FVGpaint* fvg_paint_color(FVGcolor color);
+FVGpaint* fvg_paint_linear_gradient(float start_x, float start_y, float end_x, float end_y, FVGcolor start_color, FVGcolor end_color);
+FVGpaint* fvg_paint_box_gradient(float x, float y, float width, float height, float radius, float feather, FVGcolor inner_color, FVGcolor outer_color);
+FVGpaint* fvg_paint_radial_gradient(float cx, float cy, float in_radius, float out_radius, FVGcolor inner_color, FVGcolor outer_color);
+FVGpaint* fvg_paint_image(const FVGcontext* ctx, int image, float cx, float cy, float width, float height, float angle, float alpha);
+void fvg_paint_delete(FVGpaint* paint);
+
+void fvg_paint_set_anti_alias(FVGpaint* paint, bool value);
+void fvg_paint_set_line_width(FVGpaint* paint, float width);
+void fvg_paint_set_miter_limit(FVGpaint* paint, float limit);
+void fvg_paint_set_line_cap(FVGpaint* paint, int cap);
+void fvg_paint_set_line_join(FVGpaint* paint, int join);
+void fvg_paint_set_fill_rule(FVGpaint* paint, int rule);
+void fvg_paint_set_font(const FVGcontext* ctx, FVGpaint* paint, int font);
+void fvg_paint_set_font_size(FVGpaint* paint, float size);
+void fvg_paint_set_letter_spacing(FVGpaint* paint, float spacing);
+void fvg_paint_set_text_align(FVGpaint* paint, int align);
+void fvg_paint_set_text_baseline(FVGpaint* paint, int baseline);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FEMTOVG_H */
@@ -0,0 +1,544 @@
+/*!
+ * C bindings for femtovg, see `include/femtovg.h` for the declarations.
+ *
+ * Contexts, paths and paints are opaque heap allocated objects that are created and destroyed
+ * through the `fvg_*_create` and `fvg_*_delete` functions. Images and fonts are referred to by
+ * integer handles owned by their context, negative values signal an error.
+ *
+ * All functions expect valid pointers created by this library (or null where documented) and
+ * must not be called with an object that has already been deleted.
+ */
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{
+    c_void,
+    CStr,
+};
+use std::os::raw::{
+    c_char,
+    c_int,
+};
+use std::slice;
+
+use femtovg::{
+    renderer::OpenGl,
+    Align,
+    Baseline,
+    Canvas,
+    Color,
+    FillRule,
+    FontId,
+    ImageFlags,
+    ImageId,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    Solidity,
+};
+use imgref::Img;
+use rgb::FromSlice;
+
+/// A canvas together with the fonts and images created through it.
+pub struct FVGcontext {
+    canvas: Canvas<OpenGl>,
+    fonts: Vec<FontId>,
+    images: Vec<Option<ImageId>>,
+}
+
+impl FVGcontext {
+    fn image(&self, handle: c_int) -> Option<ImageId> {
+        if handle < 0 {
+            return None;
+        }
+
+        self.images.get(handle as usize).copied().flatten()
+    }
+}
+
+/// Straight alpha color with components in the 0.0 - 1.0 range.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct FVGcolor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl From<FVGcolor> for Color {
+    fn from(color: FVGcolor) -> Self {
+        Color::rgbaf(color.r, color.g, color.b, color.a)
+    }
+}
+
+pub type FVGloadfn = extern "C" fn(name: *const c_char, user: *mut c_void) -> *const c_void;
+
+// Context
+
+/// Creates a context for the OpenGL context that is current on the calling thread. `load` resolves
+/// OpenGL function pointers, `user` is passed through to it. Returns null on failure.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_create_gl(load: FVGloadfn, user: *mut c_void) -> *mut FVGcontext {
+    let renderer = OpenGl::new(|name| {
+        // Names never contain interior nul bytes
+        let name = std::ffi::CString::new(name).unwrap_or_default();
+        load(name.as_ptr(), user)
+    });
+
+    let canvas = match renderer.and_then(Canvas::new) {
+        Ok(canvas) => canvas,
+        Err(_) => return std::ptr::null_mut(),
+    };
+
+    Box::into_raw(Box::new(FVGcontext {
+        canvas,
+        fonts: Vec::new(),
+        images: Vec::new(),
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_delete(ctx: *mut FVGcontext) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_set_size(ctx: *mut FVGcontext, width: u32, height: u32, dpi: f32) {
+    (*ctx).canvas.set_size(width, height, dpi);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_clear_rect(
+    ctx: *mut FVGcontext,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    color: FVGcolor,
+) {
+    (*ctx).canvas.clear_rect(x, y, width, height, color.into());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_flush(ctx: *mut FVGcontext) {
+    (*ctx).canvas.flush();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_save(ctx: *mut FVGcontext) {
+    (*ctx).canvas.save();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_restore(ctx: *mut FVGcontext) {
+    (*ctx).canvas.restore();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_reset(ctx: *mut FVGcontext) {
+    (*ctx).canvas.reset();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_translate(ctx: *mut FVGcontext, x: f32, y: f32) {
+    (*ctx).canvas.translate(x, y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_rotate(ctx: *mut FVGcontext, angle: f32) {
+    (*ctx).canvas.rotate(angle);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_scale(ctx: *mut FVGcontext, x: f32, y: f32) {
+    (*ctx).canvas.scale(x, y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_set_global_alpha(ctx: *mut FVGcontext, alpha: f32) {
+    (*ctx).canvas.set_global_alpha(alpha);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_scissor(ctx: *mut FVGcontext, x: f32, y: f32, width: f32, height: f32) {
+    (*ctx).canvas.scissor(x, y, width, height);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_reset_scissor(ctx: *mut FVGcontext) {
+    (*ctx).canvas.reset_scissor();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_fill_path(ctx: *mut FVGcontext, path: *mut Path, paint: *const Paint) {
+    (*ctx).canvas.fill_path(&mut *path, *paint);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_stroke_path(ctx: *mut FVGcontext, path: *mut Path, paint: *const Paint) {
+    (*ctx).canvas.stroke_path(&mut *path, *paint);
+}
+
+// Text
+
+/// Adds a font from memory, the data is copied. Returns the font handle or -1 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_add_font_mem(ctx: *mut FVGcontext, data: *const u8, len: usize) -> c_int {
+    let ctx = &mut *ctx;
+
+    match ctx.canvas.add_font_mem(slice::from_raw_parts(data, len)) {
+        Ok(id) => {
+            ctx.fonts.push(id);
+            (ctx.fonts.len() - 1) as c_int
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Fills nul terminated UTF-8 text. Returns 0 on success, -1 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_fill_text(
+    ctx: *mut FVGcontext,
+    x: f32,
+    y: f32,
+    text: *const c_char,
+    paint: *const Paint,
+) -> c_int {
+    match CStr::from_ptr(text).to_str() {
+        Ok(text) => status((*ctx).canvas.fill_text(x, y, text, *paint).is_ok()),
+        Err(_) => -1,
+    }
+}
+
+/// Strokes nul terminated UTF-8 text. Returns 0 on success, -1 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_stroke_text(
+    ctx: *mut FVGcontext,
+    x: f32,
+    y: f32,
+    text: *const c_char,
+    paint: *const Paint,
+) -> c_int {
+    match CStr::from_ptr(text).to_str() {
+        Ok(text) => status((*ctx).canvas.stroke_text(x, y, text, *paint).is_ok()),
+        Err(_) => -1,
+    }
+}
+
+// Images
+
+/// Creates an image from tightly packed RGBA8 pixels. `flags` are the `FVG_IMAGE_*` values.
+/// Returns the image handle or -1 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_create_image_rgba(
+    ctx: *mut FVGcontext,
+    width: u32,
+    height: u32,
+    flags: u32,
+    data: *const u8,
+) -> c_int {
+    let ctx = &mut *ctx;
+
+    let (width, height) = (width as usize, height as usize);
+    let pixels = slice::from_raw_parts(data, width * height * 4).as_rgba();
+
+    match ctx
+        .canvas
+        .create_image(Img::new(pixels, width, height), ImageFlags::from_bits_truncate(flags))
+    {
+        Ok(id) => {
+            ctx.images.push(Some(id));
+            (ctx.images.len() - 1) as c_int
+        }
+        Err(_) => -1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_delete_image(ctx: *mut FVGcontext, image: c_int) {
+    let ctx = &mut *ctx;
+
+    if let Some(id) = ctx.image(image) {
+        ctx.canvas.delete_image(id);
+        ctx.images[image as usize] = None;
+    }
+}
+
+// Paths
+
+#[no_mangle]
+pub extern "C" fn fvg_path_create() -> *mut Path {
+    Box::into_raw(Box::new(Path::new()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_delete(path: *mut Path) {
+    if !path.is_null() {
+        drop(Box::from_raw(path));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_move_to(path: *mut Path, x: f32, y: f32) {
+    (*path).move_to(x, y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_line_to(path: *mut Path, x: f32, y: f32) {
+    (*path).line_to(x, y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_bezier_to(path: *mut Path, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) {
+    (*path).bezier_to(c1x, c1y, c2x, c2y, x, y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_quad_to(path: *mut Path, cx: f32, cy: f32, x: f32, y: f32) {
+    (*path).quad_to(cx, cy, x, y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_arc_to(path: *mut Path, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+    (*path).arc_to(x1, y1, x2, y2, radius);
+}
+
+/// `dir` is one of the `FVG_SOLID` and `FVG_HOLE` values.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_arc(path: *mut Path, cx: f32, cy: f32, r: f32, a0: f32, a1: f32, dir: c_int) {
+    (*path).arc(cx, cy, r, a0, a1, solidity(dir));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_rect(path: *mut Path, x: f32, y: f32, width: f32, height: f32) {
+    (*path).rect(x, y, width, height);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_rounded_rect(path: *mut Path, x: f32, y: f32, width: f32, height: f32, r: f32) {
+    (*path).rounded_rect(x, y, width, height, r);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_ellipse(path: *mut Path, cx: f32, cy: f32, rx: f32, ry: f32) {
+    (*path).ellipse(cx, cy, rx, ry);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_circle(path: *mut Path, cx: f32, cy: f32, r: f32) {
+    (*path).circle(cx, cy, r);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_solidity(path: *mut Path, solidity_: c_int) {
+    (*path).solidity(solidity(solidity_));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_path_close(path: *mut Path) {
+    (*path).close();
+}
+
+// Paints
+
+#[no_mangle]
+pub extern "C" fn fvg_paint_color(color: FVGcolor) -> *mut Paint {
+    paint(Paint::color(color.into()))
+}
+
+#[no_mangle]
+pub extern "C" fn fvg_paint_linear_gradient(
+    start_x: f32,
+    start_y: f32,
+    end_x: f32,
+    end_y: f32,
+    start_color: FVGcolor,
+    end_color: FVGcolor,
+) -> *mut Paint {
+    paint(Paint::linear_gradient(
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+        start_color.into(),
+        end_color.into(),
+    ))
+}
+
+#[no_mangle]
+pub extern "C" fn fvg_paint_box_gradient(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    radius: f32,
+    feather: f32,
+    inner_color: FVGcolor,
+    outer_color: FVGcolor,
+) -> *mut Paint {
+    paint(Paint::box_gradient(
+        x,
+        y,
+        width,
+        height,
+        radius,
+        feather,
+        inner_color.into(),
+        outer_color.into(),
+    ))
+}
+
+#[no_mangle]
+pub extern "C" fn fvg_paint_radial_gradient(
+    cx: f32,
+    cy: f32,
+    in_radius: f32,
+    out_radius: f32,
+    inner_color: FVGcolor,
+    outer_color: FVGcolor,
+) -> *mut Paint {
+    paint(Paint::radial_gradient(
+        cx,
+        cy,
+        in_radius,
+        out_radius,
+        inner_color.into(),
+        outer_color.into(),
+    ))
+}
+
+/// Creates an image pattern paint. Returns null if the image handle is not valid.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_image(
+    ctx: *const FVGcontext,
+    image: c_int,
+    cx: f32,
+    cy: f32,
+    width: f32,
+    height: f32,
+    angle: f32,
+    alpha: f32,
+) -> *mut Paint {
+    match (*ctx).image(image) {
+        Some(id) => paint(Paint::image(id, cx, cy, width, height, angle, alpha)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_delete(paint: *mut Paint) {
+    if !paint.is_null() {
+        drop(Box::from_raw(paint));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_anti_alias(paint: *mut Paint, value: bool) {
+    (*paint).set_anti_alias(value);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_line_width(paint: *mut Paint, width: f32) {
+    (*paint).set_line_width(width);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_miter_limit(paint: *mut Paint, limit: f32) {
+    (*paint).set_miter_limit(limit);
+}
+
+/// `cap` is one of the `FVG_BUTT`, `FVG_ROUND` and `FVG_SQUARE` values.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_line_cap(paint: *mut Paint, cap: c_int) {
+    (*paint).set_line_cap(match cap {
+        1 => LineCap::Round,
+        2 => LineCap::Square,
+        _ => LineCap::Butt,
+    });
+}
+
+/// `join` is one of the `FVG_MITER`, `FVG_ROUND` and `FVG_BEVEL` values.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_line_join(paint: *mut Paint, join: c_int) {
+    (*paint).set_line_join(match join {
+        1 => LineJoin::Round,
+        3 => LineJoin::Bevel,
+        _ => LineJoin::Miter,
+    });
+}
+
+/// `rule` is one of the `FVG_NONZERO` and `FVG_EVENODD` values.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_fill_rule(paint: *mut Paint, rule: c_int) {
+    (*paint).set_fill_rule(match rule {
+        1 => FillRule::EvenOdd,
+        _ => FillRule::NonZero,
+    });
+}
+
+/// Sets the font used to draw text with this paint. Invalid handles are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_font(ctx: *const FVGcontext, paint: *mut Paint, font: c_int) {
+    if font < 0 {
+        return;
+    }
+
+    if let Some(id) = (&(*ctx).fonts).get(font as usize) {
+        (*paint).set_font(&[*id]);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_font_size(paint: *mut Paint, size: f32) {
+    (*paint).set_font_size(size);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_letter_spacing(paint: *mut Paint, spacing: f32) {
+    (*paint).set_letter_spacing(spacing);
+}
+
+/// `align` is one of the `FVG_ALIGN_LEFT`, `FVG_ALIGN_CENTER` and `FVG_ALIGN_RIGHT` values.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_text_align(paint: *mut Paint, align: c_int) {
+    (*paint).set_text_align(match align {
+        1 => Align::Center,
+        2 => Align::Right,
+        _ => Align::Left,
+    });
+}
+
+/// `baseline` is one of the `FVG_BASELINE_*` values.
+#[no_mangle]
+pub unsafe extern "C" fn fvg_paint_set_text_baseline(paint: *mut Paint, baseline: c_int) {
+    (*paint).set_text_baseline(match baseline {
+        0 => Baseline::Top,
+        1 => Baseline::Middle,
+        3 => Baseline::Bottom,
+        _ => Baseline::Alphabetic,
+    });
+}
+
+fn paint(paint: Paint) -> *mut Paint {
+    Box::into_raw(Box::new(paint))
+}
+
+fn solidity(value: c_int) -> Solidity {
+    if value == Solidity::Hole as c_int {
+        Solidity::Hole
+    } else {
+        Solidity::Solid
+    }
+}
+
+fn status(ok: bool) -> c_int {
+    if ok {
+        0
+    } else {
+        -1
+    }
+}
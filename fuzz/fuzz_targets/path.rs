@@ -0,0 +1,114 @@
+//! Fills, strokes and hit tests arbitrary paths under arbitrary transforms and paints.
+//!
+//! Run with `cargo +nightly fuzz run path` from the repository root.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use femtovg::{
+    renderer::Void,
+    Canvas,
+    Color,
+    FillRule,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    Solidity,
+};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    BezierTo(f32, f32, f32, f32, f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    ArcTo(f32, f32, f32, f32, f32),
+    Arc(f32, f32, f32, f32, f32, bool),
+    Rect(f32, f32, f32, f32),
+    RoundedRect(f32, f32, f32, f32, f32),
+    Ellipse(f32, f32, f32, f32),
+    Close,
+    Hole,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    ops: Vec<Op>,
+    transform: Option<[f32; 6]>,
+    scissor: Option<[f32; 4]>,
+    line_width: f32,
+    miter_limit: f32,
+    cap: u8,
+    join: u8,
+    anti_alias: bool,
+    pixel_snapping: bool,
+    dpi: u8,
+    point: (f32, f32),
+}
+
+// Paths and transforms reject NaN and infinity with a debug assertion, which fuzz builds enable.
+// Coordinates stay far beyond any render target, but small enough that the path helpers can't
+// overflow them.
+fn finite(v: f32) -> f32 {
+    if v.is_finite() {
+        v.clamp(-1e18, 1e18)
+    } else {
+        0.0
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(512, 512, 1.0 + f32::from(input.dpi % 4));
+
+    let mut path = Path::new();
+
+    for op in &input.ops {
+        match *op {
+            Op::MoveTo(x, y) => path.move_to(finite(x), finite(y)),
+            Op::LineTo(x, y) => path.line_to(finite(x), finite(y)),
+            Op::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
+                path.bezier_to(finite(c1x), finite(c1y), finite(c2x), finite(c2y), finite(x), finite(y))
+            }
+            Op::QuadTo(cx, cy, x, y) => path.quad_to(finite(cx), finite(cy), finite(x), finite(y)),
+            Op::ArcTo(x1, y1, x2, y2, radius) => {
+                path.arc_to(finite(x1), finite(y1), finite(x2), finite(y2), finite(radius))
+            }
+            Op::Arc(cx, cy, r, a0, a1, cw) => {
+                let dir = if cw { Solidity::Hole } else { Solidity::Solid };
+                path.arc(finite(cx), finite(cy), finite(r), finite(a0), finite(a1), dir)
+            }
+            Op::Rect(x, y, w, h) => path.rect(finite(x), finite(y), finite(w), finite(h)),
+            Op::RoundedRect(x, y, w, h, r) => path.rounded_rect(finite(x), finite(y), finite(w), finite(h), finite(r)),
+            Op::Ellipse(cx, cy, rx, ry) => path.ellipse(finite(cx), finite(cy), finite(rx), finite(ry)),
+            Op::Close => path.close(),
+            Op::Hole => path.solidity(Solidity::Hole),
+        }
+    }
+
+    if let Some([a, b, c, d, e, f]) = input.transform {
+        canvas.set_transform(finite(a), finite(b), finite(c), finite(d), finite(e), finite(f));
+    }
+
+    if let Some([x, y, w, h]) = input.scissor {
+        canvas.scissor(x, y, w, h);
+    }
+
+    canvas.set_pixel_snapping(input.pixel_snapping);
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_line_width(input.line_width);
+    paint.set_miter_limit(input.miter_limit);
+    paint.set_line_cap([LineCap::Butt, LineCap::Round, LineCap::Square][usize::from(input.cap % 3)]);
+    paint.set_line_join([LineJoin::Miter, LineJoin::Round, LineJoin::Bevel][usize::from(input.join % 3)]);
+    paint.set_anti_alias(input.anti_alias);
+
+    canvas.fill_path(&mut path, paint);
+    canvas.stroke_path(&mut path, paint);
+    canvas.contains_point(&mut path, input.point.0, input.point.1, FillRule::EvenOdd);
+    canvas.path_bbox(&mut path);
+
+    canvas.end_frame();
+});
@@ -0,0 +1,24 @@
+//! Parses and draws arbitrary SVG documents.
+//!
+//! Run with `cargo +nightly fuzz run svg` from the repository root.
+
+#![no_main]
+
+use femtovg::{
+    renderer::Void,
+    svg::Picture,
+    Canvas,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut picture = match Picture::from_data(data) {
+        Ok(picture) => picture,
+        Err(_) => return,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(512, 512, 1.0);
+    canvas.draw_picture(&mut picture);
+    canvas.end_frame();
+});
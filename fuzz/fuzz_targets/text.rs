@@ -0,0 +1,71 @@
+//! Shapes, draws, measures and breaks arbitrary text, and queries carets at arbitrary byte
+//! indices. Invalid UTF-8, e.g. text decoded from UTF-16 with lone surrogates, is replaced the
+//! way applications usually do before handing the text to femtovg.
+//!
+//! Run with `cargo +nightly fuzz run text` from the repository root.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use femtovg::{
+    renderer::Void,
+    Align,
+    Baseline,
+    Canvas,
+    Color,
+    Paint,
+};
+use libfuzzer_sys::fuzz_target;
+
+static LATIN: &[u8] = include_bytes!("../../examples/assets/Roboto-Regular.ttf");
+static ARABIC: &[u8] = include_bytes!("../../examples/assets/amiri-regular.ttf");
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    text: Vec<u8>,
+    repeat: u8,
+    font_size: f32,
+    letter_spacing: f32,
+    max_width: f32,
+    align: u8,
+    baseline: u8,
+    position: (f32, f32),
+    carets: Vec<usize>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut canvas = Canvas::new(Void).unwrap();
+    let latin = canvas.add_font_mem(LATIN).unwrap();
+    let arabic = canvas.add_font_mem(ARABIC).unwrap();
+
+    // Huge strings are built from short inputs
+    let text = String::from_utf8_lossy(&input.text).repeat(usize::from(input.repeat % 16) + 1);
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[latin, arabic]);
+    paint.set_font_size(input.font_size);
+    paint.set_letter_spacing(input.letter_spacing);
+    paint.set_text_align([Align::Left, Align::Center, Align::Right][usize::from(input.align % 3)]);
+    paint.set_text_baseline(
+        [Baseline::Top, Baseline::Middle, Baseline::Alphabetic, Baseline::Bottom][usize::from(input.baseline % 4)],
+    );
+
+    canvas.begin_frame(512, 512, 1.0);
+
+    let (x, y) = input.position;
+
+    if let Ok(metrics) = canvas.fill_text(x, y, &text, paint) {
+        for &caret in &input.carets {
+            metrics.caret_x(&text, caret);
+            metrics.range_spans(caret..text.len());
+        }
+    }
+
+    let _ = canvas.stroke_text(x, y, &text, paint);
+    let _ = canvas.measure_text(x, y, &text, paint);
+    let _ = canvas.measure_text_fast(&text, paint);
+    let _ = canvas.break_text(input.max_width, &text, paint);
+    let _ = canvas.break_text_vec(input.max_width, &text, paint);
+
+    canvas.end_frame();
+});
@@ -0,0 +1,360 @@
+//! Values that change smoothly over time.
+//!
+//! An [`Animated`] value moves from where it is towards a target over a duration, following an
+//! [`Easing`] curve. Any type that implements [`Lerp`] can be animated, which includes `f32`,
+//! [`Color`], [`Transform2D`] and [`Paint`].
+//!
+//! Time is passed in by the caller, so the same code runs on every platform and in tests.
+//!
+//! # Example
+//! ```
+//! use std::time::Duration;
+//! use femtovg::{animation::{Animated, Easing}, Color};
+//!
+//! let mut highlight = Animated::new(Color::BLACK);
+//! highlight.animate_to(Color::WHITE, Duration::from_millis(200), Easing::EaseOut);
+//!
+//! // Each frame
+//! highlight.advance(Duration::from_millis(16));
+//! let color = highlight.value();
+//! # assert!(color.r > 0.0);
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    paint::{
+        GradientColors,
+        PaintFlavor,
+    },
+    Color,
+    Paint,
+    Transform2D,
+};
+
+/// Maps the progress of an animation from 0 to 1 to how far the value has moved from 0 to 1.
+///
+/// The named curves are the CSS timing functions of the same names.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    Ease,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// A cubic bezier from (0, 0) to (1, 1) with the control points `(x1, y1)` and `(x2, y2)`, like
+    /// the CSS `cubic-bezier()` function. The x coordinates are clamped to 0..=1.
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// The eased value at `t`, which is clamped to 0..=1. Every curve starts at 0 and ends at 1.
+    pub fn apply(self, t: f32) -> f32 {
+        if t <= 0.0 {
+            return 0.0;
+        } else if t >= 1.0 {
+            return 1.0;
+        }
+
+        match self {
+            Self::Linear => t,
+            Self::Ease => cubic_bezier([0.25, 0.1], [0.25, 1.0], t),
+            Self::EaseIn => cubic_bezier([0.42, 0.0], [1.0, 1.0], t),
+            Self::EaseOut => cubic_bezier([0.0, 0.0], [0.58, 1.0], t),
+            Self::EaseInOut => cubic_bezier([0.42, 0.0], [0.58, 1.0], t),
+            Self::CubicBezier(x1, y1, x2, y2) => cubic_bezier([x1.clamp(0.0, 1.0), y1], [x2.clamp(0.0, 1.0), y2], t),
+        }
+    }
+}
+
+// Evaluates the cubic bezier easing curve from (0, 0) to (1, 1) with the control points `p1` and `p2` at `x`
+pub(crate) fn cubic_bezier(p1: [f32; 2], p2: [f32; 2], x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+
+    let bezier = |a: f32, b: f32, t: f32| {
+        let mt = 1.0 - t;
+        3.0 * mt * mt * t * a + 3.0 * mt * t * t * b + t * t * t
+    };
+
+    // x(t) is monotonic for control points inside the unit square, so bisection finds t
+    let (mut lo, mut hi) = (0.0, 1.0);
+
+    for _ in 0..24 {
+        let mid = (lo + hi) / 2.0;
+
+        if bezier(p1[0], p2[0], mid) < x {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    bezier(p1[1], p2[1], (lo + hi) / 2.0)
+}
+
+/// Linear interpolation between two values of a type.
+pub trait Lerp {
+    /// The value `t` of the way from `self` to `other`, `self` at 0 and `other` at 1. Easing curves
+    /// can overshoot, so `t` may be outside of 0..=1.
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Color {
+    /// Interpolates each channel, the result is clamped to valid colors.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let channel = |a: f32, b: f32| a.lerp(&b, t).clamp(0.0, 1.0);
+
+        Color::rgbaf(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+            channel(self.a, other.a),
+        )
+    }
+}
+
+impl Lerp for Transform2D {
+    /// Interpolates each matrix component. That is exact for translations and scales, rotations
+    /// pass through a slightly shrunk shape halfway.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut out = *self;
+
+        for (out, other) in out.0.iter_mut().zip(other.0.iter()) {
+            *out = out.lerp(other, t);
+        }
+
+        out
+    }
+}
+
+impl Lerp for Paint {
+    /// Interpolates the colors, gradient geometry, transform, line width, miter limit, font size
+    /// and letter spacing.
+    ///
+    /// Properties that can't be blended, like the fonts, line caps or a switch between a color and
+    /// a gradient, are taken from `self` until halfway and from `other` after that. Gradients
+    /// blend their stops if they have as many, and jump halfway otherwise.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut out = if t < 0.5 { *self } else { *other };
+
+        out.transform = self.transform.lerp(&other.transform, t);
+        out.line_width = self.line_width.lerp(&other.line_width, t);
+        out.miter_limit = self.miter_limit.lerp(&other.miter_limit, t);
+        out.font_size = self.font_size.lerp(&other.font_size, t);
+        out.letter_spacing = self.letter_spacing.lerp(&other.letter_spacing, t);
+
+        if let Some(flavor) = lerp_flavor(&self.flavor, &other.flavor, t) {
+            out.flavor = flavor;
+        }
+
+        out
+    }
+}
+
+// Blends two paint flavors of the same kind, None if they can't be blended
+fn lerp_flavor(a: &PaintFlavor, b: &PaintFlavor, t: f32) -> Option<PaintFlavor> {
+    let f = |a: &f32, b: &f32| a.lerp(b, t);
+
+    let flavor = match (a, b) {
+        (PaintFlavor::Color(a), PaintFlavor::Color(b)) => PaintFlavor::Color(a.lerp(b, t)),
+        (
+            PaintFlavor::Image {
+                id,
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+                alpha,
+            },
+            PaintFlavor::Image {
+                id: other_id,
+                cx: cx1,
+                cy: cy1,
+                width: width1,
+                height: height1,
+                angle: angle1,
+                alpha: alpha1,
+            },
+        ) if id == other_id => PaintFlavor::Image {
+            id: *id,
+            cx: f(cx, cx1),
+            cy: f(cy, cy1),
+            width: f(width, width1),
+            height: f(height, height1),
+            angle: f(angle, angle1),
+            alpha: f(alpha, alpha1),
+        },
+        (
+            PaintFlavor::LinearGradient {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                colors,
+            },
+            PaintFlavor::LinearGradient {
+                start_x: start_x1,
+                start_y: start_y1,
+                end_x: end_x1,
+                end_y: end_y1,
+                colors: colors1,
+            },
+        ) => PaintFlavor::LinearGradient {
+            start_x: f(start_x, start_x1),
+            start_y: f(start_y, start_y1),
+            end_x: f(end_x, end_x1),
+            end_y: f(end_y, end_y1),
+            colors: lerp_colors(colors, colors1, t)?,
+        },
+        (
+            PaintFlavor::BoxGradient {
+                x,
+                y,
+                width,
+                height,
+                radius,
+                feather,
+                colors,
+            },
+            PaintFlavor::BoxGradient {
+                x: x1,
+                y: y1,
+                width: width1,
+                height: height1,
+                radius: radius1,
+                feather: feather1,
+                colors: colors1,
+            },
+        ) => PaintFlavor::BoxGradient {
+            x: f(x, x1),
+            y: f(y, y1),
+            width: f(width, width1),
+            height: f(height, height1),
+            radius: f(radius, radius1),
+            feather: f(feather, feather1),
+            colors: lerp_colors(colors, colors1, t)?,
+        },
+        (
+            PaintFlavor::RadialGradient {
+                cx,
+                cy,
+                in_radius,
+                out_radius,
+                colors,
+            },
+            PaintFlavor::RadialGradient {
+                cx: cx1,
+                cy: cy1,
+                in_radius: in_radius1,
+                out_radius: out_radius1,
+                colors: colors1,
+            },
+        ) => PaintFlavor::RadialGradient {
+            cx: f(cx, cx1),
+            cy: f(cy, cy1),
+            in_radius: f(in_radius, in_radius1),
+            out_radius: f(out_radius, out_radius1),
+            colors: lerp_colors(colors, colors1, t)?,
+        },
+        _ => return None,
+    };
+
+    Some(flavor)
+}
+
+fn lerp_colors(a: &GradientColors, b: &GradientColors, t: f32) -> Option<GradientColors> {
+    let (a, b) = (a.stops(), b.stops());
+
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let stops: Vec<(f32, Color)> = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a.0.lerp(&b.0, t), a.1.lerp(&b.1, t)))
+        .collect();
+
+    Some(GradientColors::from_stops(&stops))
+}
+
+/// A value that moves towards a target over time.
+///
+/// The animation advances by the time passed to [`Self::advance()`], usually the time since the
+/// last frame. Retargeting a running animation starts the new one from where the value is, so it
+/// doesn't jump.
+#[derive(Clone, Debug)]
+pub struct Animated<T> {
+    from: T,
+    to: T,
+    elapsed: Duration,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl<T: Lerp + Clone> Animated<T> {
+    /// A value resting at `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            from: value.clone(),
+            to: value,
+            elapsed: Duration::ZERO,
+            duration: Duration::ZERO,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Starts moving from the current value to `target` over `duration`.
+    pub fn animate_to(&mut self, target: T, duration: Duration, easing: Easing) {
+        self.from = self.value();
+        self.to = target;
+        self.elapsed = Duration::ZERO;
+        self.duration = duration;
+        self.easing = easing;
+    }
+
+    /// Stops the animation and jumps to `value`.
+    pub fn set(&mut self, value: T) {
+        *self = Self::new(value);
+    }
+
+    /// Moves the animation forward by `dt`.
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    /// The current value.
+    pub fn value(&self) -> T {
+        if self.is_running() {
+            self.from.lerp(&self.to, self.easing.apply(self.progress()))
+        } else {
+            self.to.clone()
+        }
+    }
+
+    /// The value the animation ends at.
+    pub fn target(&self) -> &T {
+        &self.to
+    }
+
+    /// How far the animation is through its duration, from 0 to 1 and 1 once it has finished.
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+        }
+    }
+
+    /// Whether the value is still moving, the canvas needs redrawing while it is.
+    pub fn is_running(&self) -> bool {
+        self.elapsed < self.duration
+    }
+}
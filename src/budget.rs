@@ -0,0 +1,132 @@
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use crate::{
+    Canvas,
+    Renderer,
+};
+
+// Share of the budget after which the rest of the frame is drawn at lower quality
+const DEGRADE_THRESHOLD: f32 = 0.75;
+
+// Multiplies the tessellation tolerance of degraded draws, curves are flattened into fewer segments
+pub(crate) const DEGRADED_TESS_TOL_SCALE: f32 = 4.0;
+
+// Number of glyphs a degraded frame still renders into the glyph atlases, so that text keeps
+// appearing when every frame runs over the budget
+const DEGRADED_GLYPH_ALLOWANCE: usize = 8;
+
+#[derive(Default)]
+pub(crate) struct FrameBudget {
+    budget: Duration,
+    frame_start: Option<Instant>,
+    degraded: bool,
+    glyph_allowance: usize,
+    deferred_glyphs: usize,
+    // Set in deterministic mode, the budget is ignored
+    suspended: bool,
+}
+
+impl FrameBudget {
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame_start = now();
+        self.degraded = false;
+        self.glyph_allowance = DEGRADED_GLYPH_ALLOWANCE;
+    }
+
+    // Called after a flush, the next frame starts with its first draw unless begin_frame() is called
+    pub(crate) fn end_frame(&mut self) {
+        self.frame_start = None;
+        self.degraded = false;
+        self.glyph_allowance = DEGRADED_GLYPH_ALLOWANCE;
+    }
+
+    pub(crate) fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+        self.degraded = false;
+    }
+
+    // Returns true if the rest of the frame is drawn at lower quality
+    pub(crate) fn check(&mut self) -> bool {
+        if self.budget.is_zero() || self.suspended || self.degraded {
+            return self.degraded;
+        }
+
+        match self.frame_start {
+            Some(start) => {
+                self.degraded = start.elapsed().as_secs_f32() >= self.budget.as_secs_f32() * DEGRADE_THRESHOLD;
+            }
+            None => self.frame_start = now(),
+        }
+
+        self.degraded
+    }
+
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+
+    // Returns false if a glyph that isn't in the atlases yet should be skipped in this frame
+    pub(crate) fn allow_glyph(&mut self) -> bool {
+        if !self.check() {
+            return true;
+        }
+
+        if self.glyph_allowance > 0 {
+            self.glyph_allowance -= 1;
+            true
+        } else {
+            self.deferred_glyphs += 1;
+            false
+        }
+    }
+
+    pub(crate) fn deferred_glyphs(&self) -> usize {
+        self.deferred_glyphs
+    }
+
+    pub(crate) fn take_deferred_glyphs(&mut self) -> usize {
+        std::mem::take(&mut self.deferred_glyphs)
+    }
+}
+
+// The clock isn't available on the web
+fn now() -> Option<Instant> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Some(Instant::now())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        None
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Sets how long drawing a frame may take before the canvas lowers the quality of the remaining
+    /// draws of the frame, `Duration::ZERO` disables the budget. Disabled by default.
+    ///
+    /// A frame starts with [`Self::begin_frame()`], or with the first draw after a flush. Once three
+    /// quarters of the budget are used up, paths are drawn without anti-aliased fringes and with
+    /// coarser curves, and only a few glyphs that aren't in the glyph atlases yet are rasterized,
+    /// the others are left out until a later frame has time for them. This keeps frame times in
+    /// check on low-end hardware, the [`FrameStats`](crate::FrameStats) returned by
+    /// [`Self::end_frame()`] report when it happened. The budget has no effect on wasm32, where no
+    /// clock is available.
+    pub fn set_frame_budget(&mut self, budget: Duration) {
+        self.frame_budget.budget = budget;
+    }
+
+    /// Returns the frame budget, see [`Self::set_frame_budget()`].
+    pub fn frame_budget(&self) -> Duration {
+        self.frame_budget.budget
+    }
+
+    /// Returns true if the current frame ran out of budget and is drawn at lower quality.
+    pub fn is_frame_degraded(&self) -> bool {
+        self.frame_budget.is_degraded()
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    Canvas,
+    ErrorKind,
+    Renderer,
+    TextContext,
+};
+
+/// Creates a [`Canvas`] with its options set in one place, see [`Canvas::builder()`].
+///
+/// Options that aren't set keep the defaults of [`Canvas::new()`].
+///
+/// # Example
+/// ```
+/// use femtovg::{renderer::Void, Canvas};
+///
+/// let canvas = Canvas::builder(Void)
+///     .size(800, 600, 2.0)
+///     .antialias(false)
+///     .shaping_cache_capacity(2048)
+///     .build()
+///     .expect("Cannot create canvas");
+///
+/// assert_eq!(canvas.width(), 800.0);
+/// ```
+pub struct CanvasBuilder<T: Renderer> {
+    renderer: T,
+    text_context: Option<TextContext>,
+    size: Option<(u32, u32, f32)>,
+    antialias: bool,
+    command_batching: bool,
+    shaping_cache_capacity: Option<usize>,
+    tessellation_cache_capacity: Option<usize>,
+    glyph_atlas_size: Option<usize>,
+    glyph_worker_threads: usize,
+    validation: bool,
+}
+
+impl<T: Renderer> CanvasBuilder<T> {
+    pub(crate) fn new(renderer: T) -> Self {
+        Self {
+            renderer,
+            text_context: None,
+            size: None,
+            antialias: true,
+            command_batching: true,
+            shaping_cache_capacity: None,
+            tessellation_cache_capacity: None,
+            glyph_atlas_size: None,
+            glyph_worker_threads: 0,
+            validation: false,
+        }
+    }
+
+    /// Uses the fonts registered with `text_context`, see [`Canvas::new_with_text_context()`].
+    pub fn text_context(mut self, text_context: TextContext) -> Self {
+        self.text_context = Some(text_context);
+        self
+    }
+
+    /// Sets the initial size of the screen and the device pixel ratio, see [`Canvas::set_size()`].
+    pub fn size(mut self, width: u32, height: u32, dpi: f32) -> Self {
+        self.size = Some((width, height, dpi));
+        self
+    }
+
+    /// See [`Canvas::set_antialias()`].
+    pub fn antialias(mut self, enabled: bool) -> Self {
+        self.antialias = enabled;
+        self
+    }
+
+    /// See [`Canvas::set_command_batching()`].
+    pub fn command_batching(mut self, enabled: bool) -> Self {
+        self.command_batching = enabled;
+        self
+    }
+
+    /// See [`Canvas::set_shaping_cache_capacity()`].
+    pub fn shaping_cache_capacity(mut self, capacity: usize) -> Self {
+        self.shaping_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// See [`Canvas::set_tessellation_cache_capacity()`].
+    pub fn tessellation_cache_capacity(mut self, capacity: usize) -> Self {
+        self.tessellation_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// See [`Canvas::set_glyph_atlas_size()`].
+    pub fn glyph_atlas_size(mut self, size: usize) -> Self {
+        self.glyph_atlas_size = Some(size);
+        self
+    }
+
+    /// See [`Canvas::set_glyph_worker_threads()`].
+    pub fn glyph_worker_threads(mut self, threads: usize) -> Self {
+        self.glyph_worker_threads = threads;
+        self
+    }
+
+    /// See [`Canvas::set_validation()`].
+    pub fn validation(mut self, enabled: bool) -> Self {
+        self.validation = enabled;
+        self
+    }
+
+    /// Creates the canvas, returns [`ErrorKind::InvalidSize`] for a device pixel ratio that isn't
+    /// positive and finite.
+    pub fn build(self) -> Result<Canvas<T>, ErrorKind> {
+        let mut canvas = match self.text_context {
+            Some(text_context) => Canvas::new_with_text_context(self.renderer, text_context)?,
+            None => Canvas::new(self.renderer)?,
+        };
+
+        if let Some((width, height, dpi)) = self.size {
+            canvas.try_set_size(width, height, dpi)?;
+        }
+
+        canvas.set_antialias(self.antialias);
+        canvas.set_command_batching(self.command_batching);
+
+        if let Some(capacity) = self.shaping_cache_capacity {
+            canvas.set_shaping_cache_capacity(capacity);
+        }
+
+        if let Some(capacity) = self.tessellation_cache_capacity {
+            canvas.set_tessellation_cache_capacity(capacity);
+        }
+
+        if let Some(size) = self.glyph_atlas_size {
+            canvas.set_glyph_atlas_size(size);
+        }
+
+        canvas.set_glyph_worker_threads(self.glyph_worker_threads);
+        canvas.set_validation(self.validation);
+
+        Ok(canvas)
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Returns a builder for a canvas drawing with `renderer`, which sets the size and the options
+    /// of the canvas before it is created instead of calling their setters one by one.
+    pub fn builder(renderer: T) -> CanvasBuilder<T> {
+        CanvasBuilder::new(renderer)
+    }
+}
@@ -1,5 +1,8 @@
+use std::str::FromStr;
 use std::u8;
 
+use crate::ErrorKind;
+
 /// Struct for representing colors.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -11,28 +14,40 @@ pub struct Color {
 }
 
 impl Color {
+    pub const TRANSPARENT: Self = Self::rgbaf(0.0, 0.0, 0.0, 0.0);
+
+    // The palette has the values of the CSS color keywords with the same names
+    pub const BLACK: Self = Self::rgb(0, 0, 0);
+    pub const WHITE: Self = Self::rgb(255, 255, 255);
+    pub const GRAY: Self = Self::rgb(128, 128, 128);
+    pub const SILVER: Self = Self::rgb(192, 192, 192);
+    pub const RED: Self = Self::rgb(255, 0, 0);
+    pub const LIME: Self = Self::rgb(0, 255, 0);
+    pub const GREEN: Self = Self::rgb(0, 128, 0);
+    pub const BLUE: Self = Self::rgb(0, 0, 255);
+    pub const YELLOW: Self = Self::rgb(255, 255, 0);
+    pub const CYAN: Self = Self::rgb(0, 255, 255);
+    pub const MAGENTA: Self = Self::rgb(255, 0, 255);
+    pub const ORANGE: Self = Self::rgb(255, 165, 0);
+    pub const PURPLE: Self = Self::rgb(128, 0, 128);
+
     /// Returns a color value from red, green, blue char values. Alpha will be set to 255.
-    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
         Self::rgbf(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
     }
 
     /// Returns a color value from red, green, blue float values. Alpha will be set to 1.0.
-    pub fn rgbf(r: f32, g: f32, b: f32) -> Self {
-        Self {
-            r: r,
-            g: g,
-            b: b,
-            a: 1.0,
-        }
+    pub const fn rgbf(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
     }
 
     /// Returns a color value from red, green, blue and alpha char values.
-    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
         Self::rgbaf(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
     }
 
     /// Returns a color value from red, green, blue and alpha char values.
-    pub fn rgbaf(r: f32, g: f32, b: f32, a: f32) -> Self {
+    pub const fn rgbaf(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
 
@@ -61,38 +76,26 @@ impl Color {
             r: hue(h + 1.0 / 3.0, m1, m2).max(0.0).min(1.0),
             g: hue(h, m1, m2).max(0.0).min(1.0),
             b: hue(h - 1.0 / 3.0, m1, m2).max(0.0).min(1.0),
-            a: a,
+            a,
         }
     }
 
-    /// Returns color value for a 6-digit (`RRGGBB`) or 8-digit (`RRGGBBAA`)
-    /// HTML hexadecimal string. Any other length produces `rgb(0,0,0)`.
+    /// Returns color value for a 3-digit (`RGB`), 4-digit (`RGBA`), 6-digit (`RRGGBB`) or 8-digit
+    /// (`RRGGBBAA`) HTML hexadecimal string. Anything else produces `rgb(0,0,0)`, parse the string
+    /// with [`str::parse()`] to get an error instead.
     /// The “#” is optional.
     pub fn hex(raw_hex: &str) -> Self {
-        let hex = raw_hex.trim_start_matches('#');
-
-        if hex.len() == 8 {
-            Self::rgba(
-                hex_to_u8(&hex[0..2]),
-                hex_to_u8(&hex[2..4]),
-                hex_to_u8(&hex[4..6]),
-                hex_to_u8(&hex[6..8]),
-            )
-        } else if hex.len() == 6 {
-            Self::rgb(hex_to_u8(&hex[0..2]), hex_to_u8(&hex[2..4]), hex_to_u8(&hex[4..6]))
-        } else {
-            Self::rgb(0, 0, 0)
-        }
+        parse_hex(raw_hex.trim_start_matches('#')).unwrap_or(Self::BLACK)
     }
 
     /// Returns a white color
-    pub fn white() -> Self {
-        Self::rgbaf(1.0, 1.0, 1.0, 1.0)
+    pub const fn white() -> Self {
+        Self::WHITE
     }
 
     /// Returns a black color
-    pub fn black() -> Self {
-        Self::rgbaf(0.0, 0.0, 0.0, 1.0)
+    pub const fn black() -> Self {
+        Self::BLACK
     }
 
     /// Sets transparency of a color value.
@@ -123,6 +126,39 @@ impl Color {
     }
 }
 
+/// Parses CSS colors: hexadecimal colors with a leading `#`, the `rgb()`, `rgba()`, `hsl()` and
+/// `hsla()` functions with comma or space separated arguments, and the names of the palette
+/// constants in lower case, e.g. `"transparent"` or `"orange"`.
+///
+/// # Example
+/// ```
+/// use femtovg::Color;
+///
+/// assert_eq!("#f00".parse::<Color>().unwrap(), Color::RED);
+/// assert_eq!("rgba(255, 0, 0, 0.5)".parse::<Color>().unwrap(), Color::rgbaf(1.0, 0.0, 0.0, 0.5));
+/// assert_eq!("hsl(120deg 100% 25%)".parse::<Color>().unwrap(), Color::hsl(1.0 / 3.0, 1.0, 0.25));
+/// assert!("#ff00zz".parse::<Color>().is_err());
+/// ```
+impl FromStr for Color {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color = s.trim().to_ascii_lowercase();
+
+        let parsed = if let Some(hex) = color.strip_prefix('#') {
+            parse_hex(hex)
+        } else if let Some(open) = color.find('(') {
+            color
+                .strip_suffix(')')
+                .and_then(|args| parse_function(&color[..open], &args[open + 1..]))
+        } else {
+            named(&color)
+        };
+
+        parsed.ok_or_else(|| ErrorKind::InvalidColor(s.to_owned()))
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         Self {
@@ -155,7 +191,101 @@ fn hue(mut h: f32, m1: f32, m2: f32) -> f32 {
     m1
 }
 
-// Convert a hex string to decimal. Eg. "00" -> 0. "FF" -> 255.
-fn hex_to_u8(hex_string: &str) -> u8 {
-    u8::from_str_radix(hex_string, 16).map(|o| o as u8).unwrap_or(0)
+// The hex digits without the "#", short forms repeat each digit: "f80" is "ff8800"
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    match hex.len() {
+        3 => Some(Color::rgb(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)),
+        4 => Some(Color::rgba(
+            digit(0)? * 17,
+            digit(1)? * 17,
+            digit(2)? * 17,
+            digit(3)? * 17,
+        )),
+        6 => Some(Color::rgb(byte(0)?, byte(2)?, byte(4)?)),
+        8 => Some(Color::rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+        _ => None,
+    }
+}
+
+fn parse_function(name: &str, args: &str) -> Option<Color> {
+    let args: Vec<&str> = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+
+    if args.len() < 3 || args.len() > 4 {
+        return None;
+    }
+
+    let alpha = match args.get(3) {
+        Some(alpha) => fraction(alpha, 1.0)?,
+        None => 1.0,
+    };
+
+    match name.trim() {
+        "rgb" | "rgba" => Some(Color::rgbaf(
+            fraction(args[0], 255.0)?,
+            fraction(args[1], 255.0)?,
+            fraction(args[2], 255.0)?,
+            alpha,
+        )),
+        "hsl" | "hsla" => {
+            let hue = args[0].strip_suffix("deg").unwrap_or(args[0]).parse::<f32>().ok()?;
+
+            if !hue.is_finite() {
+                return None;
+            }
+
+            Some(Color::hsla(
+                hue / 360.0,
+                fraction(args[1], 100.0)?,
+                fraction(args[2], 100.0)?,
+                alpha,
+            ))
+        }
+        _ => None,
+    }
+}
+
+// A number relative to `max` or a percentage, clamped to 0..1
+fn fraction(value: &str, max: f32) -> Option<f32> {
+    let value = match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+        None => value.parse::<f32>().ok()? / max,
+    };
+
+    if value.is_nan() {
+        return None;
+    }
+
+    Some(value.clamp(0.0, 1.0))
+}
+
+fn named(name: &str) -> Option<Color> {
+    let color = match name {
+        "transparent" => Color::TRANSPARENT,
+        "black" => Color::BLACK,
+        "white" => Color::WHITE,
+        "gray" | "grey" => Color::GRAY,
+        "silver" => Color::SILVER,
+        "red" => Color::RED,
+        "lime" => Color::LIME,
+        "green" => Color::GREEN,
+        "blue" => Color::BLUE,
+        "yellow" => Color::YELLOW,
+        "cyan" | "aqua" => Color::CYAN,
+        "magenta" | "fuchsia" => Color::MAGENTA,
+        "orange" => Color::ORANGE,
+        "purple" => Color::PURPLE,
+        _ => return None,
+    };
+
+    Some(color)
 }
@@ -0,0 +1,439 @@
+use crate::{
+    Align,
+    Baseline,
+    Canvas,
+    Color,
+    CompositeOperation,
+    ErrorKind,
+    FillRule,
+    FontId,
+    ImageId,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    Renderer,
+    Solidity,
+    TextMetrics,
+};
+
+/// A wrapper around [`Canvas`] that mirrors the HTML Canvas 2D API.
+///
+/// Method names are the snake case versions of their web counterparts and property setters are prefixed
+/// with `set_`, e.g. `ctx.fillStyle = ...` becomes `ctx.set_fill_style(...)`. Like on the web the
+/// styles and the current path are part of the context and styles are saved and restored along with the
+/// transform and scissor state. `clip()` and `setLineDash()` have no equivalent and are missing.
+///
+/// # Example
+/// ```
+/// use femtovg::{Canvas, Color, Context2D, renderer::Void};
+///
+/// let mut ctx = Context2D::new(Canvas::new(Void).expect("Cannot create canvas"));
+///
+/// ctx.set_fill_style(Color::rgb(255, 0, 0));
+/// ctx.begin_path();
+/// ctx.arc(50.0, 50.0, 20.0, 0.0, std::f32::consts::PI * 2.0, false);
+/// ctx.fill();
+///
+/// ctx.canvas_mut().flush();
+/// ```
+pub struct Context2D<T: Renderer> {
+    canvas: Canvas<T>,
+    path: Path,
+    state: DrawState,
+    state_stack: Vec<DrawState>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct DrawState {
+    fill_style: Paint,
+    stroke_style: Paint,
+    line_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    font_ids: [Option<FontId>; 8],
+    font_size: f32,
+    text_align: Align,
+    text_baseline: Baseline,
+}
+
+impl Default for DrawState {
+    // Web defaults
+    fn default() -> Self {
+        Self {
+            fill_style: Paint::color(Color::black()),
+            stroke_style: Paint::color(Color::black()),
+            line_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 10.0,
+            font_ids: Default::default(),
+            font_size: 10.0,
+            text_align: Align::Left,
+            text_baseline: Baseline::Alphabetic,
+        }
+    }
+}
+
+impl<T: Renderer> Context2D<T> {
+    pub fn new(canvas: Canvas<T>) -> Self {
+        Self {
+            canvas,
+            path: Path::new(),
+            state: DrawState::default(),
+            state_stack: Vec::new(),
+        }
+    }
+
+    /// The wrapped canvas, e.g. for `set_size()` and `flush()`.
+    pub fn canvas(&self) -> &Canvas<T> {
+        &self.canvas
+    }
+
+    pub fn canvas_mut(&mut self) -> &mut Canvas<T> {
+        &mut self.canvas
+    }
+
+    pub fn into_inner(self) -> Canvas<T> {
+        self.canvas
+    }
+
+    // State
+
+    #[track_caller]
+    pub fn save(&mut self) {
+        self.canvas.save();
+        self.state_stack.push(self.state);
+    }
+
+    pub fn restore(&mut self) {
+        self.canvas.restore();
+
+        if let Some(state) = self.state_stack.pop() {
+            self.state = state;
+        }
+    }
+
+    /// Resets the styles, the transform, the scissor and the current path.
+    pub fn reset(&mut self) {
+        self.canvas.reset();
+        self.path = Path::new();
+        self.state = DrawState::default();
+    }
+
+    // Styles
+
+    /// Sets the fill style, either a [`Color`], a [`CanvasGradient`] or any other [`Paint`].
+    pub fn set_fill_style<P: Into<Paint>>(&mut self, style: P) {
+        self.state.fill_style = style.into();
+    }
+
+    /// Sets the stroke style, either a [`Color`], a [`CanvasGradient`] or any other [`Paint`].
+    pub fn set_stroke_style<P: Into<Paint>>(&mut self, style: P) {
+        self.state.stroke_style = style.into();
+    }
+
+    pub fn line_width(&self) -> f32 {
+        self.state.line_width
+    }
+
+    pub fn set_line_width(&mut self, width: f32) {
+        self.state.line_width = width;
+    }
+
+    pub fn set_line_cap(&mut self, cap: LineCap) {
+        self.state.line_cap = cap;
+    }
+
+    pub fn set_line_join(&mut self, join: LineJoin) {
+        self.state.line_join = join;
+    }
+
+    pub fn set_miter_limit(&mut self, limit: f32) {
+        self.state.miter_limit = limit;
+    }
+
+    pub fn set_global_alpha(&mut self, alpha: f32) {
+        self.canvas.set_global_alpha(alpha);
+    }
+
+    pub fn set_global_composite_operation(&mut self, op: CompositeOperation) {
+        self.canvas.global_composite_operation(op);
+    }
+
+    /// Sets the font from a CSS font shorthand like `"bold 16px sans-serif"`.
+    ///
+    /// Only the size in pixels is used, the font itself is picked from the fonts added to the canvas,
+    /// or from the ones set with [`Context2D::set_font_ids()`].
+    pub fn set_font(&mut self, font: &str) {
+        let size = font
+            .split_whitespace()
+            .filter_map(|token| token.split('/').next())
+            .find_map(|token| token.strip_suffix("px").and_then(|size| size.parse::<f32>().ok()));
+
+        if let Some(size) = size {
+            self.state.font_size = size;
+        }
+    }
+
+    /// The font size in pixels set with [`Context2D::set_font()`].
+    pub fn font_size(&self) -> f32 {
+        self.state.font_size
+    }
+
+    pub fn set_font_ids(&mut self, font_ids: &[FontId]) {
+        self.state.font_ids = Default::default();
+
+        for (i, id) in font_ids.iter().take(8).enumerate() {
+            self.state.font_ids[i] = Some(*id);
+        }
+    }
+
+    pub fn set_text_align(&mut self, align: Align) {
+        self.state.text_align = align;
+    }
+
+    pub fn set_text_baseline(&mut self, baseline: Baseline) {
+        self.state.text_baseline = baseline;
+    }
+
+    /// Returns a linear gradient that can be used as fill or stroke style.
+    pub fn create_linear_gradient(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> CanvasGradient {
+        CanvasGradient {
+            kind: GradientKind::Linear { x0, y0, x1, y1 },
+            stops: Vec::new(),
+        }
+    }
+
+    /// Returns a radial gradient that can be used as fill or stroke style.
+    ///
+    /// The circles are expected to share their center, femtovg can't draw two point conical gradients.
+    /// The center of the second circle is used.
+    pub fn create_radial_gradient(&self, _x0: f32, _y0: f32, r0: f32, x1: f32, y1: f32, r1: f32) -> CanvasGradient {
+        CanvasGradient {
+            kind: GradientKind::Radial { x: x1, y: y1, r0, r1 },
+            stops: Vec::new(),
+        }
+    }
+
+    // Transforms
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.canvas.translate(x, y);
+    }
+
+    pub fn rotate(&mut self, angle: f32) {
+        self.canvas.rotate(angle);
+    }
+
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.canvas.scale(x, y);
+    }
+
+    /// Multiplies the current transform with the provided matrix.
+    pub fn transform(&mut self, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
+        self.canvas.set_transform(a, b, c, d, e, f);
+    }
+
+    /// Replaces the current transform with the provided matrix.
+    pub fn set_transform(&mut self, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
+        self.canvas.reset_transform();
+        self.canvas.set_transform(a, b, c, d, e, f);
+    }
+
+    pub fn reset_transform(&mut self) {
+        self.canvas.reset_transform();
+    }
+
+    // Paths
+
+    pub fn begin_path(&mut self) {
+        self.path = Path::new();
+    }
+
+    pub fn close_path(&mut self) {
+        self.path.close();
+    }
+
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.path.move_to(x, y);
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        self.path.line_to(x, y);
+    }
+
+    pub fn bezier_curve_to(&mut self, cp1x: f32, cp1y: f32, cp2x: f32, cp2y: f32, x: f32, y: f32) {
+        self.path.bezier_to(cp1x, cp1y, cp2x, cp2y, x, y);
+    }
+
+    pub fn quadratic_curve_to(&mut self, cpx: f32, cpy: f32, x: f32, y: f32) {
+        self.path.quad_to(cpx, cpy, x, y);
+    }
+
+    pub fn arc(&mut self, x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32, anticlockwise: bool) {
+        // Positive angles turn clockwise with the y axis pointing down
+        let dir = if anticlockwise { Solidity::Solid } else { Solidity::Hole };
+
+        self.path.arc(x, y, radius, start_angle, end_angle, dir);
+    }
+
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        self.path.arc_to(x1, y1, x2, y2, radius);
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.path.rect(x, y, width, height);
+    }
+
+    /// Adds an axis aligned ellipse, the rotation and angle arguments of the web API are not supported.
+    pub fn ellipse(&mut self, x: f32, y: f32, radius_x: f32, radius_y: f32) {
+        self.path.ellipse(x, y, radius_x, radius_y);
+    }
+
+    // Drawing
+
+    /// Fills the current path using the non-zero winding rule.
+    pub fn fill(&mut self) {
+        self.fill_with_rule(FillRule::NonZero);
+    }
+
+    pub fn fill_with_rule(&mut self, rule: FillRule) {
+        let mut paint = self.state.fill_style;
+        paint.set_fill_rule(rule);
+
+        self.canvas.fill_path(&mut self.path, paint);
+    }
+
+    pub fn stroke(&mut self) {
+        let paint = self.stroke_paint();
+
+        self.canvas.stroke_path(&mut self.path, paint);
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let mut path = Path::new();
+        path.rect(x, y, width, height);
+
+        self.canvas.fill_path(&mut path, self.state.fill_style);
+    }
+
+    pub fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let mut path = Path::new();
+        path.rect(x, y, width, height);
+
+        let paint = self.stroke_paint();
+        self.canvas.stroke_path(&mut path, paint);
+    }
+
+    /// Clears the rectangle to transparent black, respecting the current transform and scissor.
+    pub fn clear_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let mut path = Path::new();
+        path.rect(x, y, width, height);
+
+        let mut paint = Paint::color(Color::rgba(0, 0, 0, 0));
+        paint.set_anti_alias(false);
+
+        // Replacing the pixels with the transparent paint clears them in the current user space,
+        // unlike Canvas::clear_rect() which works in device pixels
+        self.canvas.save();
+        self.canvas.global_composite_operation(CompositeOperation::Copy);
+        self.canvas.fill_path(&mut path, paint);
+        self.canvas.restore();
+    }
+
+    pub fn fill_text(&mut self, text: &str, x: f32, y: f32) -> Result<TextMetrics, ErrorKind> {
+        let paint = self.text_paint(self.state.fill_style);
+
+        self.canvas.fill_text(x, y, text, paint)
+    }
+
+    pub fn stroke_text(&mut self, text: &str, x: f32, y: f32) -> Result<TextMetrics, ErrorKind> {
+        let paint = self.text_paint(self.stroke_paint());
+
+        self.canvas.stroke_text(x, y, text, paint)
+    }
+
+    pub fn measure_text(&mut self, text: &str) -> Result<TextMetrics, ErrorKind> {
+        let paint = self.text_paint(self.state.fill_style);
+
+        self.canvas.measure_text(0.0, 0.0, text, paint)
+    }
+
+    /// Draws the image at its natural size.
+    pub fn draw_image(&mut self, image: ImageId, dx: f32, dy: f32) -> Result<(), ErrorKind> {
+        let (width, height) = self.canvas.image_size(image)?;
+
+        self.draw_image_scaled(image, dx, dy, width as f32, height as f32);
+
+        Ok(())
+    }
+
+    /// Draws the image scaled into the destination rectangle.
+    pub fn draw_image_scaled(&mut self, image: ImageId, dx: f32, dy: f32, dw: f32, dh: f32) {
+        let mut path = Path::new();
+        path.rect(dx, dy, dw, dh);
+
+        self.canvas
+            .fill_path(&mut path, Paint::image(image, dx, dy, dw, dh, 0.0, 1.0));
+    }
+
+    fn stroke_paint(&self) -> Paint {
+        let mut paint = self.state.stroke_style;
+        paint.set_line_width(self.state.line_width);
+        paint.set_line_cap(self.state.line_cap);
+        paint.set_line_join(self.state.line_join);
+        paint.set_miter_limit(self.state.miter_limit);
+        paint
+    }
+
+    fn text_paint(&self, mut paint: Paint) -> Paint {
+        paint.font_ids = self.state.font_ids;
+        paint.set_font_size(self.state.font_size);
+        paint.set_text_align(self.state.text_align);
+        paint.set_text_baseline(self.state.text_baseline);
+        paint
+    }
+}
+
+#[derive(Clone, Debug)]
+enum GradientKind {
+    Linear { x0: f32, y0: f32, x1: f32, y1: f32 },
+    Radial { x: f32, y: f32, r0: f32, r1: f32 },
+}
+
+/// A gradient created with [`Context2D::create_linear_gradient()`] or
+/// [`Context2D::create_radial_gradient()`].
+#[derive(Clone, Debug)]
+pub struct CanvasGradient {
+    kind: GradientKind,
+    stops: Vec<(f32, Color)>,
+}
+
+impl CanvasGradient {
+    /// Adds a color stop, `offset` ranges from 0.0 to 1.0.
+    pub fn add_color_stop(&mut self, offset: f32, color: Color) {
+        self.stops.push((offset.clamp(0.0, 1.0), color));
+    }
+}
+
+impl From<&CanvasGradient> for Paint {
+    fn from(gradient: &CanvasGradient) -> Self {
+        let mut stops = gradient.stops.clone();
+
+        // Stops added at the same offset keep their order, like on the web
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        match gradient.kind {
+            GradientKind::Linear { x0, y0, x1, y1 } => Paint::linear_gradient_stops(x0, y0, x1, y1, &stops),
+            GradientKind::Radial { x, y, r0, r1 } => Paint::radial_gradient_stops(x, y, r0, r1, &stops),
+        }
+    }
+}
+
+impl From<CanvasGradient> for Paint {
+    fn from(gradient: CanvasGradient) -> Self {
+        Paint::from(&gradient)
+    }
+}
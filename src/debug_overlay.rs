@@ -0,0 +1,205 @@
+use bitflags::bitflags;
+use rgb::RGBA8;
+
+use crate::{
+    renderer::{
+        self,
+        Command,
+        CommandType,
+        Vertex,
+    },
+    Canvas,
+    Color,
+    CompositeOperation,
+    MeshVertex,
+    Paint,
+    Path,
+    Renderer,
+};
+
+bitflags! {
+    /// What [`Canvas::draw_debug_overlay()`] draws on top of the scene.
+    pub struct DebugOverlay: u32 {
+        const WIREFRAME = 1;            // Edges of the triangles drawn so far.
+        const OVERDRAW = 1 << 1;        // Heatmap of how many triangles cover each pixel.
+        const GLYPH_ATLASES = 1 << 2;   // Pages of the glyph atlas, along the top edge.
+        const BATCHES = 1 << 3;         // Bounds of the draw calls the draws are merged into.
+    }
+}
+
+// Red added by each triangle of the heatmap, ten layers saturate it
+const OVERDRAW_STEP: u8 = 25;
+
+// Glyph atlas pages are scaled down to fit this size
+const ATLAS_THUMBNAIL_SIZE: f32 = 256.0;
+
+type Triangle = [(f32, f32); 3];
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws the overlays in `flags` on top of what was drawn into the current render target since
+    /// the last flush, needs the `debug_inspector` feature.
+    ///
+    /// Call this right before [`Self::flush()`] to see how a frame is tessellated and batched. The
+    /// overdraw heatmap counts every triangle the GPU rasterizes, including the stencil passes of
+    /// concave fills, which don't all end up as visible pixels. Batches are the runs of draws that
+    /// are merged into a single draw call when command batching is enabled, each is outlined in a
+    /// different color.
+    pub fn draw_debug_overlay(&mut self, flags: DebugOverlay) {
+        let batches = self.debug_batches();
+
+        self.save();
+        self.reset();
+
+        if flags.contains(DebugOverlay::OVERDRAW) {
+            let color = RGBA8::new(OVERDRAW_STEP, 0, 0, OVERDRAW_STEP);
+            let vertices: Vec<MeshVertex> = batches
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|&(x, y)| MeshVertex::new(x, y, 0.0, 0.0, color))
+                .collect();
+            let indices: Vec<u32> = (0..vertices.len() as u32).collect();
+
+            self.global_composite_operation(CompositeOperation::Lighter);
+            self.draw_mesh(&vertices, &indices, None);
+            self.global_composite_operation(CompositeOperation::SourceOver);
+        }
+
+        if flags.contains(DebugOverlay::WIREFRAME) {
+            let mut path = Path::new();
+
+            for [a, b, c] in batches.iter().flatten() {
+                path.move_to(a.0, a.1);
+                path.line_to(b.0, b.1);
+                path.line_to(c.0, c.1);
+                path.close();
+            }
+
+            let mut paint = Paint::color(Color::rgbaf(0.0, 1.0, 1.0, 0.6));
+            paint.set_line_width(1.0);
+            self.stroke_path(&mut path, paint);
+        }
+
+        if flags.contains(DebugOverlay::BATCHES) {
+            for (i, triangles) in batches.iter().enumerate() {
+                let points = triangles.iter().flatten();
+
+                let (mut minx, mut miny, mut maxx, mut maxy) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+
+                for &(x, y) in points {
+                    minx = minx.min(x);
+                    miny = miny.min(y);
+                    maxx = maxx.max(x);
+                    maxy = maxy.max(y);
+                }
+
+                if minx > maxx {
+                    continue;
+                }
+
+                let mut path = Path::new();
+                path.rect(minx, miny, maxx - minx, maxy - miny);
+
+                // Neighbouring batches get distant hues
+                let mut paint = Paint::color(Color::hsl((i as f32 * 0.381_966).fract(), 1.0, 0.5));
+                paint.set_line_width(2.0);
+                self.stroke_path(&mut path, paint);
+            }
+        }
+
+        if flags.contains(DebugOverlay::GLYPH_ATLASES) {
+            let pages: Vec<_> = self.glyph_textures.iter().map(|texture| texture.image_id).collect();
+            let mut x = 0.0;
+
+            for id in pages {
+                let (width, height) = match self.image_size(id) {
+                    Ok((width, height)) => (width as f32, height as f32),
+                    Err(_) => continue,
+                };
+
+                let scale = (ATLAS_THUMBNAIL_SIZE / width.max(height)).min(1.0);
+                let (width, height) = (width * scale, height * scale);
+
+                let mut page = Path::new();
+                page.rect(x, 0.0, width, height);
+
+                self.fill_path(&mut page, Paint::color(Color::rgba(0, 0, 0, 200)));
+                self.fill_path(&mut page, Paint::image(id, x, 0.0, width, height, 0.0, 1.0));
+                self.stroke_path(&mut page, Paint::color(Color::rgb(255, 255, 0)));
+
+                x += width + 4.0;
+            }
+        }
+
+        self.restore();
+    }
+
+    // The triangles drawn into the current render target since the last flush, by batch
+    fn debug_batches(&self) -> Vec<Vec<Triangle>> {
+        let mut target = self.frame_render_target;
+        let mut batches = Vec::new();
+
+        for batch in renderer::batches(&self.commands) {
+            let mut triangles = Vec::new();
+
+            for cmd in &self.commands[batch] {
+                if let CommandType::SetRenderTarget(next) = cmd.cmd_type {
+                    target = next;
+                } else if target == self.current_render_target {
+                    command_triangles(cmd, &self.verts, &mut triangles);
+                }
+            }
+
+            if !triangles.is_empty() {
+                batches.push(triangles);
+            }
+        }
+
+        batches
+    }
+}
+
+// Appends the triangles the GPU rasterizes for the command
+fn command_triangles(cmd: &Command, verts: &[Vertex], triangles: &mut Vec<Triangle>) {
+    let mut push = |a: usize, b: usize, c: usize| {
+        if let (Some(a), Some(b), Some(c)) = (verts.get(a), verts.get(b), verts.get(c)) {
+            triangles.push([(a.x, a.y), (b.x, b.y), (c.x, c.y)]);
+        }
+    };
+
+    for drawable in &cmd.drawables {
+        if let Some((start, count)) = drawable.fill_verts {
+            for i in 1..count.saturating_sub(1) {
+                push(start, start + i, start + i + 1);
+            }
+        }
+
+        if let Some((start, count)) = drawable.stroke_verts {
+            for i in 0..count.saturating_sub(2) {
+                push(start + i, start + i + 1, start + i + 2);
+            }
+        }
+    }
+
+    if let Some((start, count)) = cmd.triangles_verts {
+        match (&cmd.indices, &cmd.cmd_type) {
+            (Some(indices), _) => {
+                for triangle in indices.chunks_exact(3) {
+                    let index = |i: usize| start + triangle[i] as usize;
+                    push(index(0), index(1), index(2));
+                }
+            }
+            // The quad covering the stencil
+            (None, CommandType::ConcaveFill { .. }) => {
+                for i in 0..count.saturating_sub(2) {
+                    push(start + i, start + i + 1, start + i + 2);
+                }
+            }
+            (None, _) => {
+                for i in (0..count - count % 3).step_by(3) {
+                    push(start + i, start + i + 1, start + i + 2);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    Canvas,
+    Renderer,
+};
+
+impl<T: Renderer> Canvas<T> {
+    /// Enables or disables the deterministic mode, disabled by default.
+    ///
+    /// Some optimizations make the output of a frame depend on more than its draws: the frame
+    /// budget lowers the quality depending on how long drawing took, glyphs rasterized by the
+    /// worker threads appear whenever the threads are done, the tessellation cache reuses the
+    /// vertices of earlier frames and the placement of glyphs in the atlases depends on the text
+    /// drawn before. In deterministic mode all of them are turned off and every frame started with
+    /// [`Self::begin_frame()`] starts with empty glyph atlases, so that drawing the same frame
+    /// produces the same commands and vertices each time, regardless of what came before. With the
+    /// same build of femtovg and the same GPU and driver the rendered pixels are then identical too,
+    /// which allows comparing screenshots in tests.
+    ///
+    /// femtovg doesn't use floating point operations whose results vary between runs, but results
+    /// do differ between platforms, between builds with and without the `simd` feature and between
+    /// GPU drivers. The settings for the disabled optimizations are kept and apply again once the
+    /// mode is disabled. Rasterizing the glyphs again in every frame makes text heavy frames slower,
+    /// so the mode is meant for testing.
+    pub fn set_deterministic(&mut self, enabled: bool) {
+        self.deterministic = enabled;
+        self.frame_budget.set_suspended(enabled);
+    }
+
+    /// Returns true if the deterministic mode is enabled, see [`Self::set_deterministic()`].
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    // Starts a deterministic frame with empty glyph atlases. Atlases that are still used by draws
+    // that weren't flushed yet are released after the next flush instead.
+    pub(crate) fn reset_for_deterministic_frame(&mut self) {
+        if !self.deterministic {
+            return;
+        }
+
+        if self.commands.is_empty() && self.glyph_commands.is_empty() {
+            self.discard_glyph_atlases();
+        } else {
+            self.stale_glyph_textures = true;
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    Align2D,
+    Canvas,
+    Color,
+    CompositeOperation,
+    Effect,
+    ErrorKind,
+    FillRule,
+    Fit,
+    FontMetrics,
+    ImageId,
+    Paint,
+    Path,
+    Rect,
+    Renderer,
+    TextMetrics,
+    Transform2D,
+    TransformedTextMetrics,
+};
+
+/// The drawing surface of a [`Canvas`] without its renderer type.
+///
+/// Code that only draws can take a `&mut dyn DrawTarget` instead of being generic over the
+/// [`Renderer`] of the canvas. The methods do the same as the [`Canvas`] methods with the same
+/// names, the ones that are generic there take a `&str` or a [`Rect`] here. Creating images and
+/// loading fonts stays with the code that owns the canvas.
+///
+/// # Example
+/// ```
+/// use femtovg::{renderer::Void, Canvas, Color, DrawTarget, Paint, Path};
+///
+/// fn draw_badge(target: &mut dyn DrawTarget, x: f32, y: f32) {
+///     target.save();
+///     target.translate(x, y);
+///
+///     let mut path = Path::new();
+///     path.circle(0.0, 0.0, 8.0);
+///     target.fill_path(&mut path, Paint::color(Color::RED));
+///
+///     target.restore();
+/// }
+///
+/// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+/// draw_badge(&mut canvas, 20.0, 20.0);
+/// ```
+pub trait DrawTarget {
+    // Surface
+
+    fn width(&self) -> f32;
+    fn height(&self) -> f32;
+    fn clear_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color);
+    fn clear_transparent(&mut self);
+
+    // State
+
+    fn save(&mut self);
+    fn restore(&mut self);
+    fn reset(&mut self);
+    fn set_global_alpha(&mut self, alpha: f32);
+    fn global_composite_operation(&mut self, op: CompositeOperation);
+
+    // Transforms
+
+    fn reset_transform(&mut self);
+    /// Premultiplies the current transform, see [`Canvas::apply_transform()`].
+    fn apply_transform(&mut self, transform: Transform2D);
+    fn translate(&mut self, x: f32, y: f32);
+    fn rotate(&mut self, angle: f32);
+    fn scale(&mut self, x: f32, y: f32);
+    fn transform(&self) -> Transform2D;
+
+    // Scissoring
+
+    fn scissor(&mut self, x: f32, y: f32, w: f32, h: f32);
+    fn intersect_scissor(&mut self, x: f32, y: f32, w: f32, h: f32);
+    fn reset_scissor(&mut self);
+    fn current_clip_bounds(&self) -> Option<Rect>;
+
+    // Paths
+
+    fn fill_path(&mut self, path: &mut Path, paint: Paint);
+    fn stroke_path(&mut self, path: &mut Path, paint: Paint);
+    fn stroke_path_textured(
+        &mut self,
+        path: &Path,
+        image: ImageId,
+        pattern_length: f32,
+        paint: Paint,
+    ) -> Result<(), ErrorKind>;
+    fn contains_point(&mut self, path: &mut Path, x: f32, y: f32, fill_rule: FillRule) -> bool;
+
+    // Images
+
+    fn image_size(&self, id: ImageId) -> Result<(usize, usize), ErrorKind>;
+    fn draw_image_fit(&mut self, id: ImageId, dst: Rect, fit: Fit, align: Align2D) -> Result<(), ErrorKind>;
+    fn apply_effect(&mut self, region: Rect, effect: Effect) -> Result<(), ErrorKind>;
+
+    // Text
+
+    fn fill_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) -> Result<TextMetrics, ErrorKind>;
+    fn stroke_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) -> Result<TextMetrics, ErrorKind>;
+    fn measure_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) -> Result<TextMetrics, ErrorKind>;
+    fn measure_text_transformed(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: &str,
+        paint: Paint,
+    ) -> Result<TransformedTextMetrics, ErrorKind>;
+    fn measure_font(&mut self, paint: Paint) -> Result<FontMetrics, ErrorKind>;
+    fn break_text(&mut self, max_width: f32, text: &str, paint: Paint) -> Result<usize, ErrorKind>;
+    fn fill_icon(&mut self, x: f32, y: f32, size: f32, icon: char, paint: Paint) -> Result<TextMetrics, ErrorKind>;
+}
+
+impl<T: Renderer> DrawTarget for Canvas<T> {
+    fn width(&self) -> f32 {
+        Canvas::width(self)
+    }
+
+    fn height(&self) -> f32 {
+        Canvas::height(self)
+    }
+
+    fn clear_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color) {
+        Canvas::clear_rect(self, x, y, width, height, color)
+    }
+
+    fn clear_transparent(&mut self) {
+        Canvas::clear_transparent(self)
+    }
+
+    #[track_caller]
+    fn save(&mut self) {
+        Canvas::save(self)
+    }
+
+    fn restore(&mut self) {
+        Canvas::restore(self)
+    }
+
+    fn reset(&mut self) {
+        Canvas::reset(self)
+    }
+
+    fn set_global_alpha(&mut self, alpha: f32) {
+        Canvas::set_global_alpha(self, alpha)
+    }
+
+    fn global_composite_operation(&mut self, op: CompositeOperation) {
+        Canvas::global_composite_operation(self, op)
+    }
+
+    fn reset_transform(&mut self) {
+        Canvas::reset_transform(self)
+    }
+
+    fn apply_transform(&mut self, transform: Transform2D) {
+        Canvas::apply_transform(self, transform)
+    }
+
+    fn translate(&mut self, x: f32, y: f32) {
+        Canvas::translate(self, x, y)
+    }
+
+    fn rotate(&mut self, angle: f32) {
+        Canvas::rotate(self, angle)
+    }
+
+    fn scale(&mut self, x: f32, y: f32) {
+        Canvas::scale(self, x, y)
+    }
+
+    fn transform(&self) -> Transform2D {
+        Canvas::transform(self)
+    }
+
+    fn scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        Canvas::scissor(self, x, y, w, h)
+    }
+
+    fn intersect_scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        Canvas::intersect_scissor(self, x, y, w, h)
+    }
+
+    fn reset_scissor(&mut self) {
+        Canvas::reset_scissor(self)
+    }
+
+    fn current_clip_bounds(&self) -> Option<Rect> {
+        Canvas::current_clip_bounds(self)
+    }
+
+    fn fill_path(&mut self, path: &mut Path, paint: Paint) {
+        Canvas::fill_path(self, path, paint)
+    }
+
+    fn stroke_path(&mut self, path: &mut Path, paint: Paint) {
+        Canvas::stroke_path(self, path, paint)
+    }
+
+    fn stroke_path_textured(
+        &mut self,
+        path: &Path,
+        image: ImageId,
+        pattern_length: f32,
+        paint: Paint,
+    ) -> Result<(), ErrorKind> {
+        Canvas::stroke_path_textured(self, path, image, pattern_length, paint)
+    }
+
+    fn contains_point(&mut self, path: &mut Path, x: f32, y: f32, fill_rule: FillRule) -> bool {
+        Canvas::contains_point(self, path, x, y, fill_rule)
+    }
+
+    fn image_size(&self, id: ImageId) -> Result<(usize, usize), ErrorKind> {
+        Canvas::image_size(self, id)
+    }
+
+    fn draw_image_fit(&mut self, id: ImageId, dst: Rect, fit: Fit, align: Align2D) -> Result<(), ErrorKind> {
+        Canvas::draw_image_fit(self, id, dst, fit, align)
+    }
+
+    fn apply_effect(&mut self, region: Rect, effect: Effect) -> Result<(), ErrorKind> {
+        Canvas::apply_effect(self, region, effect)
+    }
+
+    fn fill_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        Canvas::fill_text(self, x, y, text, paint)
+    }
+
+    fn stroke_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        Canvas::stroke_text(self, x, y, text, paint)
+    }
+
+    fn measure_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        Canvas::measure_text(self, x, y, text, paint)
+    }
+
+    fn measure_text_transformed(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: &str,
+        paint: Paint,
+    ) -> Result<TransformedTextMetrics, ErrorKind> {
+        Canvas::measure_text_transformed(self, x, y, text, paint)
+    }
+
+    fn measure_font(&mut self, paint: Paint) -> Result<FontMetrics, ErrorKind> {
+        Canvas::measure_font(self, paint)
+    }
+
+    fn break_text(&mut self, max_width: f32, text: &str, paint: Paint) -> Result<usize, ErrorKind> {
+        Canvas::break_text(self, max_width, text, paint)
+    }
+
+    fn fill_icon(&mut self, x: f32, y: f32, size: f32, icon: char, paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        Canvas::fill_icon(self, x, y, size, icon, paint)
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{
+    Canvas,
+    Color,
+    ErrorKind,
+    ImageId,
+    Paint,
+    Path,
+    Point,
+    Rect,
+    Renderer,
+};
+
+/// A full-screen style effect drawn over a region with [`Canvas::apply_effect()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Effect {
+    /// Darkens the region towards its edges with `color`.
+    ///
+    /// The vignette is an ellipse that fits the region. It starts `radius` of the way from the
+    /// center to the edges and reaches the full color `feather` further out, both as fractions of
+    /// half of the region's size. The corners are a factor of about 1.41 away from the center.
+    Vignette { color: Color, radius: f32, feather: f32 },
+    /// Draws `image` stretched over the region and blurred away from `center`, like a zoom.
+    ///
+    /// The image is drawn `samples` times, each copy scaled up a bit more around `center` until the
+    /// last one is larger by `strength`, e.g. 0.1 for 10%, and the copies are averaged. Render the
+    /// scene into an image first to blur it as a whole.
+    RadialBlur {
+        image: ImageId,
+        center: Point,
+        strength: f32,
+        samples: u32,
+    },
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws an effect over `region`.
+    ///
+    /// Effects are made of ordinary fills, so they work with every renderer and are transformed and
+    /// clipped like any other drawing. Returns [`ErrorKind::ImageIdNotFound`] if the image of the
+    /// effect is not valid.
+    pub fn apply_effect(&mut self, region: impl Into<Rect>, effect: Effect) -> Result<(), ErrorKind> {
+        let region = region.into();
+
+        match effect {
+            Effect::Vignette { color, radius, feather } => {
+                let mut clear = color;
+                clear.set_alphaf(0.0);
+
+                // The gradient is circular in a unit square that is stretched over the region
+                self.save();
+                self.translate(region.x + region.w / 2.0, region.y + region.h / 2.0);
+                self.scale(region.w / 2.0, region.h / 2.0);
+
+                let inner = radius.max(0.0);
+                let paint = Paint::radial_gradient(0.0, 0.0, inner, inner + feather.max(0.0), clear, color);
+
+                let mut path = Path::new();
+                path.rect(-1.0, -1.0, 2.0, 2.0);
+                self.fill_path(&mut path, paint);
+
+                self.restore();
+            }
+            Effect::RadialBlur {
+                image,
+                center,
+                strength,
+                samples,
+            } => {
+                self.image_info(image)?;
+
+                let samples = samples.max(1);
+
+                let mut path = Path::new();
+                path.rect(region.x, region.y, region.w, region.h);
+
+                for i in 0..samples {
+                    let scale = 1.0 + strength * i as f32 / (samples - 1).max(1) as f32;
+
+                    // Drawing copy i at 1 / (i + 1) of its alpha keeps the average of all copies
+                    let paint = Paint::image(
+                        image,
+                        center.x + (region.x - center.x) * scale,
+                        center.y + (region.y - center.y) * scale,
+                        region.w * scale,
+                        region.h * scale,
+                        0.0,
+                        1.0 / (i + 1) as f32,
+                    );
+
+                    self.fill_path(&mut path, paint);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -8,6 +8,19 @@ use std::fmt::{
 use std::io;
 
 /// Enum with all possible canvas errors that could occur.
+///
+/// Path drawing calls don't fail, invalid input makes them draw nothing. The calls that
+/// return errors are the ones that create or look up resources, the text calls, and the checked
+/// variants of calls that otherwise recover silently:
+///
+/// - Image calls taking an [`ImageId`](crate::ImageId) return [`ErrorKind::ImageIdNotFound`] for ids
+///   of images that were deleted.
+/// - Text calls return [`ErrorKind::NoFontFound`] without a usable font and
+///   [`ErrorKind::FontSizeTooLargeForAtlas`] for glyphs that don't fit into a glyph atlas.
+/// - [`Canvas::try_restore()`](crate::Canvas::try_restore) returns
+///   [`ErrorKind::UnbalancedSaveRestore`] where `restore()` would only reset the state.
+/// - [`Canvas::try_set_size()`](crate::Canvas::try_set_size) returns [`ErrorKind::InvalidSize`] for
+///   a device pixel ratio that isn't positive and finite.
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum ErrorKind {
@@ -15,6 +28,10 @@ pub enum ErrorKind {
     GeneralError(String),
     #[cfg(feature = "image-loading")]
     ImageError(::image::ImageError),
+    #[cfg(feature = "svg")]
+    SvgError(usvg::Error),
+    #[cfg(feature = "lottie")]
+    LottieError(serde_json::Error),
     IoError(io::Error),
     FontParseError,
     NoFontFound,
@@ -27,11 +44,42 @@ pub enum ErrorKind {
     ImageUpdateOutOfBounds,
     ImageUpdateWithDifferentFormat,
     UnsuportedImageFromat,
+    /// A restore() without a matching save().
+    UnbalancedSaveRestore,
+    /// A surface size or device pixel ratio nothing can be rendered at.
+    InvalidSize,
+    /// A string that isn't a color, see the [`FromStr`](std::str::FromStr) impl of
+    /// [`Color`](crate::Color).
+    InvalidColor(String),
 }
 
 impl Display for ErrorKind {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "canvas error")
+        match self {
+            Self::UnknownError => write!(f, "unknown canvas error"),
+            Self::GeneralError(message) => write!(f, "{}", message),
+            #[cfg(feature = "image-loading")]
+            Self::ImageError(error) => write!(f, "image error: {}", error),
+            #[cfg(feature = "svg")]
+            Self::SvgError(error) => write!(f, "svg error: {}", error),
+            #[cfg(feature = "lottie")]
+            Self::LottieError(error) => write!(f, "lottie error: {}", error),
+            Self::IoError(error) => write!(f, "io error: {}", error),
+            Self::FontParseError => write!(f, "the font could not be parsed"),
+            Self::NoFontFound => write!(f, "no font found for the text"),
+            Self::FontInfoExtracionError => write!(f, "the font metrics could not be read"),
+            Self::FontSizeTooLargeForAtlas => write!(f, "the glyph is too large for the glyph atlas"),
+            Self::ShaderCompileError(log) => write!(f, "shader compilation failed: {}", log),
+            Self::ShaderLinkError(log) => write!(f, "shader linking failed: {}", log),
+            Self::RenderTargetError(message) => write!(f, "render target error: {}", message),
+            Self::ImageIdNotFound => write!(f, "the image id is not valid"),
+            Self::ImageUpdateOutOfBounds => write!(f, "the image update is out of the image bounds"),
+            Self::ImageUpdateWithDifferentFormat => write!(f, "the image update has a different pixel format"),
+            Self::UnsuportedImageFromat => write!(f, "unsupported image format"),
+            Self::UnbalancedSaveRestore => write!(f, "restore() without a matching save()"),
+            Self::InvalidSize => write!(f, "invalid surface size or device pixel ratio"),
+            Self::InvalidColor(color) => write!(f, "invalid color: {}", color),
+        }
     }
 }
 
@@ -42,6 +90,20 @@ impl From<::image::ImageError> for ErrorKind {
     }
 }
 
+#[cfg(feature = "svg")]
+impl From<usvg::Error> for ErrorKind {
+    fn from(error: usvg::Error) -> Self {
+        Self::SvgError(error)
+    }
+}
+
+#[cfg(feature = "lottie")]
+impl From<serde_json::Error> for ErrorKind {
+    fn from(error: serde_json::Error) -> Self {
+        Self::LottieError(error)
+    }
+}
+
 impl From<io::Error> for ErrorKind {
     fn from(error: io::Error) -> Self {
         Self::IoError(error)
@@ -62,8 +62,11 @@ pub fn normalize(x: &mut f32, y: &mut f32) -> f32 {
 }
 
 /// 2×3 matrix (2 rows, 3 columns) used for 2D linear transformations. It can represent transformations such as translation, rotation, or scaling.
+///
+/// The canvas ignores transform changes that would leave its current transform with a NaN or
+/// infinite component, they trip a debug assertion instead. See [`Self::is_finite()`].
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
-#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transform2D(pub [f32; 6]);
 
 // TODO: Implement std::ops::* on this
@@ -125,16 +128,18 @@ impl Transform2D {
         self[5] = 0.0;
     }
 
+    /// Multiplies the matrices in double precision, so that deep zoom levels composed of many
+    /// steps round each component only once.
     pub fn multiply(&mut self, other: &Self) {
-        let t0 = self[0] * other[0] + self[1] * other[2];
-        let t2 = self[2] * other[0] + self[3] * other[2];
-        let t4 = self[4] * other[0] + self[5] * other[2] + other[4];
-        self[1] = self[0] * other[1] + self[1] * other[3];
-        self[3] = self[2] * other[1] + self[3] * other[3];
-        self[5] = self[4] * other[1] + self[5] * other[3] + other[5];
-        self[0] = t0;
-        self[2] = t2;
-        self[4] = t4;
+        let s = self.0.map(f64::from);
+        let o = other.0.map(f64::from);
+
+        self[0] = (s[0] * o[0] + s[1] * o[2]) as f32;
+        self[1] = (s[0] * o[1] + s[1] * o[3]) as f32;
+        self[2] = (s[2] * o[0] + s[3] * o[2]) as f32;
+        self[3] = (s[2] * o[1] + s[3] * o[3]) as f32;
+        self[4] = (s[4] * o[0] + s[5] * o[2] + o[4]) as f32;
+        self[5] = (s[4] * o[1] + s[5] * o[3] + o[5]) as f32;
     }
 
     pub fn premultiply(&mut self, other: &Self) {
@@ -149,6 +154,7 @@ impl Transform2D {
 
         if det > -1e-6 && det < 1e-6 {
             *self = Self::identity();
+            return;
         }
 
         let invdet = 1.0 / det;
@@ -167,10 +173,16 @@ impl Transform2D {
         inv
     }
 
+    /// Transforms a point, accumulating in double precision. Under a large scale the products and
+    /// the translation are huge and nearly cancel, in single precision that would snap points to a
+    /// coarse grid on screen.
     pub fn transform_point(&self, sx: f32, sy: f32) -> (f32, f32) {
-        let dx = sx * self[0] + sy * self[2] + self[4];
-        let dy = sx * self[1] + sy * self[3] + self[5];
-        (dx, dy)
+        let (x, y) = (f64::from(sx), f64::from(sy));
+        let t = self.0.map(f64::from);
+
+        let dx = x * t[0] + y * t[2] + t[4];
+        let dy = x * t[1] + y * t[3] + t[5];
+        (dx as f32, dy as f32)
     }
 
     pub fn average_scale(&self) -> f32 {
@@ -186,6 +198,11 @@ impl Transform2D {
         ]
     }
 
+    /// Returns true if none of the components is NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        self.0.iter().all(|v| v.is_finite())
+    }
+
     pub fn cache_key(&self) -> u64 {
         let mut hasher = FnvHasher::default();
 
@@ -217,6 +234,57 @@ impl IndexMut<usize> for Transform2D {
     }
 }
 
+impl From<[f32; 6]> for Transform2D {
+    fn from(matrix: [f32; 6]) -> Self {
+        Self(matrix)
+    }
+}
+
+impl From<Transform2D> for [f32; 6] {
+    fn from(transform: Transform2D) -> Self {
+        transform.0
+    }
+}
+
+/// A point, accepted by the calls that take one as `impl Into<Point>` either as a `Point`, an
+/// `(x, y)` tuple or an `[x, y]` array.
+#[derive(Copy, Clone, Default, Debug, PartialEq, PartialOrd)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<(f32, f32)> for Point {
+    fn from((x, y): (f32, f32)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<[f32; 2]> for Point {
+    fn from([x, y]: [f32; 2]) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Point> for (f32, f32) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl From<Point> for [f32; 2] {
+    fn from(point: Point) -> Self {
+        [point.x, point.y]
+    }
+}
+
+/// Axis-aligned rectangle defined by its top-left corner and size.
 #[derive(Copy, Clone, Default, Debug, PartialEq, PartialOrd)]
 pub struct Rect {
     pub x: f32,
@@ -240,6 +308,80 @@ impl Rect {
     }
 }
 
+impl From<(f32, f32, f32, f32)> for Rect {
+    fn from((x, y, w, h): (f32, f32, f32, f32)) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
+impl From<[f32; 4]> for Rect {
+    fn from([x, y, w, h]: [f32; 4]) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
+// Conversions from and to the euclid types with any unit
+#[cfg(feature = "euclid")]
+mod euclid_conversions {
+    use super::{
+        Point,
+        Rect,
+        Transform2D,
+    };
+
+    impl<U> From<euclid::Point2D<f32, U>> for Point {
+        fn from(point: euclid::Point2D<f32, U>) -> Self {
+            Self::new(point.x, point.y)
+        }
+    }
+
+    impl<U> From<euclid::Vector2D<f32, U>> for Point {
+        fn from(vector: euclid::Vector2D<f32, U>) -> Self {
+            Self::new(vector.x, vector.y)
+        }
+    }
+
+    impl<U> From<Point> for euclid::Point2D<f32, U> {
+        fn from(point: Point) -> Self {
+            euclid::Point2D::new(point.x, point.y)
+        }
+    }
+
+    impl<U> From<euclid::Rect<f32, U>> for Rect {
+        fn from(rect: euclid::Rect<f32, U>) -> Self {
+            Self::new(rect.origin.x, rect.origin.y, rect.size.width, rect.size.height)
+        }
+    }
+
+    impl<U> From<euclid::Box2D<f32, U>> for Rect {
+        fn from(rect: euclid::Box2D<f32, U>) -> Self {
+            Self::new(rect.min.x, rect.min.y, rect.width(), rect.height())
+        }
+    }
+
+    impl<U> From<Rect> for euclid::Rect<f32, U> {
+        fn from(rect: Rect) -> Self {
+            euclid::Rect::new(
+                euclid::Point2D::new(rect.x, rect.y),
+                euclid::Size2D::new(rect.w, rect.h),
+            )
+        }
+    }
+
+    // Both map points as row vectors, the components are in the same order
+    impl<Src, Dst> From<euclid::Transform2D<f32, Src, Dst>> for Transform2D {
+        fn from(t: euclid::Transform2D<f32, Src, Dst>) -> Self {
+            Self([t.m11, t.m12, t.m21, t.m22, t.m31, t.m32])
+        }
+    }
+
+    impl<Src, Dst> From<Transform2D> for euclid::Transform2D<f32, Src, Dst> {
+        fn from(t: Transform2D) -> Self {
+            euclid::Transform2D::new(t[0], t[1], t[2], t[3], t[4], t[5])
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
 pub struct Bounds {
     pub minx: f32,
@@ -258,3 +400,37 @@ impl Default for Bounds {
         }
     }
 }
+
+impl Bounds {
+    pub(crate) fn from_rect(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self {
+            minx: x,
+            miny: y,
+            maxx: x + w,
+            maxy: y + h,
+        }
+    }
+
+    // Bounds that are empty since nothing was added to them never overlap
+    pub(crate) fn overlaps(&self, other: &Self) -> bool {
+        self.maxx >= other.minx && self.minx <= other.maxx && self.maxy >= other.miny && self.miny <= other.maxy
+    }
+
+    pub(crate) fn inflated(&self, amount: f32) -> Self {
+        Self {
+            minx: self.minx - amount,
+            miny: self.miny - amount,
+            maxx: self.maxx + amount,
+            maxy: self.maxy + amount,
+        }
+    }
+
+    pub(crate) fn intersection(&self, other: &Self) -> Self {
+        Self {
+            minx: self.minx.max(other.minx),
+            miny: self.miny.max(other.miny),
+            maxx: self.maxx.min(other.maxx),
+            maxy: self.maxy.min(other.maxy),
+        }
+    }
+}
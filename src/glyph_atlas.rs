@@ -0,0 +1,119 @@
+use crate::{
+    text,
+    Canvas,
+    ErrorKind,
+    ImageId,
+    ImageSource,
+    Paint,
+    Renderer,
+};
+
+/// A texture of the glyph atlas, returned by [`Canvas::glyph_atlas_pages()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlyphAtlasPage {
+    pub image_id: ImageId,
+    pub width: usize,
+    pub height: usize,
+    /// Share of the page covered by glyphs and packed bitmaps, between 0 and 1.
+    pub occupancy: f32,
+}
+
+/// A bitmap packed into the glyph atlas with [`Canvas::pack_into_glyph_atlas()`].
+///
+/// The region is lost together with the glyphs when the atlases are discarded, e.g. by
+/// [`Canvas::clear_glyph_atlases()`] or a trim, see [`Canvas::is_atlas_region_valid()`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AtlasRegion {
+    /// The atlas page holding the bitmap.
+    pub image_id: ImageId,
+    /// Position of the bitmap in the page, in pixels.
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    // Size of the page, the paint maps the whole page
+    page: (usize, usize),
+    generation: u64,
+}
+
+impl AtlasRegion {
+    /// An image paint that draws the bitmap with its top-left corner at `x, y` at its size in
+    /// pixels. Fill a rectangle of the size of the region at the same position with it.
+    pub fn paint(&self, x: f32, y: f32) -> Paint {
+        let page = self.page;
+
+        Paint::image(
+            self.image_id,
+            x - self.x as f32,
+            y - self.y as f32,
+            page.0 as f32,
+            page.1 as f32,
+            0.0,
+            1.0,
+        )
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Returns the textures the glyphs are rendered into, in the order they were allocated.
+    ///
+    /// Glyph quads sample the red channel of the pages as coverage. The pages change whenever
+    /// glyphs are rendered, and are replaced when the atlases are discarded.
+    pub fn glyph_atlas_pages(&self) -> Vec<GlyphAtlasPage> {
+        self.glyph_textures
+            .iter()
+            .map(|texture| {
+                let (width, height) = texture.atlas.size();
+
+                GlyphAtlasPage {
+                    image_id: texture.image_id,
+                    width,
+                    height,
+                    occupancy: texture.atlas.occupancy(),
+                }
+            })
+            .collect()
+    }
+
+    /// Packs an RGBA bitmap, e.g. a badge or a map marker, into the free space of the glyph atlas.
+    ///
+    /// Small bitmaps share the pages the text already uses instead of taking a texture each, so
+    /// draws of different bitmaps use the same texture and can be batched by the renderer. Draw
+    /// the bitmap with [`AtlasRegion::paint()`]. A new page is allocated if no page has room for
+    /// it.
+    ///
+    /// Returns [`ErrorKind::UnsuportedImageFromat`] for sources other than RGBA and
+    /// [`ErrorKind::FontSizeTooLargeForAtlas`] if the bitmap is larger than a page.
+    pub fn pack_into_glyph_atlas<'a, S: Into<ImageSource<'a>>>(&mut self, src: S) -> Result<AtlasRegion, ErrorKind> {
+        let src = src.into();
+
+        let (width, height) = match &src {
+            ImageSource::Rgba(img) => (img.width(), img.height()),
+            _ => return Err(ErrorKind::UnsuportedImageFromat),
+        };
+
+        // The margin keeps the bitmap from bleeding into its neighbours when it is filtered
+        let margin = text::GLYPH_MARGIN as usize;
+
+        let (index, image_id, (x, y)) = text::find_texture_or_alloc(self, width + margin * 2, height + margin * 2)?;
+        let page = self.glyph_textures[index].atlas.size();
+
+        self.update_image(image_id, src, x + margin, y + margin)?;
+
+        Ok(AtlasRegion {
+            image_id,
+            x: x + margin,
+            y: y + margin,
+            width,
+            height,
+            generation: self.glyph_atlas_generation,
+            page,
+        })
+    }
+
+    /// Returns false once the atlas page of the region was discarded, the bitmap has to be packed
+    /// again then.
+    pub fn is_atlas_region_valid(&self, region: &AtlasRegion) -> bool {
+        region.generation == self.glyph_atlas_generation && self.is_image_valid(region.image_id)
+    }
+}
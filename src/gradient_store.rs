@@ -62,6 +62,11 @@ impl GradientStore {
         }
     }
 
+    /// Returns the ids of all the gradient textures owned by this store.
+    pub fn image_ids(&self) -> impl Iterator<Item = ImageId> + '_ {
+        self.this_frame.values().chain(self.prev_frame.values()).copied()
+    }
+
     /// Release the textures that were not used in the most recently rendered frame. This
     /// method should be called when all the commands have been submitted.
     pub fn release_old_gradients<R: Renderer>(&mut self, images: &mut ImageStore<R::Image>, renderer: &mut R) {
@@ -0,0 +1,93 @@
+//! Helpers for small applications and demos that want a window without the glutin boilerplate.
+
+use glutin::{
+    dpi::PhysicalSize,
+    event::{
+        Event,
+        WindowEvent,
+    },
+    event_loop::{
+        ControlFlow,
+        EventLoop,
+    },
+    window::WindowBuilder,
+    ContextBuilder,
+};
+
+use crate::{
+    renderer::OpenGl,
+    Canvas,
+};
+
+/// Opens a window of the given physical size and calls `draw_fn` to draw every frame.
+///
+/// The helper owns the event loop: it creates the OpenGL context and the canvas, keeps the canvas
+/// size and dpi in sync with the window when it is resized or moved between monitors, flushes the
+/// canvas after `draw_fn` returns and presents the frame. Frames are drawn continuously until the
+/// window is closed, at which point the process exits. Use the first call to `draw_fn` to load
+/// fonts and images.
+///
+/// # Panics
+///
+/// Panics if the window, the OpenGL context or the renderer can't be created.
+///
+/// # Example
+/// ```no_run
+/// use femtovg::{Color, Paint, Path};
+///
+/// femtovg::helpers::run_window("femtovg", (800, 600), |canvas| {
+///     canvas.clear_rect(0, 0, canvas.width() as u32, canvas.height() as u32, Color::black());
+///
+///     let mut path = Path::new();
+///     path.circle(400.0, 300.0, 100.0);
+///     canvas.fill_path(&mut path, Paint::color(Color::rgb(200, 60, 60)));
+/// });
+/// ```
+pub fn run_window<F>(title: &str, size: (u32, u32), mut draw_fn: F) -> !
+where
+    F: FnMut(&mut Canvas<OpenGl>) + 'static,
+{
+    let el = EventLoop::new();
+    let wb = WindowBuilder::new()
+        .with_inner_size(PhysicalSize::new(size.0, size.1))
+        .with_title(title);
+
+    let windowed_context = ContextBuilder::new()
+        .with_vsync(true)
+        .build_windowed(wb, &el)
+        .expect("Cannot create window");
+    let windowed_context = unsafe { windowed_context.make_current().expect("Cannot make context current") };
+
+    let renderer = OpenGl::new(|s| windowed_context.get_proc_address(s) as *const _).expect("Cannot create renderer");
+    let mut canvas = Canvas::new(renderer).expect("Cannot create canvas");
+
+    el.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        match event {
+            Event::WindowEvent { ref event, .. } => match event {
+                WindowEvent::Resized(physical_size) => {
+                    windowed_context.resize(*physical_size);
+                }
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    windowed_context.resize(**new_inner_size);
+                }
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                _ => (),
+            },
+            Event::RedrawRequested(_) => {
+                let window = windowed_context.window();
+                let size = window.inner_size();
+
+                canvas.set_size(size.width, size.height, window.scale_factor() as f32);
+
+                draw_fn(&mut canvas);
+
+                canvas.flush();
+                windowed_context.swap_buffers().expect("Cannot swap buffers");
+            }
+            Event::MainEventsCleared => windowed_context.window().request_redraw(),
+            _ => (),
+        }
+    })
+}
@@ -12,16 +12,54 @@ use ::image::DynamicImage;
 
 #[cfg(feature = "image-loading")]
 use std::convert::TryFrom;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
 
 use crate::{
     ErrorKind,
+    Rect,
     Renderer,
 };
 
+#[cfg(feature = "image-loading")]
+pub(crate) mod qoi;
+
 /// An image handle.
+///
+/// Ids carry the generation of their slot, so the id of a deleted image stays invalid after a new
+/// image reuses the slot: drawing with it draws nothing instead of binding the new image.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct ImageId(pub Index);
 
+/// Owns an image and deletes it once the handle and all of its clones are dropped, see
+/// [`Canvas::image_handle()`](crate::Canvas::image_handle).
+#[derive(Clone, Debug)]
+pub struct ImageHandle(Arc<ReleaseOnDrop>);
+
+impl ImageHandle {
+    pub(crate) fn new(id: ImageId, release: Sender<ImageId>) -> Self {
+        Self(Arc::new(ReleaseOnDrop { id, release }))
+    }
+
+    /// Returns the id of the image, which is valid for as long as the handle is alive.
+    pub fn id(&self) -> ImageId {
+        self.0.id
+    }
+}
+
+#[derive(Debug)]
+struct ReleaseOnDrop {
+    id: ImageId,
+    release: Sender<ImageId>,
+}
+
+impl Drop for ReleaseOnDrop {
+    fn drop(&mut self) {
+        // The canvas is gone if this fails, and its images with it
+        let _ = self.release.send(self.id);
+    }
+}
+
 /// Image format: `Rgb8`, `Rgba8`, `Gray8`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum PixelFormat {
@@ -38,7 +76,10 @@ bitflags! {
         const REPEAT_Y = 1 << 2;        // Repeat image in Y direction.
         const FLIP_Y = 1 << 3;          // Flips (inverses) image in Y direction when rendered.
         const PREMULTIPLIED = 1 << 4;   // Image data has premultiplied alpha.
-        const NEAREST = 1 << 5;         // Image interpolation is Nearest instead Linear
+        const NEAREST_MIN = 1 << 5;     // Image is minified with Nearest instead of Linear interpolation, also between mipmap levels.
+        const NEAREST_MAG = 1 << 6;     // Image is magnified with Nearest instead of Linear interpolation.
+        const NEAREST = Self::NEAREST_MIN.bits | Self::NEAREST_MAG.bits; // Both of the above.
+        const MULTISAMPLE = 1 << 7;     // Drawing into the image as a render target is multisampled and resolved into it.
     }
 }
 
@@ -173,6 +214,10 @@ impl ImageInfo {
     pub fn set_format(&mut self, format: PixelFormat) {
         self.format = format;
     }
+
+    pub fn set_flags(&mut self, flags: ImageFlags) {
+        self.flags = flags;
+    }
 }
 
 pub struct ImageStore<T>(Arena<(ImageInfo, T)>);
@@ -237,10 +282,35 @@ impl<T> ImageStore<T> {
         }
     }
 
+    pub fn set_flags<R: Renderer<Image = T>>(
+        &mut self,
+        renderer: &mut R,
+        id: ImageId,
+        flags: ImageFlags,
+    ) -> Result<(), ErrorKind> {
+        if let Some(image) = self.0.get_mut(id.0) {
+            renderer.set_image_flags(&mut image.1, flags)?;
+            image.0.set_flags(flags);
+            Ok(())
+        } else {
+            Err(ErrorKind::ImageIdNotFound)
+        }
+    }
+
     pub fn info(&self, id: ImageId) -> Option<ImageInfo> {
         self.0.get(id.0).map(|inner| inner.0)
     }
 
+    pub fn infos(&self) -> impl Iterator<Item = (ImageId, ImageInfo)> + '_ {
+        self.0.iter().map(|(idx, inner)| (ImageId(idx), inner.0))
+    }
+
+    /// Removes the image without releasing it in the renderer, e.g. because it belongs to a renderer
+    /// that no longer exists.
+    pub fn forget(&mut self, id: ImageId) {
+        self.0.remove(id.0);
+    }
+
     pub fn remove<R: Renderer<Image = T>>(&mut self, renderer: &mut R, id: ImageId) {
         if let Some(image) = self.0.remove(id.0) {
             renderer.delete_image(image.1, id);
@@ -262,3 +332,76 @@ pub enum ImageFilter {
     /// The filter shall be a gaussian blur with given sigma as standard deviation.
     GaussianBlur { sigma: f32 },
 }
+
+/// Determines how an image is resized to fit into a rectangle by [`crate::Canvas::draw_image_fit`].
+///
+/// Modeled after the CSS `object-fit` property.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Fit {
+    /// The image is stretched to fill the rectangle, the aspect ratio is not preserved.
+    Fill,
+    /// The image is scaled to fit inside the rectangle while preserving its aspect ratio.
+    Contain,
+    /// The image is scaled to cover the whole rectangle while preserving its aspect ratio. Parts of
+    /// the image that fall outside the rectangle are clipped.
+    Cover,
+    /// Same as `Contain`, except that the image is never scaled up.
+    ScaleDown,
+}
+
+impl Fit {
+    /// Returns the rectangle the image of the provided size is drawn into when fitted inside `dst`.
+    ///
+    /// The returned rectangle may extend past `dst` for [`Fit::Cover`].
+    pub fn rect(self, image_width: f32, image_height: f32, dst: Rect, align: Align2D) -> Rect {
+        if self == Self::Fill || image_width <= 0.0 || image_height <= 0.0 {
+            return dst;
+        }
+
+        let sx = dst.w / image_width;
+        let sy = dst.h / image_height;
+
+        let scale = match self {
+            Self::Contain => sx.min(sy),
+            Self::Cover => sx.max(sy),
+            Self::ScaleDown => sx.min(sy).min(1.0),
+            Self::Fill => unreachable!(),
+        };
+
+        let w = image_width * scale;
+        let h = image_height * scale;
+
+        Rect::new(dst.x + (dst.w - w) * align.x, dst.y + (dst.h - h) * align.y, w, h)
+    }
+}
+
+/// Position of a fitted image inside its rectangle, see [`Fit`].
+///
+/// `x` and `y` range from 0.0 (left, top) to 1.0 (right, bottom). Defaults to centered.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Align2D {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Align2D {
+    pub const TOP_LEFT: Self = Self::new(0.0, 0.0);
+    pub const TOP: Self = Self::new(0.5, 0.0);
+    pub const TOP_RIGHT: Self = Self::new(1.0, 0.0);
+    pub const LEFT: Self = Self::new(0.0, 0.5);
+    pub const CENTER: Self = Self::new(0.5, 0.5);
+    pub const RIGHT: Self = Self::new(1.0, 0.5);
+    pub const BOTTOM_LEFT: Self = Self::new(0.0, 1.0);
+    pub const BOTTOM: Self = Self::new(0.5, 1.0);
+    pub const BOTTOM_RIGHT: Self = Self::new(1.0, 1.0);
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Default for Align2D {
+    fn default() -> Self {
+        Self::CENTER
+    }
+}
@@ -0,0 +1,129 @@
+//! Decoder for the Quite OK Image format (https://qoiformat.org).
+
+use imgref::ImgVec;
+use rgb::RGBA8;
+
+use crate::ErrorKind;
+
+const MAGIC: &[u8; 4] = b"qoif";
+const HEADER_SIZE: usize = 14;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xc0;
+const MASK_2: u8 = 0xc0;
+
+// Guards against allocating huge buffers for corrupt headers, same limit as the reference decoder.
+const MAX_PIXELS: usize = 400_000_000;
+
+pub(crate) fn is_qoi(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub(crate) fn decode(data: &[u8]) -> Result<ImgVec<RGBA8>, ErrorKind> {
+    let invalid = || ErrorKind::GeneralError("Invalid QOI image".to_owned());
+
+    if data.len() < HEADER_SIZE + END_MARKER.len() || !is_qoi(data) {
+        return Err(invalid());
+    }
+
+    let width = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let height = u32::from_be_bytes([data[8], data[9], data[10], data[11]]) as usize;
+    let channels = data[12];
+
+    if width == 0 || height == 0 || !(3..=4).contains(&channels) || width.saturating_mul(height) > MAX_PIXELS {
+        return Err(invalid());
+    }
+
+    let chunks = &data[HEADER_SIZE..data.len() - END_MARKER.len()];
+    let mut bytes = chunks.iter().copied();
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut index = [RGBA8::new(0, 0, 0, 0); 64];
+    let mut px = RGBA8::new(0, 0, 0, 255);
+
+    while pixels.len() < width * height {
+        let b1 = bytes.next().ok_or_else(invalid)?;
+        let mut run = 0;
+
+        match b1 {
+            OP_RGB => {
+                px.r = bytes.next().ok_or_else(invalid)?;
+                px.g = bytes.next().ok_or_else(invalid)?;
+                px.b = bytes.next().ok_or_else(invalid)?;
+            }
+            OP_RGBA => {
+                px.r = bytes.next().ok_or_else(invalid)?;
+                px.g = bytes.next().ok_or_else(invalid)?;
+                px.b = bytes.next().ok_or_else(invalid)?;
+                px.a = bytes.next().ok_or_else(invalid)?;
+            }
+            _ => match b1 & MASK_2 {
+                OP_INDEX => px = index[b1 as usize],
+                OP_DIFF => {
+                    px.r = px.r.wrapping_add((b1 >> 4) & 0x03).wrapping_sub(2);
+                    px.g = px.g.wrapping_add((b1 >> 2) & 0x03).wrapping_sub(2);
+                    px.b = px.b.wrapping_add(b1 & 0x03).wrapping_sub(2);
+                }
+                OP_LUMA => {
+                    let b2 = bytes.next().ok_or_else(invalid)?;
+                    let vg = (b1 & 0x3f).wrapping_sub(32);
+
+                    px.r = px.r.wrapping_add(vg.wrapping_sub(8).wrapping_add((b2 >> 4) & 0x0f));
+                    px.g = px.g.wrapping_add(vg);
+                    px.b = px.b.wrapping_add(vg.wrapping_sub(8).wrapping_add(b2 & 0x0f));
+                }
+                // OP_RUN, the run length is stored with a bias of -1
+                _ => run = (b1 & !OP_RUN) as usize,
+            },
+        }
+
+        index[hash(px)] = px;
+
+        let count = (run + 1).min(width * height - pixels.len());
+        pixels.resize(pixels.len() + count, px);
+    }
+
+    Ok(ImgVec::new(pixels, width, height))
+}
+
+fn hash(px: RGBA8) -> usize {
+    (px.r as usize * 3 + px.g as usize * 5 + px.b as usize * 7 + px.a as usize * 11) % 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_ops() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"qoif");
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&[4, 0]);
+
+        data.extend_from_slice(&[OP_RGBA, 10, 20, 30, 128]); // (10, 20, 30, 128)
+        data.push(OP_DIFF | (3 << 4) | (2 << 2) | 1); // +1, +0, -1
+        data.extend_from_slice(&[OP_LUMA | 40, (9 << 4) | 7]); // dg +8, dr +9, db +7
+        data.push(OP_RUN | 2); // 3 more of the same
+        data.push(OP_INDEX | hash(RGBA8::new(10, 20, 30, 128)) as u8);
+        data.push(OP_INDEX | hash(RGBA8::new(10, 20, 30, 128)) as u8);
+        data.extend_from_slice(&END_MARKER);
+
+        let image = decode(&data).unwrap();
+        let pixels = image.buf();
+
+        assert_eq!((image.width(), image.height()), (4, 2));
+        assert_eq!(pixels[0], RGBA8::new(10, 20, 30, 128));
+        assert_eq!(pixels[1], RGBA8::new(11, 20, 29, 128));
+        assert_eq!(pixels[2], RGBA8::new(20, 28, 36, 128));
+        assert_eq!(&pixels[3..6], &[pixels[2]; 3]);
+        assert_eq!(pixels[6], pixels[0]);
+        assert_eq!(pixels[7], pixels[0]);
+    }
+}
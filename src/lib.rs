@@ -5,6 +5,21 @@
  *
  * The coordinate system’s origin is the top-left corner,
  * with positive X rightwards, positive Y downwards.
+ *
+ * # Threading
+ *
+ * A [`Canvas`] draws with GPU resources that belong to the thread its renderer's context is
+ * current on, so it has to stay on that thread. The values passed to it, [`Path`], [`Paint`],
+ * [`Color`] and [`Transform2D`], are `Send` and `Sync` and can be built on any thread. So is
+ * [`TextContext`]: a clone of [`Canvas::text_context()`] registers fonts and measures text from
+ * other threads, each call locks the fonts and shaping caches it shares with the canvas.
+ *
+ * # Text
+ *
+ * Fonts are parsed and text is shaped with the `text` feature, which is enabled by default. Builds
+ * that only draw shapes can turn it off to leave out the font parser, the shaper and the unicode
+ * tables. The text API stays, but adding a font returns [`ErrorKind::FontParseError`], so text
+ * calls behave as they do before any font is added.
  */
 
 /*
@@ -17,10 +32,14 @@ TODO:
 #[macro_use]
 extern crate serde;
 
-use std::cell::RefCell;
 use std::ops::Range;
+use std::panic::Location;
 use std::path::Path as FilePath;
-use std::rc::Rc;
+use std::sync::mpsc::{
+    self,
+    Receiver,
+    Sender,
+};
 
 use imgref::ImgVec;
 use rgb::RGBA8;
@@ -29,6 +48,14 @@ use fnv::FnvHashMap;
 
 mod utils;
 
+#[macro_use]
+mod profiling;
+#[cfg(feature = "profiling")]
+pub use profiling::{
+    Profiler,
+    Stage,
+};
+
 mod text;
 
 mod error;
@@ -37,25 +64,39 @@ pub use error::ErrorKind;
 pub use text::{
     Align,
     Baseline,
+    CellStyle,
     FontId,
     FontMetrics,
+    GridCell,
+    LineBreak,
+    PaintOverrides,
+    Preedit,
+    Span,
     TextContext,
+    TextLine,
     TextMetrics,
+    TextRecord,
+    TextRun,
+    TransformedTextMetrics,
+    UnderlineStyle,
 };
 
 use text::{
     FontTexture,
+    GlyphWorkers,
     RenderMode,
     RenderedGlyph,
     RenderedGlyphId,
-    TextContextImpl,
 };
 
 mod image;
 use crate::image::ImageStore;
 pub use crate::image::{
+    Align2D,
+    Fit,
     ImageFilter,
     ImageFlags,
+    ImageHandle,
     ImageId,
     ImageInfo,
     ImageSource,
@@ -81,15 +122,25 @@ use renderer::{
 };
 
 pub(crate) mod geometry;
-pub use geometry::Transform2D;
 use geometry::*;
+pub use geometry::{
+    Point,
+    Rect,
+    Transform2D,
+};
 
 mod paint;
-pub use paint::Paint;
 use paint::PaintFlavor;
+pub use paint::{
+    Paint,
+    PaintKind,
+};
 
 mod path;
-use path::Convexity;
+use path::{
+    Convexity,
+    PathCache,
+};
 pub use path::{
     Path,
     Solidity,
@@ -98,6 +149,102 @@ pub use path::{
 mod gradient_store;
 use gradient_store::GradientStore;
 
+mod recorder;
+pub use recorder::CanvasRecorder;
+
+mod mesh;
+pub use mesh::MeshVertex;
+
+mod polyline;
+
+mod textured_stroke;
+
+mod rect_fill;
+
+mod tessellation;
+pub use tessellation::TessellationCacheStats;
+use tessellation::{
+    Tessellation,
+    TessellationCache,
+};
+
+mod budget;
+use budget::FrameBudget;
+
+mod builder;
+pub use builder::CanvasBuilder;
+
+mod draw_target;
+pub use draw_target::DrawTarget;
+
+mod memory;
+pub use memory::MemoryReport;
+
+mod glyph_atlas;
+pub use glyph_atlas::{
+    AtlasRegion,
+    GlyphAtlasPage,
+};
+
+mod effect;
+pub use effect::Effect;
+
+mod determinism;
+
+#[cfg(feature = "debug_inspector")]
+mod debug_overlay;
+#[cfg(feature = "debug_inspector")]
+pub use debug_overlay::DebugOverlay;
+
+mod validation;
+pub use validation::{
+    ValidationIssue,
+    ValidationWarning,
+};
+
+mod tiles;
+pub use tiles::{
+    TileKey,
+    TileSender,
+    TileSet,
+    TileView,
+};
+
+mod context2d;
+pub use context2d::{
+    CanvasGradient,
+    Context2D,
+};
+
+pub mod prelude;
+
+pub mod shapes;
+
+pub mod animation;
+
+#[cfg(feature = "svg")]
+pub mod svg;
+
+#[cfg(feature = "lottie")]
+pub mod lottie;
+
+#[cfg(all(feature = "helpers", not(target_arch = "wasm32")))]
+pub mod helpers;
+
+// The values that can be prepared on other threads
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[allow(dead_code)]
+    fn assert_value_types() {
+        assert_send_sync::<Path>();
+        assert_send_sync::<Paint>();
+        assert_send_sync::<Color>();
+        assert_send_sync::<Transform2D>();
+        assert_send_sync::<TextContext>();
+    }
+};
+
 /// The fill rule used when filling paths: `EvenOdd`, `NonZero` (default).
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -112,8 +259,19 @@ impl Default for FillRule {
     }
 }
 
+/// Determines how [`Canvas::draw()`] renders the path: `Fill`, `Stroke`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DrawMode {
+    /// The path is filled, like with [`Canvas::fill_path()`].
+    Fill,
+    /// The path is stroked, like with [`Canvas::stroke_path()`].
+    Stroke,
+}
+
 /// Blend factors.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BlendFactor {
     /// Not all
     Zero,
@@ -168,6 +326,7 @@ pub enum CompositeOperation {
 
 /// Determines how a new ("source") data is displayed against an existing ("destination") data.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompositeOperationState {
     src_rgb: BlendFactor,
     src_alpha: BlendFactor,
@@ -218,9 +377,11 @@ impl Default for CompositeOperationState {
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct Scissor {
     transform: Transform2D,
     extent: Option<[f32; 2]>,
+    radius: f32,
 }
 
 impl Default for Scissor {
@@ -228,6 +389,7 @@ impl Default for Scissor {
         Self {
             transform: Default::default(),
             extent: None,
+            radius: 0.0,
         }
     }
 }
@@ -279,11 +441,13 @@ impl Default for LineJoin {
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct State {
     composite_operation: CompositeOperationState,
     transform: Transform2D,
     scissor: Scissor,
     alpha: f32,
+    pixel_snapping: bool,
 }
 
 impl Default for State {
@@ -293,21 +457,175 @@ impl Default for State {
             transform: Transform2D::identity(),
             scissor: Default::default(),
             alpha: 1.0,
+            pixel_snapping: false,
         }
     }
 }
 
+impl State {
+    // Premultiplies the current transform, see `replace_transform()`
+    fn premultiply_transform(&mut self, transform: &Transform2D) {
+        let mut result = *transform;
+        result.multiply(&self.transform);
+        self.replace_transform(result);
+    }
+
+    // Makes `transform` the current one, unless it isn't finite, which would poison every
+    // following draw
+    fn replace_transform(&mut self, transform: Transform2D) {
+        let finite = transform.is_finite();
+        debug_assert!(finite, "non-finite transform: {:?}", transform);
+
+        if finite {
+            self.transform = transform;
+        }
+    }
+
+    fn scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        let w = w.max(0.0);
+        let h = h.max(0.0);
+
+        let mut transform = Transform2D::new_translation(x + w * 0.5, y + h * 0.5);
+        transform.multiply(&self.transform);
+        self.scissor.transform = transform;
+
+        self.scissor.extent = Some([w * 0.5, h * 0.5]);
+        self.scissor.radius = 0.0;
+    }
+
+    fn intersect_scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        // If no previous scissor has been set, set the scissor as current scissor.
+        let extent = match self.scissor.extent {
+            Some(extent) => extent,
+            None => return self.scissor(x, y, w, h),
+        };
+
+        // Transform the current scissor rect into current transform space.
+        // If there is difference in rotation, this will be approximation.
+
+        let mut pxform = self.scissor.transform;
+
+        let mut invxform = self.transform;
+        invxform.inverse();
+
+        pxform.multiply(&invxform);
+
+        let ex = extent[0];
+        let ey = extent[1];
+
+        let tex = ex * pxform[0].abs() + ey * pxform[2].abs();
+        let tey = ex * pxform[1].abs() + ey * pxform[3].abs();
+
+        let rect = Rect::new(pxform[4] - tex, pxform[5] - tey, tex * 2.0, tey * 2.0);
+        let res = rect.intersect(Rect::new(x, y, w, h));
+
+        // The corners of a rounded scissor stay rounded for the clips nested in it
+        let prev_radius = self.scissor.radius;
+
+        self.scissor(res.x, res.y, res.w, res.h);
+
+        if let Some(extent) = self.scissor.extent {
+            self.scissor.radius = prev_radius.min(extent[0]).min(extent[1]);
+        }
+    }
+
+    fn intersect_scissor_rounded(&mut self, x: f32, y: f32, w: f32, h: f32, radius: f32) {
+        self.intersect_scissor(x, y, w, h);
+
+        if let Some(extent) = self.scissor.extent {
+            self.scissor.radius = radius.max(self.scissor.radius).max(0.0).min(extent[0]).min(extent[1]);
+        }
+    }
+}
+
+/// Statistics about a frame, returned by [`Canvas::end_frame()`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FrameStats {
+    /// Number of commands submitted to the renderer.
+    pub commands: usize,
+    /// Number of commands that were merged into a preceding command before they were submitted, see
+    /// [`Canvas::set_command_batching()`].
+    pub merged_commands: usize,
+    /// Number of the submitted commands that rendered new glyphs into the glyph atlases. They are
+    /// submitted ahead of all other commands of the frame.
+    pub glyph_commands: usize,
+    /// Number of vertices uploaded to the renderer.
+    pub vertices: usize,
+    /// Number of draws that were skipped before generating their vertices, because they were
+    /// outside of the render target or the scissor rectangle.
+    pub culled_draws: usize,
+    /// True if the frame ran out of its budget and was partly drawn at lower quality, see
+    /// [`Canvas::set_frame_budget()`].
+    pub degraded: bool,
+    /// Number of glyphs that were left out because the frame ran out of its budget before they
+    /// could be rasterized.
+    pub deferred_glyphs: usize,
+    /// Number of glyphs that were left out because they were still being rasterized by the glyph
+    /// worker threads, see [`Canvas::set_glyph_worker_threads()`].
+    pub pending_glyphs: usize,
+    /// Number of save() calls that were not matched by a restore().
+    pub unmatched_saves: usize,
+    /// Number of restore() calls without a matching save().
+    pub unmatched_restores: usize,
+    /// Where the outermost save() that wasn't matched by a restore() was called. Only known in
+    /// debug builds, None in release builds.
+    pub unmatched_save_location: Option<&'static Location<'static>>,
+    /// True if a scissor was still applied when the frame ended, i.e. it wasn't removed with
+    /// reset_scissor() or by restoring the state from before it was set.
+    pub scissor_left_applied: bool,
+}
+
+impl FrameStats {
+    /// Returns true if every save() in the frame was matched by exactly one restore().
+    pub fn is_balanced(&self) -> bool {
+        self.unmatched_saves == 0 && self.unmatched_restores == 0
+    }
+}
+
+/// Fonts and image metadata of a canvas, captured with [`Canvas::serialize_resources()`].
+///
+/// The snapshot only contains the description of the images, not their pixels. After
+/// [`Canvas::restore_resources()`] the images have to be filled again with [`Canvas::update_image()`].
+#[derive(Clone)]
+pub struct CanvasResources {
+    text_context: TextContext,
+    images: Vec<(ImageId, ImageInfo)>,
+}
+
+impl CanvasResources {
+    /// Returns the text context holding the captured fonts.
+    pub fn text_context(&self) -> &TextContext {
+        &self.text_context
+    }
+
+    /// Returns the ids and infos of the captured images.
+    pub fn images(&self) -> &[(ImageId, ImageInfo)] {
+        &self.images
+    }
+}
+
+type DpiChangeCallback = Box<dyn FnMut(f32, f32, &[ImageId])>;
+
 /// Main 2D drawing context.
 pub struct Canvas<T: Renderer> {
     width: u32,
     height: u32,
     renderer: T,
-    text_context: Rc<RefCell<TextContextImpl>>,
+    text_context: TextContext,
     rendered_glyphs: FnvHashMap<RenderedGlyphId, RenderedGlyph>,
     glyph_textures: Vec<FontTexture>,
+    // Incremented whenever the glyph atlases are discarded
+    glyph_atlas_generation: u64,
+    // Width and height of new glyph atlases
+    glyph_atlas_size: usize,
+    // Number of flushes so far, glyphs remember when they were last drawn
+    flushes: u64,
     current_render_target: RenderTarget,
     state_stack: Vec<State>,
     commands: Vec<Command>,
+    glyph_commands: Vec<Command>,
+    glyph_render_target: RenderTarget,
+    frame_render_target: RenderTarget,
     verts: Vec<Vertex>,
     images: ImageStore<T::Image>,
     fringe_width: f32,
@@ -315,6 +633,34 @@ pub struct Canvas<T: Renderer> {
     tess_tol: f32,
     dist_tol: f32,
     gradients: GradientStore,
+    scratch_path: Path,
+    unmatched_restores: usize,
+    // Where each state of the stack was saved, for reporting unmatched saves
+    #[cfg(debug_assertions)]
+    save_locations: Vec<&'static Location<'static>>,
+    // Names given with save_named() and the depth of the state stack before each of those saves
+    named_saves: Vec<(String, usize)>,
+    text_records: Option<Vec<TextRecord>>,
+    flushed_text_records: Vec<TextRecord>,
+    image_scales: FnvHashMap<ImageId, f32>,
+    dpi_change_callback: Option<DpiChangeCallback>,
+    // Images of dropped handles, deleted by the next flush
+    image_release_sender: Sender<ImageId>,
+    released_images: Receiver<ImageId>,
+    stale_glyph_textures: bool,
+    command_batching: bool,
+    antialias: bool,
+    flush_stats: FrameStats,
+    // Draws skipped since the last flush because they were clipped away entirely
+    culled_draws: usize,
+    tessellation_cache: TessellationCache,
+    frame_budget: FrameBudget,
+    glyph_workers: Option<GlyphWorkers>,
+    // Warnings of the validation mode since they were last taken, None while it is disabled
+    validation: Option<Vec<ValidationWarning>>,
+    #[cfg(feature = "profiling")]
+    profiler: Option<Box<dyn Profiler>>,
+    deterministic: bool,
 }
 
 impl<T> Canvas<T>
@@ -323,6 +669,8 @@ where
 {
     /// Creates a new canvas.
     pub fn new(renderer: T) -> Result<Self, ErrorKind> {
+        let (image_release_sender, released_images) = mpsc::channel();
+
         let mut canvas = Self {
             width: 0,
             height: 0,
@@ -330,9 +678,15 @@ where
             text_context: Default::default(),
             rendered_glyphs: Default::default(),
             glyph_textures: Default::default(),
+            glyph_atlas_generation: 0,
+            glyph_atlas_size: text::DEFAULT_GLYPH_ATLAS_SIZE,
+            flushes: 0,
             current_render_target: RenderTarget::Screen,
             state_stack: Default::default(),
             commands: Default::default(),
+            glyph_commands: Default::default(),
+            glyph_render_target: RenderTarget::Screen,
+            frame_render_target: RenderTarget::Screen,
             verts: Default::default(),
             images: ImageStore::new(),
             fringe_width: 1.0,
@@ -340,6 +694,29 @@ where
             tess_tol: 0.25,
             dist_tol: 0.01,
             gradients: GradientStore::new(),
+            scratch_path: Path::new(),
+            unmatched_restores: 0,
+            #[cfg(debug_assertions)]
+            save_locations: Vec::new(),
+            named_saves: Vec::new(),
+            text_records: None,
+            flushed_text_records: Vec::new(),
+            image_scales: Default::default(),
+            dpi_change_callback: None,
+            image_release_sender,
+            released_images,
+            stale_glyph_textures: false,
+            command_batching: true,
+            antialias: true,
+            flush_stats: FrameStats::default(),
+            culled_draws: 0,
+            #[cfg(feature = "profiling")]
+            profiler: None,
+            tessellation_cache: TessellationCache::default(),
+            frame_budget: FrameBudget::default(),
+            glyph_workers: None,
+            validation: None,
+            deterministic: false,
         };
 
         canvas.save();
@@ -351,16 +728,24 @@ where
     /// provided [`TextContext`]. Note that the context is explicitly shared, so that any fonts
     /// registered with a clone of this context will also be visible to this canvas.
     pub fn new_with_text_context(renderer: T, text_context: TextContext) -> Result<Self, ErrorKind> {
+        let (image_release_sender, released_images) = mpsc::channel();
+
         let mut canvas = Self {
             width: 0,
             height: 0,
             renderer: renderer,
-            text_context: text_context.0,
+            text_context,
             rendered_glyphs: Default::default(),
             glyph_textures: Default::default(),
+            glyph_atlas_generation: 0,
+            glyph_atlas_size: text::DEFAULT_GLYPH_ATLAS_SIZE,
+            flushes: 0,
             current_render_target: RenderTarget::Screen,
             state_stack: Default::default(),
             commands: Default::default(),
+            glyph_commands: Default::default(),
+            glyph_render_target: RenderTarget::Screen,
+            frame_render_target: RenderTarget::Screen,
             verts: Default::default(),
             images: ImageStore::new(),
             fringe_width: 1.0,
@@ -368,6 +753,29 @@ where
             tess_tol: 0.25,
             dist_tol: 0.01,
             gradients: GradientStore::new(),
+            scratch_path: Path::new(),
+            unmatched_restores: 0,
+            #[cfg(debug_assertions)]
+            save_locations: Vec::new(),
+            named_saves: Vec::new(),
+            text_records: None,
+            flushed_text_records: Vec::new(),
+            image_scales: Default::default(),
+            dpi_change_callback: None,
+            image_release_sender,
+            released_images,
+            stale_glyph_textures: false,
+            command_batching: true,
+            antialias: true,
+            flush_stats: FrameStats::default(),
+            culled_draws: 0,
+            #[cfg(feature = "profiling")]
+            profiler: None,
+            tessellation_cache: TessellationCache::default(),
+            frame_budget: FrameBudget::default(),
+            glyph_workers: None,
+            validation: None,
+            deterministic: false,
         };
 
         canvas.save();
@@ -376,7 +784,28 @@ where
     }
 
     /// Sets the size of the default framebuffer (screen size)
+    ///
+    /// When the device pixel ratio changes, e.g. because the window moved to another monitor, glyphs
+    /// are rasterized at the new ratio and the atlas entries for the old ratio are released after the
+    /// next flush. The callback set with [`Self::set_dpi_change_callback()`] is notified as well.
     pub fn set_size(&mut self, width: u32, height: u32, dpi: f32) {
+        let old_dpi = self.device_px_ratio;
+
+        if dpi != old_dpi {
+            self.stale_glyph_textures = true;
+
+            if let Some(callback) = &mut self.dpi_change_callback {
+                let mismatched: Vec<ImageId> = self
+                    .image_scales
+                    .iter()
+                    .filter(|(_, scale)| **scale != dpi)
+                    .map(|(id, _)| *id)
+                    .collect();
+
+                callback(old_dpi, dpi, &mismatched);
+            }
+        }
+
         self.width = width;
         self.height = height;
         self.fringe_width = 1.0 / dpi;
@@ -389,19 +818,105 @@ where
         self.append_cmd(Command::new(CommandType::SetRenderTarget(RenderTarget::Screen)));
     }
 
+    /// Sets the size of the default framebuffer like [`Self::set_size()`], but returns
+    /// [`ErrorKind::InvalidSize`] instead of rendering with broken anti-aliasing and tessellation when
+    /// the device pixel ratio isn't positive and finite. The size is left unchanged in that case.
+    ///
+    /// A width or height of 0, e.g. of a minimized window, is valid and renders nothing.
+    pub fn try_set_size(&mut self, width: u32, height: u32, dpi: f32) -> Result<(), ErrorKind> {
+        if !(dpi.is_finite() && dpi > 0.0) {
+            return Err(ErrorKind::InvalidSize);
+        }
+
+        self.set_size(width, height, dpi);
+
+        Ok(())
+    }
+
+    /// Tells the renderer that the surface of the default framebuffer was destroyed and recreated.
+    ///
+    /// Mobile platforms destroy the EGL surface when an app is paused and create a new one on resume.
+    /// Call this once the new surface is current: pending drawing commands are discarded because they
+    /// were recorded for the old surface, and the screen size is updated like with [`Self::set_size()`].
+    /// Images and fonts stay valid as long as the GL context itself survived, otherwise see
+    /// [`Self::restore_resources()`].
+    pub fn reset_surface(&mut self, width: u32, height: u32, dpi: f32) {
+        self.commands.clear();
+        self.verts.clear();
+        self.current_render_target = RenderTarget::Screen;
+        self.glyph_render_target = RenderTarget::Screen;
+        self.frame_render_target = RenderTarget::Screen;
+
+        // Glyphs cached since the last flush were never rendered into the atlases
+        if !std::mem::take(&mut self.glyph_commands).is_empty() {
+            self.discard_glyph_atlases();
+        }
+
+        self.renderer.reset_surface(width, height, dpi);
+        self.set_size(width, height, dpi);
+    }
+
     /// Clears the rectangle area defined by left upper corner (x,y), width and height with the provided color.
+    ///
+    /// The color is premultiplied with its alpha before it is written, like everything else the
+    /// canvas draws, so a fully transparent color clears to transparent black whatever its RGB is.
     pub fn clear_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color) {
         let cmd = Command::new(CommandType::ClearRect {
             x,
             y,
             width,
             height,
-            color,
+            color: color.premultiplied(),
         });
 
         self.append_cmd(cmd);
     }
 
+    /// Clears the whole current render target to transparent black.
+    ///
+    /// Use this instead of clearing with an opaque color when the canvas is composited over other
+    /// content, e.g. in a transparent window, a streaming overlay or an image that is drawn later.
+    /// The canvas writes premultiplied alpha, so the window surface has to be created with a
+    /// premultiplied alpha mode, and images used as render targets should be created with
+    /// [`ImageFlags::PREMULTIPLIED`] so that drawing them doesn't premultiply their edges a
+    /// second time, which shows as dark fringes.
+    pub fn clear_transparent(&mut self) {
+        let (width, height) = (self.width() as u32, self.height() as u32);
+
+        self.clear_rect(0, 0, width, height, Color::TRANSPARENT);
+    }
+
+    /// Clears the rectangle area with the provided paint, e.g. a gradient or an image background.
+    ///
+    /// Like [`Self::clear_rect()`] the rectangle is specified in device pixels and ignores the current
+    /// transform, scissor and composite operation; the covered pixels are replaced by the paint.
+    /// Paints with a solid color use the same fast path as [`Self::clear_rect()`].
+    pub fn clear_rect_with_paint(&mut self, rect: impl Into<Rect>, paint: &Paint) {
+        let rect = rect.into();
+
+        if let PaintFlavor::Color(color) = paint.flavor {
+            let x = rect.x.max(0.0).round();
+            let y = rect.y.max(0.0).round();
+            let width = (rect.x + rect.w).round() - x;
+            let height = (rect.y + rect.h).round() - y;
+
+            if width > 0.0 && height > 0.0 {
+                self.clear_rect(x as u32, y as u32, width as u32, height as u32, color);
+            }
+
+            return;
+        }
+
+        let mut paint = *paint;
+        paint.set_anti_alias(false);
+
+        self.save();
+        self.reset();
+        self.global_composite_operation(CompositeOperation::Copy);
+        self.draw(|path| path.rect(rect.x, rect.y, rect.w, rect.h), paint, DrawMode::Fill);
+        self.restore();
+    }
+
     /// Returns the width of the current render target.
     pub fn width(&self) -> f32 {
         match self.current_render_target {
@@ -422,11 +937,180 @@ where
     ///
     /// Call this at the end of each frame.
     pub fn flush(&mut self) {
-        self.renderer
-            .render(&mut self.images, &self.verts, std::mem::take(&mut self.commands));
+        profile!(self, Flush, self.flush_commands())
+    }
+
+    fn flush_commands(&mut self) {
+        let mut commands = std::mem::take(&mut self.glyph_commands);
+        let glyph_commands = commands.len();
+
+        if glyph_commands > 0 {
+            commands.push(Command::new(CommandType::SetRenderTarget(self.frame_render_target)));
+        }
+
+        commands.append(&mut self.commands);
+
+        let merged_commands = if self.command_batching {
+            renderer::merge_commands(&mut commands, &mut self.verts)
+        } else {
+            0
+        };
+
+        self.flush_stats = FrameStats {
+            commands: commands.len(),
+            merged_commands,
+            glyph_commands,
+            vertices: self.verts.len(),
+            culled_draws: std::mem::take(&mut self.culled_draws),
+            degraded: self.frame_budget.is_degraded(),
+            deferred_glyphs: self.frame_budget.take_deferred_glyphs(),
+            pending_glyphs: self.glyph_workers.as_mut().map_or(0, |workers| workers.take_skipped()),
+            ..Default::default()
+        };
+
+        self.frame_budget.end_frame();
+        self.flushes += 1;
+
+        self.renderer.render(&mut self.images, &self.verts, commands);
         self.verts.clear();
+        self.glyph_render_target = self.current_render_target;
+        self.frame_render_target = self.current_render_target;
         self.gradients
             .release_old_gradients(&mut self.images, &mut self.renderer);
+
+        let released: Vec<ImageId> = self.released_images.try_iter().collect();
+
+        for id in released {
+            self.delete_image(id);
+        }
+
+        if let Some(records) = &mut self.text_records {
+            self.flushed_text_records = std::mem::take(records);
+        }
+
+        if self.stale_glyph_textures {
+            self.stale_glyph_textures = false;
+            self.discard_glyph_atlases();
+        }
+    }
+
+    // Deletes the glyph atlases, glyphs are rendered again when they are next drawn
+    fn discard_glyph_atlases(&mut self) {
+        self.rendered_glyphs.clear();
+        self.glyph_atlas_generation += 1;
+
+        for texture in std::mem::take(&mut self.glyph_textures) {
+            self.images.remove(&mut self.renderer, texture.image_id);
+        }
+    }
+
+    /// Enables or disables merging consecutive draw commands at flush time, enabled by default.
+    ///
+    /// Fills, strokes and text that are drawn one after another with the same paint, image and
+    /// composite operation are submitted as a single draw call. This helps scenes with many small
+    /// shapes that are bound by the number of draw calls, at the cost of uploading an index buffer
+    /// for the merged commands. The effect is reported in the [`FrameStats`] returned by
+    /// [`Self::end_frame()`].
+    pub fn set_command_batching(&mut self, enabled: bool) {
+        self.command_batching = enabled;
+    }
+
+    /// Enables or disables antialiasing the edges of fills and strokes, enabled by default.
+    ///
+    /// Disabling it is the same as disabling [`Paint::set_anti_alias()`] for every paint, which
+    /// draws fewer vertices, e.g. for pixel art or renderers that multisample. Text isn't affected.
+    pub fn set_antialias(&mut self, enabled: bool) {
+        self.antialias = enabled;
+    }
+
+    /// Returns whether fills and strokes are antialiased, see [`Self::set_antialias()`].
+    pub fn antialias(&self) -> bool {
+        self.antialias
+    }
+
+    /// Sets a callback that is called by [`Self::set_size()`] when the device pixel ratio changes.
+    ///
+    /// The callback receives the old and the new ratio and the images registered with
+    /// [`Self::set_image_scale()`] whose scale doesn't match the new ratio, so that e.g. @1x assets
+    /// can be swapped for their @2x variants.
+    pub fn set_dpi_change_callback(&mut self, callback: impl FnMut(f32, f32, &[ImageId]) + 'static) {
+        self.dpi_change_callback = Some(Box::new(callback));
+    }
+
+    /// Registers the device pixel ratio an image was authored for, e.g. 2.0 for an @2x asset.
+    ///
+    /// See [`Self::set_dpi_change_callback()`].
+    pub fn set_image_scale(&mut self, id: ImageId, scale: f32) {
+        self.image_scales.insert(id, scale);
+    }
+
+    /// Enables or disables recording the text drawn with [`Self::fill_text()`] and [`Self::stroke_text()`].
+    ///
+    /// While enabled, every drawn string is kept together with its bounding box and transform and the
+    /// records of a frame can be retrieved after the frame is flushed with [`Self::text_records()`].
+    /// This lets screen reader bridges expose femtovg user interfaces. Disabling drops all records.
+    pub fn set_text_recording(&mut self, enabled: bool) {
+        if enabled {
+            self.text_records.get_or_insert_with(Vec::new);
+        } else {
+            self.text_records = None;
+            self.flushed_text_records.clear();
+        }
+    }
+
+    /// Returns the text drawn in the last flushed frame, in drawing order.
+    ///
+    /// Empty unless text recording is enabled with [`Self::set_text_recording()`].
+    pub fn text_records(&self) -> &[TextRecord] {
+        &self.flushed_text_records
+    }
+
+    /// Starts a new frame with the specified size of the default framebuffer.
+    ///
+    /// This resets the render state and the state stack to their defaults. Every frame started with this
+    /// method should be finished with [`Self::end_frame()`].
+    pub fn begin_frame(&mut self, width: u32, height: u32, dpi: f32) {
+        self.reset_for_deterministic_frame();
+        self.set_size(width, height, dpi);
+
+        self.state_stack.clear();
+        #[cfg(debug_assertions)]
+        self.save_locations.clear();
+        self.named_saves.clear();
+        self.save();
+        self.unmatched_restores = 0;
+        self.frame_budget.begin_frame();
+    }
+
+    /// Finishes the frame started with [`Self::begin_frame()`], renders it and returns statistics about it.
+    ///
+    /// Unbalanced save() / restore() calls and a scissor that was left applied are reported in the
+    /// returned stats, and the state stack is reset so that they don't leak into the next frame.
+    pub fn end_frame(&mut self) -> FrameStats {
+        let unmatched_saves = self.state_stack.len() - 1;
+
+        #[cfg(debug_assertions)]
+        let unmatched_save_location = self.save_locations.get(1).copied();
+        #[cfg(not(debug_assertions))]
+        let unmatched_save_location = None;
+
+        let scissor_left_applied = self.state().scissor.extent.is_some();
+
+        self.flush();
+
+        let stats = FrameStats {
+            unmatched_saves,
+            unmatched_restores: self.unmatched_restores,
+            unmatched_save_location,
+            scissor_left_applied,
+            ..self.flush_stats
+        };
+
+        self.truncate_state_stack(1);
+        self.reset();
+        self.unmatched_restores = 0;
+
+        stats
     }
 
     pub fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
@@ -434,15 +1118,41 @@ where
         self.renderer.screenshot()
     }
 
+    /// Reads back the current render target and saves it as an image file in the provided format.
+    ///
+    /// Only the formats enabled on the `image` crate dependency can be encoded, e.g. by adding `image`
+    /// with the `png` feature to the dependencies of your crate.
+    #[cfg(feature = "image-loading")]
+    pub fn save_screenshot<P: AsRef<FilePath>>(
+        &mut self,
+        filename: P,
+        format: ::image::ImageFormat,
+    ) -> Result<(), ErrorKind> {
+        use rgb::ComponentBytes;
+
+        let screenshot = self.screenshot()?;
+        let (width, height) = (screenshot.width() as u32, screenshot.height() as u32);
+
+        let image = ::image::RgbaImage::from_raw(width, height, screenshot.buf().as_bytes().to_vec())
+            .ok_or_else(|| ErrorKind::GeneralError("Screenshot has an unexpected size".to_owned()))?;
+
+        image.save_with_format(filename, format)?;
+
+        Ok(())
+    }
+
     // State Handling
 
     /// Pushes and saves the current render state into a state stack.
     ///
     /// A matching restore() must be used to restore the state.
+    #[track_caller]
     pub fn save(&mut self) {
         let state = self.state_stack.last().map_or_else(State::default, |state| *state);
 
         self.state_stack.push(state);
+        #[cfg(debug_assertions)]
+        self.save_locations.push(Location::caller());
     }
 
     /// Restores the previous render state
@@ -450,12 +1160,64 @@ where
     /// Restoring the initial/first state will just reset it to the defaults
     pub fn restore(&mut self) {
         if self.state_stack.len() > 1 {
-            self.state_stack.pop();
+            self.truncate_state_stack(self.state_stack.len() - 1);
         } else {
+            self.unmatched_restores += 1;
             self.reset();
         }
     }
 
+    /// Restores the previous render state like [`Self::restore()`], but returns
+    /// [`ErrorKind::UnbalancedSaveRestore`] and leaves the state untouched if there is no matching
+    /// save().
+    pub fn try_restore(&mut self) -> Result<(), ErrorKind> {
+        if self.state_stack.len() <= 1 {
+            return Err(ErrorKind::UnbalancedSaveRestore);
+        }
+
+        self.truncate_state_stack(self.state_stack.len() - 1);
+
+        Ok(())
+    }
+
+    /// Saves the current state like [`Self::save()`] under a name that [`Self::restore_to()`]
+    /// can unwind to.
+    ///
+    /// Useful when drawing code nests an unknown number of saves, e.g. a popup that restores
+    /// everything its content saved when it closes. The name is forgotten once the state is
+    /// restored. If a name is given again, the more recent save is the one restored to.
+    #[track_caller]
+    pub fn save_named(&mut self, name: &str) {
+        self.named_saves.push((name.to_owned(), self.state_stack.len()));
+        self.save();
+    }
+
+    /// Restores the state that was current when [`Self::save_named()`] was called with `name`,
+    /// undoing that save and all saves after it at once.
+    ///
+    /// Returns [`ErrorKind::UnbalancedSaveRestore`] and leaves the state untouched if no state
+    /// saved under `name` is on the state stack.
+    pub fn restore_to(&mut self, name: &str) -> Result<(), ErrorKind> {
+        let depth = self
+            .named_saves
+            .iter()
+            .rev()
+            .find(|(saved_name, _)| saved_name == name)
+            .map(|(_, depth)| *depth)
+            .ok_or(ErrorKind::UnbalancedSaveRestore)?;
+
+        self.truncate_state_stack(depth);
+
+        Ok(())
+    }
+
+    fn truncate_state_stack(&mut self, len: usize) {
+        self.state_stack.truncate(len);
+        #[cfg(debug_assertions)]
+        self.save_locations.truncate(len);
+        self.named_saves.retain(|(_, depth)| *depth < len);
+    }
+
     /// Resets current state to default values. Does not affect the state stack.
     pub fn reset(&mut self) {
         *self.state_mut() = Default::default();
@@ -481,6 +1243,20 @@ where
         self.state_mut().alpha = alpha;
     }
 
+    /// Enables or disables snapping of horizontal and vertical path edges to the pixel grid.
+    ///
+    /// When enabled, the device space coordinates of axis-aligned segments are rounded so that the edges of
+    /// filled shapes land on pixel boundaries and odd width strokes are centered on pixels. This keeps 1px
+    /// borders and separators crisp instead of smearing them over two pixels. Disabled by default.
+    pub fn set_pixel_snapping(&mut self, enabled: bool) {
+        self.state_mut().pixel_snapping = enabled;
+    }
+
+    /// Returns true if pixel snapping is enabled, see [`Self::set_pixel_snapping()`].
+    pub fn pixel_snapping(&self) -> bool {
+        self.state().pixel_snapping
+    }
+
     /// Sets the composite operation.
     pub fn global_composite_operation(&mut self, op: CompositeOperation) {
         self.state_mut().composite_operation = CompositeOperationState::new(op);
@@ -508,6 +1284,13 @@ where
     }
 
     /// Sets a new render target. All drawing operations after this call will happen on the provided render target
+    ///
+    /// Images created with [`ImageFlags::MULTISAMPLE`] are drawn into multisampled buffers, which
+    /// smooth the edges like a multisampled window does, and copied into the image when the target
+    /// changes and at each flush. The buffers keep what was drawn, so pixels uploaded to the image
+    /// with [`Self::update_image()`] are overwritten by the next copy. Renderers without
+    /// multisampling, and images in a format other than [`PixelFormat::Rgba8`], are drawn into
+    /// directly.
     pub fn set_render_target(&mut self, target: RenderTarget) {
         if self.current_render_target != target {
             self.append_cmd(Command::new(CommandType::SetRenderTarget(target)));
@@ -519,6 +1302,21 @@ where
         self.commands.push(cmd);
     }
 
+    // Records the commands issued by `f` into the glyph pass, which is submitted ahead of the other
+    // commands of the frame. Rendering new glyphs into the atlases then doesn't switch render targets
+    // in the middle of the frame, and every glyph is in place before the first text is drawn.
+    fn with_glyph_pass<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        std::mem::swap(&mut self.commands, &mut self.glyph_commands);
+        std::mem::swap(&mut self.current_render_target, &mut self.glyph_render_target);
+
+        let result = f(self);
+
+        std::mem::swap(&mut self.commands, &mut self.glyph_commands);
+        std::mem::swap(&mut self.current_render_target, &mut self.glyph_render_target);
+
+        result
+    }
+
     // Images
 
     /// Allocates an empty image with the provided domensions and format.
@@ -534,6 +1332,17 @@ where
         self.images.alloc(&mut self.renderer, info)
     }
 
+    /// Allocates an RGBA image for video frames or other streaming content with the provided dimensions.
+    ///
+    /// The image is meant to be drawn from a texture owned by a video decoder or camera pipeline: the
+    /// OpenGL renderer binds the texture returned by its
+    /// [`ExternalImageProvider`](renderer::ExternalImageProvider) for this id at flush time, so frames
+    /// are never copied through the canvas. Without a provider the image can still be filled with
+    /// [`Self::update_image()`].
+    pub fn create_video_image(&mut self, width: usize, height: usize) -> Result<ImageId, ErrorKind> {
+        self.create_image_empty(width, height, PixelFormat::Rgba8, ImageFlags::empty())
+    }
+
     /// Creates image from specified image data.
     pub fn create_image<'a, S: Into<ImageSource<'a>>>(
         &mut self,
@@ -543,7 +1352,11 @@ where
         let src = src.into();
         let size = src.dimensions();
         let id = self.create_image_empty(size.0, size.1, src.format(), flags)?;
-        self.images.update(&mut self.renderer, id, src, 0, 0)?;
+        profile!(
+            self,
+            TextureUpload,
+            self.images.update(&mut self.renderer, id, src, 0, 0)
+        )?;
         Ok(id)
     }
 
@@ -575,6 +1388,15 @@ where
         filename: P,
         flags: ImageFlags,
     ) -> Result<ImageId, ErrorKind> {
+        let filename = filename.as_ref();
+
+        let is_qoi = matches!(filename.extension(), Some(ext) if ext.eq_ignore_ascii_case("qoi"));
+
+        if is_qoi {
+            let data = std::fs::read(filename)?;
+            return self.load_image_mem(&data, flags);
+        }
+
         let image = ::image::open(filename)?;
 
         use std::convert::TryFrom;
@@ -585,8 +1407,25 @@ where
     }
 
     /// Decode an image from memory
+    ///
+    /// The format is detected from the data. Besides the formats enabled in the `image` crate, QOI
+    /// images are always supported and AVIF images are supported with the `avif` feature.
     #[cfg(feature = "image-loading")]
     pub fn load_image_mem(&mut self, data: &[u8], flags: ImageFlags) -> Result<ImageId, ErrorKind> {
+        if image::qoi::is_qoi(data) {
+            let image = image::qoi::decode(data)?;
+            return self.create_image(image.as_ref(), flags);
+        }
+
+        // image only recognizes one variant of the AVIF file type box
+        #[cfg(feature = "avif")]
+        let image = if data.len() >= 12 && (&data[4..12] == b"ftypavif" || &data[4..12] == b"ftypavis") {
+            ::image::load_from_memory_with_format(data, ::image::ImageFormat::Avif)?
+        } else {
+            ::image::load_from_memory(data)?
+        };
+
+        #[cfg(not(feature = "avif"))]
         let image = ::image::load_from_memory(data)?;
 
         use std::convert::TryFrom;
@@ -604,14 +1443,106 @@ where
         x: usize,
         y: usize,
     ) -> Result<(), ErrorKind> {
-        self.images.update(&mut self.renderer, id, src.into(), x, y)
+        profile!(
+            self,
+            TextureUpload,
+            self.images.update(&mut self.renderer, id, src.into(), x, y)
+        )
+    }
+
+    /// Changes how the image is sampled without uploading it again, e.g. to turn tiling with
+    /// [`ImageFlags::REPEAT_X`] and [`ImageFlags::REPEAT_Y`] on and off or to switch between
+    /// nearest and linear filtering.
+    ///
+    /// Whether the image has mipmaps or is multisampled is fixed when it is created,
+    /// [`ImageFlags::GENERATE_MIPMAPS`] and [`ImageFlags::MULTISAMPLE`] stay as they were.
+    pub fn set_image_flags(&mut self, id: ImageId, flags: ImageFlags) -> Result<(), ErrorKind> {
+        let info = self.image_info(id)?;
+
+        let fixed = ImageFlags::GENERATE_MIPMAPS | ImageFlags::MULTISAMPLE;
+        let flags = (flags - fixed) | (info.flags() & fixed);
+
+        self.images.set_flags(&mut self.renderer, id, flags)
+    }
+
+    /// Wraps the image into a handle that deletes it once the handle and all of its clones are
+    /// dropped, also on other threads.
+    ///
+    /// The image is deleted by the next [`Self::flush()`] after the last handle was dropped, so
+    /// that draws that were queued with it still render.
+    pub fn image_handle(&self, id: ImageId) -> ImageHandle {
+        ImageHandle::new(id, self.image_release_sender.clone())
     }
 
     /// Deletes created image.
     pub fn delete_image(&mut self, id: ImageId) {
+        self.image_scales.remove(&id);
         self.images.remove(&mut self.renderer, id);
     }
 
+    /// Captures the fonts and the metadata of all images created on this canvas.
+    ///
+    /// Together with [`Self::restore_resources()`] this allows tearing down and recreating the renderer,
+    /// e.g. when the GL context is lost or rebuilt during hot reloading, while keeping all font and image
+    /// ids valid. Internal images like glyph atlases are not captured, they are recreated on demand.
+    pub fn serialize_resources(&self) -> CanvasResources {
+        let internal: Vec<ImageId> = self
+            .glyph_textures
+            .iter()
+            .map(|texture| texture.image_id)
+            .chain(self.gradients.image_ids())
+            .collect();
+
+        let images = self.images.infos().filter(|(id, _)| !internal.contains(id)).collect();
+
+        CanvasResources {
+            text_context: self.text_context.clone(),
+            images,
+        }
+    }
+
+    /// Replaces the renderer of this canvas and recreates the resources captured by [`Self::serialize_resources()`].
+    ///
+    /// Every captured image is allocated again in the new renderer under its old id, with empty contents.
+    /// Images that were not part of the snapshot are dropped without being released by the new renderer.
+    /// Pending drawing commands are discarded.
+    pub fn restore_resources(&mut self, renderer: T, resources: &CanvasResources) -> Result<(), ErrorKind> {
+        self.renderer = renderer;
+        self.renderer.set_size(self.width, self.height, self.device_px_ratio);
+
+        self.commands.clear();
+        self.glyph_commands.clear();
+        self.verts.clear();
+        self.current_render_target = RenderTarget::Screen;
+        self.glyph_render_target = RenderTarget::Screen;
+        self.frame_render_target = RenderTarget::Screen;
+        self.rendered_glyphs.clear();
+        self.glyph_textures.clear();
+        self.glyph_atlas_generation += 1;
+        self.gradients = GradientStore::new();
+        self.text_context = resources.text_context.clone();
+
+        let ids: Vec<ImageId> = self.images.infos().map(|(id, _)| id).collect();
+
+        for id in ids {
+            match resources.images.iter().find(|(captured, _)| *captured == id) {
+                Some((_, info)) => self.images.realloc(&mut self.renderer, id, *info)?,
+                None => self.images.forget(id),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the image exists, i.e. it was created by this canvas and has not been deleted since.
+    ///
+    /// Image ids are generational, an id of a deleted image never becomes valid again even when its slot
+    /// is reused by a newly created image. Paths filled or stroked with a paint referencing an invalid
+    /// image are not drawn.
+    pub fn is_image_valid(&self, id: ImageId) -> bool {
+        self.images.info(id).is_some()
+    }
+
     /// Returns image info
     pub fn image_info(&self, id: ImageId) -> Result<ImageInfo, ErrorKind> {
         if let Some(info) = self.images.info(id) {
@@ -634,11 +1565,17 @@ where
     ///
     /// The filtering does not take any transformation set on the Canvas into account nor does it
     /// change the current rendering target.
-    pub fn filter_image(&mut self, target_image: ImageId, filter: ImageFilter, source_image: ImageId) {
-        let (image_width, image_height) = match self.image_size(source_image) {
-            Ok((w, h)) => (w, h),
-            Err(_) => return,
-        };
+    ///
+    /// Returns [`ErrorKind::ImageIdNotFound`] if either image is not valid. Filters whose images are
+    /// deleted before the flush are skipped.
+    pub fn filter_image(
+        &mut self,
+        target_image: ImageId,
+        filter: ImageFilter,
+        source_image: ImageId,
+    ) -> Result<(), ErrorKind> {
+        let (image_width, image_height) = self.image_size(source_image)?;
+        self.image_info(target_image)?;
 
         // The renderer will receive a RenderFilteredImage command with two triangles attached that
         // cover the image and the source image.
@@ -669,7 +1606,42 @@ where
 
         cmd.triangles_verts = Some((vertex_offset, 6));
 
-        self.append_cmd(cmd)
+        self.append_cmd(cmd);
+
+        Ok(())
+    }
+
+    /// Draws the image into the `dst` rectangle, resized according to `fit` and positioned with `align`.
+    ///
+    /// The image is clipped to `dst`. The rectangle is transformed by the current transform.
+    ///
+    /// # Example
+    /// ```
+    /// use femtovg::{Align2D, Canvas, Fit, ImageFlags, PixelFormat, Rect, renderer::Void};
+    ///
+    /// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+    ///
+    /// let image_id = canvas.create_image_empty(64, 32, PixelFormat::Rgba8, ImageFlags::empty()).unwrap();
+    /// canvas.draw_image_fit(image_id, Rect::new(0.0, 0.0, 100.0, 100.0), Fit::Cover, Align2D::CENTER).unwrap();
+    /// ```
+    pub fn draw_image_fit(
+        &mut self,
+        id: ImageId,
+        dst: impl Into<Rect>,
+        fit: Fit,
+        align: Align2D,
+    ) -> Result<(), ErrorKind> {
+        let dst = dst.into();
+        let (width, height) = self.image_size(id)?;
+
+        let rect = fit.rect(width as f32, height as f32, dst, align);
+        let clip = rect.intersect(dst);
+
+        let paint = Paint::image(id, rect.x, rect.y, rect.w, rect.h, 0.0, 1.0);
+
+        self.draw(|path| path.rect(clip.x, clip.y, clip.w, clip.h), paint, DrawMode::Fill);
+
+        Ok(())
     }
 
     // Transforms
@@ -681,53 +1653,115 @@ where
 
     /// Premultiplies current coordinate system by specified matrix.
     ///
+    /// This and the other transform calls leave the current transform unchanged if the result
+    /// would have a NaN or infinite component, see [`Transform2D`].
+    ///
     /// The parameters are interpreted as matrix as follows:
     ///   [a c e]
     ///   [b d f]
     ///   [0 0 1]
     pub fn set_transform(&mut self, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
         let transform = Transform2D([a, b, c, d, e, f]);
-        self.state_mut().transform.premultiply(&transform);
+        self.state_mut().premultiply_transform(&transform);
+    }
+
+    /// Premultiplies the current coordinate system by the transform, like [`Self::set_transform()`]
+    /// for a [`Transform2D`] or a transform of another math library.
+    pub fn apply_transform(&mut self, transform: impl Into<Transform2D>) {
+        self.state_mut().premultiply_transform(&transform.into());
     }
 
     /// Translates the current coordinate system.
     pub fn translate(&mut self, x: f32, y: f32) {
         let mut t = Transform2D::identity();
         t.translate(x, y);
-        self.state_mut().transform.premultiply(&t);
+        self.state_mut().premultiply_transform(&t);
     }
 
     /// Rotates the current coordinate system. Angle is specified in radians.
     pub fn rotate(&mut self, angle: f32) {
         let mut t = Transform2D::identity();
         t.rotate(angle);
-        self.state_mut().transform.premultiply(&t);
+        self.state_mut().premultiply_transform(&t);
     }
 
     /// Skews the current coordinate system along X axis. Angle is specified in radians.
     pub fn skew_x(&mut self, angle: f32) {
         let mut t = Transform2D::identity();
         t.skew_x(angle);
-        self.state_mut().transform.premultiply(&t);
+        self.state_mut().premultiply_transform(&t);
     }
 
     /// Skews the current coordinate system along Y axis. Angle is specified in radians.
     pub fn skew_y(&mut self, angle: f32) {
         let mut t = Transform2D::identity();
         t.skew_y(angle);
-        self.state_mut().transform.premultiply(&t);
+        self.state_mut().premultiply_transform(&t);
     }
 
     /// Scales the current coordinate system.
     pub fn scale(&mut self, x: f32, y: f32) {
         let mut t = Transform2D::identity();
         t.scale(x, y);
-        self.state_mut().transform.premultiply(&t);
+        self.state_mut().premultiply_transform(&t);
     }
 
-    /// Returns the current transformation matrix
+    /// Replaces the current transform with one that maps the `world` rectangle onto the `screen` rectangle.
     ///
-    /// TODO: It's not ok that this method returns Transform2D while set_transform accepts 6 floats - make it consistant
+    /// With `flip_y` set, world Y coordinates grow upwards, which is what plots and maps usually expect.
+    /// Use [`Self::screen_to_world()`] to convert input event positions back into world coordinates.
+    /// Does nothing if the world rectangle has zero width or height.
+    pub fn set_viewport(&mut self, world: impl Into<Rect>, screen: impl Into<Rect>, flip_y: bool) {
+        let (world, screen) = (world.into(), screen.into());
+
+        if world.w == 0.0 || world.h == 0.0 {
+            return;
+        }
+
+        let sx = screen.w / world.w;
+        let sy = screen.h / world.h;
+
+        let transform = if flip_y {
+            Transform2D([
+                sx,
+                0.0,
+                0.0,
+                -sy,
+                screen.x - world.x * sx,
+                screen.y + screen.h + world.y * sy,
+            ])
+        } else {
+            Transform2D([sx, 0.0, 0.0, sy, screen.x - world.x * sx, screen.y - world.y * sy])
+        };
+
+        self.state_mut().replace_transform(transform);
+    }
+
+    /// Converts a point from the current coordinate system to screen coordinates.
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        self.state().transform.transform_point(x, y)
+    }
+
+    /// Converts a point in screen coordinates (eg. a mouse position) to the current coordinate system.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        // Solved in double precision rather than through the rounded inverse, which would lose
+        // the position at deep zoom levels
+        let t = self.state().transform.0.map(f64::from);
+        let det = t[0] * t[3] - t[2] * t[1];
+
+        if det > -1e-6 && det < 1e-6 {
+            return (x, y);
+        }
+
+        let (dx, dy) = (f64::from(x) - t[4], f64::from(y) - t[5]);
+
+        (
+            ((t[3] * dx - t[2] * dy) / det) as f32,
+            ((t[0] * dy - t[1] * dx) / det) as f32,
+        )
+    }
+
+    /// Returns the current transformation matrix, [`Self::apply_transform()`] accepts it back.
     pub fn transform(&self) -> Transform2D {
         self.state().transform
     }
@@ -738,16 +1772,7 @@ where
     ///
     /// The scissor rectangle is transformed by the current transform.
     pub fn scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
-        let state = self.state_mut();
-
-        let w = w.max(0.0);
-        let h = h.max(0.0);
-
-        let mut transform = Transform2D::new_translation(x + w * 0.5, y + h * 0.5);
-        transform.multiply(&state.transform);
-        state.scissor.transform = transform;
-
-        state.scissor.extent = Some([w * 0.5, h * 0.5]);
+        self.state_mut().scissor(x, y, w, h);
     }
 
     /// Intersects current scissor rectangle with the specified rectangle.
@@ -757,42 +1782,74 @@ where
     /// the current one, the intersection will be done between the specified
     /// rectangle and the previous scissor rectangle transformed in the current
     /// transform space. The resulting shape is always rectangle.
+    ///
+    /// Inside a scissor set with [`Self::intersect_scissor_rounded()`] the intersection keeps the
+    /// radius of the rounded corners, limited to half of its size.
     pub fn intersect_scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
-        let state = self.state_mut();
-
-        // If no previous scissor has been set, set the scissor as current scissor.
-        if state.scissor.extent.is_none() {
-            self.scissor(x, y, w, h);
-            return;
-        }
-
-        let extent = state.scissor.extent.unwrap();
-
-        // Transform the current scissor rect into current transform space.
-        // If there is difference in rotation, this will be approximation.
+        self.state_mut().intersect_scissor(x, y, w, h);
+    }
 
-        let mut pxform = state.scissor.transform;
+    /// Intersects current scissor rectangle with the specified rectangle and rounds its corners.
+    ///
+    /// The rounded corners are evaluated analytically while shading, so this is as cheap as a regular
+    /// scissor and much cheaper than clipping to a rounded rectangle path.
+    /// Note: the intersection of two rounded scissors uses the larger of the two radii, which
+    /// is an approximation when the corners of the rectangles differ.
+    pub fn intersect_scissor_rounded(&mut self, x: f32, y: f32, w: f32, h: f32, radius: f32) {
+        self.state_mut().intersect_scissor_rounded(x, y, w, h, radius);
+    }
 
-        let mut invxform = state.transform;
-        invxform.inverse();
+    /// Reset and disables scissoring.
+    pub fn reset_scissor(&mut self) {
+        self.state_mut().scissor = Scissor::default();
+    }
 
-        pxform.multiply(&invxform);
+    /// Returns the part of the current render target that isn't clipped away by the scissor, in
+    /// the coordinate system of the current transform, or `None` if nothing would be visible.
+    ///
+    /// Layout code can skip everything that lies outside of this rectangle before making any canvas
+    /// calls for it. Rotated scissors and transforms are approximated by the bounding box, so the
+    /// rectangle may be larger than the visible area, but never smaller.
+    pub fn current_clip_bounds(&self) -> Option<Rect> {
+        let clip = self.clip_bounds();
+        let transform = self.state().transform;
 
-        let ex = extent[0];
-        let ey = extent[1];
+        let det = transform[0] * transform[3] - transform[2] * transform[1];
 
-        let tex = ex * pxform[0].abs() + ey * pxform[2].abs();
-        let tey = ex * pxform[1].abs() + ey * pxform[3].abs();
+        if clip.minx >= clip.maxx || clip.miny >= clip.maxy || det.abs() < 1e-6 {
+            return None;
+        }
 
-        let rect = Rect::new(pxform[4] - tex, pxform[5] - tey, tex * 2.0, tey * 2.0);
-        let res = rect.intersect(Rect::new(x, y, w, h));
+        let inverse = transform.inversed();
+
+        let corners = [
+            inverse.transform_point(clip.minx, clip.miny),
+            inverse.transform_point(clip.maxx, clip.miny),
+            inverse.transform_point(clip.maxx, clip.maxy),
+            inverse.transform_point(clip.minx, clip.maxy),
+        ];
+
+        // Zoomed out far, the local coordinates are beyond the range of Bounds::default()
+        let mut bounds = Bounds {
+            minx: f32::MAX,
+            miny: f32::MAX,
+            maxx: f32::MIN,
+            maxy: f32::MIN,
+        };
 
-        self.scissor(res.x, res.y, res.w, res.h);
-    }
+        for (x, y) in corners.iter() {
+            bounds.minx = bounds.minx.min(*x);
+            bounds.miny = bounds.miny.min(*y);
+            bounds.maxx = bounds.maxx.max(*x);
+            bounds.maxy = bounds.maxy.max(*y);
+        }
 
-    /// Reset and disables scissoring.
-    pub fn reset_scissor(&mut self) {
-        self.state_mut().scissor = Scissor::default();
+        Some(Rect::new(
+            bounds.minx,
+            bounds.miny,
+            bounds.maxx - bounds.minx,
+            bounds.maxy - bounds.miny,
+        ))
     }
 
     // Paths
@@ -827,40 +1884,68 @@ where
     }
 
     /// Fills the provided Path with the specified Paint.
-    pub fn fill_path(&mut self, path: &mut Path, mut paint: Paint) {
-        let transform = self.state().transform;
+    pub fn fill_path(&mut self, path: &mut Path, paint: Paint) {
+        self.validate_draw("fill_path", None, &paint);
 
-        // The path cache saves a flattened and transformed version of the path.
-        let path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+        if self.has_dangling_image(&paint) {
+            return;
+        }
 
-        // Early out if path is outside the canvas bounds
-        if path_cache.bounds.maxx < 0.0
-            || path_cache.bounds.minx > self.width()
-            || path_cache.bounds.maxy < 0.0
-            || path_cache.bounds.miny > self.height()
-        {
+        self.frame_budget.check();
+
+        let tessellation = self.fill_tessellation(paint, self.state().transform);
+
+        if self.fill_rect_fast(path, &tessellation) {
             return;
         }
 
+        if self.draw_cached(path, paint, &tessellation) {
+            return;
+        }
+
+        if profile!(self, Tessellate, tessellation.run(path)) {
+            self.emit_fill(&path.cache, &tessellation);
+        } else {
+            self.culled_draws += 1;
+        }
+    }
+
+    // Captures the state fill_path() tessellates with under the transform
+    fn fill_tessellation(&self, mut paint: Paint, transform: Transform2D) -> Tessellation {
         // Transform paint
         paint.transform = transform;
 
+        if self.frame_budget.is_degraded() || !self.antialias {
+            paint.set_anti_alias(false);
+        }
+
         // Apply global alpha
         paint.mul_alpha(self.state().alpha);
 
-        let scissor = self.state().scissor;
+        Tessellation {
+            mode: DrawMode::Fill,
+            paint,
+            transform,
+            // Edges of filled shapes are snapped to pixel boundaries
+            snap_offset: if self.state().pixel_snapping { Some(0.0) } else { None },
+            tess_tol: self.tessellation_tolerance(),
+            dist_tol: self.dist_tol,
+            // fringe_with is the size of the strip of triangles generated at the path border used for AA
+            fringe_width: if paint.anti_alias() { self.fringe_width } else { 0.0 },
+            clip_bounds: self.clip_bounds(),
+        }
+    }
 
-        // Calculate fill vertices.
-        // expand_fill will fill path_cache.contours[].{stroke, fill} with vertex data for the GPU
-        // fringe_with is the size of the strip of triangles generated at the path border used for AA
-        let fringe_width = if paint.anti_alias() { self.fringe_width } else { 0.0 };
-        path_cache.expand_fill(fringe_width, LineJoin::Miter, 2.4);
+    fn emit_fill(&mut self, path_cache: &PathCache, tessellation: &Tessellation) {
+        let paint = &tessellation.paint;
+        let fringe_width = tessellation.fringe_width;
+        let scissor = self.state().scissor;
 
         // GPU uniforms
         let flavor = if path_cache.contours.len() == 1 && path_cache.contours[0].convexity == Convexity::Convex {
             let params = Params::new(
                 &self.images,
-                &paint,
+                paint,
                 &scissor,
                 self.fringe_width,
                 self.fringe_width,
@@ -875,7 +1960,7 @@ where
 
             let fill_params = Params::new(
                 &self.images,
-                &paint,
+                paint,
                 &scissor,
                 self.fringe_width,
                 self.fringe_width,
@@ -895,6 +1980,9 @@ where
 
         if let PaintFlavor::Image { id, .. } = paint.flavor {
             cmd.image = Some(id);
+        } else if let PaintFlavor::ColorMap { id, lut, .. } = paint.flavor {
+            cmd.image = Some(id);
+            cmd.lut = Some(lut);
         } else if let Some(paint::GradientColors::MultiStop { stops }) = paint.flavor.gradient_colors() {
             cmd.image = self
                 .gradients
@@ -963,22 +2051,37 @@ where
     }
 
     /// Strokes the provided Path with the specified Paint.
-    pub fn stroke_path(&mut self, path: &mut Path, mut paint: Paint) {
-        let transform = self.state().transform;
+    pub fn stroke_path(&mut self, path: &mut Path, paint: Paint) {
+        self.validate_draw("stroke_path", None, &paint);
 
-        // The path cache saves a flattened and transformed version of the path.
-        let path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+        if self.has_dangling_image(&paint) {
+            return;
+        }
 
-        // Early out if path is outside the canvas bounds
-        if path_cache.bounds.maxx < 0.0
-            || path_cache.bounds.minx > self.width()
-            || path_cache.bounds.maxy < 0.0
-            || path_cache.bounds.miny > self.height()
-        {
+        self.frame_budget.check();
+
+        let tessellation = self.stroke_tessellation(paint, self.state().transform);
+
+        if self.draw_cached(path, paint, &tessellation) {
             return;
         }
 
-        let scissor = self.state().scissor;
+        if profile!(self, Tessellate, tessellation.run(path)) {
+            self.emit_stroke(&path.cache, &tessellation);
+        } else {
+            self.culled_draws += 1;
+        }
+    }
+
+    // Captures the state stroke_path() tessellates with under the transform
+    fn stroke_tessellation(&self, mut paint: Paint, transform: Transform2D) -> Tessellation {
+        // Odd width strokes are centered on pixel centers, even width ones on pixel boundaries
+        let snap_offset = if self.state().pixel_snapping {
+            let device_width = (paint.line_width * transform.average_scale()).round().max(1.0);
+            Some(if device_width % 2.0 == 1.0 { 0.5 } else { 0.0 })
+        } else {
+            None
+        };
 
         // Transform paint
         paint.transform = transform;
@@ -1002,38 +2105,45 @@ where
         // Apply global alpha
         paint.mul_alpha(self.state().alpha);
 
-        // Calculate stroke vertices.
-        // expand_stroke will fill path_cache.contours[].stroke with vertex data for the GPU
-        let fringe_with = if paint.anti_alias() { self.fringe_width } else { 0.0 };
-        path_cache.expand_stroke(
-            paint.line_width * 0.5,
-            fringe_with,
-            paint.line_cap_start,
-            paint.line_cap_end,
-            paint.line_join,
-            paint.miter_limit,
-            self.tess_tol,
-        );
+        if self.frame_budget.is_degraded() || !self.antialias {
+            paint.set_anti_alias(false);
+        }
+
+        Tessellation {
+            mode: DrawMode::Stroke,
+            paint,
+            transform,
+            snap_offset,
+            tess_tol: self.tessellation_tolerance(),
+            dist_tol: self.dist_tol,
+            fringe_width: if paint.anti_alias() { self.fringe_width } else { 0.0 },
+            clip_bounds: self.clip_bounds(),
+        }
+    }
+
+    fn emit_stroke(&mut self, path_cache: &PathCache, tessellation: &Tessellation) {
+        let paint = &tessellation.paint;
+        let scissor = self.state().scissor;
 
         // GPU uniforms
-        let params = Params::new(
-            &self.images,
-            &paint,
-            &scissor,
-            paint.line_width,
-            self.fringe_width,
-            -1.0,
-        );
+        let mut params = Params::new(&self.images, paint, &scissor, paint.line_width, self.fringe_width, -1.0);
+
+        // Without a fringe the stroke must stay opaque all the way to its edges. The multiplier stays
+        // finite, GPUs with the minimum highp range would overflow to infinity and NaN at the edge.
+        if !paint.anti_alias() {
+            params.stroke_mult = 1e6;
+        }
 
         let flavor = if paint.stencil_strokes() {
-            let params2 = Params::new(
+            let mut params2 = Params::new(
                 &self.images,
-                &paint,
+                paint,
                 &scissor,
                 paint.line_width,
                 self.fringe_width,
                 1.0 - 0.5 / 255.0,
             );
+            params2.stroke_mult = params.stroke_mult;
 
             CommandType::StencilStroke {
                 params1: params,
@@ -1049,6 +2159,9 @@ where
 
         if let PaintFlavor::Image { id, .. } = paint.flavor {
             cmd.image = Some(id);
+        } else if let PaintFlavor::ColorMap { id, lut, .. } = paint.flavor {
+            cmd.image = Some(id);
+            cmd.lut = Some(lut);
         } else if let Some(paint::GradientColors::MultiStop { stops }) = paint.flavor.gradient_colors() {
             cmd.image = self
                 .gradients
@@ -1075,21 +2188,60 @@ where
         self.append_cmd(cmd);
     }
 
+    /// Builds a path with the provided callback and fills or strokes it with the specified Paint.
+    ///
+    /// The path handed to the callback is empty and is owned by the canvas, which reuses its buffers between
+    /// calls. This avoids allocating a new [`Path`] for every shape drawn in immediate mode style code, once
+    /// the buffers have grown to fit the largest shape neither building nor tessellating the path allocates.
+    ///
+    /// # Example
+    /// ```
+    /// use femtovg::{Paint, Color, Canvas, DrawMode, renderer::Void};
+    ///
+    /// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+    ///
+    /// canvas.draw(|path| path.rounded_rect(10.0, 10.0, 100.0, 100.0, 20.0), Paint::color(Color::hex("454545")), DrawMode::Fill);
+    /// ```
+    pub fn draw(&mut self, build: impl FnOnce(&mut Path), paint: Paint, mode: DrawMode) {
+        let mut path = std::mem::take(&mut self.scratch_path);
+        path.clear();
+
+        build(&mut path);
+
+        match mode {
+            DrawMode::Fill => self.fill_path(&mut path, paint),
+            DrawMode::Stroke => self.stroke_path(&mut path, paint),
+        }
+
+        self.scratch_path = path;
+    }
+
     // Text
 
+    /// Returns the text context holding the fonts of this canvas, see [`TextContext`] for using a
+    /// clone of it on other threads.
+    pub fn text_context(&self) -> &TextContext {
+        &self.text_context
+    }
+
     /// Adds a font file to the canvas
     pub fn add_font<P: AsRef<FilePath>>(&mut self, file_path: P) -> Result<FontId, ErrorKind> {
-        self.text_context.as_ref().borrow_mut().add_font_file(file_path)
+        self.text_context.lock().add_font_file(file_path)
     }
 
     /// Adds a font to the canvas by reading it from the specified chunk of memory.
     pub fn add_font_mem(&mut self, data: &[u8]) -> Result<FontId, ErrorKind> {
-        self.text_context.as_ref().borrow_mut().add_font_mem(data)
+        self.text_context.lock().add_font_mem(data)
     }
 
     /// Adds all .ttf files from a directory
     pub fn add_font_dir<P: AsRef<FilePath>>(&mut self, dir_path: P) -> Result<Vec<FontId>, ErrorKind> {
-        self.text_context.as_ref().borrow_mut().add_font_dir(dir_path)
+        self.text_context.lock().add_font_dir(dir_path)
+    }
+
+    /// Returns true if the font is registered with the text context of this canvas.
+    pub fn is_font_valid(&self, id: FontId) -> bool {
+        self.text_context.lock().font(id).is_some()
     }
 
     /// Returns information on how the provided text will be drawn with the specified paint.
@@ -1107,8 +2259,7 @@ where
         let invscale = 1.0 / scale;
 
         self.text_context
-            .as_ref()
-            .borrow_mut()
+            .lock()
             .measure_text(x * scale, y * scale, text, paint)
             .map(|mut metrics| {
                 metrics.scale(invscale);
@@ -1116,11 +2267,60 @@ where
             })
     }
 
+    /// Returns the width of the text like [`Self::measure_text()`], but without shaping it if it only
+    /// uses characters up to U+00FF.
+    ///
+    /// The advances of these characters are looked up per font and summed up, which is much faster
+    /// than shaping for layout passes that measure lots of distinct strings. Kerning and ligatures
+    /// are not applied, so the width can differ slightly from the shaped one for fonts that use them;
+    /// monospaced fonts measure exactly. Text with other characters, e.g. complex scripts or emoji,
+    /// is shaped as by [`Self::measure_text()`].
+    pub fn measure_text_fast<S: AsRef<str>>(&mut self, text: S, mut paint: Paint) -> Result<f32, ErrorKind> {
+        self.transform_text_paint(&mut paint);
+
+        let text = text.as_ref();
+        let scale = self.font_scale() * self.device_px_ratio;
+
+        let mut text_context = self.text_context.lock();
+
+        let width = match text_context.measure_text_fast(text, &paint) {
+            Some(width) => width,
+            None => text_context.measure_text(0.0, 0.0, text, paint)?.width(),
+        };
+
+        Ok(width / scale)
+    }
+
+    /// Measures text like [`Self::measure_text()`] and maps the box of its glyphs by the current
+    /// transform.
+    ///
+    /// The metrics of [`Self::measure_text()`] are in the coordinate system the text is drawn in,
+    /// so they can't be compared with a mouse position once the text is rotated or skewed. The
+    /// quad of the result can, e.g. with [`TransformedTextMetrics::contains_point()`].
+    pub fn measure_text_transformed<S: AsRef<str>>(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: S,
+        paint: Paint,
+    ) -> Result<TransformedTextMetrics, ErrorKind> {
+        let metrics = self.measure_text(x, y, text, paint)?;
+        let local_bounds = Rect::new(metrics.x, metrics.y, metrics.width(), metrics.height());
+
+        let quad = text::transformed_quad(&local_bounds, &self.state().transform);
+
+        Ok(TransformedTextMetrics {
+            metrics,
+            bounds: text::quad_bounds(&quad),
+            quad,
+        })
+    }
+
     /// Returns font metrics for a particular Paint.
     pub fn measure_font(&mut self, mut paint: Paint) -> Result<FontMetrics, ErrorKind> {
         self.transform_text_paint(&mut paint);
 
-        self.text_context.as_ref().borrow_mut().measure_font(paint)
+        self.text_context.lock().measure_font(paint)
     }
 
     /// Returns the maximum index-th byte of text that will fit inside max_width.
@@ -1133,10 +2333,7 @@ where
         let scale = self.font_scale() * self.device_px_ratio;
         let max_width = max_width * scale;
 
-        self.text_context
-            .as_ref()
-            .borrow_mut()
-            .break_text(max_width, text, paint)
+        self.text_context.lock().break_text(max_width, text, paint)
     }
 
     /// Returnes a list of ranges representing each line of text that will fit inside max_width
@@ -1152,13 +2349,43 @@ where
         let scale = self.font_scale() * self.device_px_ratio;
         let max_width = max_width * scale;
 
-        self.text_context
-            .as_ref()
-            .borrow_mut()
-            .break_text_vec(max_width, text, paint)
+        self.text_context.lock().break_text_vec(max_width, text, paint)
+    }
+
+    /// Breaks text into lines that fit inside max_width like [`Self::break_text_vec()`], and also at
+    /// newlines.
+    ///
+    /// Each line comes with its advance width, measured without the whitespace at its end, and
+    /// whether it wrapped or ended at a newline, so aligning or justifying the lines and placing a
+    /// cursor don't need to measure them again. Only soft breaks are usually justified. Empty text
+    /// and empty paragraphs give empty lines.
+    pub fn break_text_lines<S: AsRef<str>>(
+        &mut self,
+        max_width: f32,
+        text: S,
+        mut paint: Paint,
+    ) -> Result<Vec<TextLine>, ErrorKind> {
+        self.transform_text_paint(&mut paint);
+
+        let text = text.as_ref();
+        let scale = self.font_scale() * self.device_px_ratio;
+        let invscale = 1.0 / scale;
+        let max_width = max_width * scale;
+
+        let mut lines = self.text_context.lock().break_text_lines(max_width, text, paint)?;
+
+        for line in &mut lines {
+            line.width *= invscale;
+            line.whitespace_width *= invscale;
+        }
+
+        Ok(lines)
     }
 
     /// Fills the provided string with the specified Paint.
+    ///
+    /// Gradients and images are placed in the current coordinate system like for paths, so
+    /// several texts drawn with the same paint share one continuous gradient.
     pub fn fill_text<S: AsRef<str>>(
         &mut self,
         x: f32,
@@ -1170,6 +2397,10 @@ where
     }
 
     /// Strokes the provided string with the specified Paint.
+    ///
+    /// The glyph outlines are stroked with the line width, joins, caps and miter limit of the
+    /// paint, like paths are. Miter joins reach far out of sharp glyph corners, a miter limit
+    /// around 2 or a round or bevel join keeps them close to the outline.
     pub fn stroke_text<S: AsRef<str>>(
         &mut self,
         x: f32,
@@ -1197,19 +2428,23 @@ where
         mut paint: Paint,
         render_mode: RenderMode,
     ) -> Result<TextMetrics, ErrorKind> {
+        let call = match render_mode {
+            RenderMode::Fill => "fill_text",
+            RenderMode::Stroke => "stroke_text",
+        };
+
+        self.validate_draw(call, Some((x, y)), &paint);
+
         let transform = self.state().transform;
         let scale = self.font_scale() * self.device_px_ratio;
         let invscale = 1.0 / scale;
 
         self.transform_text_paint(&mut paint);
 
-        let mut layout = text::shape(
-            x * scale,
-            y * scale,
-            &mut self.text_context.as_ref().borrow_mut(),
-            &paint,
-            text,
-            None,
+        let mut layout = profile!(
+            self,
+            Shape,
+            text::shape(x * scale, y * scale, &mut self.text_context.lock(), &paint, text, None)
         )?;
         //let layout = self.layout_text(x, y, text, paint)?;
 
@@ -1220,38 +2455,51 @@ where
         } else {
             let cmds = text::render_atlas(self, &layout, &paint, render_mode)?;
 
-            for cmd in &cmds {
-                let mut verts = Vec::with_capacity(cmd.quads.len() * 6);
+            self.render_glyph_quads(&cmds, paint, transform, invscale);
+        }
 
-                for quad in &cmd.quads {
-                    let (p0, p1) = transform.transform_point(quad.x0 * invscale, quad.y0 * invscale);
-                    let (p2, p3) = transform.transform_point(quad.x1 * invscale, quad.y0 * invscale);
-                    let (p4, p5) = transform.transform_point(quad.x1 * invscale, quad.y1 * invscale);
-                    let (p6, p7) = transform.transform_point(quad.x0 * invscale, quad.y1 * invscale);
+        layout.scale(invscale);
 
-                    verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
-                    verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
-                    verts.push(Vertex::new(p2, p3, quad.s1, quad.t0));
-                    verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
-                    verts.push(Vertex::new(p6, p7, quad.s0, quad.t1));
-                    verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
-                }
+        if let Some(records) = &mut self.text_records {
+            records.push(TextRecord::new(text, &layout, transform));
+        }
 
-                paint.set_alpha_mask(Some(cmd.image_id));
+        Ok(layout)
+    }
 
-                // Apply global alpha
-                paint.mul_alpha(self.state().alpha);
+    // Draws glyph quads that sample the glyph atlases, the quads are in scaled text coordinates
+    fn render_glyph_quads(&mut self, cmds: &[text::DrawCmd], mut paint: Paint, transform: Transform2D, invscale: f32) {
+        // Gradients and images are placed in the coordinates the text is drawn in, like for paths
+        paint.transform = transform;
 
-                self.render_triangles(&verts, &paint);
-            }
-        }
+        for cmd in cmds {
+            let mut verts = Vec::with_capacity(cmd.quads.len() * 6);
+            text::quad_vertices(&cmd.quads, transform, invscale, &mut verts);
 
-        layout.scale(invscale);
+            paint.set_alpha_mask(Some(cmd.image_id));
 
-        Ok(layout)
+            // Apply global alpha
+            paint.mul_alpha(self.state().alpha);
+
+            self.render_triangles(&verts, &paint);
+        }
     }
 
     fn render_triangles(&mut self, verts: &[Vertex], paint: &Paint) {
+        let mut bounds = Bounds::default();
+
+        for vert in verts {
+            bounds.minx = bounds.minx.min(vert.x);
+            bounds.miny = bounds.miny.min(vert.y);
+            bounds.maxx = bounds.maxx.max(vert.x);
+            bounds.maxy = bounds.maxy.max(vert.y);
+        }
+
+        if !bounds.overlaps(&self.clip_bounds()) {
+            self.culled_draws += 1;
+            return;
+        }
+
         let scissor = self.state().scissor;
 
         let params = Params::new(&self.images, paint, &scissor, 1.0, 1.0, -1.0);
@@ -1262,6 +2510,9 @@ where
 
         if let PaintFlavor::Image { id, .. } = paint.flavor {
             cmd.image = Some(id);
+        } else if let PaintFlavor::ColorMap { id, lut, .. } = paint.flavor {
+            cmd.image = Some(id);
+            cmd.lut = Some(lut);
         } else if let Some(paint::GradientColors::MultiStop { stops }) = paint.flavor.gradient_colors() {
             cmd.image = self
                 .gradients
@@ -1275,12 +2526,52 @@ where
         self.verts.extend_from_slice(verts);
     }
 
+    // Returns the part of the render target that isn't clipped away by the scissor, in render
+    // target coordinates. A rotated scissor is approximated by its bounding box.
+    fn clip_bounds(&self) -> Bounds {
+        let target = Bounds::from_rect(0.0, 0.0, self.width(), self.height());
+        let scissor = &self.state().scissor;
+
+        match scissor.extent {
+            Some([ex, ey]) => {
+                let [a, b, c, d, e, f] = scissor.transform.0;
+                let half_width = ex * a.abs() + ey * c.abs();
+                let half_height = ex * b.abs() + ey * d.abs();
+
+                target.intersection(&Bounds::from_rect(
+                    e - half_width,
+                    f - half_height,
+                    half_width * 2.0,
+                    half_height * 2.0,
+                ))
+            }
+            None => target,
+        }
+    }
+
+    // Curves are flattened more coarsely once the frame ran out of budget
+    fn tessellation_tolerance(&self) -> f32 {
+        if self.frame_budget.is_degraded() {
+            self.tess_tol * budget::DEGRADED_TESS_TOL_SCALE
+        } else {
+            self.tess_tol
+        }
+    }
+
     fn font_scale(&self) -> f32 {
         let avg_scale = self.state().transform.average_scale();
 
         geometry::quantize(avg_scale, 0.1).min(7.0)
     }
 
+    fn has_dangling_image(&self, paint: &Paint) -> bool {
+        match paint.flavor {
+            PaintFlavor::Image { id, .. } => !self.is_image_valid(id),
+            PaintFlavor::ColorMap { id, lut, .. } => !self.is_image_valid(id) || !self.is_image_valid(lut),
+            _ => false,
+        }
+    }
+
     //
 
     fn state(&self) -> &State {
@@ -1291,9 +2582,17 @@ where
         self.state_stack.last_mut().unwrap()
     }
 
+    /// Returns the glyph atlas textures, see [`Self::glyph_atlas_pages()`].
     #[cfg(feature = "debug_inspector")]
     pub fn debug_inspector_get_font_textures(&self) -> Vec<ImageId> {
-        self.glyph_textures.iter().map(|t| t.image_id).collect()
+        self.glyph_atlas_pages().iter().map(|page| page.image_id).collect()
+    }
+
+    /// Returns the share of each glyph atlas that is covered by glyphs, in the order of
+    /// [`Self::debug_inspector_get_font_textures()`].
+    #[cfg(feature = "debug_inspector")]
+    pub fn debug_inspector_glyph_atlas_occupancy(&self) -> Vec<f32> {
+        self.glyph_atlas_pages().iter().map(|page| page.occupancy).collect()
     }
 
     #[cfg(feature = "debug_inspector")]
@@ -0,0 +1,942 @@
+use serde_json::Value;
+
+use crate::{
+    animation::cubic_bezier,
+    Canvas,
+    Color,
+    ErrorKind,
+    FillRule,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    Renderer,
+    Transform2D,
+};
+
+// Approximates a quarter circle with a cubic bezier
+const KAPPA: f32 = 0.552_284_8;
+
+// Parent chains longer than this are treated as broken (and cycles are cut off)
+const MAX_PARENT_DEPTH: usize = 32;
+
+/// A parsed Lottie (Bodymovin) animation that can be drawn with [`Canvas::draw_animation()`].
+///
+/// Shape layers are supported, with paths, rectangles, ellipses, groups, solid and gradient fills and
+/// strokes, animated transforms and layer parenting. Keyframes are interpolated with their bezier easing.
+/// Precompositions, masks, mattes, trim paths, repeaters, text and image layers are ignored.
+/// Requires the `lottie` feature.
+///
+/// # Example
+/// ```
+/// use femtovg::{Canvas, lottie::Animation, renderer::Void};
+///
+/// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+///
+/// let data = br#"{"w": 10, "h": 10, "fr": 30, "ip": 0, "op": 30, "layers": []}"#;
+/// let animation = Animation::from_json(data).expect("Cannot parse animation");
+///
+/// canvas.draw_animation(&animation, animation.frame_at(0.5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Animation {
+    width: f32,
+    height: f32,
+    frame_rate: f32,
+    in_point: f32,
+    out_point: f32,
+    layers: Vec<Layer>,
+}
+
+impl Animation {
+    /// Parses an animation from its JSON representation.
+    pub fn from_json(data: &[u8]) -> Result<Self, ErrorKind> {
+        let json: Value = serde_json::from_slice(data)?;
+
+        let number = |key: &str| {
+            json.get(key)
+                .and_then(Value::as_f64)
+                .map(|value| value as f32)
+                .ok_or_else(|| ErrorKind::GeneralError(format!("Lottie animation is missing \"{}\"", key)))
+        };
+
+        let layers = json
+            .get("layers")
+            .and_then(Value::as_array)
+            .map(|layers| layers.iter().map(Layer::parse).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            width: number("w")?,
+            height: number("h")?,
+            frame_rate: number("fr")?,
+            in_point: number("ip")?,
+            out_point: number("op")?,
+            layers,
+        })
+    }
+
+    /// Width of the animation.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Height of the animation.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// Frames per second.
+    pub fn frame_rate(&self) -> f32 {
+        self.frame_rate
+    }
+
+    /// First frame of the animation.
+    pub fn in_point(&self) -> f32 {
+        self.in_point
+    }
+
+    /// Frame at which the animation ends, exclusive.
+    pub fn out_point(&self) -> f32 {
+        self.out_point
+    }
+
+    /// Duration of the animation in seconds.
+    pub fn duration(&self) -> f32 {
+        (self.out_point - self.in_point) / self.frame_rate
+    }
+
+    /// Returns the frame to draw `seconds` after the start of the animation, looping over its duration.
+    pub fn frame_at(&self, seconds: f32) -> f32 {
+        let frames = self.out_point - self.in_point;
+
+        if frames <= 0.0 {
+            return self.in_point;
+        }
+
+        self.in_point + (seconds * self.frame_rate).rem_euclid(frames)
+    }
+
+    fn layer_transform(&self, layer: &Layer, frame: f32, depth: usize) -> Transform2D {
+        let mut transform = layer.transform.matrix(frame - layer.start_time);
+
+        if depth < MAX_PARENT_DEPTH {
+            let parent = layer
+                .parent
+                .and_then(|parent| self.layers.iter().find(|other| other.index == Some(parent)));
+
+            if let Some(parent) = parent {
+                transform.multiply(&self.layer_transform(parent, frame, depth + 1));
+            }
+        }
+
+        transform
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws the animation at the specified frame with its top left corner at the origin of the current
+    /// coordinate system.
+    ///
+    /// Opacity is applied to each shape separately, so overlapping shapes of a translucent layer show
+    /// through each other.
+    pub fn draw_animation(&mut self, animation: &Animation, frame: f32) {
+        let alpha = self.state().alpha;
+
+        // The first layer is the topmost
+        for layer in animation.layers.iter().rev() {
+            if !layer.is_shape || frame < layer.in_point || frame >= layer.out_point {
+                continue;
+            }
+
+            let local_frame = frame - layer.start_time;
+            let transform = animation.layer_transform(layer, frame, 0);
+            let opacity = layer.transform.opacity(local_frame);
+
+            let mut ops = Vec::new();
+            collect_shapes(&layer.shapes, local_frame, &transform, opacity, &mut ops);
+
+            // Items listed first are drawn on top
+            for mut op in ops.into_iter().rev() {
+                let t = op.transform;
+
+                if !t.is_finite() {
+                    continue;
+                }
+
+                self.save();
+                self.set_global_alpha(alpha * op.alpha);
+                self.set_transform(t[0], t[1], t[2], t[3], t[4], t[5]);
+
+                if op.stroke {
+                    self.stroke_path(&mut op.path, op.paint);
+                } else {
+                    self.fill_path(&mut op.path, op.paint);
+                }
+
+                self.restore();
+            }
+        }
+    }
+}
+
+struct DrawOp {
+    path: Path,
+    paint: Paint,
+    stroke: bool,
+    transform: Transform2D,
+    alpha: f32,
+}
+
+// Returns the outlines of the shapes in `items`, in the coordinate system of the items. Every style
+// applies to the outlines listed before it in its group, including those of nested groups.
+fn collect_shapes(
+    items: &[Shape],
+    frame: f32,
+    transform: &Transform2D,
+    opacity: f32,
+    ops: &mut Vec<DrawOp>,
+) -> Vec<Outline> {
+    let mut outlines: Vec<Outline> = Vec::new();
+
+    for item in items {
+        match item {
+            Shape::Group {
+                items,
+                transform: group_transform,
+            } => {
+                let local = group_transform.matrix(frame);
+
+                let mut world = local;
+                world.multiply(transform);
+
+                let group_opacity = opacity * group_transform.opacity(frame);
+
+                for outline in collect_shapes(items, frame, &world, group_opacity, ops) {
+                    outlines.push(outline.transformed(&local));
+                }
+            }
+            Shape::Path(bezier) => outlines.push(bezier.value(frame).outline()),
+            Shape::Rect {
+                position,
+                size,
+                roundness,
+            } => {
+                let position = position.value(frame);
+                let size = size.value(frame);
+                outlines.push(Outline::rect(
+                    component(&position, 0),
+                    component(&position, 1),
+                    component(&size, 0),
+                    component(&size, 1),
+                    component(&roundness.value(frame), 0),
+                ));
+            }
+            Shape::Ellipse { position, size } => {
+                let position = position.value(frame);
+                let size = size.value(frame);
+                outlines.push(Outline::ellipse(
+                    component(&position, 0),
+                    component(&position, 1),
+                    component(&size, 0) / 2.0,
+                    component(&size, 1) / 2.0,
+                ));
+            }
+            Shape::Style(style) => {
+                if outlines.is_empty() {
+                    continue;
+                }
+
+                let mut path = Path::new();
+
+                for outline in &outlines {
+                    outline.append_to(&mut path);
+                }
+
+                ops.push(DrawOp {
+                    path,
+                    paint: style.paint(frame),
+                    stroke: style.stroke.is_some(),
+                    transform: *transform,
+                    alpha: opacity,
+                });
+            }
+        }
+    }
+
+    outlines
+}
+
+#[derive(Clone, Debug)]
+struct Layer {
+    index: Option<i64>,
+    parent: Option<i64>,
+    is_shape: bool,
+    in_point: f32,
+    out_point: f32,
+    start_time: f32,
+    transform: ShapeTransform,
+    shapes: Vec<Shape>,
+}
+
+impl Layer {
+    fn parse(json: &Value) -> Self {
+        let number = |key: &str| json.get(key).and_then(Value::as_f64).map(|value| value as f32);
+
+        Self {
+            index: json.get("ind").and_then(Value::as_i64),
+            parent: json.get("parent").and_then(Value::as_i64),
+            is_shape: json.get("ty").and_then(Value::as_i64) == Some(4) && !is_hidden(json),
+            in_point: number("ip").unwrap_or(f32::MIN),
+            out_point: number("op").unwrap_or(f32::MAX),
+            start_time: number("st").unwrap_or(0.0),
+            transform: ShapeTransform::parse(json.get("ks")),
+            shapes: json.get("shapes").map(parse_shapes).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Shape {
+    Group {
+        items: Vec<Shape>,
+        transform: ShapeTransform,
+    },
+    Path(Property<Bezier>),
+    Rect {
+        position: Property<Vec<f32>>,
+        size: Property<Vec<f32>>,
+        roundness: Property<Vec<f32>>,
+    },
+    Ellipse {
+        position: Property<Vec<f32>>,
+        size: Property<Vec<f32>>,
+    },
+    Style(Style),
+}
+
+fn parse_shapes(json: &Value) -> Vec<Shape> {
+    let items = match json.as_array() {
+        Some(items) => items,
+        None => return Vec::new(),
+    };
+
+    items
+        .iter()
+        .filter(|item| !is_hidden(item))
+        .filter_map(|item| {
+            let property = |key: &str| Property::parse(item.get(key), vec![0.0, 0.0]);
+
+            let shape = match item.get("ty")?.as_str()? {
+                "gr" => {
+                    let items = item.get("it")?;
+
+                    // The group transform is stored as the last item of the group
+                    let transform = items
+                        .as_array()?
+                        .iter()
+                        .find(|item| item.get("ty").and_then(Value::as_str) == Some("tr"));
+
+                    Shape::Group {
+                        items: parse_shapes(items),
+                        transform: ShapeTransform::parse(transform),
+                    }
+                }
+                "sh" => Shape::Path(Property::parse(item.get("ks"), Bezier::default())),
+                "rc" => Shape::Rect {
+                    position: property("p"),
+                    size: property("s"),
+                    roundness: Property::parse(item.get("r"), vec![0.0]),
+                },
+                "el" => Shape::Ellipse {
+                    position: property("p"),
+                    size: property("s"),
+                },
+                "fl" | "st" | "gf" | "gs" => Shape::Style(Style::parse(item)?),
+                _ => return None,
+            };
+
+            Some(shape)
+        })
+        .collect()
+}
+
+fn is_hidden(json: &Value) -> bool {
+    json.get("hd").and_then(Value::as_bool) == Some(true)
+}
+
+#[derive(Clone, Debug)]
+struct Style {
+    source: PaintSource,
+    opacity: Property<Vec<f32>>,
+    fill_rule: FillRule,
+    stroke: Option<StrokeStyle>,
+}
+
+#[derive(Clone, Debug)]
+enum PaintSource {
+    Color(Property<Vec<f32>>),
+    Gradient {
+        radial: bool,
+        start: Property<Vec<f32>>,
+        end: Property<Vec<f32>>,
+        count: usize,
+        stops: Property<Vec<f32>>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct StrokeStyle {
+    width: Property<Vec<f32>>,
+    cap: LineCap,
+    join: LineJoin,
+    miter_limit: f32,
+}
+
+impl Style {
+    fn parse(json: &Value) -> Option<Self> {
+        let kind = json.get("ty")?.as_str()?;
+
+        let source = if kind == "fl" || kind == "st" {
+            PaintSource::Color(Property::parse(json.get("c"), vec![0.0, 0.0, 0.0, 1.0]))
+        } else {
+            let gradient = json.get("g")?;
+
+            PaintSource::Gradient {
+                radial: json.get("t").and_then(Value::as_i64) == Some(2),
+                start: Property::parse(json.get("s"), vec![0.0, 0.0]),
+                end: Property::parse(json.get("e"), vec![0.0, 0.0]),
+                count: gradient.get("p").and_then(Value::as_u64).unwrap_or(0) as usize,
+                stops: Property::parse(gradient.get("k"), Vec::new()),
+            }
+        };
+
+        let stroke = if kind == "st" || kind == "gs" {
+            Some(StrokeStyle {
+                width: Property::parse(json.get("w"), vec![1.0]),
+                cap: match json.get("lc").and_then(Value::as_i64) {
+                    Some(2) => LineCap::Round,
+                    Some(3) => LineCap::Square,
+                    _ => LineCap::Butt,
+                },
+                join: match json.get("lj").and_then(Value::as_i64) {
+                    Some(2) => LineJoin::Round,
+                    Some(3) => LineJoin::Bevel,
+                    _ => LineJoin::Miter,
+                },
+                miter_limit: json.get("ml").and_then(Value::as_f64).unwrap_or(4.0) as f32,
+            })
+        } else {
+            None
+        };
+
+        Some(Self {
+            source,
+            opacity: Property::parse(json.get("o"), vec![100.0]),
+            fill_rule: match json.get("r").and_then(Value::as_i64) {
+                Some(2) => FillRule::EvenOdd,
+                _ => FillRule::NonZero,
+            },
+            stroke,
+        })
+    }
+
+    fn paint(&self, frame: f32) -> Paint {
+        let opacity = component(&self.opacity.value(frame), 0) / 100.0;
+
+        let mut paint = match &self.source {
+            PaintSource::Color(color) => Paint::color(convert_color(&color.value(frame), opacity)),
+            PaintSource::Gradient {
+                radial,
+                start,
+                end,
+                count,
+                stops,
+            } => {
+                let start = start.value(frame);
+                let end = end.value(frame);
+                let stops = gradient_stops(&stops.value(frame), *count, opacity);
+
+                let (sx, sy) = (component(&start, 0), component(&start, 1));
+                let (ex, ey) = (component(&end, 0), component(&end, 1));
+
+                if *radial {
+                    let radius = (ex - sx).hypot(ey - sy);
+                    Paint::radial_gradient_stops(sx, sy, 0.0, radius, &stops)
+                } else {
+                    Paint::linear_gradient_stops(sx, sy, ex, ey, &stops)
+                }
+            }
+        };
+
+        paint.set_fill_rule(self.fill_rule);
+
+        if let Some(stroke) = &self.stroke {
+            paint.set_line_width(component(&stroke.width.value(frame), 0));
+            paint.set_line_cap(stroke.cap);
+            paint.set_line_join(stroke.join);
+            paint.set_miter_limit(stroke.miter_limit);
+        }
+
+        paint
+    }
+}
+
+// Lottie stores gradients as `count` (offset, r, g, b) tuples optionally followed by (offset, alpha) pairs
+fn gradient_stops(data: &[f32], count: usize, opacity: f32) -> Vec<(f32, Color)> {
+    let colors = data.len().min(count * 4) / 4;
+    let alphas: Vec<&[f32]> = data[colors * 4..].chunks_exact(2).collect();
+
+    let alpha_at = |offset: f32| {
+        let next = match alphas.iter().position(|stop| stop[0] >= offset) {
+            Some(next) => next,
+            None => return alphas.last().map_or(1.0, |stop| stop[1]),
+        };
+
+        if next == 0 {
+            return alphas[0][1];
+        }
+
+        let (a, b) = (alphas[next - 1], alphas[next]);
+        let t = if b[0] > a[0] {
+            (offset - a[0]) / (b[0] - a[0])
+        } else {
+            0.0
+        };
+
+        a[1] + (b[1] - a[1]) * t
+    };
+
+    data[..colors * 4]
+        .chunks_exact(4)
+        .map(|stop| {
+            let color = [stop[1], stop[2], stop[3], alpha_at(stop[0])];
+            (stop[0], convert_color(&color, opacity))
+        })
+        .collect()
+}
+
+fn convert_color(color: &[f32], opacity: f32) -> Color {
+    // Old exports store colors in the 0 - 255 range
+    let scale = if color.iter().take(3).any(|c| *c > 1.0) {
+        255.0
+    } else {
+        1.0
+    };
+
+    let channel = |index: usize| (component(color, index) / scale).clamp(0.0, 1.0);
+    let alpha = color.get(3).copied().unwrap_or(1.0).clamp(0.0, 1.0);
+
+    Color::rgbaf(channel(0), channel(1), channel(2), alpha * opacity.clamp(0.0, 1.0))
+}
+
+fn component(value: &[f32], index: usize) -> f32 {
+    value.get(index).copied().unwrap_or(0.0)
+}
+
+#[derive(Clone, Debug)]
+struct ShapeTransform {
+    anchor: Property<Vec<f32>>,
+    position: Position,
+    scale: Property<Vec<f32>>,
+    rotation: Property<Vec<f32>>,
+    opacity: Property<Vec<f32>>,
+}
+
+#[derive(Clone, Debug)]
+enum Position {
+    Combined(Property<Vec<f32>>),
+    Split(Property<Vec<f32>>, Property<Vec<f32>>),
+}
+
+impl ShapeTransform {
+    fn parse(json: Option<&Value>) -> Self {
+        let get = |key: &str| json.and_then(|json| json.get(key));
+
+        let position = match get("p") {
+            Some(position) if position.get("s").and_then(Value::as_bool) == Some(true) => Position::Split(
+                Property::parse(position.get("x"), vec![0.0]),
+                Property::parse(position.get("y"), vec![0.0]),
+            ),
+            position => Position::Combined(Property::parse(position, vec![0.0, 0.0])),
+        };
+
+        Self {
+            anchor: Property::parse(get("a"), vec![0.0, 0.0]),
+            position,
+            scale: Property::parse(get("s"), vec![100.0, 100.0]),
+            rotation: Property::parse(get("r").or_else(|| get("rz")), vec![0.0]),
+            opacity: Property::parse(get("o"), vec![100.0]),
+        }
+    }
+
+    fn matrix(&self, frame: f32) -> Transform2D {
+        let anchor = self.anchor.value(frame);
+        let scale = self.scale.value(frame);
+        let rotation = component(&self.rotation.value(frame), 0);
+
+        let (x, y) = match &self.position {
+            Position::Combined(position) => {
+                let position = position.value(frame);
+                (component(&position, 0), component(&position, 1))
+            }
+            Position::Split(x, y) => (component(&x.value(frame), 0), component(&y.value(frame), 0)),
+        };
+
+        let mut transform = Transform2D::new_translation(-component(&anchor, 0), -component(&anchor, 1));
+
+        let mut t = Transform2D::identity();
+        t.scale(component(&scale, 0) / 100.0, component(&scale, 1) / 100.0);
+        transform.multiply(&t);
+
+        t.rotate(rotation.to_radians());
+        transform.multiply(&t);
+
+        transform.multiply(&Transform2D::new_translation(x, y));
+
+        transform
+    }
+
+    fn opacity(&self, frame: f32) -> f32 {
+        component(&self.opacity.value(frame), 0) / 100.0
+    }
+}
+
+trait Interpolate: Clone {
+    fn from_json(json: &Value) -> Option<Self>;
+
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for Vec<f32> {
+    fn from_json(json: &Value) -> Option<Self> {
+        match json {
+            Value::Number(number) => Some(vec![number.as_f64()? as f32]),
+            Value::Array(values) => values.iter().map(|value| Some(value.as_f64()? as f32)).collect(),
+            _ => None,
+        }
+    }
+
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.iter().zip(other.iter()).map(|(a, b)| a + (b - a) * t).collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Bezier {
+    closed: bool,
+    vertices: Vec<[f32; 2]>,
+    in_tangents: Vec<[f32; 2]>,
+    out_tangents: Vec<[f32; 2]>,
+}
+
+impl Bezier {
+    fn outline(&self) -> Outline {
+        let mut outline = Outline::default();
+
+        let count = self
+            .vertices
+            .len()
+            .min(self.in_tangents.len())
+            .min(self.out_tangents.len());
+
+        if count == 0 {
+            return outline;
+        }
+
+        let segment = |from: usize, to: usize| {
+            let (v0, v1) = (self.vertices[from], self.vertices[to]);
+            let (o, i) = (self.out_tangents[from], self.in_tangents[to]);
+
+            Command::Cubic([v0[0] + o[0], v0[1] + o[1]], [v1[0] + i[0], v1[1] + i[1]], v1)
+        };
+
+        outline.0.push(Command::Move(self.vertices[0]));
+
+        for index in 1..count {
+            outline.0.push(segment(index - 1, index));
+        }
+
+        if self.closed {
+            outline.0.push(segment(count - 1, 0));
+            outline.0.push(Command::Close);
+        }
+
+        outline
+    }
+}
+
+impl Interpolate for Bezier {
+    fn from_json(json: &Value) -> Option<Self> {
+        // Keyframed shapes are wrapped in an array
+        let json = match json {
+            Value::Array(values) => values.first()?,
+            json => json,
+        };
+
+        let points = |key: &str| -> Option<Vec<[f32; 2]>> {
+            json.get(key)?
+                .as_array()?
+                .iter()
+                .map(|point| {
+                    let point = Vec::<f32>::from_json(point)?;
+                    Some([component(&point, 0), component(&point, 1)])
+                })
+                .collect()
+        };
+
+        Some(Self {
+            closed: json.get("c").and_then(Value::as_bool).unwrap_or(false),
+            vertices: points("v")?,
+            in_tangents: points("i")?,
+            out_tangents: points("o")?,
+        })
+    }
+
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        if self.vertices.len() != other.vertices.len() {
+            return self.clone();
+        }
+
+        let lerp = |a: &[[f32; 2]], b: &[[f32; 2]]| {
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t])
+                .collect()
+        };
+
+        Self {
+            closed: self.closed,
+            vertices: lerp(&self.vertices, &other.vertices),
+            in_tangents: lerp(&self.in_tangents, &other.in_tangents),
+            out_tangents: lerp(&self.out_tangents, &other.out_tangents),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property<T> {
+    Static(T),
+    Animated(Vec<Keyframe<T>>),
+}
+
+#[derive(Clone, Debug)]
+struct Keyframe<T> {
+    time: f32,
+    start: Option<T>,
+    end: Option<T>,
+    hold: bool,
+    ease_out: [f32; 2],
+    ease_in: [f32; 2],
+}
+
+impl<T: Interpolate> Property<T> {
+    fn parse(json: Option<&Value>, default: T) -> Self {
+        let value = match json.and_then(|json| json.get("k")) {
+            Some(value) => value,
+            None => return Self::Static(default),
+        };
+
+        let keyframes = value
+            .as_array()
+            .filter(|values| values.first().and_then(|first| first.get("t")).is_some());
+
+        match keyframes {
+            Some(keyframes) => {
+                let keyframes: Vec<Keyframe<T>> = keyframes.iter().map(Keyframe::parse).collect();
+
+                if keyframes.iter().any(|keyframe| keyframe.start.is_some()) {
+                    Self::Animated(keyframes)
+                } else {
+                    Self::Static(default)
+                }
+            }
+            None => Self::Static(T::from_json(value).unwrap_or(default)),
+        }
+    }
+
+    fn value(&self, frame: f32) -> T {
+        let keyframes = match self {
+            Self::Static(value) => return value.clone(),
+            Self::Animated(keyframes) => keyframes,
+        };
+
+        // Old exports leave out the value of the last keyframe and store it as the end of the previous one
+        let start = |index: usize| {
+            keyframes[index]
+                .start
+                .as_ref()
+                .or_else(|| index.checked_sub(1).and_then(|prev| keyframes[prev].end.as_ref()))
+        };
+
+        let first = keyframes
+            .iter()
+            .position(|keyframe| keyframe.start.is_some())
+            .unwrap_or(0);
+
+        let next = match keyframes.iter().position(|keyframe| keyframe.time > frame) {
+            Some(next) if next > first => next,
+            Some(_) => return start(first).cloned().unwrap(),
+            None => {
+                let last = (first..keyframes.len()).rev().find_map(start);
+                return last.cloned().unwrap();
+            }
+        };
+
+        let current = &keyframes[next - 1];
+
+        let from = match start(next - 1) {
+            Some(from) => from,
+            None => return start(first).cloned().unwrap(),
+        };
+
+        if current.hold {
+            return from.clone();
+        }
+
+        let to = match current.end.as_ref().or_else(|| start(next)) {
+            Some(to) => to,
+            None => return from.clone(),
+        };
+
+        let duration = keyframes[next].time - current.time;
+        let progress = if duration > 0.0 {
+            (frame - current.time) / duration
+        } else {
+            1.0
+        };
+
+        from.interpolate(to, cubic_bezier(current.ease_out, current.ease_in, progress))
+    }
+}
+
+impl<T: Interpolate> Keyframe<T> {
+    fn parse(json: &Value) -> Self {
+        let handle = |key: &str, default: [f32; 2]| {
+            let handle = match json.get(key) {
+                Some(handle) => handle,
+                None => return default,
+            };
+
+            // Handles hold one value per dimension, only the first one is used
+            let coord = |axis: &str, default: f32| {
+                handle
+                    .get(axis)
+                    .and_then(Vec::<f32>::from_json)
+                    .and_then(|values| values.first().copied())
+                    .unwrap_or(default)
+            };
+
+            [coord("x", default[0]), coord("y", default[1])]
+        };
+
+        Self {
+            time: json.get("t").and_then(Value::as_f64).unwrap_or(0.0) as f32,
+            start: json.get("s").and_then(T::from_json),
+            end: json.get("e").and_then(T::from_json),
+            hold: json.get("h").and_then(Value::as_i64) == Some(1),
+            ease_out: handle("o", [0.0, 0.0]),
+            ease_in: handle("i", [1.0, 1.0]),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Command {
+    Move([f32; 2]),
+    Line([f32; 2]),
+    Cubic([f32; 2], [f32; 2], [f32; 2]),
+    Close,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Outline(Vec<Command>);
+
+impl Outline {
+    fn rect(cx: f32, cy: f32, width: f32, height: f32, radius: f32) -> Self {
+        let (x0, y0) = (cx - width / 2.0, cy - height / 2.0);
+        let (x1, y1) = (cx + width / 2.0, cy + height / 2.0);
+        let r = radius.clamp(0.0, width.abs().min(height.abs()) / 2.0);
+
+        if r <= 0.0 {
+            return Self(vec![
+                Command::Move([x1, y0]),
+                Command::Line([x1, y1]),
+                Command::Line([x0, y1]),
+                Command::Line([x0, y0]),
+                Command::Close,
+            ]);
+        }
+
+        let k = r * (1.0 - KAPPA);
+
+        Self(vec![
+            Command::Move([x1, y0 + r]),
+            Command::Line([x1, y1 - r]),
+            Command::Cubic([x1, y1 - k], [x1 - k, y1], [x1 - r, y1]),
+            Command::Line([x0 + r, y1]),
+            Command::Cubic([x0 + k, y1], [x0, y1 - k], [x0, y1 - r]),
+            Command::Line([x0, y0 + r]),
+            Command::Cubic([x0, y0 + k], [x0 + k, y0], [x0 + r, y0]),
+            Command::Line([x1 - r, y0]),
+            Command::Cubic([x1 - k, y0], [x1, y0 + k], [x1, y0 + r]),
+            Command::Close,
+        ])
+    }
+
+    fn ellipse(cx: f32, cy: f32, rx: f32, ry: f32) -> Self {
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+
+        Self(vec![
+            Command::Move([cx, cy - ry]),
+            Command::Cubic([cx + kx, cy - ry], [cx + rx, cy - ky], [cx + rx, cy]),
+            Command::Cubic([cx + rx, cy + ky], [cx + kx, cy + ry], [cx, cy + ry]),
+            Command::Cubic([cx - kx, cy + ry], [cx - rx, cy + ky], [cx - rx, cy]),
+            Command::Cubic([cx - rx, cy - ky], [cx - kx, cy - ry], [cx, cy - ry]),
+            Command::Close,
+        ])
+    }
+
+    fn transformed(&self, transform: &Transform2D) -> Self {
+        let point = |p: [f32; 2]| {
+            let (x, y) = transform.transform_point(p[0], p[1]);
+            [x, y]
+        };
+
+        Self(
+            self.0
+                .iter()
+                .map(|command| match *command {
+                    Command::Move(p) => Command::Move(point(p)),
+                    Command::Line(p) => Command::Line(point(p)),
+                    Command::Cubic(c1, c2, p) => Command::Cubic(point(c1), point(c2), point(p)),
+                    Command::Close => Command::Close,
+                })
+                .collect(),
+        )
+    }
+
+    // Commands with coordinates that overflowed are left out, paths only take finite ones
+    fn append_to(&self, path: &mut Path) {
+        let finite = |points: &[[f32; 2]]| points.iter().flatten().all(|v| v.is_finite());
+
+        for command in &self.0 {
+            let is_finite = match *command {
+                Command::Move(p) | Command::Line(p) => finite(&[p]),
+                Command::Cubic(c1, c2, p) => finite(&[c1, c2, p]),
+                Command::Close => true,
+            };
+
+            if !is_finite {
+                continue;
+            }
+
+            match *command {
+                Command::Move(p) => path.move_to(p[0], p[1]),
+                Command::Line(p) => path.line_to(p[0], p[1]),
+                Command::Cubic(c1, c2, p) => path.bezier_to(c1[0], c1[1], c2[0], c2[1], p[0], p[1]),
+                Command::Close => path.close(),
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::mem::size_of;
+
+use crate::renderer::{
+    Command,
+    Vertex,
+};
+use crate::{
+    Canvas,
+    ImageFlags,
+    ImageId,
+    ImageInfo,
+    PixelFormat,
+    Renderer,
+};
+
+/// Memory held by the caches and buffers of a canvas, returned by [`Canvas::memory_report()`].
+///
+/// Byte counts are estimates of the allocated capacity, not of the used part. Image sizes are
+/// computed from their dimensions and pixel format, the renderer may store them differently.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryReport {
+    /// Number of shaped text runs in the shaping cache.
+    pub shaped_runs: usize,
+    /// Number of shaped words in the shaping cache.
+    pub shaped_words: usize,
+    /// Number of distinct strings the shaping cache is keyed by.
+    pub interned_strings: usize,
+    /// Number of glyph atlas textures.
+    pub glyph_atlases: usize,
+    /// Number of glyphs rendered into the glyph atlases.
+    pub rendered_glyphs: usize,
+    /// Bytes of the glyph atlas textures.
+    pub glyph_atlas_bytes: usize,
+    /// Number of images, including the internal gradient textures but not the glyph atlases.
+    pub images: usize,
+    /// Bytes of the images, including their mipmaps.
+    pub image_bytes: usize,
+    /// Bytes of the flattened and tessellated paths kept by the tessellation cache, see
+    /// [`Canvas::set_tessellation_cache_capacity()`].
+    pub path_cache_bytes: usize,
+    /// Bytes of the vertex and command buffers that collect the draws of a frame. They keep the
+    /// capacity of the largest frame so far.
+    pub vertex_buffer_bytes: usize,
+}
+
+impl MemoryReport {
+    /// Returns the sum of all byte counts of the report.
+    pub fn total_bytes(&self) -> usize {
+        self.glyph_atlas_bytes + self.image_bytes + self.path_cache_bytes + self.vertex_buffer_bytes
+    }
+}
+
+// Share of an atlas that glyphs are expected to cover after repacking
+const TRIM_PACKING_EFFICIENCY: f32 = 0.8;
+
+fn image_bytes(info: &ImageInfo) -> usize {
+    let bytes_per_pixel = match info.format() {
+        PixelFormat::Rgb8 => 3,
+        PixelFormat::Rgba8 => 4,
+        PixelFormat::Gray8 => 1,
+    };
+
+    let bytes = info.width() * info.height() * bytes_per_pixel;
+
+    // A full mipmap chain adds a third
+    if info.flags().contains(ImageFlags::GENERATE_MIPMAPS) {
+        bytes + bytes / 3
+    } else {
+        bytes
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Returns how much memory the caches and buffers of the canvas currently hold.
+    ///
+    /// Comparing reports over time shows which part of the canvas grows. Each part can be trimmed
+    /// on its own: [`Self::clear_shaping_caches()`], [`Self::clear_glyph_atlases()`] or
+    /// [`Self::trim_glyph_atlases()`],
+    /// [`Self::delete_image()`], [`Self::clear_tessellation_cache()`] and [`Self::shrink_buffers()`].
+    pub fn memory_report(&self) -> MemoryReport {
+        let (shaped_runs, shaped_words, interned_strings) = self.text_context.lock().shaping_cache_len();
+
+        let atlas_ids: Vec<ImageId> = self.glyph_textures.iter().map(|texture| texture.image_id).collect();
+
+        let mut report = MemoryReport {
+            shaped_runs,
+            shaped_words,
+            interned_strings,
+            glyph_atlases: atlas_ids.len(),
+            rendered_glyphs: self.rendered_glyphs.len(),
+            path_cache_bytes: self.tessellation_cache.heap_bytes() + self.scratch_path.cache.heap_bytes(),
+            vertex_buffer_bytes: self.verts.capacity() * size_of::<Vertex>()
+                + (self.commands.capacity() + self.glyph_commands.capacity()) * size_of::<Command>(),
+            ..Default::default()
+        };
+
+        for (id, info) in self.images.infos() {
+            if atlas_ids.contains(&id) {
+                report.glyph_atlas_bytes += image_bytes(&info);
+            } else {
+                report.images += 1;
+                report.image_bytes += image_bytes(&info);
+            }
+        }
+
+        report
+    }
+
+    /// Sets how many shaped text runs and words are kept for reuse, at least one of each. Defaults
+    /// to 1000.
+    ///
+    /// Text that is measured or drawn again with the same font settings is laid out from the cache
+    /// instead of being shaped again. Apps that show a lot of distinct text can raise the capacity,
+    /// memory constrained ones lower it. The least recently used entries are evicted beyond the
+    /// capacity. The cache is shared with the other canvases that use the same
+    /// [`TextContext`](crate::TextContext).
+    pub fn set_shaping_cache_capacity(&mut self, capacity: usize) {
+        self.text_context.lock().set_shaping_cache_capacity(capacity);
+    }
+
+    /// Returns the capacity of the shaping cache, see [`Self::set_shaping_cache_capacity()`].
+    pub fn shaping_cache_capacity(&self) -> usize {
+        self.text_context.lock().shaping_cache_capacity()
+    }
+
+    /// Sets the width and height in pixels of the textures glyphs are rendered into. Defaults to 512.
+    ///
+    /// Larger atlases need fewer textures and draw calls for text with many distinct glyphs, smaller
+    /// ones waste less memory for little text. Glyphs that don't fit into an empty atlas fail to
+    /// render with [`ErrorKind::FontSizeTooLargeForAtlas`](crate::ErrorKind::FontSizeTooLargeForAtlas).
+    /// Changing the size releases the existing atlases after the next flush.
+    pub fn set_glyph_atlas_size(&mut self, size: usize) {
+        if size != self.glyph_atlas_size && !self.glyph_textures.is_empty() {
+            self.clear_glyph_atlases();
+        }
+
+        self.glyph_atlas_size = size;
+    }
+
+    /// Returns the size of the glyph atlases, see [`Self::set_glyph_atlas_size()`].
+    pub fn glyph_atlas_size(&self) -> usize {
+        self.glyph_atlas_size
+    }
+
+    /// Clears the shaping cache, the glyph atlases and the tessellation cache, e.g. to periodically
+    /// trim a long running app. Everything is computed again on demand.
+    pub fn clear_caches(&mut self) {
+        self.clear_shaping_caches();
+        self.clear_glyph_atlases();
+        self.clear_tessellation_cache();
+    }
+
+    /// Discards the shaping results kept for text that was measured or drawn before. They are
+    /// computed again the next time the text is used. The cache is shared with the other canvases
+    /// that use the same [`TextContext`](crate::TextContext).
+    pub fn clear_shaping_caches(&mut self) {
+        self.text_context.lock().clear_shaping_caches();
+    }
+
+    /// Releases the glyph atlases after the next flush, glyphs are rendered again when they are next
+    /// drawn.
+    pub fn clear_glyph_atlases(&mut self) {
+        self.stale_glyph_textures = true;
+    }
+
+    /// Repacks the glyph atlases if the glyphs drawn within the last `max_age` flushes would fit into
+    /// fewer atlases than there are. Returns true if the atlases are released after the next flush,
+    /// the glyphs that are still drawn are then rendered again, packed tightly into new atlases.
+    ///
+    /// Atlases only grow while text is drawn, text that was shown once keeps its glyphs in them.
+    /// Calling this periodically, e.g. every few seconds with the frames drawn in between as
+    /// `max_age`, gives the memory of glyphs that are no longer shown back after their text
+    /// changed, at the cost of rendering the remaining glyphs again.
+    pub fn trim_glyph_atlases(&mut self, max_age: u64) -> bool {
+        let live_area: usize = self
+            .rendered_glyphs
+            .values()
+            .filter(|glyph| self.flushes - glyph.last_used <= max_age)
+            .map(|glyph| glyph.packed_area())
+            .sum();
+
+        // The skyline packer wastes some space below the skyline with glyphs of mixed sizes
+        let atlas_area = (self.glyph_atlas_size * self.glyph_atlas_size) as f32 * TRIM_PACKING_EFFICIENCY;
+        let needed = (live_area as f32 / atlas_area).ceil() as usize;
+
+        if needed < self.glyph_textures.len() {
+            self.clear_glyph_atlases();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Releases the capacity of the buffers that collect the draws of a frame, e.g. after an
+    /// unusually large frame. Pending draws are kept.
+    pub fn shrink_buffers(&mut self) {
+        self.verts.shrink_to_fit();
+        self.commands.shrink_to_fit();
+        self.glyph_commands.shrink_to_fit();
+    }
+}
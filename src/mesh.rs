@@ -0,0 +1,156 @@
+use rgb::RGBA8;
+
+use crate::{
+    renderer::{
+        Command,
+        CommandType,
+        Params,
+        ShaderType,
+        Vertex,
+    },
+    Canvas,
+    Color,
+    ImageFlags,
+    ImageId,
+    Paint,
+    PixelFormat,
+    Renderer,
+};
+
+/// A vertex of a mesh drawn with [`Canvas::draw_mesh()`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MeshVertex {
+    pub x: f32,
+    pub y: f32,
+    /// Texture coordinates in the 0.0 - 1.0 range.
+    pub u: f32,
+    pub v: f32,
+    /// Color with premultiplied alpha, multiplied with the texture color.
+    pub color: RGBA8,
+}
+
+impl MeshVertex {
+    pub fn new(x: f32, y: f32, u: f32, v: f32, color: RGBA8) -> Self {
+        Self { x, y, u, v, color }
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws an indexed triangle list with per vertex colors and an optional texture.
+    ///
+    /// This is meant for rendering the meshes produced by immediate mode GUI libraries like egui
+    /// or Dear ImGui, which can then share a canvas and its GL state with regular femtovg drawing.
+    /// Vertices are transformed by the current transform and clipped to the current scissor, the
+    /// triangles are not anti-aliased. Triangles with out of range indices are skipped.
+    pub fn draw_mesh(&mut self, vertices: &[MeshVertex], indices: &[u32], image: Option<ImageId>) {
+        if let Some(id) = image {
+            if !self.is_image_valid(id) {
+                return;
+            }
+        }
+
+        let transform = self.state().transform;
+
+        let mut verts = Vec::with_capacity(indices.len());
+        let mut colors = Vec::with_capacity(indices.len());
+
+        for triangle in indices.chunks_exact(3) {
+            let in_range = triangle.iter().all(|index| (*index as usize) < vertices.len());
+
+            if !in_range {
+                continue;
+            }
+
+            for index in triangle {
+                let vertex = vertices[*index as usize];
+                let (x, y) = transform.transform_point(vertex.x, vertex.y);
+
+                verts.push(Vertex::new(x, y, vertex.u, vertex.v));
+                colors.push(vertex.color);
+            }
+        }
+
+        if verts.is_empty() {
+            return;
+        }
+
+        let scissor = self.state().scissor;
+        let paint = Paint::color(Color::rgbaf(1.0, 1.0, 1.0, self.state().alpha));
+
+        let mut params = Params::new(&self.images, &paint, &scissor, 1.0, 1.0, -1.0);
+        params.shader_type = ShaderType::TexturedMesh.to_f32();
+
+        params.tex_type = match image.and_then(|id| self.images.info(id)) {
+            Some(info) => match info.format() {
+                PixelFormat::Rgba8 if !info.flags().contains(ImageFlags::PREMULTIPLIED) => 1.0,
+                PixelFormat::Gray8 => 2.0,
+                _ => 0.0,
+            },
+            // Vertex colors only
+            None => 3.0,
+        };
+
+        let mut cmd = Command::new(CommandType::Triangles { params });
+        cmd.composite_operation = self.state().composite_operation;
+        cmd.image = image;
+        cmd.triangles_verts = Some((self.verts.len(), verts.len()));
+        cmd.vertex_colors = Some(colors);
+
+        self.append_cmd(cmd);
+
+        self.verts.extend_from_slice(&verts);
+    }
+
+    /// Fills an indexed triangle list, e.g. the output of an external tessellator like lyon, with the
+    /// specified Paint.
+    ///
+    /// The vertices are in the current coordinate system and go through the same batching, scissoring,
+    /// transform and paint pipeline as paths, but the triangles are not anti-aliased. The winding of
+    /// the triangles doesn't matter. Triangles with out of range indices are skipped.
+    pub fn fill_triangles(&mut self, vertices: &[[f32; 2]], indices: &[u32], mut paint: Paint) {
+        if self.has_dangling_image(&paint) {
+            return;
+        }
+
+        let transform = self.state().transform;
+
+        let mut verts = Vec::with_capacity(indices.len());
+
+        for triangle in indices.chunks_exact(3) {
+            let points: Option<Vec<(f32, f32)>> = triangle
+                .iter()
+                .map(|index| {
+                    vertices
+                        .get(*index as usize)
+                        .map(|vertex| transform.transform_point(vertex[0], vertex[1]))
+                })
+                .collect();
+
+            let mut points = match points {
+                Some(points) => points,
+                None => continue,
+            };
+
+            // Back faces are culled by the renderer
+            let winding = (points[1].0 - points[0].0) * (points[2].1 - points[0].1)
+                - (points[2].0 - points[0].0) * (points[1].1 - points[0].1);
+
+            if winding > 0.0 {
+                points.swap(1, 2);
+            }
+
+            for (x, y) in points {
+                verts.push(Vertex::new(x, y, 0.5, 1.0));
+            }
+        }
+
+        if verts.is_empty() {
+            return;
+        }
+
+        paint.transform = transform;
+        paint.mul_alpha(self.state().alpha);
+
+        self.render_triangles(&verts, &paint);
+    }
+}
@@ -60,7 +60,19 @@ impl GradientColors {
             }
         }
     }
-    fn from_stops(stops: &[(f32, Color)]) -> GradientColors {
+    // The stops the colors were created from, two-stop gradients span 0 to 1
+    pub(crate) fn stops(&self) -> Vec<(f32, Color)> {
+        match self {
+            GradientColors::TwoStop { start_color, end_color } => vec![(0.0, *start_color), (1.0, *end_color)],
+            GradientColors::MultiStop { stops } => stops
+                .iter()
+                .take_while(|stop| stop.0 <= 1.0)
+                .map(|stop| (stop.0, stop.1))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn from_stops(stops: &[(f32, Color)]) -> GradientColors {
         if stops.is_empty() {
             // No stops, we use black.
             GradientColors::TwoStop {
@@ -111,6 +123,17 @@ pub(crate) enum PaintFlavor {
         angle: f32,
         alpha: f32,
     },
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ColorMap {
+        id: ImageId,
+        lut: ImageId,
+        cx: f32,
+        cy: f32,
+        width: f32,
+        height: f32,
+        angle: f32,
+        alpha: f32,
+    },
     LinearGradient {
         start_x: f32,
         start_y: f32,
@@ -146,6 +169,63 @@ impl PaintFlavor {
             _ => None,
         }
     }
+
+    fn gradient_colors_mut(&mut self) -> Option<&mut GradientColors> {
+        match self {
+            PaintFlavor::LinearGradient { colors, .. } => Some(colors),
+            PaintFlavor::BoxGradient { colors, .. } => Some(colors),
+            PaintFlavor::RadialGradient { colors, .. } => Some(colors),
+            _ => None,
+        }
+    }
+}
+
+/// What a [`Paint`] fills and strokes with, returned by [`Paint::kind()`].
+///
+/// The variants hold the parameters the paint was created with, the colors of gradients are
+/// returned by [`Paint::gradient_stops()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PaintKind {
+    Color(Color),
+    Image {
+        id: ImageId,
+        cx: f32,
+        cy: f32,
+        width: f32,
+        height: f32,
+        angle: f32,
+        alpha: f32,
+    },
+    ColorMap {
+        id: ImageId,
+        lut: ImageId,
+        cx: f32,
+        cy: f32,
+        width: f32,
+        height: f32,
+        angle: f32,
+        alpha: f32,
+    },
+    LinearGradient {
+        start_x: f32,
+        start_y: f32,
+        end_x: f32,
+        end_y: f32,
+    },
+    BoxGradient {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radius: f32,
+        feather: f32,
+    },
+    RadialGradient {
+        cx: f32,
+        cy: f32,
+        in_radius: f32,
+        out_radius: f32,
+    },
 }
 
 /// Struct controlling how graphical shapes are rendered.
@@ -174,11 +254,11 @@ impl PaintFlavor {
 /// canvas.stroke_path(&mut path, stroke_paint);
 /// ```
 #[derive(Copy, Clone, Debug)]
-#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Paint {
     pub(crate) flavor: PaintFlavor,
     pub(crate) transform: Transform2D,
-    #[cfg_attr(feature = "serialization", serde(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) alpha_mask: Option<ImageId>,
     pub(crate) shape_anti_alias: bool,
     pub(crate) stencil_strokes: bool,
@@ -187,7 +267,7 @@ pub struct Paint {
     pub(crate) line_cap_start: LineCap,
     pub(crate) line_cap_end: LineCap,
     pub(crate) line_join: LineJoin,
-    #[cfg_attr(feature = "serialization", serde(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) font_ids: [Option<FontId>; 8],
     pub(crate) font_size: f32,
     pub(crate) letter_spacing: f32,
@@ -219,6 +299,12 @@ impl Default for Paint {
     }
 }
 
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Self::color(color)
+    }
+}
+
 impl Paint {
     /// Creates a new solid color paint
     pub fn color(color: Color) -> Self {
@@ -262,6 +348,63 @@ impl Paint {
         new
     }
 
+    /// Creates a paint that looks the values of a scalar image up in a color texture, e.g. to draw
+    /// a heatmap or tint an elevation map.
+    ///
+    /// * `id` - is handle to the image with the values, its red channel is read, so a
+    ///   [`PixelFormat::Gray8`](crate::PixelFormat::Gray8) image is enough
+    /// * `lut` - is handle to the image with the colors, a value of 0 takes the color of its left
+    ///   and a value of 1 the color of its right edge. Images one pixel high are usual, taller ones
+    ///   are read in their middle row
+    /// * `cx` `cy` `width` `height` `angle` - Place the value image like [`Self::image()`]
+    /// * `alpha` - Transparency applied on the colors
+    ///
+    /// The colors are sampled with the filter of `lut`, [`ImageFlags::NEAREST`](crate::ImageFlags::NEAREST)
+    /// gives distinct bands instead of blending neighbouring colors. Colors along a path are drawn with the
+    /// multi-stop gradients, such as [`Self::linear_gradient_stops()`], instead.
+    ///
+    /// # Example
+    /// ```
+    /// use femtovg::{Paint, Path, Canvas, ImageFlags, PixelFormat, renderer::Void};
+    /// use imgref::Img;
+    /// use rgb::RGBA8;
+    ///
+    /// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+    ///
+    /// let values = canvas.create_image_empty(64, 64, PixelFormat::Gray8, ImageFlags::empty()).expect("Cannot create image");
+    ///
+    /// let colors = [RGBA8::new(0, 0, 255, 255), RGBA8::new(255, 255, 0, 255), RGBA8::new(255, 0, 0, 255)];
+    /// let lut = canvas.create_image(Img::new(&colors[..], 3, 1), ImageFlags::empty()).expect("Cannot create image");
+    ///
+    /// let mut path = Path::new();
+    /// path.rect(10.0, 10.0, 64.0, 64.0);
+    /// canvas.fill_path(&mut path, Paint::color_map(values, lut, 10.0, 10.0, 64.0, 64.0, 0.0, 1.0));
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn color_map(
+        id: ImageId,
+        lut: ImageId,
+        cx: f32,
+        cy: f32,
+        width: f32,
+        height: f32,
+        angle: f32,
+        alpha: f32,
+    ) -> Self {
+        let mut new = Self::default();
+        new.flavor = PaintFlavor::ColorMap {
+            id,
+            lut,
+            cx,
+            cy,
+            width,
+            height,
+            angle,
+            alpha,
+        };
+        new
+    }
+
     /// Creates and returns a linear gradient paint.
     ///
     /// The gradient is transformed by the current transform when it is passed to fill_path() or stroke_path().
@@ -337,7 +480,8 @@ impl Paint {
     /// Box gradient is a feathered rounded rectangle, it is useful for rendering
     /// drop shadows or highlights for boxes. Parameters (x,y) define the top-left corner of the rectangle,
     /// (w,h) define the size of the rectangle, r defines the corner radius, and f feather. Feather defines how blurry
-    /// the border of the rectangle is. Parameter inner_color specifies the inner color and outer_color the outer color of the gradient.
+    /// the border of the rectangle is. Unlike nanovg, which widens feathers to at least 1, feathers below a pixel are
+    /// kept, so a feather of 0 draws a hard edged box. Parameter inner_color specifies the inner color and outer_color the outer color of the gradient.
     /// The gradient is transformed by the current transform when it is passed to fill_path() or stroke_path().
     ///
     /// # Example
@@ -487,6 +631,122 @@ impl Paint {
         self.flavor = PaintFlavor::Color(color);
     }
 
+    /// Returns what the paint draws with, e.g. to show it in an inspector.
+    pub fn kind(&self) -> PaintKind {
+        match self.flavor {
+            PaintFlavor::Color(color) => PaintKind::Color(color),
+            PaintFlavor::Image {
+                id,
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+                alpha,
+            } => PaintKind::Image {
+                id,
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+                alpha,
+            },
+            PaintFlavor::ColorMap {
+                id,
+                lut,
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+                alpha,
+            } => PaintKind::ColorMap {
+                id,
+                lut,
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+                alpha,
+            },
+            PaintFlavor::LinearGradient {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                ..
+            } => PaintKind::LinearGradient {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+            },
+            PaintFlavor::BoxGradient {
+                x,
+                y,
+                width,
+                height,
+                radius,
+                feather,
+                ..
+            } => PaintKind::BoxGradient {
+                x,
+                y,
+                width,
+                height,
+                radius,
+                feather,
+            },
+            PaintFlavor::RadialGradient {
+                cx,
+                cy,
+                in_radius,
+                out_radius,
+                ..
+            } => PaintKind::RadialGradient {
+                cx,
+                cy,
+                in_radius,
+                out_radius,
+            },
+        }
+    }
+
+    /// Returns the color of a solid color paint, `None` for images and gradients.
+    pub fn solid_color(&self) -> Option<Color> {
+        match self.flavor {
+            PaintFlavor::Color(color) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// Returns the image of an image pattern paint.
+    pub fn image_id(&self) -> Option<ImageId> {
+        match self.flavor {
+            PaintFlavor::Image { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Returns the positions and colors of the stops of a gradient paint, empty for other paints.
+    ///
+    /// Gradients created with two colors have stops at 0 and 1.
+    pub fn gradient_stops(&self) -> Vec<(f32, Color)> {
+        self.flavor
+            .gradient_colors()
+            .map_or_else(Vec::new, GradientColors::stops)
+    }
+
+    /// Replaces the stops of a gradient paint and keeps its geometry, other paints are left
+    /// unchanged. Takes up to 16 stops like [`Self::linear_gradient_stops()`].
+    pub fn set_gradient_stops(&mut self, stops: &[(f32, Color)]) {
+        if let Some(colors) = self.flavor.gradient_colors_mut() {
+            *colors = GradientColors::from_stops(stops);
+        }
+    }
+
     pub(crate) fn alpha_mask(&self) -> Option<ImageId> {
         self.alpha_mask
     }
@@ -505,6 +765,9 @@ impl Paint {
     }
 
     /// Sets whether shapes drawn with this paint will be anti aliased. Enabled by default.
+    ///
+    /// Disabling antialiasing skips the generation of the fringe around the shape, which saves vertices
+    /// and fragment shading when drawing many small shapes or pixel-art content.
     pub fn set_anti_alias(&mut self, value: bool) {
         self.shape_anti_alias = value;
     }
@@ -585,6 +848,11 @@ impl Paint {
         self.line_join = join;
     }
 
+    /// Returns the fonts text is drawn with, in the order they are tried.
+    pub fn font_ids(&self) -> Vec<FontId> {
+        self.font_ids.iter().map_while(|id| *id).collect()
+    }
+
     pub fn set_font(&mut self, font_ids: &[FontId]) {
         self.font_ids = Default::default();
 
@@ -660,7 +928,7 @@ impl Paint {
             PaintFlavor::Color(color) => {
                 color.a *= a;
             }
-            PaintFlavor::Image { alpha, .. } => {
+            PaintFlavor::Image { alpha, .. } | PaintFlavor::ColorMap { alpha, .. } => {
                 *alpha *= a;
             }
             PaintFlavor::LinearGradient { colors, .. } => {
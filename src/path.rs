@@ -1,17 +1,33 @@
 use std::f32::consts::PI;
+use std::hash::{
+    Hash,
+    Hasher,
+};
 use std::slice;
 
+use fnv::FnvHasher;
+
 use crate::geometry::{
     self,
+    Point,
+    Rect,
     Transform2D,
 };
+use crate::FillRule;
 
 mod cache;
+mod simd;
 pub use cache::{
     Convexity,
     PathCache,
 };
 
+#[cfg(feature = "lyon")]
+mod lyon;
+
+// Tessellation tolerance area() and centroid() flatten curves with, the default of the canvas
+const FLATTENING_TOLERANCE: f32 = 0.25;
+
 // Length proportional to radius of a cubic bezier handle for 90deg arcs.
 const KAPPA90: f32 = 0.5522847493;
 
@@ -28,7 +44,7 @@ impl Default for Solidity {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 #[repr(u8)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PackedVerb {
@@ -40,7 +56,7 @@ pub enum PackedVerb {
     Close,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Verb {
     MoveTo(f32, f32),
     LineTo(f32, f32),
@@ -76,6 +92,10 @@ impl Verb {
 
 /// A collection of verbs (`move_to()`, `line_to()`, `bezier_to()`, etc.)
 /// describing one or more contours.
+///
+/// Coordinates must be finite. A call whose coordinates include NaN or infinity trips an assertion
+/// in debug builds and is dropped silently in release builds, so that it can't poison the
+/// tessellation of the path. A path therefore never holds non-finite coordinates.
 #[derive(Default, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Path {
@@ -85,7 +105,9 @@ pub struct Path {
     lasty: f32,
     dist_tol: f32,
     #[cfg_attr(feature = "serde", serde(skip))]
-    pub(crate) cache: Option<(u64, PathCache)>,
+    cache_key: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) cache: PathCache,
 }
 
 impl Path {
@@ -105,8 +127,21 @@ impl Path {
         self.verbs.is_empty()
     }
 
+    /// Removes all verbs from the path while keeping the allocated memory for reuse, including the
+    /// memory used to flatten and tessellate the path when it is drawn.
+    pub fn clear(&mut self) {
+        self.verbs.clear();
+        self.coords.clear();
+        self.lastx = 0.0;
+        self.lasty = 0.0;
+        self.cache_key = None;
+    }
+
     pub fn set_distance_tolerance(&mut self, value: f32) {
         self.dist_tol = value;
+
+        // The path is flattened again the next time it is drawn
+        self.cache_key = None;
     }
 
     pub fn verbs(&self) -> PathIter<'_> {
@@ -116,27 +151,210 @@ impl Path {
         }
     }
 
+    // Hash of the verbs and coordinates, identifies the shape independently of the Path instance
+    pub(crate) fn content_hash(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+
+        self.verbs.hash(&mut hasher);
+
+        for coord in &self.coords {
+            coord.to_bits().hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
+    // A copy of the path with all of its points mapped by the transform
+    pub(crate) fn transformed(&self, transform: &Transform2D) -> Self {
+        let mut path = Self {
+            verbs: self.verbs.clone(),
+            coords: self.coords.clone(),
+            lastx: self.lastx,
+            lasty: self.lasty,
+            dist_tol: self.dist_tol,
+            cache_key: None,
+            cache: PathCache::default(),
+        };
+
+        for point in path.coords.chunks_exact_mut(2) {
+            let (x, y) = transform.transform_point(point[0], point[1]);
+            point[0] = x;
+            point[1] = y;
+        }
+
+        let (lastx, lasty) = transform.transform_point(path.lastx, path.lasty);
+        path.lastx = lastx;
+        path.lasty = lasty;
+
+        path
+    }
+
+    // Returns the corners of the path in order if it consists of a single axis-aligned rectangle,
+    // as added by rect()
+    pub(crate) fn rect_corners(&self) -> Option<[(f32, f32); 4]> {
+        let rect_verbs = [
+            PackedVerb::MoveTo,
+            PackedVerb::LineTo,
+            PackedVerb::LineTo,
+            PackedVerb::LineTo,
+            PackedVerb::Close,
+        ];
+
+        if self.verbs != rect_verbs || self.coords.len() != 8 {
+            return None;
+        }
+
+        let c = &self.coords;
+
+        let vertical_first = c[0] == c[2] && c[3] == c[5] && c[4] == c[6] && c[7] == c[1];
+        let horizontal_first = c[1] == c[3] && c[2] == c[4] && c[5] == c[7] && c[6] == c[0];
+
+        if vertical_first || horizontal_first {
+            Some([(c[0], c[1]), (c[2], c[3]), (c[4], c[5]), (c[6], c[7])])
+        } else {
+            None
+        }
+    }
+
+    // Returns the contours of the path as verbs, each one wound the way the path cache winds it for
+    // drawing: solid contours get a positive area and holes a negative one. The solidity verbs are
+    // left out, so that the winding carries the solidity for formats without them.
+    pub(crate) fn wound_contours(&self) -> Vec<Vec<Verb>> {
+        self.contours()
+            .into_iter()
+            .map(|(verbs, solidity)| {
+                let area = contour_area(&verbs);
+
+                if (solidity == Solidity::Solid && area < 0.0) || (solidity == Solidity::Hole && area > 0.0) {
+                    reverse_contour(&verbs)
+                } else {
+                    verbs
+                }
+            })
+            .collect()
+    }
+
+    // Makes the contours that wind the other way round than the largest one holes, which is how the
+    // nonzero fill rule of SVG cuts them out. The path cache winds every contour as a solid one
+    // otherwise.
+    #[cfg(feature = "svg")]
+    pub(crate) fn mark_holes_by_winding(&mut self) {
+        let contours = self.contours();
+        let areas: Vec<f64> = contours.iter().map(|(verbs, _)| contour_area(verbs)).collect();
+
+        let outer = areas.iter().copied().fold(
+            0.0,
+            |outer: f64, area| {
+                if area.abs() > outer.abs() {
+                    area
+                } else {
+                    outer
+                }
+            },
+        );
+
+        self.clear();
+
+        for ((verbs, _), area) in contours.into_iter().zip(areas) {
+            for verb in verbs {
+                match verb {
+                    Verb::MoveTo(x, y) => self.move_to(x, y),
+                    Verb::LineTo(x, y) => self.line_to(x, y),
+                    Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => self.bezier_to(c1x, c1y, c2x, c2y, x, y),
+                    Verb::Close => self.close(),
+                    Verb::Solid | Verb::Hole => (),
+                }
+            }
+
+            if area * outer < 0.0 {
+                self.solidity(Solidity::Hole);
+            }
+        }
+    }
+
+    // Splits the path into contours that start with a move_to(), along with their solidity
+    fn contours(&self) -> Vec<(Vec<Verb>, Solidity)> {
+        let mut contours: Vec<(Vec<Verb>, Solidity)> = Vec::new();
+
+        for verb in self.verbs() {
+            match verb {
+                Verb::MoveTo(..) => contours.push((vec![verb], Solidity::Solid)),
+                Verb::Solid | Verb::Hole => {
+                    if let Some((_, solidity)) = contours.last_mut() {
+                        *solidity = if verb == Verb::Solid {
+                            Solidity::Solid
+                        } else {
+                            Solidity::Hole
+                        };
+                    }
+                }
+                // Like the path cache, segments before the first move_to() draw nothing
+                _ => {
+                    if let Some((verbs, _)) = contours.last_mut() {
+                        verbs.push(verb);
+                    }
+                }
+            }
+        }
+
+        contours
+    }
+
+    // Returns true if the path was last flattened for the transform and tolerance without pixel snapping
+    pub(crate) fn is_cached_for(&self, transform: &Transform2D, tess_tol: f32) -> bool {
+        self.cache_key == Some(Self::flattening_key(transform, tess_tol, None))
+    }
+
+    fn flattening_key(transform: &Transform2D, tess_tol: f32, snap_offset: Option<f32>) -> u64 {
+        let mut hasher = FnvHasher::default();
+        transform.cache_key().hash(&mut hasher);
+        tess_tol.to_bits().hash(&mut hasher);
+
+        if let Some(offset) = snap_offset {
+            offset.to_bits().hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
     pub(crate) fn cache<'a>(&'a mut self, transform: &Transform2D, tess_tol: f32, dist_tol: f32) -> &'a mut PathCache {
+        self.cache_snapped(transform, tess_tol, dist_tol, None)
+    }
+
+    /// Same as cache() but with the axis-aligned segments of the flattened path snapped to the pixel grid
+    /// shifted by `snap_offset`, see PathCache::snap_to_pixels().
+    pub(crate) fn cache_snapped<'a>(
+        &'a mut self,
+        transform: &Transform2D,
+        tess_tol: f32,
+        dist_tol: f32,
+        snap_offset: Option<f32>,
+    ) -> &'a mut PathCache {
         // The path cache saves a flattened and transformed version of the path. If client code calls
         // (fill|stroke)_path repeatedly with the same Path under the same transform circumstances then it will be
         // retrieved from cache. I'm not sure if transform.cache_key() is actually good enough for this
         // and if it will produce the correct cache keys under different float edge cases.
 
-        let key = transform.cache_key();
+        let key = Self::flattening_key(transform, tess_tol, snap_offset);
 
-        // this shouldn't need a bool once non lexic lifetimes are stable
-        let mut needs_rebuild = true;
+        if self.cache_key != Some(key) {
+            // The buffers of the previous tessellation are reused, redrawing a path under a changing
+            // transform doesn't allocate once they have grown large enough.
+            let verbs = PathIter {
+                verbs: self.verbs.iter(),
+                coords: &self.coords,
+            };
 
-        if let Some((transform_cache_key, _cache)) = self.cache.as_ref() {
-            needs_rebuild = key != *transform_cache_key;
-        }
+            self.cache.rebuild(verbs, &transform, tess_tol, dist_tol);
+
+            if let Some(offset) = snap_offset {
+                self.cache.snap_to_pixels(offset);
+            }
 
-        if needs_rebuild {
-            let path_cache = PathCache::new(self.verbs(), &transform, tess_tol, dist_tol);
-            self.cache = Some((key, path_cache));
+            self.cache_key = Some(key);
         }
 
-        &mut self.cache.as_mut().unwrap().1
+        &mut self.cache
     }
 
     // Path funcs
@@ -174,6 +392,31 @@ impl Path {
         );
     }
 
+    /// Starts new sub-path at the point, like [`Self::move_to()`] for coordinates from other math
+    /// libraries, tuples or arrays.
+    pub fn move_to_point(&mut self, point: impl Into<Point>) {
+        let point = point.into();
+        self.move_to(point.x, point.y);
+    }
+
+    /// Adds a line segment to the point, see [`Self::move_to_point()`].
+    pub fn line_to_point(&mut self, point: impl Into<Point>) {
+        let point = point.into();
+        self.line_to(point.x, point.y);
+    }
+
+    /// Adds a cubic bezier segment to the point, see [`Self::move_to_point()`].
+    pub fn bezier_to_point(&mut self, c1: impl Into<Point>, c2: impl Into<Point>, point: impl Into<Point>) {
+        let (c1, c2, point) = (c1.into(), c2.into(), point.into());
+        self.bezier_to(c1.x, c1.y, c2.x, c2.y, point.x, point.y);
+    }
+
+    /// Adds a quadratic bezier segment to the point, see [`Self::move_to_point()`].
+    pub fn quad_to_point(&mut self, c: impl Into<Point>, point: impl Into<Point>) {
+        let (c, point) = (c.into(), point.into());
+        self.quad_to(c.x, c.y, point.x, point.y);
+    }
+
     /// Closes current sub-path with a line segment.
     pub fn close(&mut self) {
         self.append(&[PackedVerb::Close], &[]);
@@ -212,7 +455,12 @@ impl Path {
         // Split arc into max 90 degree segments.
         let ndivs = ((da.abs() / (PI * 0.5) + 0.5) as i32).min(5).max(1);
         let hda = (da / ndivs as f32) / 2.0;
-        let mut kappa = (4.0 / 3.0 * (1.0 - hda.cos()) / hda.sin()).abs();
+        // An empty arc has no tangents
+        let mut kappa = if hda == 0.0 {
+            0.0
+        } else {
+            (4.0 / 3.0 * (1.0 - hda.cos()) / hda.sin()).abs()
+        };
 
         let mut commands = Vec::with_capacity(ndivs as usize);
         let mut coords = Vec::with_capacity(ndivs as usize);
@@ -270,7 +518,7 @@ impl Path {
             || geometry::dist_pt_segment(x1, y1, x0, y0, x2, y2) < self.dist_tol * self.dist_tol
             || radius < self.dist_tol
         {
-            self.line_to(x1, y1);
+            return self.line_to(x1, y1);
         }
 
         let mut dx0 = x0 - x1;
@@ -281,7 +529,7 @@ impl Path {
         geometry::normalize(&mut dx0, &mut dy0);
         geometry::normalize(&mut dx1, &mut dy1);
 
-        let a = (dx0 * dx1 + dy0 * dy1).acos();
+        let a = (dx0 * dx1 + dy0 * dy1).clamp(-1.0, 1.0).acos();
         let d = radius / (a / 2.0).tan();
 
         if d > 10000.0 {
@@ -307,6 +555,12 @@ impl Path {
         self.arc(cx, cy, radius, a0, a1, dir);
     }
 
+    /// Creates new rectangle shaped sub-path from a [`Rect`] or anything that converts into one.
+    pub fn rect_from(&mut self, rect: impl Into<Rect>) {
+        let rect = rect.into();
+        self.rect(rect.x, rect.y, rect.w, rect.h);
+    }
+
     /// Creates new rectangle shaped sub-path.
     pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.append(
@@ -463,8 +717,44 @@ impl Path {
         self.ellipse(cx, cy, r, r);
     }
 
-    /// Appends a slice of verbs to the path
+    /// Returns the area the path covers when it is filled with `fill_rule`.
+    ///
+    /// Curves are flattened like they are for drawing without a transform. Overlapping sub-paths
+    /// are counted once and holes are left out the way the fill rule cuts them, so a ring has the
+    /// area of the outer circle less that of the inner one.
+    pub fn area(&self, fill_rule: FillRule) -> f32 {
+        self.flattened().fill_area(fill_rule).0
+    }
+
+    /// Returns the center of the area the path covers when it is filled with `fill_rule`, or
+    /// None if it covers none, see [`Self::area()`].
+    ///
+    /// The centroid of a concave shape or one with holes can lie outside of it.
+    pub fn centroid(&self, fill_rule: FillRule) -> Option<Point> {
+        self.flattened().fill_area(fill_rule).1.map(Point::from)
+    }
+
+    // The path flattened without a transform, leaving the cache of the path for drawing alone
+    fn flattened(&self) -> PathCache {
+        let mut cache = PathCache::default();
+        cache.rebuild(
+            self.verbs(),
+            &Transform2D::identity(),
+            FLATTENING_TOLERANCE,
+            self.dist_tol,
+        );
+        cache
+    }
+
+    /// Appends a slice of verbs to the path, unless any of the coordinates isn't finite
     fn append(&mut self, verbs: &[PackedVerb], coords: &[f32]) {
+        let finite = coords.iter().all(|coord| coord.is_finite());
+        debug_assert!(finite, "non-finite path coordinates: {:?}", coords);
+
+        if !finite {
+            return;
+        }
+
         if coords.len() > 1 {
             self.lastx = coords[coords.len() - 2];
             self.lasty = coords[coords.len() - 1];
@@ -472,9 +762,91 @@ impl Path {
 
         self.verbs.extend_from_slice(verbs);
         self.coords.extend_from_slice(coords);
+
+        // The path is flattened again the next time it is drawn
+        self.cache_key = None;
     }
 }
 
+// The signed area of a contour that starts with a move_to(), with the sign convention of the path
+// cache. Curves are sampled, which is precise enough for the sign.
+fn contour_area(verbs: &[Verb]) -> f64 {
+    const CURVE_STEPS: usize = 16;
+
+    let mut points = Vec::new();
+
+    for verb in verbs {
+        match *verb {
+            Verb::MoveTo(x, y) | Verb::LineTo(x, y) => points.push((x, y)),
+            Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
+                let (x0, y0) = points.last().copied().unwrap_or((x, y));
+
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let mt = 1.0 - t;
+                    let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+
+                    points.push((a * x0 + b * c1x + c * c2x + d * x, a * y0 + b * c1y + c * c2y + d * y));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let base = match points.first() {
+        Some(point) => f64::from(point.1),
+        None => return 0.0,
+    };
+
+    let mut area = 0.0;
+
+    for (i, p1) in points.iter().enumerate() {
+        let p0 = points[(i + points.len() - 1) % points.len()];
+        let (y0, y1) = (f64::from(p0.1) - base, f64::from(p1.1) - base);
+        area += (f64::from(p1.0) - f64::from(p0.0)) * (y1 + y0);
+    }
+
+    area * 0.5
+}
+
+// Returns the contour traced backwards, from its last point to the one it was moved to
+fn reverse_contour(verbs: &[Verb]) -> Vec<Verb> {
+    let mut points = Vec::with_capacity(verbs.len());
+
+    for verb in verbs {
+        match *verb {
+            Verb::MoveTo(x, y) | Verb::LineTo(x, y) | Verb::BezierTo(.., x, y) => points.push((x, y)),
+            _ => (),
+        }
+    }
+
+    let (x, y) = match points.last() {
+        Some(&point) => point,
+        None => return verbs.to_vec(),
+    };
+
+    let mut reversed = vec![Verb::MoveTo(x, y)];
+    let segments = verbs
+        .iter()
+        .filter(|verb| matches!(verb, Verb::LineTo(..) | Verb::BezierTo(..)));
+
+    // Segment i ends at point i + 1 and starts at point i, where the reversed one ends
+    for (i, segment) in segments.enumerate().collect::<Vec<_>>().into_iter().rev() {
+        let (x, y) = points[i];
+
+        reversed.push(match *segment {
+            Verb::BezierTo(c1x, c1y, c2x, c2y, ..) => Verb::BezierTo(c2x, c2y, c1x, c1y, x, y),
+            _ => Verb::LineTo(x, y),
+        });
+    }
+
+    if verbs.contains(&Verb::Close) {
+        reversed.push(Verb::Close);
+    }
+
+    reversed
+}
+
 pub struct PathIter<'a> {
     verbs: slice::Iter<'a, PackedVerb>,
     coords: &'a [f32],
@@ -495,6 +867,7 @@ impl<'a> Iterator for PathIter<'a> {
     }
 }
 
+#[cfg(feature = "text")]
 impl owned_ttf_parser::OutlineBuilder for Path {
     fn move_to(&mut self, x: f32, y: f32) {
         self.move_to(x, y);
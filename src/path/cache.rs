@@ -18,6 +18,7 @@ use crate::{
     Solidity,
 };
 
+use super::simd::F32x4;
 use super::Verb;
 
 bitflags! {
@@ -77,10 +78,104 @@ impl Default for Convexity {
     }
 }
 
+// Classifies a closed contour from the directions of its segments, fed in order. The contour is
+// convex if it winds around once and never turns right. Segments shorter than the tolerance
+// are ignored and turns that deviate less than it from a straight line count as straight, so
+// that coincident and collinear points, e.g. where arcs of rounded shapes meet, don't make the
+// contour concave.
+#[derive(Default)]
+struct ConvexityTracker {
+    first: Option<(f32, f32, f32)>,
+    last: Option<(f32, f32, f32)>,
+    concave: bool,
+    x_sign: i32,
+    y_sign: i32,
+    x_first_sign: i32, // Sign of first nonzero edge vector x
+    y_first_sign: i32, // Sign of first nonzero edge vector y
+    x_flips: u32,      // Number of sign changes in x
+    y_flips: u32,      // Number of sign changes in y
+}
+
+// Distance in pixels below which a point counts as lying on the line through its neighbours
+const CONVEXITY_TOLERANCE: f32 = 0.001;
+
+// Distance from the origin in device pixels beyond which f32 coordinates are too coarse for the
+// flattening tolerance. Curves that lie entirely beyond it on one side aren't subdivided, they can't
+// be visible and subdividing huge curves would add up to 1024 points each.
+const FAR_AWAY: f32 = 16_777_216.0;
+
+impl ConvexityTracker {
+    // `dx` and `dy` are the normalized direction of the segment
+    fn add_segment(&mut self, dx: f32, dy: f32, len: f32) {
+        if len < CONVEXITY_TOLERANCE {
+            return;
+        }
+
+        if let Some(last) = self.last {
+            self.add_turn(last, (dx, dy, len));
+        } else {
+            self.first = Some((dx, dy, len));
+        }
+
+        self.last = Some((dx, dy, len));
+
+        Self::track_sign(dx, &mut self.x_sign, &mut self.x_first_sign, &mut self.x_flips);
+        Self::track_sign(dy, &mut self.y_sign, &mut self.y_first_sign, &mut self.y_flips);
+    }
+
+    fn add_turn(&mut self, (dx0, dy0, len0): (f32, f32, f32), (dx1, dy1, len1): (f32, f32, f32)) {
+        let cross = dx1 * dy0 - dx0 * dy1;
+        let deviation = cross * len0.min(len1);
+
+        // Right turns and reversals
+        if deviation < -CONVEXITY_TOLERANCE || (deviation <= CONVEXITY_TOLERANCE && dx0 * dx1 + dy0 * dy1 < 0.0) {
+            self.concave = true;
+        }
+    }
+
+    fn track_sign(delta: f32, sign: &mut i32, first_sign: &mut i32, flips: &mut u32) {
+        let new_sign = match delta.partial_cmp(&0.0) {
+            Some(Ordering::Greater) => 1,
+            Some(Ordering::Less) => -1,
+            _ => return,
+        };
+
+        if *sign == 0 {
+            *first_sign = new_sign;
+        } else if *sign != new_sign {
+            *flips += 1;
+        }
+
+        *sign = new_sign;
+    }
+
+    fn finish(mut self) -> Convexity {
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            self.add_turn(last, first);
+        }
+
+        if self.x_sign != 0 && self.x_first_sign != 0 && self.x_sign != self.x_first_sign {
+            self.x_flips += 1;
+        }
+
+        if self.y_sign != 0 && self.y_first_sign != 0 && self.y_sign != self.y_first_sign {
+            self.y_flips += 1;
+        }
+
+        if !self.concave && self.x_flips == 2 && self.y_flips == 2 {
+            Convexity::Convex
+        } else {
+            Convexity::Concave
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Contour {
     point_range: Range<usize>,
     closed: bool,
+    // Whether the contour has a segment, even one of zero length, and isn't a lone move_to
+    segments: bool,
     bevel: usize,
     solidity: Solidity,
     pub(crate) fill: Vec<Vertex>,
@@ -93,6 +188,7 @@ impl Default for Contour {
         Self {
             point_range: 0..0,
             closed: Default::default(),
+            segments: Default::default(),
             bevel: Default::default(),
             solidity: Default::default(),
             fill: Default::default(),
@@ -111,13 +207,21 @@ impl Contour {
     }
 
     fn polygon_area(points: &[Point]) -> f32 {
+        // Relative to the first point and in double precision, the terms of contours far from the
+        // origin would cancel out otherwise and flip the winding
+        let base = match points.first() {
+            Some(point) => f64::from(point.y),
+            None => return 0.0,
+        };
+
         let mut area = 0.0;
 
         for (p0, p1) in (PointPairsIter { curr: 0, points }) {
-            area += (p1.x - p0.x) * (p1.y + p0.y);
+            let (y0, y1) = (f64::from(p0.y) - base, f64::from(p1.y) - base);
+            area += (f64::from(p1.x) - f64::from(p0.x)) * (y1 + y0);
         }
 
-        area * 0.5
+        (area * 0.5) as f32
     }
 
     fn point_count(&self) -> usize {
@@ -125,6 +229,27 @@ impl Contour {
     }
 }
 
+// The height at which two edges going down cross, if they do between their ends
+fn edge_crossing(a: &[f64; 4], b: &[f64; 4]) -> Option<f64> {
+    let (top, bottom) = (a[1].max(b[1]), a[3].min(b[3]));
+
+    if top >= bottom {
+        return None;
+    }
+
+    let x_at = |e: &[f64; 4], y: f64| e[0] + (e[2] - e[0]) * (y - e[1]) / (e[3] - e[1]);
+
+    // Signed horizontal distance between the edges at the top and the bottom of their overlap
+    let d0 = x_at(a, top) - x_at(b, top);
+    let d1 = x_at(a, bottom) - x_at(b, bottom);
+
+    if (d0 < 0.0 && d1 > 0.0) || (d0 > 0.0 && d1 < 0.0) {
+        Some(top + (bottom - top) * d0 / (d0 - d1))
+    } else {
+        None
+    }
+}
+
 struct PointPairsIter<'a> {
     curr: usize,
     points: &'a [Point],
@@ -153,31 +278,82 @@ pub struct PathCache {
     pub(crate) contours: Vec<Contour>,
     pub(crate) bounds: Bounds,
     points: Vec<Point>,
+    // Vertex buffers of previous contours, handed out again by add_contour()
+    spare_verts: Vec<Vec<Vertex>>,
+    snaps: Vec<(bool, bool)>,
+    // Identifies the parameters the contours were last expanded with, None after flattening
+    pub(crate) expansion_key: Option<u64>,
 }
 
 impl PathCache {
-    pub fn new(verbs: impl Iterator<Item = Verb>, transform: &Transform2D, tess_tol: f32, dist_tol: f32) -> Self {
-        let mut cache = Self::default();
+    /// Returns the number of bytes allocated by the buffers of the cache, including the spare ones.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let vertex_bytes: usize = self
+            .contours
+            .iter()
+            .map(|contour| contour.fill.capacity() + contour.stroke.capacity())
+            .chain(self.spare_verts.iter().map(Vec::capacity))
+            .sum::<usize>()
+            * std::mem::size_of::<Vertex>();
+
+        vertex_bytes
+            + self.contours.capacity() * std::mem::size_of::<Contour>()
+            + self.points.capacity() * std::mem::size_of::<Point>()
+            + self.spare_verts.capacity() * std::mem::size_of::<Vec<Vertex>>()
+            + self.snaps.capacity() * std::mem::size_of::<(bool, bool)>()
+    }
+
+    /// Returns the flattened points of each contour and whether the contour is closed, the closing
+    /// point of a closed contour isn't repeated.
+    pub(crate) fn polylines(&self) -> impl Iterator<Item = (Vec<(f32, f32)>, bool)> + '_ {
+        self.contours.iter().map(move |contour| {
+            let points = self.points[contour.point_range.clone()]
+                .iter()
+                .map(|point| (point.x, point.y))
+                .collect();
+
+            (points, contour.closed)
+        })
+    }
+
+    /// Flattens and transforms the path described by `verbs`, replacing the previous contents.
+    ///
+    /// The memory of the previous contents is reused, so a path that is flattened again with a
+    /// similar number of points doesn't allocate.
+    pub(crate) fn rebuild(
+        &mut self,
+        verbs: impl Iterator<Item = Verb>,
+        transform: &Transform2D,
+        tess_tol: f32,
+        dist_tol: f32,
+    ) {
+        self.clear();
 
         // Convert path verbs to a set of contours
         for verb in verbs {
+            if matches!(verb, Verb::LineTo(..) | Verb::BezierTo(..) | Verb::Close) {
+                if let Some(contour) = self.contours.last_mut() {
+                    contour.segments = true;
+                }
+            }
+
             match verb {
                 Verb::MoveTo(x, y) => {
-                    cache.add_contour();
+                    self.add_contour();
                     let (x, y) = transform.transform_point(x, y);
-                    cache.add_point(x, y, PointFlags::CORNER, dist_tol);
+                    self.add_point(x, y, PointFlags::CORNER, dist_tol);
                 }
                 Verb::LineTo(x, y) => {
                     let (x, y) = transform.transform_point(x, y);
-                    cache.add_point(x, y, PointFlags::CORNER, dist_tol);
+                    self.add_point(x, y, PointFlags::CORNER, dist_tol);
                 }
                 Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
-                    if let Some(last) = cache.points.last().copied() {
+                    if let Some(last) = self.points.last().copied() {
                         let (c1x, c1y) = transform.transform_point(c1x, c1y);
                         let (c2x, c2y) = transform.transform_point(c2x, c2y);
                         let (x, y) = transform.transform_point(x, y);
 
-                        cache.tesselate_bezier(
+                        self.tesselate_bezier(
                             last.x,
                             last.y,
                             c1x,
@@ -192,7 +368,7 @@ impl PathCache {
                             dist_tol,
                         );
 
-                        // cache.tesselate_bezier_afd(
+                        // self.tesselate_bezier_afd(
                         //     last.x,
                         //     last.y,
                         //     c1x,
@@ -208,39 +384,40 @@ impl PathCache {
                     }
                 }
                 Verb::Close => {
-                    if let Some(contour) = cache.contours.last_mut() {
+                    if let Some(contour) = self.contours.last_mut() {
                         contour.closed = true;
                     }
                 }
                 Verb::Solid => {
-                    if let Some(contour) = cache.contours.last_mut() {
+                    if let Some(contour) = self.contours.last_mut() {
                         contour.solidity = Solidity::Solid;
                     }
                 }
                 Verb::Hole => {
-                    if let Some(contour) = cache.contours.last_mut() {
+                    if let Some(contour) = self.contours.last_mut() {
                         contour.solidity = Solidity::Hole;
                     }
                 }
             }
         }
 
-        let all_points = &mut cache.points;
-        let bounds = &mut cache.bounds;
+        let all_points = &mut self.points;
+        let bounds = &mut self.bounds;
 
-        cache.contours.retain_mut(|contour| {
+        self.contours.retain_mut(|contour| {
             let mut points = &mut all_points[contour.point_range.clone()];
 
             // If the first and last points are the same, remove the last, mark as closed contour.
             if let (Some(p0), Some(p1)) = (points.last(), points.first()) {
-                if p0.approx_eq(&p1, dist_tol) {
+                if points.len() > 1 && p0.approx_eq(&p1, dist_tol) {
                     contour.point_range.end -= 1;
                     contour.closed = true;
                     points = &mut all_points[contour.point_range.clone()];
                 }
             }
 
-            if points.len() < 2 {
+            // Zero length subpaths are kept for the caps a stroke draws for them
+            if points.is_empty() || (points.len() == 1 && !contour.segments) {
                 return false;
             }
 
@@ -255,33 +432,110 @@ impl PathCache {
                 points.reverse();
             }
 
-            for i in 0..contour.point_count() {
-                let p1 = points.get(i).copied().unwrap();
+            update_segments(points, bounds);
 
-                let p0 = if i == 0 {
-                    points.last_mut().unwrap()
-                } else {
-                    points.get_mut(i - 1).unwrap()
-                };
+            true
+        });
+    }
 
-                p0.dx = p1.x - p0.x;
-                p0.dy = p1.y - p0.y;
-                p0.len = geometry::normalize(&mut p0.dx, &mut p0.dy);
+    /// Copies the expanded vertices to `out`, mapped by the transform.
+    ///
+    /// Only what drawing needs is copied: the vertices and convexity of the contours and the bounds,
+    /// which cover the vertices. The copy can't be expanded again.
+    pub(crate) fn transform_into(&self, transform: &Transform2D, out: &mut Self) {
+        out.clear();
+
+        for contour in &self.contours {
+            out.add_contour();
+
+            let bounds = &mut out.bounds;
+            let copy = out.contours.last_mut().unwrap();
+            copy.closed = contour.closed;
+            copy.solidity = contour.solidity;
+            copy.convexity = contour.convexity;
+
+            for (src, dst) in [(&contour.fill, &mut copy.fill), (&contour.stroke, &mut copy.stroke)] {
+                dst.extend(src.iter().map(|vertex| {
+                    let (x, y) = transform.transform_point(vertex.x, vertex.y);
+
+                    bounds.minx = bounds.minx.min(x);
+                    bounds.miny = bounds.miny.min(y);
+                    bounds.maxx = bounds.maxx.max(x);
+                    bounds.maxy = bounds.maxy.max(y);
+
+                    Vertex::new(x, y, vertex.u, vertex.v)
+                }));
+            }
+        }
+    }
 
-                bounds.minx = bounds.minx.min(p0.x);
-                bounds.miny = bounds.miny.min(p0.y);
-                bounds.maxx = bounds.maxx.max(p0.x);
-                bounds.maxy = bounds.maxy.max(p0.y);
+    fn clear(&mut self) {
+        // In reverse so that every contour gets its own buffers back when the path hasn't changed
+        for contour in self.contours.drain(..).rev() {
+            for mut verts in [contour.stroke, contour.fill] {
+                if verts.capacity() > 0 {
+                    verts.clear();
+                    self.spare_verts.push(verts);
+                }
             }
+        }
 
-            true
-        });
+        self.points.clear();
+        self.bounds = Bounds::default();
+        self.expansion_key = None;
+    }
+
+    /// Moves the points of horizontal and vertical segments onto the pixel grid shifted by `offset`.
+    ///
+    /// An offset of 0.0 puts edges of filled shapes on pixel boundaries, 0.5 puts the center of odd
+    /// width strokes on pixel centers.
+    pub(crate) fn snap_to_pixels(&mut self, offset: f32) {
+        const TOLERANCE: f32 = 1e-3;
+
+        let snap = |v: f32| (v - offset).round() + offset;
+
+        self.bounds = Bounds::default();
+        self.expansion_key = None;
+
+        for contour in &self.contours {
+            let points = &mut self.points[contour.point_range.clone()];
+            let len = points.len();
+
+            // Decide what to snap before moving anything so that the decision for a point doesn't
+            // depend on whether its neighbour has already been snapped.
+            let snaps = &mut self.snaps;
+            snaps.clear();
+
+            for (i, p) in points.iter().enumerate() {
+                let prev = points[(i + len - 1) % len];
+                let next = points[(i + 1) % len];
+
+                let snap_x = (p.x - prev.x).abs() < TOLERANCE || (p.x - next.x).abs() < TOLERANCE;
+                let snap_y = (p.y - prev.y).abs() < TOLERANCE || (p.y - next.y).abs() < TOLERANCE;
+
+                snaps.push((snap_x, snap_y));
+            }
+
+            for (point, (snap_x, snap_y)) in points.iter_mut().zip(snaps.iter().copied()) {
+                if snap_x {
+                    point.x = snap(point.x);
+                }
+
+                if snap_y {
+                    point.y = snap(point.y);
+                }
+            }
 
-        cache
+            update_segments(points, &mut self.bounds);
+        }
     }
 
     fn add_contour(&mut self) {
-        let mut contour = Contour::default();
+        let mut contour = Contour {
+            fill: self.spare_verts.pop().unwrap_or_default(),
+            stroke: self.spare_verts.pop().unwrap_or_default(),
+            ..Default::default()
+        };
 
         contour.point_range.start = self.points.len();
         contour.point_range.end = self.points.len();
@@ -294,7 +548,9 @@ impl PathCache {
             let new_point = Point::new(x, y, flags);
 
             // If last point equals this new point just OR the flags and ignore the new point
-            if let Some(last_point) = self.points.get_mut(contour.point_range.end) {
+            if contour.point_range.end > contour.point_range.start {
+                let last_point = &mut self.points[contour.point_range.end - 1];
+
                 if last_point.approx_eq(&new_point, dist_tol) {
                     last_point.flags |= new_point.flags;
                     return;
@@ -325,14 +581,29 @@ impl PathCache {
             return;
         }
 
-        let x12 = (x1 + x2) * 0.5;
-        let y12 = (y1 + y2) * 0.5;
-        let x23 = (x2 + x3) * 0.5;
-        let y23 = (y2 + y3) * 0.5;
-        let x34 = (x3 + x4) * 0.5;
-        let y34 = (y3 + y4) * 0.5;
-        let x123 = (x12 + x23) * 0.5;
-        let y123 = (y12 + y23) * 0.5;
+        let xs = [x1, x2, x3, x4];
+        let ys = [y1, y2, y3, y4];
+
+        let far_away = xs.iter().all(|x| *x > FAR_AWAY)
+            || xs.iter().all(|x| *x < -FAR_AWAY)
+            || ys.iter().all(|y| *y > FAR_AWAY)
+            || ys.iter().all(|y| *y < -FAR_AWAY);
+
+        if far_away {
+            self.add_point(x4, y4, flags, dist_tol);
+            return;
+        }
+
+        // The x and y coordinates of two midpoints are computed at once
+        let half = F32x4::splat(0.5);
+        let p12 = F32x4::new(x1, y1, x2, y2);
+        let p23 = F32x4::new(x2, y2, x3, y3);
+        let p34 = F32x4::new(x3, y3, x4, y4);
+
+        let [x12, y12, x23, y23] = ((p12 + p23) * half).to_array();
+        let [_, _, x34, y34] = ((p23 + p34) * half).to_array();
+        let [x123, y123, x234, y234] =
+            ((F32x4::new(x12, y12, x23, y23) + F32x4::new(x23, y23, x34, y34)) * half).to_array();
 
         let dx = x4 - x1;
         let dy = y4 - y1;
@@ -344,8 +615,6 @@ impl PathCache {
             return;
         }
 
-        let x234 = (x23 + x34) * 0.5;
-        let y234 = (y23 + y34) * 0.5;
         let x1234 = (x123 + x234) * 0.5;
         let y1234 = (y123 + y234) * 0.5;
 
@@ -496,27 +765,25 @@ impl PathCache {
             return false;
         }
 
+        // Like the stencil the renderer fills with, crossings are counted over all contours so
+        // that holes are cut out of the contours around them
         if fill_rule == FillRule::EvenOdd {
-            for contour in &self.contours {
-                let mut crossing = false;
+            let mut crossing = false;
 
+            for contour in &self.contours {
                 for (p0, p1) in contour.point_pairs(&self.points) {
                     if (p1.y > y) != (p0.y > y) && (x < (p0.x - p1.x) * (y - p1.y) / (p0.y - p1.y) + p1.x) {
                         crossing = !crossing;
                     }
                 }
-
-                if crossing {
-                    return true;
-                }
             }
 
-            false
+            crossing
         } else {
             // NonZero
-            for contour in &self.contours {
-                let mut winding_number: i32 = 0;
+            let mut winding_number: i32 = 0;
 
+            for contour in &self.contours {
                 for (p0, p1) in contour.point_pairs(&self.points) {
                     if p0.y <= y {
                         if p1.y > y && Point::is_left(p0, p1, x, y) > 0.0 {
@@ -526,14 +793,106 @@ impl PathCache {
                         winding_number = winding_number.wrapping_sub(1);
                     }
                 }
+            }
+
+            winding_number != 0
+        }
+    }
+
+    /// Returns the area covered by the fill of the flattened path and the center of that area, or
+    /// None for the center if the area is empty.
+    ///
+    /// The plane is cut into horizontal slabs at every vertex and edge crossing, so that no edges
+    /// cross inside a slab. The filled parts of a slab are trapezoids between neighbouring edges,
+    /// which are filled or not by the winding number left of them like the stencil fill decides.
+    pub(crate) fn fill_area(&self, fill_rule: FillRule) -> (f32, Option<(f32, f32)>) {
+        // Edges going down, with +1 or -1 for the direction they had in the contour
+        let mut edges: Vec<([f64; 4], i32)> = Vec::new();
+
+        for contour in &self.contours {
+            for (p0, p1) in contour.point_pairs(&self.points) {
+                let (x0, y0, x1, y1) = (p0.x as f64, p0.y as f64, p1.x as f64, p1.y as f64);
+
+                if y0 < y1 {
+                    edges.push(([x0, y0, x1, y1], 1));
+                } else if y1 < y0 {
+                    edges.push(([x1, y1, x0, y0], -1));
+                }
+            }
+        }
+
+        let mut ys: Vec<f64> = edges.iter().flat_map(|(e, _)| [e[1], e[3]]).collect();
 
-                if winding_number != 0 {
-                    return true;
+        for (i, (a, _)) in edges.iter().enumerate() {
+            for (b, _) in &edges[i + 1..] {
+                if let Some(y) = edge_crossing(a, b) {
+                    ys.push(y);
                 }
             }
+        }
+
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        ys.dedup();
 
-            false
+        let x_at = |e: &[f64; 4], y: f64| e[0] + (e[2] - e[0]) * (y - e[1]) / (e[3] - e[1]);
+
+        let (mut area, mut moment_x, mut moment_y) = (0.0, 0.0, 0.0);
+        let mut slab: Vec<(f64, f64, i32)> = Vec::new();
+
+        for pair in ys.windows(2) {
+            let (top, bottom) = (pair[0], pair[1]);
+            let height = bottom - top;
+
+            slab.clear();
+            slab.extend(
+                edges
+                    .iter()
+                    .filter(|(e, _)| e[1] <= top && e[3] >= bottom)
+                    .map(|(e, dir)| (x_at(e, top), x_at(e, bottom), *dir)),
+            );
+            slab.sort_by(|a, b| (a.0 + a.1).partial_cmp(&(b.0 + b.1)).unwrap_or(Ordering::Equal));
+
+            let mut winding = 0;
+
+            for pair in slab.windows(2) {
+                let (left, right) = (pair[0], pair[1]);
+                winding += left.2;
+
+                let filled = match fill_rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+
+                if !filled {
+                    continue;
+                }
+
+                let (top_width, bottom_width) = (right.0 - left.0, right.1 - left.1);
+                let trapezoid = height * (top_width + bottom_width) / 2.0;
+
+                if trapezoid <= 0.0 {
+                    continue;
+                }
+
+                // The centroid of a trapezoid lies on the line between the midpoints of its
+                // parallel sides, closer to the longer one
+                let t = (top_width + 2.0 * bottom_width) / (3.0 * (top_width + bottom_width));
+                let top_mid = (left.0 + right.0) / 2.0;
+                let bottom_mid = (left.1 + right.1) / 2.0;
+
+                area += trapezoid;
+                moment_x += trapezoid * (top_mid + (bottom_mid - top_mid) * t);
+                moment_y += trapezoid * (top + height * t);
+            }
         }
+
+        let centroid = if area > 0.0 {
+            Some(((moment_x / area) as f32, (moment_y / area) as f32))
+        } else {
+            None
+        };
+
+        (area as f32, centroid)
     }
 
     pub(crate) fn expand_fill(&mut self, fringe_width: f32, line_join: LineJoin, miter_limit: f32) {
@@ -560,6 +919,11 @@ impl PathCache {
             contour.stroke.clear();
             contour.fill.clear();
 
+            // Zero length subpaths have no area
+            if contour.point_count() < 2 {
+                continue;
+            }
+
             // TODO: woff = 0.0 produces no artifaacts for small sizes
             let woff = 0.5 * fringe_width;
             //let woff = 0.0; // Makes everything thicker
@@ -609,12 +973,7 @@ impl PathCache {
                     if p1.flags.contains(PointFlags::BEVEL | PointFlags::INNERBEVEL) {
                         bevel_join(&mut contour.stroke, p0, &p1, lw, rw, lu, ru);
                     } else {
-                        contour
-                            .stroke
-                            .push(Vertex::new(p1.x + (p1.dmx * lw), p1.y + (p1.dmy * lw), lu, 1.0));
-                        contour
-                            .stroke
-                            .push(Vertex::new(p1.x - (p1.dmx * rw), p1.y - (p1.dmy * rw), ru, 1.0));
+                        miter_pair(&mut contour.stroke, p1, lw, rw, lu, ru);
                     }
                 }
 
@@ -637,6 +996,8 @@ impl PathCache {
         miter_limit: f32,
         tess_tol: f32,
     ) {
+        // The points, the stroke width and the tolerance are in device pixels, so round caps and
+        // joins get fewer segments the smaller they appear on screen
         let ncap = curve_divisions(stroke_width, PI, tess_tol);
 
         let stroke_width = stroke_width + (fringe_width * 0.5);
@@ -646,37 +1007,37 @@ impl PathCache {
 
         self.calculate_joins(stroke_width, line_join, miter_limit);
 
+        let caps = Caps {
+            start: line_cap_start,
+            end: line_cap_end,
+            width: stroke_width,
+            fringe_width,
+            ncap: ncap as usize,
+            u0,
+            u1,
+        };
+
         for contour in &mut self.contours {
             contour.stroke.clear();
 
+            // Zero length subpaths are drawn as their two caps, facing right like in SVG
+            if contour.point_count() == 1 {
+                let mut point = self.points[contour.point_range.start];
+                point.dx = 1.0;
+                point.dy = 0.0;
+
+                if line_cap_start != LineCap::Butt || line_cap_end != LineCap::Butt {
+                    caps.add_start(&mut contour.stroke, &point, &point);
+                    caps.add_end(&mut contour.stroke, &point, &point);
+                }
+
+                continue;
+            }
+
             for (i, (p0, p1)) in contour.point_pairs(&self.points).enumerate() {
                 // Add start cap
                 if !contour.closed && i == 1 {
-                    match line_cap_start {
-                        LineCap::Butt => butt_cap_start(
-                            &mut contour.stroke,
-                            &p0,
-                            &p0,
-                            stroke_width,
-                            -fringe_width * 0.5,
-                            fringe_width,
-                            u0,
-                            u1,
-                        ),
-                        LineCap::Square => butt_cap_start(
-                            &mut contour.stroke,
-                            &p0,
-                            &p0,
-                            stroke_width,
-                            stroke_width - fringe_width,
-                            fringe_width,
-                            u0,
-                            u1,
-                        ),
-                        LineCap::Round => {
-                            round_cap_start(&mut contour.stroke, &p0, &p0, stroke_width, ncap as usize, u0, u1)
-                        }
-                    }
+                    caps.add_start(&mut contour.stroke, p0, p0);
                 }
 
                 if (i > 0 && i < contour.point_count() - 1) || contour.closed {
@@ -696,48 +1057,13 @@ impl PathCache {
                             bevel_join(&mut contour.stroke, &p0, &p1, stroke_width, stroke_width, u0, u1);
                         }
                     } else {
-                        contour.stroke.push(Vertex::new(
-                            p1.x + (p1.dmx * stroke_width),
-                            p1.y + (p1.dmy * stroke_width),
-                            u0,
-                            1.0,
-                        ));
-                        contour.stroke.push(Vertex::new(
-                            p1.x - (p1.dmx * stroke_width),
-                            p1.y - (p1.dmy * stroke_width),
-                            u1,
-                            1.0,
-                        ));
+                        miter_pair(&mut contour.stroke, p1, stroke_width, stroke_width, u0, u1);
                     }
                 }
 
                 // Add end cap
                 if !contour.closed && i == contour.point_count() - 1 {
-                    match line_cap_end {
-                        LineCap::Butt => butt_cap_end(
-                            &mut contour.stroke,
-                            &p1,
-                            &p0,
-                            stroke_width,
-                            -fringe_width * 0.5,
-                            fringe_width,
-                            u0,
-                            u1,
-                        ),
-                        LineCap::Square => butt_cap_end(
-                            &mut contour.stroke,
-                            &p1,
-                            &p0,
-                            stroke_width,
-                            stroke_width - fringe_width,
-                            fringe_width,
-                            u0,
-                            u1,
-                        ),
-                        LineCap::Round => {
-                            round_cap_end(&mut contour.stroke, &p1, &p0, stroke_width, ncap as usize, u0, u1)
-                        }
-                    }
+                    caps.add_end(&mut contour.stroke, p1, p0);
                 }
             }
 
@@ -757,17 +1083,10 @@ impl PathCache {
 
         for contour in &mut self.contours {
             let points = &mut self.points[contour.point_range.clone()];
-            let mut nleft = 0;
+            let mut convexity = ConvexityTracker::default();
 
             contour.bevel = 0;
 
-            let mut x_sign = 0;
-            let mut y_sign = 0;
-            let mut x_first_sign = 0; // Sign of first nonzero edge vector x
-            let mut y_first_sign = 0; // Sign of first nonzero edge vector y
-            let mut x_flips = 0; // Number of sign changes in x
-            let mut y_flips = 0; // Number of sign changes in y
-
             for i in 0..points.len() {
                 let p0 = if i == 0 {
                     points.get(points.len() - 1).copied().unwrap()
@@ -805,54 +1124,10 @@ impl PathCache {
                 let cross = p1.dx * p0.dy - p0.dx * p1.dy;
 
                 if cross > 0.0 {
-                    nleft += 1;
                     p1.flags |= PointFlags::LEFT;
                 }
 
-                // Determine sign for convexity
-                match p1.dx.partial_cmp(&0.0) {
-                    Some(Ordering::Greater) => {
-                        match x_sign.cmp(&0) {
-                            Ordering::Equal => x_first_sign = 1,
-                            Ordering::Less => x_flips += 1,
-                            _ => (),
-                        }
-
-                        x_sign = 1;
-                    }
-                    Some(Ordering::Less) => {
-                        match x_sign.cmp(&0) {
-                            Ordering::Equal => x_first_sign = -1,
-                            Ordering::Greater => x_flips += 1,
-                            _ => (),
-                        }
-
-                        x_sign = -1;
-                    }
-                    _ => (),
-                }
-
-                match p1.dy.partial_cmp(&0.0) {
-                    Some(Ordering::Greater) => {
-                        match y_sign.cmp(&0) {
-                            Ordering::Equal => y_first_sign = 1,
-                            Ordering::Less => y_flips += 1,
-                            _ => (),
-                        }
-
-                        y_sign = 1;
-                    }
-                    Some(Ordering::Less) => {
-                        match y_sign.cmp(&0) {
-                            Ordering::Equal => y_first_sign = -1,
-                            Ordering::Greater => y_flips += 1,
-                            _ => (),
-                        }
-
-                        y_sign = -1;
-                    }
-                    _ => (),
-                }
+                convexity.add_segment(p1.dx, p1.dy, p1.len);
 
                 // Calculate if we should use bevel or miter for inner join.
                 let limit = (p0.len.min(p1.len) * inv_stroke_width).max(1.01);
@@ -876,29 +1151,99 @@ impl PathCache {
                 }
             }
 
-            if x_sign != 0 && x_first_sign != 0 && x_sign != x_first_sign {
-                x_flips += 1;
-            }
+            contour.convexity = convexity.finish();
+        }
+    }
+}
 
-            if y_sign != 0 && y_first_sign != 0 && y_sign != y_first_sign {
-                y_flips += 1;
-            }
+// Calculates the direction and length of the segment starting at each point and grows bounds to include the points
+fn update_segments(points: &mut [Point], bounds: &mut Bounds) {
+    for i in 0..points.len() {
+        let p1 = points.get(i).copied().unwrap();
 
-            let convex = x_flips == 2 && y_flips == 2;
+        let p0 = if i == 0 {
+            points.last_mut().unwrap()
+        } else {
+            points.get_mut(i - 1).unwrap()
+        };
 
-            contour.convexity = if nleft == points.len() && convex {
-                Convexity::Convex
-            } else {
-                Convexity::Concave
-            };
-        }
+        p0.dx = p1.x - p0.x;
+        p0.dy = p1.y - p0.y;
+        p0.len = geometry::normalize(&mut p0.dx, &mut p0.dy);
+
+        bounds.minx = bounds.minx.min(p0.x);
+        bounds.miny = bounds.miny.min(p0.y);
+        bounds.maxx = bounds.maxx.max(p0.x);
+        bounds.maxy = bounds.maxy.max(p0.y);
     }
 }
 
+// Upper bound of curve_divisions(), round caps and joins of strokes that are far wider than any
+// render target would otherwise allocate billions of vertices
+const MAX_CURVE_DIVISIONS: u32 = 1024;
+
 fn curve_divisions(radius: f32, arc: f32, tol: f32) -> u32 {
     let da = (radius / (radius + tol)).acos() * 2.0;
 
-    ((arc / da).ceil() as u32).max(2)
+    ((arc / da).ceil() as u32).clamp(2, MAX_CURVE_DIVISIONS)
+}
+
+// Adds the vertices `lw` to the left and `rw` to the right of the point along its miter direction
+fn miter_pair(verts: &mut Vec<Vertex>, p1: &Point, lw: f32, rw: f32, lu: f32, ru: f32) {
+    let position = F32x4::new(p1.x, p1.y, p1.x, p1.y);
+    let miter = F32x4::new(p1.dmx, p1.dmy, p1.dmx, p1.dmy);
+
+    let [lx, ly, rx, ry] = (position + miter * F32x4::new(lw, lw, -rw, -rw)).to_array();
+
+    verts.push(Vertex::new(lx, ly, lu, 1.0));
+    verts.push(Vertex::new(rx, ry, ru, 1.0));
+}
+
+// The caps of a stroke, the width includes half of the fringe
+struct Caps {
+    start: LineCap,
+    end: LineCap,
+    width: f32,
+    fringe_width: f32,
+    ncap: usize,
+    u0: f32,
+    u1: f32,
+}
+
+impl Caps {
+    // Adds the start cap at p0, facing away from the direction of p1
+    fn add_start(&self, verts: &mut Vec<Vertex>, p0: &Point, p1: &Point) {
+        let Self {
+            width: w,
+            fringe_width: aa,
+            u0,
+            u1,
+            ..
+        } = *self;
+
+        match self.start {
+            LineCap::Butt => butt_cap_start(verts, p0, p1, w, -aa * 0.5, aa, u0, u1),
+            LineCap::Square => butt_cap_start(verts, p0, p1, w, w - aa, aa, u0, u1),
+            LineCap::Round => round_cap_start(verts, p0, p1, w, self.ncap, u0, u1),
+        }
+    }
+
+    // Adds the end cap at p0, facing the direction of p1
+    fn add_end(&self, verts: &mut Vec<Vertex>, p0: &Point, p1: &Point) {
+        let Self {
+            width: w,
+            fringe_width: aa,
+            u0,
+            u1,
+            ..
+        } = *self;
+
+        match self.end {
+            LineCap::Butt => butt_cap_end(verts, p0, p1, w, -aa * 0.5, aa, u0, u1),
+            LineCap::Square => butt_cap_end(verts, p0, p1, w, w - aa, aa, u0, u1),
+            LineCap::Round => round_cap_end(verts, p0, p1, w, self.ncap, u0, u1),
+        }
+    }
 }
 
 fn butt_cap_start(verts: &mut Vec<Vertex>, p0: &Point, p1: &Point, w: f32, d: f32, aa: f32, u0: f32, u1: f32) {
@@ -1154,11 +1499,246 @@ mod tests {
 
         let transform = Transform2D::identity();
 
-        let mut path_cache = PathCache::new(path.verbs(), &transform, 0.25, 0.01);
+        let mut path_cache = PathCache::default();
+        path_cache.rebuild(path.verbs(), &transform, 0.25, 0.01);
         path_cache.expand_fill(1.0, LineJoin::Miter, 10.0);
 
         assert_eq!(path_cache.contours[0].convexity, Convexity::Concave);
     }
+
+    #[test]
+    fn axis_aligned_edges_are_snapped_to_pixels() {
+        let mut path = Path::new();
+        path.rect(10.3, 20.6, 30.2, 10.0);
+        path.move_to(0.0, 0.0);
+        path.line_to(5.3, 5.3);
+
+        let transform = Transform2D::identity();
+
+        let mut path_cache = PathCache::default();
+        path_cache.rebuild(path.verbs(), &transform, 0.25, 0.01);
+        path_cache.snap_to_pixels(0.5);
+
+        let rect = &path_cache.points[path_cache.contours[0].point_range.clone()];
+        assert!(rect.iter().all(|p| p.x.fract() == 0.5 && p.y.fract() == 0.5));
+
+        // Diagonal segments are left alone
+        let line = &path_cache.points[path_cache.contours[1].point_range.clone()];
+        assert_eq!(line[1].x, 5.3);
+
+        assert_eq!(path_cache.bounds.minx, 0.0);
+        assert_eq!(path_cache.bounds.maxx, 40.5);
+        assert_eq!(path_cache.bounds.maxy, 30.5);
+    }
+
+    #[test]
+    fn rebuild_reuses_buffers() {
+        let mut path = Path::new();
+        path.rounded_rect(10.0, 10.0, 100.0, 50.0, 8.0);
+        path.circle(50.0, 50.0, 20.0);
+
+        let transform = Transform2D::identity();
+
+        let mut path_cache = PathCache::default();
+        path_cache.rebuild(path.verbs(), &transform, 0.25, 0.01);
+        path_cache.expand_fill(1.0, LineJoin::Miter, 10.0);
+
+        let points = path_cache.points.as_ptr();
+        let mut buffers: Vec<*const Vertex> = path_cache
+            .contours
+            .iter()
+            .flat_map(|contour| [contour.fill.as_ptr(), contour.stroke.as_ptr()])
+            .collect();
+
+        let mut transform = Transform2D::identity();
+        transform.translate(5.0, 5.0);
+
+        path_cache.rebuild(path.verbs(), &transform, 0.25, 0.01);
+        path_cache.expand_fill(1.0, LineJoin::Miter, 10.0);
+
+        let mut reused: Vec<*const Vertex> = path_cache
+            .contours
+            .iter()
+            .flat_map(|contour| [contour.fill.as_ptr(), contour.stroke.as_ptr()])
+            .collect();
+
+        buffers.sort();
+        reused.sort();
+
+        assert_eq!(path_cache.points.as_ptr(), points);
+        assert_eq!(buffers, reused);
+        assert_eq!(path_cache.bounds.minx, 15.0);
+    }
+
+    #[test]
+    fn rounded_shapes_are_convex() {
+        use std::f32::consts::PI;
+
+        let mut pill = Path::new();
+        pill.rounded_rect(10.0, 10.0, 100.0, 50.0, 25.0);
+
+        // Rounded rectangle with corners drawn as separate arcs
+        let mut arcs = Path::new();
+        arcs.arc(30.0, 30.0, 8.0, PI, 1.5 * PI, crate::Solidity::Hole);
+        arcs.arc(90.0, 30.0, 8.0, 1.5 * PI, 0.0, crate::Solidity::Hole);
+        arcs.arc(90.0, 60.0, 8.0, 0.0, 0.5 * PI, crate::Solidity::Hole);
+        arcs.arc(30.0, 60.0, 8.0, 0.5 * PI, PI, crate::Solidity::Hole);
+        arcs.close();
+
+        let mut notched = Path::new();
+        notched.move_to(10.0, 10.0);
+        notched.line_to(60.0, 10.0);
+        notched.line_to(60.0, 40.0);
+        notched.line_to(110.0, 40.0);
+        notched.line_to(110.0, 60.0);
+        notched.line_to(10.0, 60.0);
+        notched.close();
+
+        for rotation in [0.0, 0.3, 1.1] {
+            for scale in [0.1, 1.0, 40.0] {
+                let mut transform = Transform2D::identity();
+                transform.rotate(rotation);
+                transform.scale(scale, scale);
+
+                let convexity = |path: &Path| {
+                    let mut path_cache = PathCache::default();
+                    path_cache.rebuild(path.verbs(), &transform, 0.25, 0.01);
+                    path_cache.expand_fill(1.0, LineJoin::Miter, 2.4);
+                    path_cache.contours[0].convexity
+                };
+
+                assert_eq!(convexity(&pill), Convexity::Convex);
+                assert_eq!(convexity(&arcs), Convexity::Convex);
+                assert_eq!(convexity(&notched), Convexity::Concave);
+            }
+        }
+    }
+
+    // Polylines with zero length segments, duplicate points and reversals
+    fn degenerate_polylines() -> Vec<Vec<(f32, f32)>> {
+        vec![
+            vec![(10.0, 10.0), (50.0, 10.0), (50.0, 10.0)],
+            vec![(10.0, 10.0), (10.0, 10.0), (50.0, 10.0)],
+            vec![(10.0, 10.0), (30.0, 10.0), (30.0, 10.0), (30.0, 10.0), (50.0, 30.0)],
+            vec![(10.0, 10.0), (50.0, 10.0), (10.0, 10.0)],
+            vec![(10.0, 10.0), (50.0, 10.0), (20.0, 10.0), (60.0, 10.0)],
+            vec![(10.0, 10.0), (50.0, 10.0), (10.0, 10.001)],
+            vec![(10.0, 10.0), (50.0, 10.0), (50.001, 10.0), (10.0, 10.0)],
+            vec![(10.0, 10.0), (50.0, 10.0), (50.0, 10.005), (50.0, 40.0)],
+        ]
+    }
+
+    fn stroke(points: &[(f32, f32)], cap: LineCap, join: LineJoin) -> PathCache {
+        let mut path = Path::new();
+        path.move_to(points[0].0, points[0].1);
+
+        for &(x, y) in &points[1..] {
+            path.line_to(x, y);
+        }
+
+        let mut path_cache = PathCache::default();
+        path_cache.rebuild(path.verbs(), &Transform2D::identity(), 0.25, 0.01);
+        path_cache.expand_stroke(4.0, 1.0, cap, cap, join, 4.0, 0.25);
+        path_cache
+    }
+
+    fn distance_to_polyline(points: &[(f32, f32)], x: f32, y: f32) -> f32 {
+        let mut distance = f32::MAX;
+
+        for pair in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            let (dx, dy) = (x1 - x0, y1 - y0);
+            let len2 = dx * dx + dy * dy;
+            let t = if len2 > 0.0 {
+                (((x - x0) * dx + (y - y0) * dy) / len2).max(0.0).min(1.0)
+            } else {
+                0.0
+            };
+
+            distance = distance.min((x - x0 - t * dx).hypot(y - y0 - t * dy));
+        }
+
+        distance
+    }
+
+    #[test]
+    fn degenerate_polylines_stroke_without_spikes() {
+        let caps = [LineCap::Butt, LineCap::Square, LineCap::Round];
+        let joins = [LineJoin::Miter, LineJoin::Bevel, LineJoin::Round];
+
+        for points in degenerate_polylines() {
+            for &cap in &caps {
+                for &join in &joins {
+                    let path_cache = stroke(&points, cap, join);
+                    let verts: Vec<&Vertex> = path_cache.contours.iter().flat_map(|c| &c.stroke).collect();
+
+                    assert!(!verts.is_empty(), "{:?} {:?} {:?}", points, cap, join);
+
+                    // Half width and fringe, times the miter limit, and the diagonal of square caps
+                    let reach = 4.5 * 4.0 * 2f32.sqrt();
+
+                    for vertex in verts {
+                        let distance = distance_to_polyline(&points, vertex.x, vertex.y);
+
+                        assert!(
+                            vertex.x.is_finite() && vertex.y.is_finite() && distance <= reach,
+                            "{:?} {:?} {:?}: vertex ({}, {}) is {} away",
+                            points,
+                            cap,
+                            join,
+                            vertex.x,
+                            vertex.y,
+                            distance
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_polylines_keep_their_caps() {
+        let reaches = |path_cache: &PathCache, x: f32, y: f32| {
+            path_cache
+                .contours
+                .iter()
+                .flat_map(|c| &c.stroke)
+                .any(|v| (v.x - x).abs() < 0.1 && (v.y - y).abs() < 0.1)
+        };
+
+        // A duplicated end point doesn't turn the square cap
+        let end = stroke(
+            &[(10.0, 10.0), (50.0, 10.0), (50.0, 10.0)],
+            LineCap::Square,
+            LineJoin::Miter,
+        );
+        assert!(reaches(&end, 53.5, 14.5));
+        let start = stroke(
+            &[(10.0, 10.0), (10.0, 10.0), (50.0, 10.0)],
+            LineCap::Square,
+            LineJoin::Miter,
+        );
+        assert!(reaches(&start, 6.5, 14.5));
+
+        // A reversal with a round join is rounded off beyond the turning point
+        let reversal = stroke(
+            &[(10.0, 10.0), (50.0, 10.0), (10.0, 10.0)],
+            LineCap::Butt,
+            LineJoin::Round,
+        );
+        assert!(reaches(&reversal, 54.5, 10.0));
+
+        // Zero length subpaths are drawn as their caps, a lone move_to isn't drawn at all
+        let dot = stroke(&[(10.0, 10.0), (10.0, 10.0)], LineCap::Round, LineJoin::Miter);
+        assert!(reaches(&dot, 14.5, 10.0) && reaches(&dot, 5.5, 10.0) && reaches(&dot, 10.0, 14.5));
+        let square = stroke(&[(10.0, 10.0), (10.0, 10.0)], LineCap::Square, LineJoin::Miter);
+        assert!(reaches(&square, 13.5, 14.5) && reaches(&square, 6.5, 5.5));
+        let butt = stroke(&[(10.0, 10.0), (10.0, 10.0)], LineCap::Butt, LineJoin::Miter);
+        assert!(butt.contours.iter().all(|c| c.stroke.is_empty()));
+        assert!(stroke(&[(10.0, 10.0)], LineCap::Round, LineJoin::Miter)
+            .contours
+            .is_empty());
+    }
 }
 
 /*
@@ -1207,5 +1787,6 @@ fn main() {
     while let Some(subslice) = iter.next() {
         println!("{:?}", subslice);
     }
+
 }
 */
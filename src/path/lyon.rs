@@ -0,0 +1,39 @@
+use lyon_path::{
+    Event,
+    PathEvent,
+};
+
+use super::Path;
+
+impl From<&lyon_path::Path> for Path {
+    /// Converts a lyon path, keeping its curves. Open sub-paths stay open.
+    fn from(lyon_path: &lyon_path::Path) -> Self {
+        let mut path = Path::new();
+
+        for event in lyon_path.iter() {
+            add_event(&mut path, event);
+        }
+
+        path
+    }
+}
+
+impl From<lyon_path::Path> for Path {
+    fn from(lyon_path: lyon_path::Path) -> Self {
+        Self::from(&lyon_path)
+    }
+}
+
+fn add_event(path: &mut Path, event: PathEvent) {
+    match event {
+        Event::Begin { at } => path.move_to(at.x, at.y),
+        Event::Line { to, .. } => path.line_to(to.x, to.y),
+        Event::Quadratic { ctrl, to, .. } => path.quad_to(ctrl.x, ctrl.y, to.x, to.y),
+        Event::Cubic { ctrl1, ctrl2, to, .. } => path.bezier_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y),
+        Event::End { close, .. } => {
+            if close {
+                path.close();
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Four lane vector used by the flattening and expansion inner loops.
+//!
+//! With the `simd` feature the lanes map to an SSE register on x86_64 and a NEON register on
+//! aarch64, other targets and builds without the feature use a plain array that the compiler is
+//! free to vectorize. Only additions and multiplications without fused multiply-add are provided
+//! so that all implementations produce bit for bit the same results as the scalar code.
+
+use std::ops::{
+    Add,
+    Mul,
+};
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+use std::arch::x86_64 as arch;
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+use std::arch::aarch64 as arch;
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+type Lanes = arch::__m128;
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+type Lanes = arch::float32x4_t;
+
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+type Lanes = [f32; 4];
+
+#[derive(Copy, Clone)]
+pub(crate) struct F32x4(Lanes);
+
+impl F32x4 {
+    #[inline(always)]
+    pub(crate) fn new(a: f32, b: f32, c: f32, d: f32) -> Self {
+        Self::from_array([a, b, c, d])
+    }
+
+    #[inline(always)]
+    pub(crate) fn splat(value: f32) -> Self {
+        Self::from_array([value; 4])
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline(always)]
+    fn from_array(lanes: [f32; 4]) -> Self {
+        // SSE is part of the x86_64 baseline
+        unsafe { Self(arch::_mm_loadu_ps(lanes.as_ptr())) }
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[inline(always)]
+    fn from_array(lanes: [f32; 4]) -> Self {
+        // NEON is part of the aarch64 baseline
+        unsafe { Self(arch::vld1q_f32(lanes.as_ptr())) }
+    }
+
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    #[inline(always)]
+    fn from_array(lanes: [f32; 4]) -> Self {
+        Self(lanes)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline(always)]
+    pub(crate) fn to_array(self) -> [f32; 4] {
+        let mut lanes = [0.0; 4];
+        unsafe { arch::_mm_storeu_ps(lanes.as_mut_ptr(), self.0) };
+        lanes
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[inline(always)]
+    pub(crate) fn to_array(self) -> [f32; 4] {
+        let mut lanes = [0.0; 4];
+        unsafe { arch::vst1q_f32(lanes.as_mut_ptr(), self.0) };
+        lanes
+    }
+
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    #[inline(always)]
+    pub(crate) fn to_array(self) -> [f32; 4] {
+        self.0
+    }
+}
+
+impl Add for F32x4 {
+    type Output = Self;
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        unsafe { Self(arch::_mm_add_ps(self.0, other.0)) }
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        unsafe { Self(arch::vaddq_f32(self.0, other.0)) }
+    }
+
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        let [a0, a1, a2, a3] = self.0;
+        let [b0, b1, b2, b3] = other.0;
+        Self([a0 + b0, a1 + b1, a2 + b2, a3 + b3])
+    }
+}
+
+impl Mul for F32x4 {
+    type Output = Self;
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        unsafe { Self(arch::_mm_mul_ps(self.0, other.0)) }
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        unsafe { Self(arch::vmulq_f32(self.0, other.0)) }
+    }
+
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        let [a0, a1, a2, a3] = self.0;
+        let [b0, b1, b2, b3] = other.0;
+        Self([a0 * b0, a1 * b1, a2 * b2, a3 * b3])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_match_scalar_math() {
+        let a = [1.5, -2.25, 1e-7, 3.0e8];
+        let b = [0.1, 7.0, -0.3, 0.5];
+
+        let va = F32x4::new(a[0], a[1], a[2], a[3]);
+        let vb = F32x4::new(b[0], b[1], b[2], b[3]);
+
+        let sum = (va + vb).to_array();
+        let product = (va * vb).to_array();
+        let half = (va * F32x4::splat(0.5)).to_array();
+
+        for i in 0..4 {
+            assert_eq!(sum[i].to_bits(), (a[i] + b[i]).to_bits());
+            assert_eq!(product[i].to_bits(), (a[i] * b[i]).to_bits());
+            assert_eq!(half[i].to_bits(), (a[i] * 0.5).to_bits());
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    Canvas,
+    Paint,
+    Path,
+    Renderer,
+};
+
+// Smallest column width, keeps the column index finite for degenerate tolerances
+const MIN_TOLERANCE: f32 = 0.01;
+
+// Consecutive points that fall into the same column of device pixels
+struct Column {
+    index: f32,
+    first: usize,
+    last: usize,
+    min: (usize, f32),
+    max: (usize, f32),
+}
+
+impl Column {
+    fn new(index: f32, point: usize, y: f32) -> Self {
+        Self {
+            index,
+            first: point,
+            last: point,
+            min: (point, y),
+            max: (point, y),
+        }
+    }
+
+    fn add(&mut self, point: usize, y: f32) {
+        self.last = point;
+
+        if y < self.min.1 {
+            self.min = (point, y);
+        }
+
+        if y > self.max.1 {
+            self.max = (point, y);
+        }
+    }
+
+    // Appends the points that make up the envelope of the column in their original order
+    fn append_to(&self, points: &[[f32; 2]], path: &mut Path, pen_down: &mut bool) {
+        let mut indices = [self.first, self.min.0, self.max.0, self.last];
+        indices.sort_unstable();
+
+        let mut previous = None;
+
+        for index in indices {
+            if previous == Some(index) {
+                continue;
+            }
+
+            previous = Some(index);
+
+            let [x, y] = points[index];
+
+            if *pen_down {
+                path.line_to(x, y);
+            } else {
+                path.move_to(x, y);
+                *pen_down = true;
+            }
+        }
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Strokes a polyline through `points` with the specified Paint, reducing the number of points
+    /// before the stroke is expanded.
+    ///
+    /// This is meant for plots and oscilloscope style traces with far more samples than there are
+    /// pixels. Runs of consecutive points that fall into the same `pixel_tolerance` wide column of
+    /// device pixels are replaced by the first, last, lowest and highest point of the run, which keeps
+    /// the envelope of the signal intact. With a tolerance of 1.0 the result is indistinguishable from
+    /// stroking every point. Points with a non-finite coordinate break the line, so gaps in the data
+    /// can be marked with NaN.
+    pub fn stroke_polyline_decimated(&mut self, points: &[[f32; 2]], paint: Paint, pixel_tolerance: f32) {
+        let transform = self.state().transform;
+        let tolerance = pixel_tolerance.max(MIN_TOLERANCE);
+
+        let mut path = Path::new();
+        let mut pen_down = false;
+        let mut column: Option<Column> = None;
+
+        for (index, point) in points.iter().enumerate() {
+            if !point[0].is_finite() || !point[1].is_finite() {
+                if let Some(column) = column.take() {
+                    column.append_to(points, &mut path, &mut pen_down);
+                }
+
+                pen_down = false;
+                continue;
+            }
+
+            let (x, y) = transform.transform_point(point[0], point[1]);
+            let column_index = (x / tolerance).floor();
+
+            match &mut column {
+                Some(column) if column.index == column_index => column.add(index, y),
+                _ => {
+                    if let Some(previous) = column.replace(Column::new(column_index, index, y)) {
+                        previous.append_to(points, &mut path, &mut pen_down);
+                    }
+                }
+            }
+        }
+
+        if let Some(column) = column {
+            column.append_to(points, &mut path, &mut pen_down);
+        }
+
+        self.stroke_path(&mut path, paint);
+    }
+}
@@ -0,0 +1,28 @@
+//! The types most drawing code needs, `use femtovg::prelude::*;` imports them all.
+
+pub use crate::{
+    Align,
+    Baseline,
+    Canvas,
+    CanvasBuilder,
+    Color,
+    CompositeOperation,
+    DrawTarget,
+    ErrorKind,
+    FillRule,
+    FontId,
+    ImageFlags,
+    ImageId,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    PixelFormat,
+    Point,
+    Rect,
+    RenderTarget,
+    Renderer,
+    Solidity,
+    TextContext,
+    Transform2D,
+};
@@ -0,0 +1,103 @@
+// Wraps `$body` into the `$stage` span of the canvas profiler and into a `tracing` span, expands to
+// just `$body` without the `profiling` and `tracing` features. Errors should be propagated outside
+// of the macro so that the span is closed.
+macro_rules! profile {
+    ($canvas:expr, $stage:ident, $body:expr) => {{
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(stage_name!($stage)).entered();
+
+        #[cfg(feature = "profiling")]
+        $canvas.profile_event(crate::Stage::$stage, true);
+
+        let result = $body;
+
+        #[cfg(feature = "profiling")]
+        $canvas.profile_event(crate::Stage::$stage, false);
+
+        result
+    }};
+}
+
+// The span names of the stages, tracing needs them as constants
+#[allow(unused_macros)]
+macro_rules! stage_name {
+    (Flush) => {
+        "femtovg::flush"
+    };
+    (Tessellate) => {
+        "femtovg::tessellate"
+    };
+    (Shape) => {
+        "femtovg::shape"
+    };
+    (TextureUpload) => {
+        "femtovg::texture_upload"
+    };
+}
+
+#[cfg(feature = "profiling")]
+use crate::{
+    Canvas,
+    Renderer,
+};
+
+/// A stage of drawing that is reported to a [`Profiler`].
+#[cfg(feature = "profiling")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Stage {
+    /// [`Canvas::flush()`], including the submission of the commands to the renderer.
+    Flush,
+    /// Flattening and expanding a path into vertices, for a fill, a stroke or a glyph outline.
+    Tessellate,
+    /// Shaping text into positioned glyphs.
+    Shape,
+    /// Copying pixels into an image with [`Canvas::create_image()`] or [`Canvas::update_image()`].
+    TextureUpload,
+}
+
+#[cfg(feature = "profiling")]
+impl Stage {
+    /// Returns a name for the stage, suitable as a span name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flush => stage_name!(Flush),
+            Self::Tessellate => stage_name!(Tessellate),
+            Self::Shape => stage_name!(Shape),
+            Self::TextureUpload => stage_name!(TextureUpload),
+        }
+    }
+}
+
+/// Receives the stages of drawing as they begin and end, needs the `profiling` feature.
+///
+/// Implement this to forward the stages into a profiler such as Tracy or perfetto. Stages nest, a
+/// flush may tessellate glyph outlines for instance, and every `begin()` is followed by a matching
+/// `end()`.
+///
+/// The `tracing` feature emits the stages as trace level `tracing` spans, named like
+/// [`Stage::name()`], without a profiler.
+#[cfg(feature = "profiling")]
+pub trait Profiler {
+    fn begin(&mut self, stage: Stage);
+
+    fn end(&mut self, stage: Stage);
+}
+
+#[cfg(feature = "profiling")]
+impl<T: Renderer> Canvas<T> {
+    /// Sets the profiler that receives the stages of drawing, None removes it.
+    pub fn set_profiler(&mut self, profiler: Option<Box<dyn Profiler>>) {
+        self.profiler = profiler;
+    }
+
+    pub(crate) fn profile_event(&mut self, stage: Stage, begin: bool) {
+        if let Some(profiler) = &mut self.profiler {
+            if begin {
+                profiler.begin(stage);
+            } else {
+                profiler.end(stage);
+            }
+        }
+    }
+}
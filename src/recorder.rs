@@ -0,0 +1,357 @@
+use crate::{
+    BlendFactor,
+    Canvas,
+    Color,
+    CompositeOperation,
+    CompositeOperationState,
+    ErrorKind,
+    Paint,
+    Path,
+    Renderer,
+    State,
+    Transform2D,
+};
+
+mod export;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum RecordedCommand {
+    ClearRect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        color: Color,
+    },
+    FillPath {
+        path: Path,
+        paint: Paint,
+    },
+    StrokePath {
+        path: Path,
+        paint: Paint,
+    },
+    FillText {
+        x: f32,
+        y: f32,
+        text: String,
+        paint: Paint,
+    },
+    StrokeText {
+        x: f32,
+        y: f32,
+        text: String,
+        paint: Paint,
+    },
+}
+
+/// Records drawing commands away from the thread that owns the [`Canvas`].
+///
+/// A `CanvasRecorder` is created from a canvas with [`Canvas::recorder()`] and starts out with
+/// the canvas's current render state. Unlike the canvas it is `Send`, so it can be moved to a worker
+/// thread and filled with paths and text there. Paths are flattened while they are recorded so that
+/// the expensive part of the tessellation happens on the worker thread. Text is shaped when the
+/// recording is submitted, because the font data is owned by the canvas's text context.
+///
+/// Image and font ids can be used freely in the recorded paints, they are only resolved on submission.
+/// Recordings can be merged with [`Self::append()`] and are replayed in order with [`Canvas::submit()`].
+///
+/// With the `serde` feature enabled recordings implement `Serialize` and `Deserialize`, so scenes can
+/// be written by other tools to JSON (with `serde_json`) or a compact binary format (e.g. `bincode`)
+/// and attached to bug reports. The serialized form is a direct mirror of the recording: a `state_stack`
+/// and a list of `commands`, each a pair of the render state (`composite_operation`, `transform` as the
+/// six matrix values, `scissor`, `alpha`, `pixel_snapping`) and one of the `ClearRect`, `FillPath`,
+/// `StrokePath`, `FillText` or `StrokeText` commands. Paths are stored as their `verbs` and `coords`.
+/// Font and image ids are not serialized: deserialized text is drawn with the fonts loaded into the
+/// canvas it is submitted to, and recordings that use image paints fail to serialize.
+///
+/// # Example
+/// ```
+/// use femtovg::{Paint, Path, Color, Canvas, renderer::Void};
+///
+/// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+/// let recorder = canvas.recorder();
+///
+/// let recorder = std::thread::spawn(move || {
+///     let mut recorder = recorder;
+///     let mut path = Path::new();
+///     path.rect(10.0, 10.0, 100.0, 100.0);
+///     recorder.fill_path(&path, Paint::color(Color::hex("454545")));
+///     recorder
+/// }).join().unwrap();
+///
+/// canvas.submit(recorder).expect("Cannot submit recording");
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CanvasRecorder {
+    state_stack: Vec<State>,
+    commands: Vec<(State, RecordedCommand)>,
+    tess_tol: f32,
+    dist_tol: f32,
+}
+
+impl CanvasRecorder {
+    fn new<T: Renderer>(canvas: &Canvas<T>) -> Self {
+        Self {
+            state_stack: vec![*canvas.state()],
+            commands: Vec::new(),
+            tess_tol: canvas.tess_tol,
+            dist_tol: canvas.dist_tol,
+        }
+    }
+
+    /// Returns true if no drawing commands have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Returns the number of recorded drawing commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Moves all commands recorded by `other` to the end of this recording.
+    ///
+    /// The render state of this recorder is not affected.
+    pub fn append(&mut self, mut other: CanvasRecorder) {
+        self.commands.append(&mut other.commands);
+    }
+
+    // State Handling
+
+    /// Pushes and saves the current render state into a state stack.
+    ///
+    /// A matching restore() must be used to restore the state.
+    pub fn save(&mut self) {
+        let state = *self.state();
+
+        self.state_stack.push(state);
+    }
+
+    /// Restores the previous render state
+    ///
+    /// Restoring the initial/first state will just reset it to the defaults
+    pub fn restore(&mut self) {
+        if self.state_stack.len() > 1 {
+            self.state_stack.pop();
+        } else {
+            self.reset();
+        }
+    }
+
+    /// Resets current state to default values. Does not affect the state stack.
+    pub fn reset(&mut self) {
+        *self.state_mut() = Default::default();
+    }
+
+    /// Saves the current state before calling the callback and restores it afterwards
+    pub fn save_with(&mut self, mut callback: impl FnMut(&mut Self)) {
+        self.save();
+
+        callback(self);
+
+        self.restore();
+    }
+
+    // Render styles
+
+    /// Sets the transparency applied to all recorded shapes.
+    pub fn set_global_alpha(&mut self, alpha: f32) {
+        self.state_mut().alpha = alpha;
+    }
+
+    /// Sets the composite operation.
+    pub fn global_composite_operation(&mut self, op: CompositeOperation) {
+        self.state_mut().composite_operation = CompositeOperationState::new(op);
+    }
+
+    /// Sets the composite operation with custom pixel arithmetic.
+    pub fn global_composite_blend_func(&mut self, src_factor: BlendFactor, dst_factor: BlendFactor) {
+        self.state_mut().composite_operation = CompositeOperationState::with_blend_factors(src_factor, dst_factor);
+    }
+
+    // Transforms
+
+    /// Resets current transform to a identity matrix.
+    pub fn reset_transform(&mut self) {
+        self.state_mut().transform = Transform2D::identity();
+    }
+
+    /// Premultiplies current coordinate system by specified matrix.
+    ///
+    /// See [`Canvas::set_transform()`].
+    pub fn set_transform(&mut self, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
+        let transform = Transform2D([a, b, c, d, e, f]);
+        self.state_mut().premultiply_transform(&transform);
+    }
+
+    /// Translates the current coordinate system.
+    pub fn translate(&mut self, x: f32, y: f32) {
+        let mut t = Transform2D::identity();
+        t.translate(x, y);
+        self.state_mut().premultiply_transform(&t);
+    }
+
+    /// Rotates the current coordinate system. Angle is specified in radians.
+    pub fn rotate(&mut self, angle: f32) {
+        let mut t = Transform2D::identity();
+        t.rotate(angle);
+        self.state_mut().premultiply_transform(&t);
+    }
+
+    /// Scales the current coordinate system.
+    pub fn scale(&mut self, x: f32, y: f32) {
+        let mut t = Transform2D::identity();
+        t.scale(x, y);
+        self.state_mut().premultiply_transform(&t);
+    }
+
+    /// Returns the current transformation matrix
+    pub fn transform(&self) -> Transform2D {
+        self.state().transform
+    }
+
+    // Scissoring
+
+    /// Sets the current scissor rectangle.
+    ///
+    /// The scissor rectangle is transformed by the current transform.
+    pub fn scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.state_mut().scissor(x, y, w, h);
+    }
+
+    /// Intersects current scissor rectangle with the specified rectangle.
+    ///
+    /// See [`Canvas::intersect_scissor()`].
+    pub fn intersect_scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.state_mut().intersect_scissor(x, y, w, h);
+    }
+
+    /// Intersects current scissor rectangle with the specified rectangle and rounds its corners.
+    ///
+    /// See [`Canvas::intersect_scissor_rounded()`].
+    pub fn intersect_scissor_rounded(&mut self, x: f32, y: f32, w: f32, h: f32, radius: f32) {
+        self.state_mut().intersect_scissor_rounded(x, y, w, h, radius);
+    }
+
+    /// Reset and disables scissoring.
+    pub fn reset_scissor(&mut self) {
+        self.state_mut().scissor = Default::default();
+    }
+
+    // Drawing
+
+    /// Records clearing the rectangle area defined by left upper corner (x,y), width and height with the provided color.
+    pub fn clear_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color) {
+        self.record(RecordedCommand::ClearRect {
+            x,
+            y,
+            width,
+            height,
+            color,
+        });
+    }
+
+    /// Records filling the provided Path with the specified Paint.
+    pub fn fill_path(&mut self, path: &Path, paint: Paint) {
+        let path = self.flatten(path);
+        self.record(RecordedCommand::FillPath { path, paint });
+    }
+
+    /// Records stroking the provided Path with the specified Paint.
+    pub fn stroke_path(&mut self, path: &Path, paint: Paint) {
+        let path = self.flatten(path);
+        self.record(RecordedCommand::StrokePath { path, paint });
+    }
+
+    /// Records filling the provided string with the specified Paint.
+    pub fn fill_text<S: AsRef<str>>(&mut self, x: f32, y: f32, text: S, paint: Paint) {
+        let text = text.as_ref().to_owned();
+        self.record(RecordedCommand::FillText { x, y, text, paint });
+    }
+
+    /// Records stroking the provided string with the specified Paint.
+    pub fn stroke_text<S: AsRef<str>>(&mut self, x: f32, y: f32, text: S, paint: Paint) {
+        let text = text.as_ref().to_owned();
+        self.record(RecordedCommand::StrokeText { x, y, text, paint });
+    }
+
+    // Private
+
+    fn flatten(&self, path: &Path) -> Path {
+        let mut path = path.clone();
+
+        // Canvas::(fill|stroke)_path will find this in the path cache as long as it is replayed
+        // with the same transform.
+        path.cache(&self.state().transform, self.tess_tol, self.dist_tol);
+
+        path
+    }
+
+    fn record(&mut self, command: RecordedCommand) {
+        let state = *self.state();
+        self.commands.push((state, command));
+    }
+
+    fn state(&self) -> &State {
+        self.state_stack.last().unwrap()
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        self.state_stack.last_mut().unwrap()
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Creates a new [`CanvasRecorder`] that starts out with the current render state of this canvas.
+    pub fn recorder(&self) -> CanvasRecorder {
+        CanvasRecorder::new(self)
+    }
+
+    /// Replays the commands of a [`CanvasRecorder`] on this canvas in the order they were recorded.
+    ///
+    /// Every command is drawn with the render state that was active in the recorder when it was recorded.
+    /// The render state of the canvas is left unchanged. If shaping recorded text fails the remaining
+    /// commands are still submitted and the first error is returned.
+    pub fn submit(&mut self, recorder: CanvasRecorder) -> Result<(), ErrorKind> {
+        let mut result = Ok(());
+
+        for (state, command) in recorder.commands {
+            self.save();
+            *self.state_mut() = state;
+
+            let res = match command {
+                RecordedCommand::ClearRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => {
+                    self.clear_rect(x, y, width, height, color);
+                    Ok(())
+                }
+                RecordedCommand::FillPath { mut path, paint } => {
+                    self.fill_path(&mut path, paint);
+                    Ok(())
+                }
+                RecordedCommand::StrokePath { mut path, paint } => {
+                    self.stroke_path(&mut path, paint);
+                    Ok(())
+                }
+                RecordedCommand::FillText { x, y, text, paint } => self.fill_text(x, y, text, paint).map(|_| ()),
+                RecordedCommand::StrokeText { x, y, text, paint } => self.stroke_text(x, y, text, paint).map(|_| ()),
+            };
+
+            self.restore();
+
+            if result.is_ok() {
+                result = res;
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,683 @@
+use std::fmt::Write;
+
+use super::{
+    CanvasRecorder,
+    RecordedCommand,
+};
+use crate::{
+    paint::GradientColors,
+    paint::PaintFlavor,
+    path::Verb,
+    Align,
+    Baseline,
+    Color,
+    FillRule,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    State,
+    Transform2D,
+};
+
+// A paint converted into something both exporters understand.
+enum ExportPaint {
+    Color(Color),
+    Linear {
+        start: (f32, f32),
+        end: (f32, f32),
+        stops: Vec<(f32, Color)>,
+    },
+    Radial {
+        center: (f32, f32),
+        radius: f32,
+        stops: Vec<(f32, Color)>,
+    },
+}
+
+impl ExportPaint {
+    // Images can't be exported, box gradients are approximated by their inner color.
+    fn new(paint: &Paint) -> Option<Self> {
+        match paint.flavor {
+            PaintFlavor::Color(color) => Some(Self::Color(color)),
+            PaintFlavor::Image { .. } | PaintFlavor::ColorMap { .. } => None,
+            PaintFlavor::LinearGradient {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                colors,
+            } => Some(Self::Linear {
+                start: (start_x, start_y),
+                end: (end_x, end_y),
+                stops: gradient_stops(&colors),
+            }),
+            PaintFlavor::BoxGradient { colors, .. } => Some(Self::Color(gradient_stops(&colors)[0].1)),
+            PaintFlavor::RadialGradient {
+                cx,
+                cy,
+                in_radius,
+                out_radius,
+                colors,
+            } => {
+                let radius = out_radius.max(0.0001);
+                let inner = (in_radius / radius).clamp(0.0, 1.0);
+
+                let stops = gradient_stops(&colors)
+                    .into_iter()
+                    .map(|(offset, color)| (inner + offset * (1.0 - inner), color))
+                    .collect();
+
+                Some(Self::Radial {
+                    center: (cx, cy),
+                    radius,
+                    stops,
+                })
+            }
+        }
+    }
+}
+
+fn gradient_stops(colors: &GradientColors) -> Vec<(f32, Color)> {
+    match colors {
+        GradientColors::TwoStop { start_color, end_color } => vec![(0.0, *start_color), (1.0, *end_color)],
+        // Stops with an offset above 1.0 are unused sentinels
+        GradientColors::MultiStop { stops } => stops
+            .iter()
+            .filter(|stop| stop.0 <= 1.0)
+            .map(|stop| (stop.0, stop.1))
+            .collect(),
+    }
+}
+
+fn scissor_corners(state: &State) -> Option<[(f32, f32); 4]> {
+    let extent = state.scissor.extent?;
+    let t = &state.scissor.transform;
+
+    let (ex, ey) = (extent[0], extent[1]);
+
+    Some([
+        t.transform_point(-ex, -ey),
+        t.transform_point(ex, -ey),
+        t.transform_point(ex, ey),
+        t.transform_point(-ex, ey),
+    ])
+}
+
+impl CanvasRecorder {
+    /// Writes the recorded drawing commands as an SVG document of the given size.
+    ///
+    /// Paths keep their curves, so the output stays sharp at any zoom level. Fonts are not embedded,
+    /// recorded text is written as SVG text elements using the font size of the paint. Image paints,
+    /// composite operations and box gradients (drawn with their inner color) are not supported.
+    pub fn to_svg(&self, width: f32, height: f32) -> String {
+        let mut defs = String::new();
+        let mut body = String::new();
+
+        for (index, (state, command)) in self.commands.iter().enumerate() {
+            let mut group = format!(r#"<g opacity="{}""#, state.alpha);
+
+            if let Some(extent) = state.scissor.extent {
+                let _ = write!(
+                    defs,
+                    r#"<clipPath id="clip{}"><rect x="{}" y="{}" width="{}" height="{}" rx="{}" transform="{}"/></clipPath>"#,
+                    index,
+                    -extent[0],
+                    -extent[1],
+                    extent[0] * 2.0,
+                    extent[1] * 2.0,
+                    state.scissor.radius,
+                    svg_matrix(&state.scissor.transform)
+                );
+                let _ = write!(group, r#" clip-path="url(#clip{})""#, index);
+            }
+
+            let element = match command {
+                RecordedCommand::ClearRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => {
+                    // Clearing is not affected by the render state
+                    let _ = writeln!(
+                        body,
+                        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" fill-opacity="{}"/>"#,
+                        x,
+                        y,
+                        width,
+                        height,
+                        svg_color(color),
+                        color.a
+                    );
+                    continue;
+                }
+                RecordedCommand::FillPath { path, paint } => {
+                    let fill = match svg_paint(&mut defs, paint, index) {
+                        Some(fill) => fill,
+                        None => continue,
+                    };
+
+                    let rule = if paint.fill_rule == FillRule::EvenOdd {
+                        "evenodd"
+                    } else {
+                        "nonzero"
+                    };
+
+                    format!(
+                        r#"<path transform="{}" d="{}" fill={} fill-rule="{}"/>"#,
+                        svg_matrix(&state.transform),
+                        svg_path_data(path),
+                        fill,
+                        rule
+                    )
+                }
+                RecordedCommand::StrokePath { path, paint } => {
+                    let stroke = match svg_paint(&mut defs, paint, index) {
+                        Some(stroke) => stroke,
+                        None => continue,
+                    };
+
+                    format!(
+                        r#"<path transform="{}" d="{}" fill="none" stroke={} stroke-width="{}" stroke-linecap="{}" stroke-linejoin="{}" stroke-miterlimit="{}"/>"#,
+                        svg_matrix(&state.transform),
+                        svg_path_data(path),
+                        stroke,
+                        paint.line_width,
+                        svg_line_cap(paint.line_cap_start),
+                        svg_line_join(paint.line_join),
+                        paint.miter_limit
+                    )
+                }
+                RecordedCommand::FillText { x, y, text, paint } | RecordedCommand::StrokeText { x, y, text, paint } => {
+                    let color = match svg_paint(&mut defs, paint, index) {
+                        Some(color) => color,
+                        None => continue,
+                    };
+
+                    let style = if let RecordedCommand::StrokeText { .. } = command {
+                        format!(r#"fill="none" stroke={} stroke-width="{}""#, color, paint.line_width)
+                    } else {
+                        format!("fill={}", color)
+                    };
+
+                    let anchor = match paint.text_align {
+                        Align::Left => "start",
+                        Align::Center => "middle",
+                        Align::Right => "end",
+                    };
+
+                    let baseline = match paint.text_baseline {
+                        Baseline::Top => "text-before-edge",
+                        Baseline::Middle => "middle",
+                        Baseline::Alphabetic => "alphabetic",
+                        Baseline::Bottom => "text-after-edge",
+                    };
+
+                    format!(
+                        r#"<text transform="{}" x="{}" y="{}" font-size="{}" text-anchor="{}" dominant-baseline="{}" {}>{}</text>"#,
+                        svg_matrix(&state.transform),
+                        x,
+                        y,
+                        paint.font_size,
+                        anchor,
+                        baseline,
+                        style,
+                        escape_xml(text)
+                    )
+                }
+            };
+
+            let _ = writeln!(body, "{}>{}</g>", group, element);
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n<defs>{}</defs>\n{}</svg>\n",
+            defs,
+            body,
+            w = width,
+            h = height
+        )
+    }
+
+    /// Writes the recorded drawing commands as a single page PDF document of the given size.
+    ///
+    /// Has the same limitations as [`Self::to_svg()`]. In addition gradients are drawn opaque and text is
+    /// always set left aligned in Helvetica.
+    pub fn to_pdf(&self, width: f32, height: f32) -> Vec<u8> {
+        let mut pdf = PdfWriter::new(height);
+
+        for (state, command) in &self.commands {
+            pdf.content.push_str("q\n");
+
+            match command {
+                RecordedCommand::ClearRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => {
+                    pdf.set_alpha(color.a);
+                    let _ = writeln!(
+                        pdf.content,
+                        "{} {} {} rg\n{} {} {} {} re f",
+                        color.r, color.g, color.b, x, y, width, height
+                    );
+                }
+                RecordedCommand::FillPath { path, paint } => {
+                    if let Some(export_paint) = ExportPaint::new(paint) {
+                        pdf.clip_scissor(state);
+                        pdf.set_transform(&state.transform);
+                        pdf.set_paint(&export_paint, state, false);
+                        pdf.path(path);
+
+                        let op = if paint.fill_rule == FillRule::EvenOdd {
+                            "f*"
+                        } else {
+                            "f"
+                        };
+                        let _ = writeln!(pdf.content, "{}", op);
+                    }
+                }
+                RecordedCommand::StrokePath { path, paint } => {
+                    if let Some(export_paint) = ExportPaint::new(paint) {
+                        pdf.clip_scissor(state);
+                        pdf.set_transform(&state.transform);
+                        pdf.set_paint(&export_paint, state, true);
+                        pdf.set_stroke_style(paint);
+                        pdf.path(path);
+                        pdf.content.push_str("S\n");
+                    }
+                }
+                RecordedCommand::FillText { x, y, text, paint } | RecordedCommand::StrokeText { x, y, text, paint } => {
+                    if let Some(export_paint) = ExportPaint::new(paint) {
+                        let stroke = matches!(command, RecordedCommand::StrokeText { .. });
+
+                        pdf.clip_scissor(state);
+                        pdf.set_transform(&state.transform);
+                        pdf.set_paint(&export_paint, state, stroke);
+
+                        if stroke {
+                            pdf.set_stroke_style(paint);
+                        }
+
+                        let _ = writeln!(
+                            pdf.content,
+                            "BT\n/F1 {} Tf\n{} Tr\n1 0 0 -1 {} {} Tm\n({}) Tj\nET",
+                            paint.font_size,
+                            if stroke { 1 } else { 0 },
+                            x,
+                            y,
+                            escape_pdf(text)
+                        );
+                    }
+                }
+            }
+
+            pdf.content.push_str("Q\n");
+        }
+
+        pdf.finish(width, height)
+    }
+}
+
+fn svg_matrix(t: &Transform2D) -> String {
+    format!("matrix({} {} {} {} {} {})", t[0], t[1], t[2], t[3], t[4], t[5])
+}
+
+fn svg_color(color: &Color) -> String {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+// Returns the quoted value of a fill or stroke attribute together with its opacity, adding gradients to defs.
+fn svg_paint(defs: &mut String, paint: &Paint, index: usize) -> Option<String> {
+    let (tag, attributes, stops) = match ExportPaint::new(paint)? {
+        ExportPaint::Color(color) => return Some(format!(r#""{}" opacity="{}""#, svg_color(&color), color.a)),
+        ExportPaint::Linear { start, end, stops } => (
+            "linearGradient",
+            format!(r#"x1="{}" y1="{}" x2="{}" y2="{}""#, start.0, start.1, end.0, end.1),
+            stops,
+        ),
+        ExportPaint::Radial { center, radius, stops } => (
+            "radialGradient",
+            format!(r#"cx="{}" cy="{}" r="{}""#, center.0, center.1, radius),
+            stops,
+        ),
+    };
+
+    let _ = write!(
+        defs,
+        r#"<{} id="paint{}" gradientUnits="userSpaceOnUse" {}>"#,
+        tag, index, attributes
+    );
+
+    for (offset, color) in stops {
+        let _ = write!(
+            defs,
+            r#"<stop offset="{}" stop-color="{}" stop-opacity="{}"/>"#,
+            offset,
+            svg_color(&color),
+            color.a
+        );
+    }
+
+    let _ = write!(defs, "</{}>", tag);
+
+    Some(format!(r#""url(#paint{})""#, index))
+}
+
+fn svg_path_data(path: &Path) -> String {
+    let mut data = String::new();
+
+    // The winding carries the solidity of the contours for the nonzero fill rule
+    for verb in path.wound_contours().into_iter().flatten() {
+        let _ = match verb {
+            Verb::MoveTo(x, y) => write!(data, "M{} {} ", x, y),
+            Verb::LineTo(x, y) => write!(data, "L{} {} ", x, y),
+            Verb::BezierTo(x1, y1, x2, y2, x, y) => write!(data, "C{} {} {} {} {} {} ", x1, y1, x2, y2, x, y),
+            Verb::Close => write!(data, "Z "),
+            Verb::Solid | Verb::Hole => Ok(()),
+        };
+    }
+
+    data.trim_end().to_owned()
+}
+
+fn svg_line_cap(cap: LineCap) -> &'static str {
+    match cap {
+        LineCap::Butt => "butt",
+        LineCap::Round => "round",
+        LineCap::Square => "square",
+    }
+}
+
+fn svg_line_join(join: LineJoin) -> &'static str {
+    match join {
+        LineJoin::Miter => "miter",
+        LineJoin::Round => "round",
+        LineJoin::Bevel => "bevel",
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_pdf(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            // The standard fonts only cover a single byte encoding
+            _ => "?".to_owned(),
+        })
+        .collect()
+}
+
+// Minimal PDF 1.4 writer with a single page. The page content is set up with a top-left origin so
+// that recorded coordinates can be used as they are.
+struct PdfWriter {
+    height: f32,
+    content: String,
+    alphas: Vec<f32>,
+    // Pattern dictionaries, each one referencing the shading following it.
+    patterns: Vec<(String, String)>,
+}
+
+impl PdfWriter {
+    fn new(height: f32) -> Self {
+        Self {
+            height,
+            content: format!("1 0 0 -1 0 {} cm\n", height),
+            alphas: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    // The graphics state sets both the fill and the stroke alpha
+    fn set_alpha(&mut self, alpha: f32) {
+        if alpha >= 1.0 {
+            return;
+        }
+
+        let index = match self.alphas.iter().position(|a| *a == alpha) {
+            Some(index) => index,
+            None => {
+                self.alphas.push(alpha);
+                self.alphas.len() - 1
+            }
+        };
+
+        let _ = writeln!(self.content, "/GS{} gs", index);
+    }
+
+    fn clip_scissor(&mut self, state: &State) {
+        if let Some(corners) = scissor_corners(state) {
+            let _ = writeln!(
+                self.content,
+                "{} {} m {} {} l {} {} l {} {} l h W n",
+                corners[0].0,
+                corners[0].1,
+                corners[1].0,
+                corners[1].1,
+                corners[2].0,
+                corners[2].1,
+                corners[3].0,
+                corners[3].1
+            );
+        }
+    }
+
+    fn set_transform(&mut self, t: &Transform2D) {
+        let _ = writeln!(self.content, "{} {} {} {} {} {} cm", t[0], t[1], t[2], t[3], t[4], t[5]);
+    }
+
+    fn set_paint(&mut self, export_paint: &ExportPaint, state: &State, stroke: bool) {
+        match export_paint {
+            ExportPaint::Color(color) => {
+                self.set_alpha(color.a * state.alpha);
+                let op = if stroke { "RG" } else { "rg" };
+                let _ = writeln!(self.content, "{} {} {} {}", color.r, color.g, color.b, op);
+            }
+            ExportPaint::Linear { start, end, stops } => {
+                let shading = format!(
+                    "<< /ShadingType 2 /ColorSpace /DeviceRGB /Coords [{} {} {} {}] /Function {} /Extend [true true] >>",
+                    start.0,
+                    start.1,
+                    end.0,
+                    end.1,
+                    pdf_function(stops)
+                );
+                self.set_pattern(shading, state, stroke);
+            }
+            ExportPaint::Radial { center, radius, stops } => {
+                let shading = format!(
+                    "<< /ShadingType 3 /ColorSpace /DeviceRGB /Coords [{x} {y} 0 {x} {y} {}] /Function {} /Extend [true true] >>",
+                    radius,
+                    pdf_function(stops),
+                    x = center.0,
+                    y = center.1
+                );
+                self.set_pattern(shading, state, stroke);
+            }
+        }
+    }
+
+    fn set_pattern(&mut self, shading: String, state: &State, stroke: bool) {
+        self.set_alpha(state.alpha);
+
+        // Pattern space is the default page space, so it includes the flip and the current transform.
+        let t = &state.transform;
+        let pattern = format!(
+            "<< /Type /Pattern /PatternType 2 /Matrix [{} {} {} {} {} {}] /Shading {{shading}} >>",
+            t[0],
+            -t[1],
+            t[2],
+            -t[3],
+            t[4],
+            self.height - t[5]
+        );
+
+        self.patterns.push((pattern, shading));
+
+        let (space, op) = if stroke { ("CS", "SCN") } else { ("cs", "scn") };
+        let _ = writeln!(self.content, "/Pattern {} /P{} {}", space, self.patterns.len() - 1, op);
+    }
+
+    fn set_stroke_style(&mut self, paint: &Paint) {
+        let cap = match paint.line_cap_start {
+            LineCap::Butt => 0,
+            LineCap::Round => 1,
+            LineCap::Square => 2,
+        };
+
+        let join = match paint.line_join {
+            LineJoin::Miter => 0,
+            LineJoin::Round => 1,
+            LineJoin::Bevel => 2,
+        };
+
+        let _ = writeln!(
+            self.content,
+            "{} w {} J {} j {} M",
+            paint.line_width, cap, join, paint.miter_limit
+        );
+    }
+
+    fn path(&mut self, path: &Path) {
+        for verb in path.wound_contours().into_iter().flatten() {
+            let _ = match verb {
+                Verb::MoveTo(x, y) => writeln!(self.content, "{} {} m", x, y),
+                Verb::LineTo(x, y) => writeln!(self.content, "{} {} l", x, y),
+                Verb::BezierTo(x1, y1, x2, y2, x, y) => {
+                    writeln!(self.content, "{} {} {} {} {} {} c", x1, y1, x2, y2, x, y)
+                }
+                Verb::Close => writeln!(self.content, "h"),
+                Verb::Solid | Verb::Hole => Ok(()),
+            };
+        }
+    }
+
+    fn finish(self, width: f32, height: f32) -> Vec<u8> {
+        // Object numbers: 1 catalog, 2 pages, 3 page, 4 contents, 5 font, followed by the graphics
+        // states and the pattern/shading pairs.
+        let first_pattern = 6 + self.alphas.len();
+
+        let mut resources = String::from("/Font << /F1 5 0 R >>");
+
+        resources.push_str(" /ExtGState <<");
+        for index in 0..self.alphas.len() {
+            let _ = write!(resources, " /GS{} {} 0 R", index, 6 + index);
+        }
+        resources.push_str(" >>");
+
+        resources.push_str(" /Pattern <<");
+        for index in 0..self.patterns.len() {
+            let _ = write!(resources, " /P{} {} 0 R", index, first_pattern + index * 2);
+        }
+        resources.push_str(" >>");
+
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_owned(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << {} >> /Contents 4 0 R >>",
+                width, height, resources
+            ),
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                self.content.len(),
+                self.content
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_owned(),
+        ];
+
+        for alpha in &self.alphas {
+            objects.push(format!("<< /Type /ExtGState /ca {a} /CA {a} >>", a = alpha));
+        }
+
+        for (index, (pattern, shading)) in self.patterns.into_iter().enumerate() {
+            let shading_ref = format!("{} 0 R", first_pattern + index * 2 + 1);
+            objects.push(pattern.replace("{shading}", &shading_ref));
+            objects.push(shading);
+        }
+
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).as_bytes());
+        }
+
+        let xref = out.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+
+        out.extend_from_slice(trailer.as_bytes());
+        out
+    }
+}
+
+// Interpolation function for the stops of a gradient
+fn pdf_function(stops: &[(f32, Color)]) -> String {
+    let rgb = |color: &Color| format!("[{} {} {}]", color.r, color.g, color.b);
+
+    let exponential = |c0: &Color, c1: &Color| {
+        format!(
+            "<< /FunctionType 2 /Domain [0 1] /C0 {} /C1 {} /N 1 >>",
+            rgb(c0),
+            rgb(c1)
+        )
+    };
+
+    // The function has to cover the whole [0 1] domain of the shading
+    let mut stops = stops.to_vec();
+
+    if let (Some(first), Some(last)) = (stops.first().copied(), stops.last().copied()) {
+        if first.0 > 0.0 {
+            stops.insert(0, (0.0, first.1));
+        }
+
+        if last.0 < 1.0 {
+            stops.push((1.0, last.1));
+        }
+    }
+
+    match stops.len() {
+        0 => exponential(&Color::black(), &Color::black()),
+        1 => exponential(&stops[0].1, &stops[0].1),
+        _ => {
+            let functions: Vec<String> = stops.windows(2).map(|w| exponential(&w[0].1, &w[1].1)).collect();
+            let bounds: Vec<String> = stops[1..stops.len() - 1].iter().map(|s| s.0.to_string()).collect();
+            let encode = vec!["0 1"; functions.len()];
+
+            format!(
+                "<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+                functions.join(" "),
+                bounds.join(" "),
+                encode.join(" ")
+            )
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    geometry::{
+        self,
+        Bounds,
+    },
+    paint::PaintFlavor,
+    renderer::{
+        Command,
+        CommandType,
+        Drawable,
+        Params,
+        Vertex,
+    },
+    tessellation::Tessellation,
+    Canvas,
+    Path,
+    Renderer,
+};
+
+impl<T: Renderer> Canvas<T> {
+    // Fills a path that is a single rectangle with a solid color, under a transform without
+    // rotation or skew, without flattening and expanding the path. Produces the same vertices as
+    // the path cache would. Returns false if the path or the paint doesn't qualify.
+    pub(crate) fn fill_rect_fast(&mut self, path: &Path, tessellation: &Tessellation) -> bool {
+        let transform = &tessellation.transform;
+
+        if transform[1] != 0.0 || transform[2] != 0.0 || !matches!(tessellation.paint.flavor, PaintFlavor::Color(_)) {
+            return false;
+        }
+
+        let corners = match path.rect_corners() {
+            Some(corners) => corners,
+            None => return false,
+        };
+
+        let mut points = [(0.0, 0.0); 4];
+
+        for (point, (x, y)) in points.iter_mut().zip(corners) {
+            *point = transform.transform_point(x, y);
+
+            if let Some(offset) = tessellation.snap_offset {
+                let snap = |v: f32| (v - offset).round() + offset;
+                *point = (snap(point.0), snap(point.1));
+            }
+        }
+
+        // The path cache merges the corners of degenerate rectangles
+        let width = (points[2].0 - points[0].0).abs();
+        let height = (points[2].1 - points[0].1).abs();
+
+        if width <= tessellation.dist_tol || height <= tessellation.dist_tol {
+            return false;
+        }
+
+        let mut bounds = Bounds::default();
+
+        for (x, y) in points {
+            bounds.minx = bounds.minx.min(x);
+            bounds.miny = bounds.miny.min(y);
+            bounds.maxx = bounds.maxx.max(x);
+            bounds.maxy = bounds.maxy.max(y);
+        }
+
+        if !tessellation.is_visible(&bounds) {
+            self.culled_draws += 1;
+            return true;
+        }
+
+        // Solid contours are wound the same way as in the path cache
+        let mut area = 0.0;
+
+        for i in 0..4 {
+            let (p0, p1) = (points[(i + 3) % 4], points[i]);
+            area += (p1.0 - p0.0) * (p1.1 + p0.1);
+        }
+
+        if area * 0.5 < 0.0 {
+            points.reverse();
+        }
+
+        // Direction of the edge from each corner to the next
+        let mut directions = [(0.0, 0.0); 4];
+
+        for (i, direction) in directions.iter_mut().enumerate() {
+            let (p0, p1) = (points[i], points[(i + 1) % 4]);
+            let (mut dx, mut dy) = (p1.0 - p0.0, p1.1 - p0.1);
+            geometry::normalize(&mut dx, &mut dy);
+            *direction = (dx, dy);
+        }
+
+        // Miter extrusion of each corner
+        let mut miters = [(0.0, 0.0); 4];
+
+        for (i, miter) in miters.iter_mut().enumerate() {
+            let (d0, d1) = (directions[(i + 3) % 4], directions[i]);
+
+            let mut dmx = (d0.1 + d1.1) * 0.5;
+            let mut dmy = (-d0.0 + -d1.0) * 0.5;
+            let dmr2 = dmx * dmx + dmy * dmy;
+
+            if dmr2 > 0.000_001 {
+                let scale = (1.0 / dmr2).min(600.0);
+                dmx *= scale;
+                dmy *= scale;
+            }
+
+            *miter = (dmx, dmy);
+        }
+
+        let paint = &tessellation.paint;
+        let fringe_width = tessellation.fringe_width;
+        let scissor = self.state().scissor;
+
+        let params = Params::new(
+            &self.images,
+            paint,
+            &scissor,
+            self.fringe_width,
+            self.fringe_width,
+            -1.0,
+        );
+
+        let mut cmd = Command::new(CommandType::ConvexFill { params });
+        cmd.fill_rule = paint.fill_rule;
+        cmd.composite_operation = self.state().composite_operation;
+
+        let offset = self.verts.len();
+        let mut drawable = Drawable::default();
+
+        if fringe_width > 0.0 {
+            // Half a fringe on each side of the edges, as for convex paths
+            let woff = 0.5 * fringe_width;
+            let (lw, rw, lu, ru) = (woff, fringe_width - woff, 0.5, 1.0);
+
+            for ((x, y), (dmx, dmy)) in points.iter().zip(miters) {
+                self.verts.push(Vertex::new(x + dmx * woff, y + dmy * woff, 0.5, 1.0));
+            }
+
+            for ((x, y), (dmx, dmy)) in points.iter().zip(miters) {
+                self.verts.push(Vertex::new(x + dmx * lw, y + dmy * lw, lu, 1.0));
+                self.verts.push(Vertex::new(x + dmx * -rw, y + dmy * -rw, ru, 1.0));
+            }
+
+            let (p0, p1) = (self.verts[offset + 4], self.verts[offset + 5]);
+            self.verts.push(Vertex::new(p0.x, p0.y, lu, 1.0));
+            self.verts.push(Vertex::new(p1.x, p1.y, ru, 1.0));
+
+            drawable.fill_verts = Some((offset, 4));
+            drawable.stroke_verts = Some((offset + 4, 10));
+        } else {
+            for (x, y) in points {
+                self.verts.push(Vertex::new(x, y, 0.5, 1.0));
+            }
+
+            drawable.fill_verts = Some((offset, 4));
+        }
+
+        cmd.drawables.push(drawable);
+
+        self.append_cmd(cmd);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        renderer::Void,
+        Canvas,
+        Color,
+        Paint,
+        Path,
+        Transform2D,
+    };
+
+    type Fill = (Vec<(f32, f32, f32, f32)>, usize);
+
+    // Returns the vertices and the number of commands of filling the path with and without the
+    // fast path, or None if the fast path doesn't take the path
+    fn fill_both(canvas: &mut Canvas<Void>, path: &mut Path, paint: Paint) -> Option<[Fill; 2]> {
+        let tessellation = canvas.fill_tessellation(paint, canvas.state().transform);
+
+        let mut results = [(Vec::new(), 0), (Vec::new(), 0)];
+
+        for (fast, result) in [true, false].iter().copied().zip(results.iter_mut()) {
+            canvas.verts.clear();
+            canvas.commands.clear();
+
+            if fast {
+                if !canvas.fill_rect_fast(path, &tessellation) {
+                    return None;
+                }
+            } else if tessellation.run(path) {
+                canvas.emit_fill(&path.cache, &tessellation);
+            }
+
+            *result = (
+                canvas.verts.iter().map(|v| (v.x, v.y, v.u, v.v)).collect(),
+                canvas.commands.len(),
+            );
+        }
+
+        Some(results)
+    }
+
+    #[test]
+    fn fast_rects_match_path_cache() {
+        let mut canvas = Canvas::new(Void).unwrap();
+        canvas.set_size(400, 400, 1.0);
+
+        let transforms = [
+            Transform2D::identity(),
+            Transform2D([1.5, 0.0, 0.0, 1.5, 10.3, 20.7]),
+            Transform2D([-1.0, 0.0, 0.0, 2.0, 300.0, 5.0]),
+            Transform2D([0.7, 0.0, 0.0, -0.3, 50.0, 300.0]),
+        ];
+
+        let rects = [
+            (10.0, 20.0, 100.0, 50.0),
+            (33.3, 7.1, 0.9, 2.6),
+            (200.0, 200.0, -80.0, -30.0),
+        ];
+
+        let mut compared = 0;
+
+        for anti_alias in [true, false] {
+            for snapping in [false, true] {
+                for transform in transforms {
+                    for (x, y, w, h) in rects {
+                        let mut paint = Paint::color(Color::rgb(200, 50, 50));
+                        paint.set_anti_alias(anti_alias);
+
+                        canvas.reset();
+                        canvas.set_pixel_snapping(snapping);
+                        let [a, b, c, d, e, f] = transform.0;
+                        canvas.set_transform(a, b, c, d, e, f);
+
+                        let mut path = Path::new();
+                        path.rect(x, y, w, h);
+
+                        if let Some([fast, general]) = fill_both(&mut canvas, &mut path, paint) {
+                            assert_eq!(fast, general, "rect {:?} under {:?}", (x, y, w, h), transform);
+                            compared += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Only snapped rects that collapse to a line are left to the path cache
+        assert!(compared >= 44);
+    }
+}
@@ -9,6 +9,7 @@ use crate::{
     ErrorKind,
     FillRule,
     ImageFilter,
+    ImageFlags,
     ImageId,
     ImageInfo,
     ImageSource,
@@ -16,7 +17,13 @@ use crate::{
 };
 
 mod opengl;
-pub use opengl::OpenGl;
+#[cfg(all(feature = "window-handle", not(target_arch = "wasm32")))]
+pub use opengl::WindowContext;
+pub use opengl::{
+    Capabilities,
+    ExternalImageProvider,
+    OpenGl,
+};
 
 mod void;
 pub use void::Void;
@@ -24,6 +31,11 @@ pub use void::Void;
 mod params;
 pub(crate) use params::Params;
 
+mod batch;
+#[cfg(feature = "debug_inspector")]
+pub(crate) use batch::batches;
+pub(crate) use batch::merge_commands;
+
 #[derive(Copy, Clone, Default, Debug)]
 pub struct Drawable {
     pub(crate) fill_verts: Option<(usize, usize)>,
@@ -67,8 +79,15 @@ pub struct Command {
     pub(crate) cmd_type: CommandType,
     pub(crate) drawables: Vec<Drawable>,
     pub(crate) triangles_verts: Option<(usize, usize)>,
+    // Triangle list indices relative to the start of `triangles_verts`, which is then the range of
+    // vertices they refer to
+    pub(crate) indices: Option<Vec<u16>>,
+    // Per vertex colors of the triangles, used by meshes
+    pub(crate) vertex_colors: Option<Vec<RGBA8>>,
     pub(crate) image: Option<ImageId>,
     pub(crate) alpha_mask: Option<ImageId>,
+    // Color texture the values of `image` are looked up in by color map paints
+    pub(crate) lut: Option<ImageId>,
     pub(crate) fill_rule: FillRule,
     pub(crate) composite_operation: CompositeOperationState,
 }
@@ -79,8 +98,11 @@ impl Command {
             cmd_type: flavor,
             drawables: Default::default(),
             triangles_verts: Default::default(),
+            indices: Default::default(),
+            vertex_colors: Default::default(),
             image: Default::default(),
             alpha_mask: Default::default(),
+            lut: Default::default(),
             fill_rule: Default::default(),
             composite_operation: Default::default(),
         }
@@ -99,6 +121,12 @@ pub trait Renderer {
 
     fn set_size(&mut self, width: u32, height: u32, dpi: f32);
 
+    /// Called when the surface the screen target draws to was destroyed and recreated, e.g. when a
+    /// mobile app is resumed. Renderers should drop any state tied to the old surface.
+    fn reset_surface(&mut self, width: u32, height: u32, dpi: f32) {
+        self.set_size(width, height, dpi);
+    }
+
     fn render(&mut self, images: &mut ImageStore<Self::Image>, verts: &[Vertex], commands: Vec<Command>);
 
     fn alloc_image(&mut self, info: ImageInfo) -> Result<Self::Image, ErrorKind>;
@@ -106,6 +134,17 @@ pub trait Renderer {
         -> Result<(), ErrorKind>;
     fn delete_image(&mut self, image: Self::Image, image_id: ImageId);
 
+    /// Changes how an existing image is sampled, see
+    /// [`Canvas::set_image_flags()`](crate::Canvas::set_image_flags). The flags have the same
+    /// [`ImageFlags::GENERATE_MIPMAPS`] as the image. Renderers that can't change the flags of an
+    /// image without recreating it return an error.
+    fn set_image_flags(&mut self, image: &mut Self::Image, flags: ImageFlags) -> Result<(), ErrorKind> {
+        let _ = (image, flags);
+        Err(ErrorKind::GeneralError(
+            "the renderer can't change the flags of an image".into(),
+        ))
+    }
+
     fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind>;
 }
 
@@ -136,6 +175,8 @@ pub enum ShaderType {
     Stencil,
     FillImageGradient,
     FilterImage,
+    TexturedMesh,
+    ColorMap,
 }
 
 impl Default for ShaderType {
@@ -152,6 +193,8 @@ impl ShaderType {
             Self::Stencil => 2.0,
             Self::FillImageGradient => 3.0,
             Self::FilterImage => 4.0,
+            Self::TexturedMesh => 5.0,
+            Self::ColorMap => 6.0,
         }
     }
 }
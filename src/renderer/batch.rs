@@ -0,0 +1,205 @@
+use std::ops::Range;
+
+use super::{
+    Command,
+    CommandType,
+    Params,
+    Vertex,
+};
+
+/// Merges runs of consecutive commands that draw with identical uniforms and images into single
+/// triangle list commands, so that they are drawn with one draw call. Returns the number of
+/// commands that were merged away.
+///
+/// Convex fills, strokes and triangles all use the same shader setup and only differ in how their
+/// vertices are assembled. Runs of them are rewritten into indexed triangle lists that share the
+/// vertices already in `verts`, fans and strips are unrolled with the facing the GPU would give them
+/// so that backface culling is unaffected. Commands that depend on the stencil buffer break runs.
+/// Runs with per vertex colors are rewritten into plain triangle lists appended at the end of
+/// `verts` instead, their colors have to line up with the vertices.
+pub(crate) fn merge_commands(commands: &mut Vec<Command>, verts: &mut Vec<Vertex>) -> usize {
+    let mut merged_away = 0;
+    let mut output = Vec::with_capacity(commands.len());
+    let mut drained = commands.drain(..);
+
+    for batch in batches(drained.as_slice()) {
+        let mut run: Vec<Command> = drained.by_ref().take(batch.len()).collect();
+        merged_away += flush_run(&mut run, &mut output, verts);
+    }
+
+    drop(drained);
+    *commands = output;
+
+    merged_away
+}
+
+/// Splits the commands into the runs that [`merge_commands()`] draws as a single command, in order.
+/// Commands that can't be merged are runs of their own.
+pub(crate) fn batches(commands: &[Command]) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut run_range = None;
+
+    for (i, cmd) in commands.iter().enumerate() {
+        let extends_run = i > start && compatible(&commands[i - 1], cmd) && fits_indices(run_range, vertex_range(cmd));
+
+        if i > start && !extends_run {
+            batches.push(start..i);
+            start = i;
+            run_range = None;
+        }
+
+        run_range = union(run_range, vertex_range(cmd));
+    }
+
+    if start < commands.len() {
+        batches.push(start..commands.len());
+    }
+
+    batches
+}
+
+fn mergeable_params(cmd: &Command) -> Option<&Params> {
+    match &cmd.cmd_type {
+        CommandType::ConvexFill { params } | CommandType::Stroke { params } | CommandType::Triangles { params } => {
+            Some(params)
+        }
+        _ => None,
+    }
+}
+
+fn compatible(a: &Command, b: &Command) -> bool {
+    match (mergeable_params(a), mergeable_params(b)) {
+        (Some(params_a), Some(params_b)) => {
+            params_a == params_b
+                && a.image == b.image
+                && a.alpha_mask == b.alpha_mask
+                && a.lut == b.lut
+                && a.composite_operation == b.composite_operation
+                && a.vertex_colors.is_some() == b.vertex_colors.is_some()
+        }
+        _ => false,
+    }
+}
+
+// The first and one past the last vertex the command draws
+fn vertex_range(cmd: &Command) -> Option<(usize, usize)> {
+    let ranges = cmd
+        .drawables
+        .iter()
+        .flat_map(|drawable| [drawable.fill_verts, drawable.stroke_verts])
+        .chain([cmd.triangles_verts])
+        .flatten()
+        .filter(|range| range.1 > 0);
+
+    ranges.fold(None, |merged, (start, count)| {
+        union(merged, Some((start, start + count)))
+    })
+}
+
+fn union(a: Option<(usize, usize)>, b: Option<(usize, usize)>) -> Option<(usize, usize)> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some((a.0.min(b.0), a.1.max(b.1))),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+// Indices are relative to the first vertex of the run and have to fit in 16 bits, which all GL
+// versions can draw
+fn fits_indices(run_range: Option<(usize, usize)>, range: Option<(usize, usize)>) -> bool {
+    match union(run_range, range) {
+        Some((start, end)) => end - start <= u16::MAX as usize + 1,
+        None => true,
+    }
+}
+
+// Moves the run to the output, as a single command if it has more than one
+fn flush_run(run: &mut Vec<Command>, output: &mut Vec<Command>, verts: &mut Vec<Vertex>) -> usize {
+    if run.len() < 2 {
+        output.append(run);
+        return 0;
+    }
+
+    let merged_away = run.len() - 1;
+
+    let mut cmd = Command::new(CommandType::Triangles {
+        params: *mergeable_params(&run[0]).unwrap(),
+    });
+    cmd.image = run[0].image;
+    cmd.alpha_mask = run[0].alpha_mask;
+    cmd.lut = run[0].lut;
+    cmd.composite_operation = run[0].composite_operation;
+
+    if run[0].vertex_colors.is_some() {
+        let mut triangles = Vec::new();
+        let mut colors = Vec::new();
+
+        for cmd in run.iter() {
+            if let Some(range) = cmd.triangles_verts {
+                triangles.extend_from_slice(&verts[range.0..range.0 + range.1]);
+            }
+
+            if let Some(vertex_colors) = &cmd.vertex_colors {
+                colors.extend_from_slice(vertex_colors);
+            }
+        }
+
+        cmd.triangles_verts = Some((verts.len(), triangles.len()));
+        cmd.vertex_colors = Some(colors);
+
+        verts.extend_from_slice(&triangles);
+    } else {
+        let mut run_range = None;
+
+        for cmd in run.iter() {
+            run_range = union(run_range, vertex_range(cmd));
+        }
+
+        let base = run_range.map_or(0, |range| range.0);
+        let mut indices = Vec::new();
+
+        for cmd in run.iter() {
+            for drawable in &cmd.drawables {
+                if let Some(range) = drawable.fill_verts {
+                    unroll_fan(range.0 - base, range.1, &mut indices);
+                }
+
+                if let Some(range) = drawable.stroke_verts {
+                    unroll_strip(range.0 - base, range.1, &mut indices);
+                }
+            }
+
+            if let Some(range) = cmd.triangles_verts {
+                indices.extend((range.0 - base..range.0 - base + range.1).map(|index| index as u16));
+            }
+        }
+
+        cmd.triangles_verts = run_range.map(|(start, end)| (start, end - start));
+        cmd.indices = Some(indices);
+    }
+
+    run.clear();
+    output.push(cmd);
+
+    merged_away
+}
+
+fn unroll_fan(first: usize, count: usize, indices: &mut Vec<u16>) {
+    for i in 1..count.saturating_sub(1) {
+        indices.extend_from_slice(&[first as u16, (first + i) as u16, (first + i + 1) as u16]);
+    }
+}
+
+// Every other triangle of a strip has its first two vertices swapped to keep the facing of the strip
+fn unroll_strip(first: usize, count: usize, indices: &mut Vec<u16>) {
+    for i in 0..count.saturating_sub(2) {
+        let index = first + i;
+
+        if i % 2 == 0 {
+            indices.extend_from_slice(&[index as u16, (index + 1) as u16, (index + 2) as u16]);
+        } else {
+            indices.extend_from_slice(&[(index + 1) as u16, index as u16, (index + 2) as u16]);
+        }
+    }
+}
@@ -1,12 +1,17 @@
+use std::cell::Cell;
 use std::mem;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::ffi::c_void;
 
+use bitflags::bitflags;
 use fnv::FnvHashMap;
 use imgref::ImgVec;
-use rgb::RGBA8;
+use rgb::{
+    ComponentBytes,
+    RGBA8,
+};
 
 use crate::{
     renderer::{
@@ -19,6 +24,7 @@ use crate::{
     ErrorKind,
     FillRule,
     ImageFilter,
+    ImageFlags,
     ImageInfo,
     ImageSource,
     ImageStore,
@@ -37,7 +43,14 @@ use super::{
 };
 
 mod program;
-use program::MainProgram;
+use program::{
+    MainProgram,
+    FRAG_BLOCK_BINDING,
+    TEXTURE_UNITS,
+};
+
+mod program_cache;
+use program_cache::ProgramCache;
 
 mod gl_texture;
 use gl_texture::GlTexture;
@@ -48,19 +61,84 @@ use framebuffer::Framebuffer;
 mod uniform_array;
 use uniform_array::UniformArray;
 
+mod opaque;
+use opaque::Step;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod gl_state;
+#[cfg(not(target_arch = "wasm32"))]
+use gl_state::GlState;
+
+#[cfg(all(feature = "window-handle", not(target_arch = "wasm32")))]
+mod window;
+#[cfg(all(feature = "window-handle", not(target_arch = "wasm32")))]
+pub use window::WindowContext;
+
+/// Supplies externally updated textures, such as camera feeds, video decoder output or shared
+/// surfaces, for images drawn by an [`OpenGl`] renderer.
+///
+/// Create a placeholder image with [`Canvas::create_image_empty()`](crate::Canvas::create_image_empty)
+/// that has the size and pixel format of the external content and draw it like any other image. Every
+/// time the canvas is flushed the renderer asks the provider for a texture for each image used by the
+/// frame and samples the returned texture instead of the placeholder, so streaming content doesn't
+/// have to be copied through [`Canvas::update_image()`](crate::Canvas::update_image).
+pub trait ExternalImageProvider {
+    /// Returns the texture to bind for `image`, or `None` to use the image's own texture.
+    ///
+    /// The texture must belong to the renderer's OpenGL context and must stay alive until the flush
+    /// is done.
+    fn texture(&mut self, image: ImageId) -> Option<<glow::Context as glow::HasContext>::Texture>;
+}
+
+bitflags! {
+    /// Optional features of the context an [`OpenGl`] renderer draws with, see
+    /// [`OpenGl::capabilities()`].
+    pub struct Capabilities: u32 {
+        /// Mipmaps and repeat wrapping for images whose sizes aren't powers of two. Missing on
+        /// OpenGL ES 2.0 and WebGL 1, where such images are created without their
+        /// `GENERATE_MIPMAPS`, `REPEAT_X` and `REPEAT_Y` flags.
+        const NPOT_IMAGE_MIPMAPS_AND_REPEAT = 1;
+        /// The fragment parameters of a flush are uploaded in a single uniform buffer.
+        const UNIFORM_BUFFERS = 1 << 1;
+        /// KHR_debug groups, see [`OpenGl::set_debug_groups()`].
+        const DEBUG_GROUPS = 1 << 2;
+        /// Multisampled render targets for images created with `MULTISAMPLE`. Missing on OpenGL ES
+        /// 2.0 and WebGL 1, where such images are drawn into like other images.
+        const MULTISAMPLED_RENDER_TARGETS = 1 << 3;
+    }
+}
+
 pub struct OpenGl {
     debug: bool,
+    // Wraps every command in a KHR_debug group, see set_debug_groups()
+    debug_groups: bool,
     antialias: bool,
     is_opengles_2_0: bool,
+    capabilities: Capabilities,
     view: [f32; 2],
     screen_view: [f32; 2],
     main_program: MainProgram,
     vert_arr: Option<<glow::Context as glow::HasContext>::VertexArray>,
     vert_buff: Option<<glow::Context as glow::HasContext>::Buffer>,
+    color_buff: Option<<glow::Context as glow::HasContext>::Buffer>,
+    index_buff: Option<<glow::Context as glow::HasContext>::Buffer>,
+    // Holds the fragment parameters of every draw of a flush if the program reads them from a
+    // uniform buffer, each in its own slot of `uniform_stride` floats
+    uniform_buff: Option<<glow::Context as glow::HasContext>::Buffer>,
+    uniform_stride: usize,
+    uniforms: Vec<f32>,
+    // The slot of the next draw, draws consume the slots in the order their uniforms were packed
+    uniform_slot: Cell<usize>,
     framebuffers: FnvHashMap<ImageId, Result<Framebuffer, ErrorKind>>,
-    context: Rc<glow::Context>,
+    // Samples per pixel of the render targets of images with `MULTISAMPLE`, 0 if unsupported
+    render_target_samples: i32,
+    context: Arc<glow::Context>,
     screen_target: Option<Framebuffer>,
     current_render_target: RenderTarget,
+    preserve_state: bool,
+    opaque_prepass: bool,
+    external_image_provider: Option<Box<dyn ExternalImageProvider>>,
+    external_textures: FnvHashMap<ImageId, <glow::Context as glow::HasContext>::Texture>,
 }
 
 impl OpenGl {
@@ -72,45 +150,147 @@ impl OpenGl {
         Self::new_from_context(unsafe { glow::Context::from_loader_function(load_fn) }, false)
     }
 
+    /// Creates a renderer like [`Self::new()`] that keeps its compiled shader programs in
+    /// `cache_dir`.
+    ///
+    /// Compiling the shaders can take hundreds of milliseconds on some mobile drivers, which stalls
+    /// the first frame. The linked programs are written to the directory with `glGetProgramBinary()`
+    /// and loaded from there on later runs. The files are keyed by the vendor, renderer and version
+    /// strings of the driver and by the shader sources, so a driver or femtovg update compiles the
+    /// shaders again. Binaries the driver rejects are compiled again and replaced. Without support
+    /// for program binaries (OpenGL 4.1, OpenGL ES 3.0 or `ARB_get_program_binary`), or if the
+    /// directory can't be written, this behaves like [`Self::new()`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_with_program_cache<F>(
+        mut load_fn: F,
+        cache_dir: impl Into<std::path::PathBuf>,
+    ) -> Result<Self, ErrorKind>
+    where
+        F: FnMut(&str) -> *const c_void,
+    {
+        let fns = program_cache::BinaryFns::load(&mut load_fn);
+        let context = unsafe { glow::Context::from_loader_function(load_fn) };
+
+        let cache = fns.and_then(|fns| ProgramCache::new(&context, fns, cache_dir.into()));
+
+        Self::new_from_shared_context(Arc::new(context), false, cache.as_ref())
+    }
+
+    /// Creates a renderer that shares a glow context owned by the host application.
+    ///
+    /// The GL state femtovg touches (bound program, buffers, textures and framebuffer, blending,
+    /// stencil, scissor and viewport settings) is saved before and restored after every flush and
+    /// image upload, so femtovg can be layered into an existing rendering loop without the host
+    /// having to reset its own state. The context must be current whenever the canvas is used.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_from_glow_context(context: Arc<glow::Context>) -> Result<Self, ErrorKind> {
+        let version = unsafe { context.get_parameter_string(glow::VERSION) };
+        let is_opengles_2_0 = version.starts_with("OpenGL ES 2");
+
+        let mut opengl = Self::new_from_shared_context(context, is_opengles_2_0, None)?;
+        opengl.preserve_state = true;
+
+        Ok(opengl)
+    }
+
+    /// Creates a renderer that draws into the provided canvas element.
+    ///
+    /// A WebGL 2 context is used when the browser supports it, otherwise falls back to WebGL 1.
     #[cfg(target_arch = "wasm32")]
     pub fn new_from_html_canvas(canvas: &web_sys::HtmlCanvasElement) -> Result<Self, ErrorKind> {
+        Self::new_from_webgl(|kind, attrs| canvas.get_context_with_context_options(kind, attrs).ok().flatten())
+    }
+
+    /// Creates a renderer that draws into the provided offscreen canvas.
+    ///
+    /// This allows rendering from a web worker: transfer control of a canvas element to the worker with
+    /// `transferControlToOffscreen()` and create the renderer and the [`Canvas`](crate::Canvas) there.
+    /// All femtovg objects have to be created and used on the worker, they can't be shared with the main
+    /// thread. A WebGL 2 context is used when the browser supports it, otherwise falls back to WebGL 1.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new_from_offscreen_canvas(canvas: &web_sys::OffscreenCanvas) -> Result<Self, ErrorKind> {
+        Self::new_from_webgl(|kind, attrs| canvas.get_context_with_context_options(kind, attrs).ok().flatten())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn new_from_webgl<F, C>(get_context: F) -> Result<Self, ErrorKind>
+    where
+        F: Fn(&str, &wasm_bindgen::JsValue) -> Option<C>,
+        C: wasm_bindgen::JsCast,
+    {
         let mut attrs = web_sys::WebGlContextAttributes::new();
         attrs.stencil(true);
         attrs.antialias(false);
 
-        use wasm_bindgen::JsCast;
-        let webgl1_context = canvas
-            .get_context_with_context_options("webgl", attrs.as_ref())
-            .map_err(|_| ErrorKind::GeneralError("Canvas::getContext failed to retrieve WebGL 1 context".to_owned()))?
-            .unwrap()
-            .dyn_into::<web_sys::WebGlRenderingContext>()
-            .unwrap();
+        let webgl2_context = get_context("webgl2", attrs.as_ref())
+            .and_then(|context| context.dyn_into::<web_sys::WebGl2RenderingContext>().ok());
+
+        if let Some(webgl2_context) = webgl2_context {
+            let context = glow::Context::from_webgl2_context(webgl2_context);
+            return Self::new_from_context(context, false);
+        }
+
+        let webgl1_context = get_context("webgl", attrs.as_ref())
+            .and_then(|context| context.dyn_into::<web_sys::WebGlRenderingContext>().ok())
+            .ok_or_else(|| {
+                ErrorKind::GeneralError("Canvas::getContext failed to retrieve a WebGL 2 or WebGL 1 context".to_owned())
+            })?;
 
         let context = glow::Context::from_webgl1_context(webgl1_context);
         Self::new_from_context(context, true)
     }
 
     fn new_from_context(context: glow::Context, is_opengles_2_0: bool) -> Result<Self, ErrorKind> {
+        Self::new_from_shared_context(Arc::new(context), is_opengles_2_0, None)
+    }
+
+    fn new_from_shared_context(
+        context: Arc<glow::Context>,
+        is_opengles_2_0: bool,
+        program_cache: Option<&ProgramCache>,
+    ) -> Result<Self, ErrorKind> {
         let debug = cfg!(debug_assertions);
         let antialias = true;
 
-        let context = Rc::new(context);
+        let uniform_buffer_glsl_version = if is_opengles_2_0 {
+            None
+        } else {
+            program::uniform_buffer_glsl_version(&context)
+        };
 
-        let main_program = MainProgram::new(&context, antialias)?;
+        let main_program = match uniform_buffer_glsl_version {
+            // Drivers that fail to compile the uniform buffer variant still get the uniform array
+            Some(glsl_version) => MainProgram::with_uniform_buffer(&context, antialias, glsl_version, program_cache)
+                .or_else(|_| MainProgram::new(&context, antialias, program_cache))?,
+            None => MainProgram::new(&context, antialias, program_cache)?,
+        };
 
         let mut opengl = OpenGl {
             debug: debug,
+            debug_groups: false,
             antialias: antialias,
             is_opengles_2_0: false,
+            capabilities: Capabilities::empty(),
             view: [0.0, 0.0],
             screen_view: [0.0, 0.0],
             main_program: main_program,
             vert_arr: Default::default(),
             vert_buff: Default::default(),
+            color_buff: Default::default(),
+            index_buff: Default::default(),
+            uniform_buff: Default::default(),
+            uniform_stride: 0,
+            uniforms: Vec::new(),
+            uniform_slot: Cell::new(0),
             framebuffers: Default::default(),
+            render_target_samples: 0,
             context: context.clone(),
             screen_target: None,
             current_render_target: RenderTarget::Screen,
+            preserve_state: false,
+            opaque_prepass: false,
+            external_image_provider: None,
+            external_textures: Default::default(),
         };
 
         unsafe {
@@ -118,8 +298,39 @@ impl OpenGl {
 
             opengl.vert_arr = opengl.context.create_vertex_array().ok();
             opengl.vert_buff = opengl.context.create_buffer().ok();
+            opengl.color_buff = opengl.context.create_buffer().ok();
+            opengl.index_buff = opengl.context.create_buffer().ok();
+
+            if opengl.main_program.uses_uniform_buffer() {
+                let alignment = opengl
+                    .context
+                    .get_parameter_i32(glow::UNIFORM_BUFFER_OFFSET_ALIGNMENT)
+                    .max(1) as usize;
+                let slot_size = mem::size_of::<UniformArray>().div_ceil(alignment) * alignment;
+
+                opengl.uniform_buff = opengl.context.create_buffer().ok();
+                opengl.uniform_stride = slot_size / mem::size_of::<f32>();
+            }
+
+            if !is_opengles_2_0 {
+                opengl.render_target_samples = opengl.context.get_parameter_i32(glow::MAX_SAMPLES).clamp(0, 4);
+            }
         }
 
+        opengl
+            .capabilities
+            .set(Capabilities::NPOT_IMAGE_MIPMAPS_AND_REPEAT, !is_opengles_2_0);
+        opengl
+            .capabilities
+            .set(Capabilities::UNIFORM_BUFFERS, opengl.main_program.uses_uniform_buffer());
+        opengl
+            .capabilities
+            .set(Capabilities::DEBUG_GROUPS, opengl.context.supports_debug());
+        opengl.capabilities.set(
+            Capabilities::MULTISAMPLED_RENDER_TARGETS,
+            opengl.render_target_samples > 1,
+        );
+
         Ok(opengl)
     }
 
@@ -127,6 +338,102 @@ impl OpenGl {
         self.is_opengles_2_0
     }
 
+    /// Returns the optional features the context supports.
+    ///
+    /// femtovg degrades instead of failing where a feature is missing, e.g. on WebGL 1, so this is
+    /// how to find out which ones are in use. [`Canvas::image_info()`](crate::Canvas::image_info)
+    /// keeps reporting the flags an image was created with, even if the renderer had to drop some.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Enables or disables labeling the GL calls of every draw with a KHR_debug group, disabled by
+    /// default.
+    ///
+    /// Each command of a flush is wrapped in a debug group named after its kind and its index in
+    /// the flush, e.g. `femtovg ConcaveFill #12`, so that GPU debuggers like RenderDoc show which
+    /// draw issued which GL calls. Has no effect on contexts without KHR_debug.
+    pub fn set_debug_groups(&mut self, enabled: bool) {
+        self.debug_groups = enabled && self.context.supports_debug();
+    }
+
+    /// Enables or disables drawing opaque fills ahead of the other draws of a flush, disabled by default.
+    ///
+    /// Scenes of stacked opaque panels draw most pixels several times, which is expensive on
+    /// integrated GPUs with little memory bandwidth. With the pre-pass enabled, the insides of
+    /// convex fills with opaque colors or gradients are drawn first, front to back with
+    /// blending disabled, and record their depth. Everything else is then drawn back to front as
+    /// usual, and pixels hidden by a later opaque fill are skipped by the depth test. The result
+    /// looks the same as without the pre-pass.
+    ///
+    /// Fills with images, alpha masks, scissors or composite operations other than source-over
+    /// are never treated as opaque, nor are fills merged by
+    /// [`Canvas::set_command_batching()`](crate::Canvas::set_command_batching). The render
+    /// target needs a depth buffer. Images used as render targets always have one, the screen
+    /// framebuffer has to be created with one.
+    pub fn set_opaque_prepass(&mut self, enabled: bool) {
+        self.opaque_prepass = enabled;
+    }
+
+    /// Sets the provider that is queried for external textures on every flush.
+    ///
+    /// Pass `None` to go back to drawing all images from their own textures.
+    pub fn set_external_image_provider(&mut self, provider: Option<Box<dyn ExternalImageProvider>>) {
+        self.external_image_provider = provider;
+    }
+
+    fn query_external_textures(&mut self, commands: &[Command]) {
+        self.external_textures.clear();
+
+        let provider = match &mut self.external_image_provider {
+            Some(provider) => provider,
+            None => return,
+        };
+
+        for id in commands
+            .iter()
+            .flat_map(|cmd| cmd.image.into_iter().chain(cmd.alpha_mask).chain(cmd.lut))
+        {
+            if self.external_textures.contains_key(&id) {
+                continue;
+            }
+
+            if let Some(texture) = provider.texture(id) {
+                self.external_textures.insert(id, texture);
+            }
+        }
+    }
+
+    fn texture(
+        &self,
+        images: &ImageStore<GlTexture>,
+        id: Option<ImageId>,
+    ) -> Option<<glow::Context as glow::HasContext>::Texture> {
+        let id = id?;
+
+        if let Some(texture) = self.external_textures.get(&id) {
+            return Some(*texture);
+        }
+
+        images.get(id).map(|tex| tex.id())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_state(&self) -> Option<GlState> {
+        if self.preserve_state {
+            Some(GlState::save(&self.context, self.main_program.uses_uniform_buffer()))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restore_state(&self, state: Option<GlState>) {
+        if let Some(state) = state {
+            state.restore(&self.context);
+        }
+    }
+
     fn check_error(&self, label: &str) {
         if !self.debug {
             return;
@@ -150,6 +457,19 @@ impl OpenGl {
         eprintln!("({}) Error on {} - {}", err, label, message);
     }
 
+    fn command_label(cmd_type: &CommandType) -> &'static str {
+        match cmd_type {
+            CommandType::ConvexFill { .. } => "ConvexFill",
+            CommandType::ConcaveFill { .. } => "ConcaveFill",
+            CommandType::Stroke { .. } => "Stroke",
+            CommandType::StencilStroke { .. } => "StencilStroke",
+            CommandType::Triangles { .. } => "Triangles",
+            CommandType::ClearRect { .. } => "ClearRect",
+            CommandType::SetRenderTarget(_) => "SetRenderTarget",
+            CommandType::RenderFilteredImage { .. } => "RenderFilteredImage",
+        }
+    }
+
     fn gl_factor(factor: BlendFactor) -> u32 {
         match factor {
             BlendFactor::Zero => glow::ZERO,
@@ -177,11 +497,12 @@ impl OpenGl {
         }
     }
 
-    fn convex_fill(&self, images: &ImageStore<GlTexture>, cmd: &Command, gpu_paint: &Params) {
-        self.set_uniforms(images, gpu_paint, cmd.image, cmd.alpha_mask);
+    // With `fringe_only` set only the anti-aliased edges are drawn, the inside was drawn by opaque_fill()
+    fn convex_fill(&self, images: &ImageStore<GlTexture>, cmd: &Command, gpu_paint: &Params, fringe_only: bool) {
+        self.set_uniforms(images, gpu_paint, cmd.image, cmd.alpha_mask, cmd.lut);
 
         for drawable in &cmd.drawables {
-            if let Some((start, count)) = drawable.fill_verts {
+            if let Some((start, count)) = drawable.fill_verts.filter(|_| !fringe_only) {
                 unsafe {
                     self.context.draw_arrays(glow::TRIANGLE_FAN, start as i32, count as i32);
                 }
@@ -198,6 +519,21 @@ impl OpenGl {
         self.check_error("convex_fill");
     }
 
+    // Draws the inside of an opaque convex fill and writes its depth, blending has to be disabled
+    fn opaque_fill(&self, images: &ImageStore<GlTexture>, cmd: &Command, gpu_paint: &Params) {
+        self.set_uniforms(images, gpu_paint, cmd.image, cmd.alpha_mask, cmd.lut);
+
+        for drawable in &cmd.drawables {
+            if let Some((start, count)) = drawable.fill_verts {
+                unsafe {
+                    self.context.draw_arrays(glow::TRIANGLE_FAN, start as i32, count as i32);
+                }
+            }
+        }
+
+        self.check_error("opaque_fill");
+    }
+
     fn concave_fill(&self, images: &ImageStore<GlTexture>, cmd: &Command, stencil_paint: &Params, fill_paint: &Params) {
         unsafe {
             self.context.enable(glow::STENCIL_TEST);
@@ -207,7 +543,7 @@ impl OpenGl {
             //glow::DepthMask(glow::FALSE);
         }
 
-        self.set_uniforms(images, stencil_paint, None, None);
+        self.set_uniforms(images, stencil_paint, None, None, None);
 
         unsafe {
             self.context
@@ -232,7 +568,7 @@ impl OpenGl {
             //glow::DepthMask(glow::TRUE);
         }
 
-        self.set_uniforms(images, fill_paint, cmd.image, cmd.alpha_mask);
+        self.set_uniforms(images, fill_paint, cmd.image, cmd.alpha_mask, cmd.lut);
 
         if self.antialias {
             unsafe {
@@ -275,7 +611,7 @@ impl OpenGl {
     }
 
     fn stroke(&self, images: &ImageStore<GlTexture>, cmd: &Command, paint: &Params) {
-        self.set_uniforms(images, paint, cmd.image, cmd.alpha_mask);
+        self.set_uniforms(images, paint, cmd.image, cmd.alpha_mask, cmd.lut);
 
         for drawable in &cmd.drawables {
             if let Some((start, count)) = drawable.stroke_verts {
@@ -299,7 +635,7 @@ impl OpenGl {
             self.context.stencil_op(glow::KEEP, glow::KEEP, glow::INCR);
         }
 
-        self.set_uniforms(images, paint2, cmd.image, cmd.alpha_mask);
+        self.set_uniforms(images, paint2, cmd.image, cmd.alpha_mask, cmd.lut);
 
         for drawable in &cmd.drawables {
             if let Some((start, count)) = drawable.stroke_verts {
@@ -311,7 +647,7 @@ impl OpenGl {
         }
 
         // Draw anti-aliased pixels.
-        self.set_uniforms(images, paint1, cmd.image, cmd.alpha_mask);
+        self.set_uniforms(images, paint1, cmd.image, cmd.alpha_mask, cmd.lut);
 
         unsafe {
             self.context.stencil_func(glow::EQUAL, 0x0, 0xff);
@@ -352,49 +688,219 @@ impl OpenGl {
     }
 
     fn triangles(&self, images: &ImageStore<GlTexture>, cmd: &Command, paint: &Params) {
-        self.set_uniforms(images, paint, cmd.image, cmd.alpha_mask);
+        self.set_uniforms(images, paint, cmd.image, cmd.alpha_mask, cmd.lut);
 
         if let Some((start, count)) = cmd.triangles_verts {
-            unsafe {
-                self.context.draw_arrays(glow::TRIANGLES, start as i32, count as i32);
+            if let Some(indices) = &cmd.indices {
+                self.indexed_triangles(start, indices);
+            } else if let Some(colors) = &cmd.vertex_colors {
+                self.colored_triangles(start, count, colors);
+            } else {
+                unsafe {
+                    self.context.draw_arrays(glow::TRIANGLES, start as i32, count as i32);
+                }
             }
         }
 
         self.check_error("triangles");
     }
 
+    // The indices are relative to the first vertex of the triangles, the vertex attributes are rebased on that
+    // vertex while drawing so that 16 bit indices, which every GL version supports, are enough.
+    fn indexed_triangles(&self, start: usize, indices: &[u16]) {
+        unsafe {
+            self.set_vertex_offset(start);
+
+            self.context.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, self.index_buff);
+            self.context
+                .buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, indices.align_to().1, glow::STREAM_DRAW);
+            self.context
+                .draw_elements(glow::TRIANGLES, indices.len() as i32, glow::UNSIGNED_SHORT, 0);
+            self.context.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None);
+
+            self.set_vertex_offset(0);
+        }
+    }
+
+    // The colors are uploaded to their own buffer that starts at the first vertex of the triangles, so the
+    // vertex attributes are rebased on that vertex while drawing.
+    fn colored_triangles(&self, start: usize, count: usize, colors: &[RGBA8]) {
+        unsafe {
+            self.set_vertex_offset(start);
+
+            self.context.bind_buffer(glow::ARRAY_BUFFER, self.color_buff);
+            self.context
+                .buffer_data_u8_slice(glow::ARRAY_BUFFER, colors.as_bytes(), glow::STREAM_DRAW);
+            self.context.enable_vertex_attrib_array(2);
+            self.context
+                .vertex_attrib_pointer_f32(2, 4, glow::UNSIGNED_BYTE, true, 0, 0);
+
+            // Meshes from other libraries don't follow our winding order
+            self.context.disable(glow::CULL_FACE);
+            self.context.draw_arrays(glow::TRIANGLES, 0, count as i32);
+            self.context.enable(glow::CULL_FACE);
+
+            self.context.disable_vertex_attrib_array(2);
+            self.context.bind_buffer(glow::ARRAY_BUFFER, self.vert_buff);
+
+            self.set_vertex_offset(0);
+        }
+    }
+
+    // Points the position and texture coordinate attributes at the vertex buffer, starting at `first_vertex`.
+    // The vertex buffer has to be bound.
+    unsafe fn set_vertex_offset(&self, first_vertex: usize) {
+        let vertex_size = mem::size_of::<Vertex>() as i32;
+        let offset = (first_vertex * mem::size_of::<Vertex>()) as i32;
+
+        self.context
+            .vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, vertex_size, offset);
+        self.context.vertex_attrib_pointer_f32(
+            1,
+            2,
+            glow::FLOAT,
+            false,
+            vertex_size,
+            offset + 2 * mem::size_of::<f32>() as i32,
+        );
+    }
+
     fn set_uniforms(
         &self,
         images: &ImageStore<GlTexture>,
         paint: &Params,
         image_tex: Option<ImageId>,
         alpha_tex: Option<ImageId>,
+        lut_tex: Option<ImageId>,
     ) {
-        let arr = UniformArray::from(paint);
-        self.main_program.set_config(arr.as_slice());
+        if self.main_program.uses_uniform_buffer() {
+            let slot = self.uniform_slot.get();
+            self.uniform_slot.set(slot + 1);
+
+            let start = slot * self.uniform_stride;
+            let size = mem::size_of::<UniformArray>();
+
+            debug_assert!(
+                self.uniforms.get(start..start + size / mem::size_of::<f32>())
+                    == Some(UniformArray::from(paint).as_slice()),
+                "draws and packed uniforms are out of order"
+            );
+
+            unsafe {
+                self.context.bind_buffer_range(
+                    glow::UNIFORM_BUFFER,
+                    FRAG_BLOCK_BINDING,
+                    self.uniform_buff,
+                    (start * mem::size_of::<f32>()) as i32,
+                    size as i32,
+                );
+            }
+        } else {
+            let arr = UniformArray::from(paint);
+            self.main_program.set_config(arr.as_slice());
+        }
+
         self.check_error("set_uniforms uniforms");
 
-        let tex = image_tex
-            .and_then(|id| images.get(id))
-            .map_or(None, |tex| Some(tex.id()));
+        let tex = self.texture(images, image_tex);
 
         unsafe {
             self.context.active_texture(glow::TEXTURE0);
             self.context.bind_texture(glow::TEXTURE_2D, tex);
         }
 
-        let masktex = alpha_tex
-            .and_then(|id| images.get(id))
-            .map_or(None, |tex| Some(tex.id()));
+        let masktex = self.texture(images, alpha_tex);
 
         unsafe {
             self.context.active_texture(glow::TEXTURE0 + 1);
             self.context.bind_texture(glow::TEXTURE_2D, masktex);
         }
 
+        let luttex = self.texture(images, lut_tex);
+
+        unsafe {
+            self.context.active_texture(glow::TEXTURE0 + 2);
+            self.context.bind_texture(glow::TEXTURE_2D, luttex);
+        }
+
         self.check_error("set_uniforms texture");
     }
 
+    // Uploads the fragment parameters of all draws of the commands to the uniform buffer at once, in
+    // the order the draws set them
+    fn upload_uniforms(&mut self, images: &ImageStore<GlTexture>, commands: &[Command], steps: &[Step]) {
+        let mut uniforms = mem::take(&mut self.uniforms);
+        uniforms.clear();
+
+        let stride = self.uniform_stride;
+        let mut push = |params: &Params| {
+            let start = uniforms.len();
+            uniforms.extend_from_slice(UniformArray::from(params).as_slice());
+            uniforms.resize(start + stride, 0.0);
+        };
+
+        for step in steps {
+            let cmd = match *step {
+                Step::Draw { index, .. } => &commands[index],
+                Step::Opaque { index, .. } => {
+                    if let CommandType::ConvexFill { params } = &commands[index].cmd_type {
+                        push(params);
+                    }
+
+                    continue;
+                }
+                Step::BeginDepth | Step::EndOpaque | Step::EndDepth => continue,
+            };
+
+            // The same commands are skipped when drawing
+            if cmd.image.into_iter().chain(cmd.lut).any(|id| images.get(id).is_none()) {
+                continue;
+            }
+
+            match &cmd.cmd_type {
+                CommandType::ConvexFill { params }
+                | CommandType::Stroke { params }
+                | CommandType::Triangles { params } => push(params),
+                CommandType::ConcaveFill {
+                    stencil_params,
+                    fill_params,
+                } => {
+                    push(stencil_params);
+                    push(fill_params);
+                }
+                // stencil_stroke() draws with the second parameters first
+                CommandType::StencilStroke { params1, params2 } => {
+                    push(params2);
+                    push(params1);
+                }
+                CommandType::RenderFilteredImage { target_image, filter } => match *filter {
+                    ImageFilter::GaussianBlur { sigma } => {
+                        if images.get(*target_image).is_none() {
+                            continue;
+                        }
+
+                        if let Some(mut blur_params) = Self::gaussian_blur_params(images, cmd, sigma) {
+                            push(&blur_params);
+
+                            blur_params.image_blur_filter_direction = [0.0, 1.0];
+                            push(&blur_params);
+                        }
+                    }
+                },
+                CommandType::ClearRect { .. } | CommandType::SetRenderTarget(_) => (),
+            }
+        }
+
+        unsafe {
+            self.context.bind_buffer(glow::UNIFORM_BUFFER, self.uniform_buff);
+            self.context
+                .buffer_data_u8_slice(glow::UNIFORM_BUFFER, uniforms.align_to().1, glow::STREAM_DRAW);
+        }
+
+        self.uniforms = uniforms;
+        self.uniform_slot.set(0);
+    }
+
     fn clear_rect(&self, x: u32, y: u32, width: u32, height: u32, color: Color) {
         unsafe {
             self.context.enable(glow::SCISSOR_TEST);
@@ -411,6 +917,10 @@ impl OpenGl {
     }
 
     fn set_target(&mut self, images: &ImageStore<GlTexture>, target: RenderTarget) {
+        if target != self.current_render_target {
+            self.resolve_target();
+        }
+
         self.current_render_target = target;
         match (target, &self.screen_target) {
             (RenderTarget::Screen, None) => unsafe {
@@ -427,12 +937,18 @@ impl OpenGl {
             }
             (RenderTarget::Image(id), _) => {
                 let context = self.context.clone();
+                let samples = self.render_target_samples;
                 if let Some(texture) = images.get(id) {
-                    if let Ok(fb) = self
-                        .framebuffers
-                        .entry(id)
-                        .or_insert_with(|| Framebuffer::new(&context, texture))
-                    {
+                    if let Ok(fb) = self.framebuffers.entry(id).or_insert_with(|| {
+                        if samples > 1 && texture.info().flags().contains(ImageFlags::MULTISAMPLE) {
+                            // Drivers may still refuse a sample count and only RGBA8 images are multisampled, the
+                            // image is drawn into directly otherwise
+                            Framebuffer::new_multisampled(&context, texture, samples)
+                                .or_else(|_| Framebuffer::new(&context, texture))
+                        } else {
+                            Framebuffer::new(&context, texture)
+                        }
+                    }) {
                         fb.bind();
 
                         self.view[0] = texture.info().width() as f32;
@@ -448,6 +964,15 @@ impl OpenGl {
         }
     }
 
+    // Copies the drawing of a multisampled image target into its image, so that it can be drawn
+    fn resolve_target(&self) {
+        if let RenderTarget::Image(id) = self.current_render_target {
+            if let Some(Ok(framebuffer)) = self.framebuffers.get(&id) {
+                framebuffer.resolve();
+            }
+        }
+    }
+
     /// Make the "Screen" RenderTarget actually render to a framebuffer object. This is useful when
     /// embedding femtovg into another program where final composition is handled by an external task.
     /// The given `framebuffer_object` must refer to a Framebuffer Object created on the current OpenGL
@@ -462,34 +987,14 @@ impl OpenGl {
         }
     }
 
-    fn render_filtered_image(
-        &mut self,
-        images: &mut ImageStore<GlTexture>,
-        cmd: Command,
-        target_image: ImageId,
-        filter: ImageFilter,
-    ) {
-        match filter {
-            ImageFilter::GaussianBlur { sigma } => self.render_gaussian_blur(images, cmd, target_image, sigma),
-        }
-    }
-
-    fn render_gaussian_blur(
-        &mut self,
-        images: &mut ImageStore<GlTexture>,
-        mut cmd: Command,
-        target_image: ImageId,
-        sigma: f32,
-    ) {
-        let original_render_target = self.current_render_target;
-
-        // The filtering happens in two passes, first a horizontal blur and then the vertial blur. The
-        // first pass therefore renders into an intermediate, temporarily allocated texture.
-
-        let source_image_info = images.get(cmd.image.unwrap()).unwrap().info();
+    // Parameters of the horizontal pass of the blur, the vertical pass only changes the direction.
+    // Returns None if the source image was deleted after the filter was recorded.
+    fn gaussian_blur_params(images: &ImageStore<GlTexture>, cmd: &Command, sigma: f32) -> Option<Params> {
+        let source_image = cmd.image?;
+        let source_image_info = images.get(source_image)?.info();
 
         let image_paint = crate::Paint::image(
-            cmd.image.unwrap(),
+            source_image,
             0.,
             0.,
             source_image_info.width() as _,
@@ -514,7 +1019,52 @@ impl OpenGl {
         // on the number of iterations in the fragment shader.
         blur_params.image_blur_filter_sigma = sigma.min(8.);
 
-        let horizontal_blur_buffer = images.alloc(self, source_image_info).unwrap();
+        Some(blur_params)
+    }
+
+    fn render_filtered_image(
+        &mut self,
+        images: &mut ImageStore<GlTexture>,
+        cmd: &Command,
+        target_image: ImageId,
+        filter: ImageFilter,
+    ) {
+        match filter {
+            ImageFilter::GaussianBlur { sigma } => self.render_gaussian_blur(images, cmd, target_image, sigma),
+        }
+    }
+
+    fn render_gaussian_blur(
+        &mut self,
+        images: &mut ImageStore<GlTexture>,
+        cmd: &Command,
+        target_image: ImageId,
+        sigma: f32,
+    ) {
+        let original_render_target = self.current_render_target;
+
+        // The filtering happens in two passes, first a horizontal blur and then the vertial blur. The
+        // first pass therefore renders into an intermediate, temporarily allocated texture.
+
+        let mut blur_params = match Self::gaussian_blur_params(images, cmd, sigma) {
+            Some(params) => params,
+            None => return,
+        };
+
+        // The same filters are skipped when packing the uniforms
+        if images.get(target_image).is_none() {
+            return;
+        }
+
+        let source_image_info = match cmd.image.and_then(|id| images.get(id)) {
+            Some(image) => image.info(),
+            None => return,
+        };
+
+        let horizontal_blur_buffer = match images.alloc(self, source_image_info) {
+            Ok(id) => id,
+            Err(_) => return,
+        };
         self.set_target(images, RenderTarget::Image(horizontal_blur_buffer));
         self.main_program.set_view(self.view);
 
@@ -526,7 +1076,7 @@ impl OpenGl {
             Color::rgbaf(0., 0., 0., 0.),
         );
 
-        self.triangles(images, &cmd, &blur_params);
+        self.triangles(images, cmd, &blur_params);
 
         self.set_target(images, RenderTarget::Image(target_image));
         self.main_program.set_view(self.view);
@@ -541,9 +1091,12 @@ impl OpenGl {
 
         blur_params.image_blur_filter_direction = [0.0, 1.0];
 
-        cmd.image = Some(horizontal_blur_buffer);
+        // The vertical pass samples the result of the horizontal pass
+        let mut vertical_cmd = Command::new(CommandType::Triangles { params: blur_params });
+        vertical_cmd.triangles_verts = cmd.triangles_verts;
+        vertical_cmd.image = Some(horizontal_blur_buffer);
 
-        self.triangles(images, &cmd, &blur_params);
+        self.triangles(images, &vertical_cmd, &blur_params);
 
         images.remove(self, horizontal_blur_buffer);
 
@@ -562,14 +1115,51 @@ impl Renderer for OpenGl {
 
         self.screen_view = self.view;
 
+        // The viewport of a shared context is only touched while rendering
+        if self.preserve_state {
+            return;
+        }
+
         unsafe {
             self.context.viewport(0, 0, width as i32, height as i32);
         }
     }
 
+    fn reset_surface(&mut self, width: u32, height: u32, dpi: f32) {
+        self.set_size(width, height, dpi);
+
+        // Render targets of the old surface are not valid anymore, a framebuffer object set with
+        // set_screen_target() has to be set again if it was recreated along with the surface.
+        self.current_render_target = RenderTarget::Screen;
+
+        if !self.preserve_state {
+            match &self.screen_target {
+                Some(framebuffer) => framebuffer.bind(),
+                None => Framebuffer::unbind(&self.context),
+            }
+        }
+    }
+
     fn render(&mut self, images: &mut ImageStore<Self::Image>, verts: &[Vertex], commands: Vec<Command>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let saved_state = self.save_state();
+
+        self.query_external_textures(&commands);
+
+        let steps = opaque::plan(&commands, self.opaque_prepass);
+
+        if self.main_program.uses_uniform_buffer() {
+            self.upload_uniforms(images, &commands, &steps);
+        }
+
         self.main_program.bind();
 
+        // The host application may have bound its own framebuffer and viewport since the last flush
+        if self.preserve_state {
+            self.set_target(images, self.current_render_target);
+            self.main_program.set_view(self.view);
+        }
+
         unsafe {
             self.context.enable(glow::CULL_FACE);
 
@@ -582,10 +1172,11 @@ impl Renderer for OpenGl {
             self.context.stencil_mask(0xffff_ffff);
             self.context.stencil_op(glow::KEEP, glow::KEEP, glow::KEEP);
             self.context.stencil_func(glow::ALWAYS, 0, 0xffff_ffff);
-            self.context.active_texture(glow::TEXTURE0);
-            self.context.bind_texture(glow::TEXTURE_2D, None);
-            self.context.active_texture(glow::TEXTURE0 + 1);
-            self.context.bind_texture(glow::TEXTURE_2D, None);
+
+            for unit in 0..TEXTURE_UNITS {
+                self.context.active_texture(glow::TEXTURE0 + unit);
+                self.context.bind_texture(glow::TEXTURE_2D, None);
+            }
 
             self.context.bind_vertex_array(self.vert_arr);
 
@@ -610,27 +1201,98 @@ impl Renderer for OpenGl {
             );
         }
 
-        // Bind the two uniform samplers to texture units
+        // Bind the uniform samplers to texture units
         self.main_program.set_tex(0);
         self.main_program.set_masktex(1);
+        self.main_program.set_luttex(2);
+        self.main_program.set_depth(0.0);
 
         self.check_error("render prepare");
 
-        for cmd in commands.into_iter() {
+        let mut current_depth = 0.0;
+
+        for step in steps {
+            let (index, depth, fringe_only) = match step {
+                Step::BeginDepth => {
+                    unsafe {
+                        self.context.enable(glow::DEPTH_TEST);
+                        self.context.depth_func(glow::LESS);
+                        self.context.depth_mask(true);
+                        self.context.disable(glow::BLEND);
+                        self.context.clear_depth_f32(1.0);
+                        self.context.clear(glow::DEPTH_BUFFER_BIT);
+                    }
+
+                    continue;
+                }
+                Step::EndOpaque => {
+                    unsafe {
+                        self.context.depth_mask(false);
+                        self.context.enable(glow::BLEND);
+                    }
+
+                    continue;
+                }
+                Step::EndDepth => {
+                    unsafe {
+                        self.context.disable(glow::DEPTH_TEST);
+                        self.context.depth_mask(true);
+                    }
+
+                    continue;
+                }
+                Step::Opaque { index, depth } => {
+                    if let CommandType::ConvexFill { ref params } = commands[index].cmd_type {
+                        self.main_program.set_depth(depth);
+                        current_depth = depth;
+                        self.opaque_fill(images, &commands[index], params);
+                    }
+
+                    continue;
+                }
+                Step::Draw {
+                    index,
+                    depth,
+                    fringe_only,
+                } => (index, depth, fringe_only),
+            };
+
+            let cmd = &commands[index];
+
+            if depth != current_depth {
+                self.main_program.set_depth(depth);
+                current_depth = depth;
+            }
+
+            // Skip drawing with images that were deleted after the command was recorded instead of
+            // sampling whatever texture happens to be bound.
+            if cmd.image.into_iter().chain(cmd.lut).any(|id| images.get(id).is_none()) {
+                continue;
+            }
+
             self.set_composite_operation(cmd.composite_operation);
 
+            if self.debug_groups {
+                let label = format!("femtovg {} #{}", Self::command_label(&cmd.cmd_type), index);
+
+                unsafe {
+                    self.context
+                        .push_debug_group(glow::DEBUG_SOURCE_APPLICATION, index as u32, &label);
+                }
+            }
+
             match cmd.cmd_type {
-                CommandType::ConvexFill { ref params } => self.convex_fill(images, &cmd, params),
+                CommandType::ConvexFill { ref params } => self.convex_fill(images, cmd, params, fringe_only),
                 CommandType::ConcaveFill {
                     ref stencil_params,
                     ref fill_params,
-                } => self.concave_fill(images, &cmd, stencil_params, fill_params),
-                CommandType::Stroke { ref params } => self.stroke(images, &cmd, params),
+                } => self.concave_fill(images, cmd, stencil_params, fill_params),
+                CommandType::Stroke { ref params } => self.stroke(images, cmd, params),
                 CommandType::StencilStroke {
                     ref params1,
                     ref params2,
-                } => self.stencil_stroke(images, &cmd, params1, params2),
-                CommandType::Triangles { ref params } => self.triangles(images, &cmd, params),
+                } => self.stencil_stroke(images, cmd, params1, params2),
+                CommandType::Triangles { ref params } => self.triangles(images, cmd, params),
                 CommandType::ClearRect {
                     x,
                     y,
@@ -648,6 +1310,12 @@ impl Renderer for OpenGl {
                     self.render_filtered_image(images, cmd, target_image, filter)
                 }
             }
+
+            if self.debug_groups {
+                unsafe {
+                    self.context.pop_debug_group();
+                }
+            }
         }
 
         unsafe {
@@ -657,16 +1325,31 @@ impl Renderer for OpenGl {
 
             self.context.disable(glow::CULL_FACE);
             self.context.bind_buffer(glow::ARRAY_BUFFER, None);
+            self.context.bind_buffer(glow::UNIFORM_BUFFER, None);
             self.context.bind_texture(glow::TEXTURE_2D, None);
         }
 
         self.main_program.unbind();
 
+        // The image target stays bound for the next flush, but its image can be drawn before that
+        self.resolve_target();
+
         self.check_error("render done");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.restore_state(saved_state);
     }
 
     fn alloc_image(&mut self, info: ImageInfo) -> Result<Self::Image, ErrorKind> {
-        Self::Image::new(&self.context, info, self.is_opengles_2_0)
+        #[cfg(not(target_arch = "wasm32"))]
+        let saved_state = self.save_state();
+
+        let image = Self::Image::new(&self.context, info, self.is_opengles_2_0);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.restore_state(saved_state);
+
+        image
     }
 
     fn update_image(
@@ -676,7 +1359,15 @@ impl Renderer for OpenGl {
         x: usize,
         y: usize,
     ) -> Result<(), ErrorKind> {
-        image.update(data, x, y, self.is_opengles_2_0)
+        #[cfg(not(target_arch = "wasm32"))]
+        let saved_state = self.save_state();
+
+        let result = image.update(data, x, y, self.is_opengles_2_0);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.restore_state(saved_state);
+
+        result
     }
 
     fn delete_image(&mut self, image: Self::Image, image_id: ImageId) {
@@ -684,6 +1375,18 @@ impl Renderer for OpenGl {
         image.delete();
     }
 
+    fn set_image_flags(&mut self, image: &mut Self::Image, flags: ImageFlags) -> Result<(), ErrorKind> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let saved_state = self.save_state();
+
+        image.set_flags(flags, self.is_opengles_2_0);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.restore_state(saved_state);
+
+        Ok(())
+    }
+
     fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
         //let mut image = image::RgbaImage::new(self.view[0] as u32, self.view[1] as u32);
         let w = self.view[0] as usize;
@@ -703,6 +1406,19 @@ impl Renderer for OpenGl {
             h,
         );
 
+        // Multisampled image targets are read from the image they were resolved into
+        let target = match self.current_render_target {
+            RenderTarget::Image(id) => self
+                .framebuffers
+                .get(&id)
+                .and_then(|framebuffer| framebuffer.as_ref().ok()),
+            RenderTarget::Screen => None,
+        };
+
+        if let Some(framebuffer) = target {
+            framebuffer.bind_read();
+        }
+
         unsafe {
             self.context.read_pixels(
                 0,
@@ -715,6 +1431,10 @@ impl Renderer for OpenGl {
             );
         }
 
+        if let Some(framebuffer) = target {
+            framebuffer.bind();
+        }
+
         let mut flipped = Vec::with_capacity(w * h);
 
         for row in image.rows().rev() {
@@ -738,5 +1458,324 @@ impl Drop for OpenGl {
                 self.context.delete_buffer(vert_buff);
             }
         }
+
+        if let Some(color_buff) = self.color_buff {
+            unsafe {
+                self.context.delete_buffer(color_buff);
+            }
+        }
+
+        if let Some(index_buff) = self.index_buff {
+            unsafe {
+                self.context.delete_buffer(index_buff);
+            }
+        }
+
+        if let Some(uniform_buff) = self.uniform_buff {
+            unsafe {
+                self.context.delete_buffer(uniform_buff);
+            }
+        }
+    }
+}
+
+// Renders with Mesa's software rasterizer on EGL's surfaceless platform, without a window. Without
+// EGL the tests pass without running, unless FEMTOVG_REQUIRE_GL is set, as it is on CI.
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::sync::Arc;
+
+    use glow::HasContext;
+    use imgref::Img;
+    use khronos_egl as egl;
+    use rgb::RGBA8;
+
+    use super::{
+        program::MainProgram,
+        OpenGl,
+    };
+    use crate::{
+        Canvas,
+        Color,
+        ImageFlags,
+        Paint,
+        Path,
+        PixelFormat,
+        RenderTarget,
+    };
+
+    const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+    const SIZE: usize = 64;
+
+    #[derive(Copy, Clone)]
+    enum Api {
+        Gl,
+        Gles,
+    }
+
+    fn headless_context(api: Api) -> Option<Arc<glow::Context>> {
+        let context = unsafe { create_context(api) };
+
+        if context.is_err() && std::env::var_os("FEMTOVG_REQUIRE_GL").is_some() {
+            panic!("no headless GL context: {}", context.as_ref().err().unwrap());
+        }
+
+        context.ok().map(Arc::new)
+    }
+
+    // The EGL instance and context are leaked, they have to outlive the glow context and test
+    // processes are short-lived
+    unsafe fn create_context(api: Api) -> Result<glow::Context, String> {
+        let egl = egl::DynamicInstance::<egl::EGL1_5>::load_required().map_err(|err| err.to_string())?;
+        let egl: &'static _ = Box::leak(Box::new(egl));
+
+        let display = egl
+            .get_platform_display(PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE])
+            .map_err(|err| err.to_string())?;
+        egl.initialize(display).map_err(|err| err.to_string())?;
+
+        let (api, renderable, context_attribs) = match api {
+            Api::Gl => (
+                egl::OPENGL_API,
+                egl::OPENGL_BIT,
+                vec![
+                    egl::CONTEXT_MAJOR_VERSION,
+                    3,
+                    egl::CONTEXT_MINOR_VERSION,
+                    3,
+                    egl::CONTEXT_OPENGL_PROFILE_MASK,
+                    egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                    egl::NONE,
+                ],
+            ),
+            Api::Gles => (
+                egl::OPENGL_ES_API,
+                egl::OPENGL_ES3_BIT,
+                vec![egl::CONTEXT_MAJOR_VERSION, 3, egl::NONE],
+            ),
+        };
+
+        egl.bind_api(api).map_err(|err| err.to_string())?;
+
+        let config = egl
+            .choose_first_config(
+                display,
+                &[
+                    egl::SURFACE_TYPE,
+                    egl::PBUFFER_BIT,
+                    egl::RENDERABLE_TYPE,
+                    renderable,
+                    egl::NONE,
+                ],
+            )
+            .map_err(|err| err.to_string())?
+            .ok_or("no EGL config")?;
+        let context = egl
+            .create_context(display, config, None, &context_attribs)
+            .map_err(|err| err.to_string())?;
+        egl.make_current(display, None, None, Some(context))
+            .map_err(|err| err.to_string())?;
+
+        Ok(glow::Context::from_loader_function(|name| {
+            egl.get_proc_address(name)
+                .map_or(std::ptr::null(), |function| function as *const _)
+        }))
+    }
+
+    // Draws fills of every kind of paint, a stroke and a clipped fill into an image and reads it back
+    fn render(renderer: OpenGl) -> Vec<RGBA8> {
+        let mut canvas = Canvas::new(renderer).unwrap();
+        canvas.set_size(SIZE as u32, SIZE as u32, 1.0);
+
+        let target = canvas
+            .create_image_empty(SIZE, SIZE, PixelFormat::Rgba8, ImageFlags::empty())
+            .unwrap();
+        canvas.set_render_target(RenderTarget::Image(target));
+        canvas.clear_rect(0, 0, SIZE as u32, SIZE as u32, Color::white());
+
+        let pixels = [
+            RGBA8::new(255, 0, 0, 255),
+            RGBA8::new(0, 255, 0, 255),
+            RGBA8::new(0, 0, 255, 255),
+            RGBA8::new(255, 255, 0, 128),
+        ];
+        let image = canvas
+            .create_image(Img::new(&pixels[..], 2, 2), ImageFlags::NEAREST)
+            .unwrap();
+
+        let mut panel = Path::new();
+        panel.rect(4.0, 4.0, 40.0, 40.0);
+        canvas.fill_path(&mut panel, Paint::color(Color::rgb(40, 80, 160)));
+
+        let mut covering = Path::new();
+        covering.rounded_rect(12.0, 12.0, 40.0, 30.0, 6.0);
+        canvas.fill_path(
+            &mut covering,
+            Paint::linear_gradient(12.0, 0.0, 52.0, 0.0, Color::rgb(200, 30, 30), Color::rgb(30, 200, 30)),
+        );
+
+        let mut star = Path::new();
+        star.move_to(32.0, 2.0);
+        star.line_to(44.0, 60.0);
+        star.line_to(2.0, 22.0);
+        star.line_to(62.0, 22.0);
+        star.line_to(20.0, 60.0);
+        star.close();
+        canvas.fill_path(&mut star, Paint::color(Color::rgba(0, 0, 0, 96)));
+
+        let mut square = Path::new();
+        square.rect(40.0, 40.0, 20.0, 20.0);
+        canvas.fill_path(&mut square, Paint::image(image, 40.0, 40.0, 20.0, 20.0, 0.0, 1.0));
+
+        // Merged into one indexed draw
+        for x in [46.0, 54.0] {
+            let mut dot = Path::new();
+            dot.rect(x, 6.0, 4.0, 4.0);
+            canvas.fill_path(&mut dot, Paint::color(Color::rgb(0, 160, 160)));
+        }
+
+        let mut stroke = Paint::color(Color::rgb(250, 200, 0));
+        stroke.set_line_width(3.0);
+        canvas.stroke_path(&mut star, stroke);
+
+        canvas.save();
+        canvas.rotate(0.3);
+        canvas.scissor(20.0, 0.0, 20.0, 30.0);
+        canvas.fill_path(
+            &mut panel,
+            Paint::radial_gradient(24.0, 24.0, 0.0, 20.0, Color::rgb(255, 255, 255), Color::rgb(0, 0, 0)),
+        );
+        canvas.restore();
+
+        canvas.screenshot().unwrap().into_buf()
+    }
+
+    // Shader variants and draw orders may round differently by a step or two, parameters that are
+    // read wrong change the pixels far more
+    fn assert_same_pixels(a: &[RGBA8], b: &[RGBA8]) {
+        let close = |a: u8, b: u8| (i16::from(a) - i16::from(b)).abs() <= 2;
+
+        let differences: Vec<_> = a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .filter(|(_, (a, b))| !(close(a.r, b.r) && close(a.g, b.g) && close(a.b, b.b) && close(a.a, b.a)))
+            .map(|(i, (a, b))| (i % SIZE, i / SIZE, *a, *b))
+            .collect();
+
+        assert_eq!(a.len(), b.len());
+        assert!(
+            differences.is_empty(),
+            "{} pixels differ, the first one at {:?}",
+            differences.len(),
+            differences.first()
+        );
+    }
+
+    #[test]
+    fn uniform_buffers_render_like_uniform_arrays() {
+        for api in [Api::Gles, Api::Gl] {
+            let context = match headless_context(api) {
+                Some(context) => context,
+                None => return,
+            };
+
+            let renderer = OpenGl::new_from_glow_context(context.clone()).unwrap();
+            assert!(renderer.main_program.uses_uniform_buffer());
+
+            let with_buffer = render(renderer);
+
+            let mut renderer = OpenGl::new_from_glow_context(context.clone()).unwrap();
+            renderer.main_program = MainProgram::new(&context, renderer.antialias, None).unwrap();
+
+            assert_same_pixels(&with_buffer, &render(renderer));
+        }
+    }
+
+    #[test]
+    fn opaque_prepass_renders_the_same() {
+        let context = match headless_context(Api::Gles) {
+            Some(context) => context,
+            None => return,
+        };
+
+        let without_prepass = render(OpenGl::new_from_glow_context(context.clone()).unwrap());
+
+        let mut renderer = OpenGl::new_from_glow_context(context).unwrap();
+        renderer.set_opaque_prepass(true);
+
+        assert_same_pixels(&without_prepass, &render(renderer));
+    }
+
+    #[test]
+    fn shared_contexts_get_their_state_back() {
+        let context = match headless_context(Api::Gles) {
+            Some(context) => context,
+            None => return,
+        };
+
+        unsafe {
+            let host_buffer = context.create_buffer().unwrap();
+            context.bind_buffer(glow::UNIFORM_BUFFER, Some(host_buffer));
+            context.buffer_data_size(glow::UNIFORM_BUFFER, 1024, glow::STATIC_DRAW);
+            context.bind_buffer_range(glow::UNIFORM_BUFFER, 0, Some(host_buffer), 256, 512);
+
+            let generic_buffer = context.create_buffer().unwrap();
+            context.bind_buffer(glow::UNIFORM_BUFFER, Some(generic_buffer));
+
+            context.enable(glow::DEPTH_TEST);
+            context.depth_func(glow::GEQUAL);
+            context.depth_mask(false);
+            context.clear_depth_f32(0.25);
+
+            let index_buffer = context.create_buffer().unwrap();
+            context.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
+
+            let lut_unit_texture = context.create_texture().unwrap();
+            context.active_texture(glow::TEXTURE0 + 2);
+            context.bind_texture(glow::TEXTURE_2D, Some(lut_unit_texture));
+            context.active_texture(glow::TEXTURE0 + 1);
+
+            let mut renderer = OpenGl::new_from_glow_context(context.clone()).unwrap();
+            renderer.set_opaque_prepass(true);
+
+            // Like on a context without vertex arrays, where the index buffer binding is global
+            if let Some(vert_arr) = renderer.vert_arr.take() {
+                context.delete_vertex_array(vert_arr);
+            }
+
+            render(renderer);
+
+            assert_eq!(
+                context.get_parameter_i32(glow::UNIFORM_BUFFER_BINDING) as u32,
+                generic_buffer
+            );
+            assert_eq!(
+                context.get_parameter_indexed_i32(glow::UNIFORM_BUFFER_BINDING, 0) as u32,
+                host_buffer
+            );
+            assert_eq!(context.get_parameter_indexed_i32(glow::UNIFORM_BUFFER_START, 0), 256);
+            assert_eq!(context.get_parameter_indexed_i32(glow::UNIFORM_BUFFER_SIZE, 0), 512);
+
+            assert!(context.is_enabled(glow::DEPTH_TEST));
+            assert_eq!(context.get_parameter_i32(glow::DEPTH_FUNC) as u32, glow::GEQUAL);
+            assert_eq!(context.get_parameter_i32(glow::DEPTH_WRITEMASK), 0);
+            assert_eq!(context.get_parameter_f32(glow::DEPTH_CLEAR_VALUE), 0.25);
+
+            assert_eq!(
+                context.get_parameter_i32(glow::ELEMENT_ARRAY_BUFFER_BINDING) as u32,
+                index_buffer
+            );
+
+            assert_eq!(
+                context.get_parameter_i32(glow::ACTIVE_TEXTURE) as u32,
+                glow::TEXTURE0 + 1
+            );
+            context.active_texture(glow::TEXTURE0 + 2);
+            assert_eq!(
+                context.get_parameter_i32(glow::TEXTURE_BINDING_2D) as u32,
+                lut_unit_texture
+            );
+        }
     }
 }
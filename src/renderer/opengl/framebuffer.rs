@@ -1,27 +1,38 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::GlTexture;
 
 use glow::HasContext;
 
-use crate::ErrorKind;
+use crate::{
+    ErrorKind,
+    PixelFormat,
+};
 
 pub struct Framebuffer {
-    context: Rc<glow::Context>,
+    context: Arc<glow::Context>,
     fbo: <glow::Context as glow::HasContext>::Framebuffer,
     depth_stencil_rbo: Option<<glow::Context as glow::HasContext>::Renderbuffer>,
+    // Set for multisampled framebuffers, which draw into `color_rbo` and are resolved into the
+    // texture attached to `resolve_fbo`
+    color_rbo: Option<<glow::Context as glow::HasContext>::Renderbuffer>,
+    resolve_fbo: Option<<glow::Context as glow::HasContext>::Framebuffer>,
+    size: (i32, i32),
 }
 
 impl Framebuffer {
-    pub fn from_external(context: &Rc<glow::Context>, fbo: <glow::Context as glow::HasContext>::Framebuffer) -> Self {
+    pub fn from_external(context: &Arc<glow::Context>, fbo: <glow::Context as glow::HasContext>::Framebuffer) -> Self {
         Framebuffer {
             context: context.clone(),
             fbo,
             depth_stencil_rbo: None,
+            color_rbo: None,
+            resolve_fbo: None,
+            size: (0, 0),
         }
     }
-    pub fn new(context: &Rc<glow::Context>, texture: &GlTexture) -> Result<Self, ErrorKind> {
-        let fbo = unsafe { context.create_framebuffer().unwrap() };
+    pub fn new(context: &Arc<glow::Context>, texture: &GlTexture) -> Result<Self, ErrorKind> {
+        let fbo = unsafe { context.create_framebuffer() }.map_err(ErrorKind::RenderTargetError)?;
         unsafe {
             context.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
         }
@@ -43,7 +54,17 @@ impl Framebuffer {
 
         attach_texture();
 
-        let depth_stencil_rbo = unsafe { context.create_renderbuffer().unwrap() };
+        let depth_stencil_rbo = match unsafe { context.create_renderbuffer() } {
+            Ok(rbo) => rbo,
+            Err(message) => {
+                unsafe {
+                    context.bind_framebuffer(glow::FRAMEBUFFER, None);
+                    context.delete_framebuffer(fbo);
+                }
+
+                return Err(ErrorKind::RenderTargetError(message));
+            }
+        };
         unsafe {
             context.bind_renderbuffer(glow::RENDERBUFFER, Some(depth_stencil_rbo));
             context.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, width as i32, height as i32);
@@ -78,25 +99,7 @@ impl Framebuffer {
                 let status = context.check_framebuffer_status(glow::FRAMEBUFFER);
 
                 if status != glow::FRAMEBUFFER_COMPLETE {
-                    let reason = match status {
-                        glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => {
-                            format!("({}) Framebuffer incomplete attachment", status)
-                        }
-                        //glow::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => format!("({}) Framebuffer incomplete draw buffer", status),
-                        //glow::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => format!("({}) Framebuffer incomplete layer targets", status),
-                        //FIXME: will be in next glow release: glow::FRAMEBUFFER_INCOMPLETE_DIMENSIONS => format!("({}) Framebuffer incomplete dimensions", status),
-                        glow::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
-                            format!("({}) Framebuffer incomplete missing attachment", status)
-                        }
-                        glow::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => {
-                            format!("({}) Framebuffer incomplete multisample", status)
-                        }
-                        //glow::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => format!("({}) Framebuffer incomplete read buffer", status),
-                        glow::FRAMEBUFFER_UNSUPPORTED => format!("({}) Framebuffer unsupported", status),
-                        _ => format!("({}) Framebuffer not complete!", status),
-                    };
-
-                    return Err(ErrorKind::RenderTargetError(reason));
+                    return Err(incomplete(status));
                 }
             }
 
@@ -107,45 +110,182 @@ impl Framebuffer {
             context: context.clone(),
             fbo,
             depth_stencil_rbo: Some(depth_stencil_rbo),
+            color_rbo: None,
+            resolve_fbo: None,
+            size: (width as i32, height as i32),
         })
     }
 
+    /// Creates a framebuffer that draws into renderbuffers with `samples` samples per pixel,
+    /// [`Self::resolve()`] copies the drawing into the texture.
+    ///
+    /// Needs OpenGL 3, OpenGL ES 3 or WebGL 2. Only RGBA8 textures are supported, as the drawing is
+    /// resolved from an RGBA8 renderbuffer.
+    pub fn new_multisampled(
+        context: &Arc<glow::Context>,
+        texture: &GlTexture,
+        samples: i32,
+    ) -> Result<Self, ErrorKind> {
+        if texture.info().format() != PixelFormat::Rgba8 {
+            return Err(ErrorKind::RenderTargetError(format!(
+                "multisampling needs an RGBA8 image, not {:?}",
+                texture.info().format()
+            )));
+        }
+
+        let width = texture.info().width() as i32;
+        let height = texture.info().height() as i32;
+
+        let fbo = unsafe { context.create_framebuffer() }.map_err(ErrorKind::RenderTargetError)?;
+
+        // Whatever was created so far is deleted when this is dropped on an error
+        let mut framebuffer = Framebuffer {
+            context: context.clone(),
+            fbo,
+            depth_stencil_rbo: None,
+            color_rbo: None,
+            resolve_fbo: None,
+            size: (width, height),
+        };
+
+        unsafe {
+            let color_rbo = context.create_renderbuffer().map_err(ErrorKind::RenderTargetError)?;
+            framebuffer.color_rbo = Some(color_rbo);
+
+            let depth_stencil_rbo = context.create_renderbuffer().map_err(ErrorKind::RenderTargetError)?;
+            framebuffer.depth_stencil_rbo = Some(depth_stencil_rbo);
+
+            let resolve_fbo = context.create_framebuffer().map_err(ErrorKind::RenderTargetError)?;
+            framebuffer.resolve_fbo = Some(resolve_fbo);
+
+            context.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+
+            context.bind_renderbuffer(glow::RENDERBUFFER, Some(color_rbo));
+            context.renderbuffer_storage_multisample(glow::RENDERBUFFER, samples, glow::RGBA8, width, height);
+            context.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(color_rbo),
+            );
+
+            context.bind_renderbuffer(glow::RENDERBUFFER, Some(depth_stencil_rbo));
+            context.renderbuffer_storage_multisample(
+                glow::RENDERBUFFER,
+                samples,
+                glow::DEPTH24_STENCIL8,
+                width,
+                height,
+            );
+            context.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_STENCIL_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(depth_stencil_rbo),
+            );
+
+            context.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+            let status = context.check_framebuffer_status(glow::FRAMEBUFFER);
+
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                context.bind_framebuffer(glow::FRAMEBUFFER, None);
+                return Err(incomplete(status));
+            }
+
+            context.bind_framebuffer(glow::FRAMEBUFFER, Some(resolve_fbo));
+            context.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture.id()),
+                0,
+            );
+
+            let status = context.check_framebuffer_status(glow::FRAMEBUFFER);
+
+            context.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                return Err(incomplete(status));
+            }
+        }
+
+        Ok(framebuffer)
+    }
+
     pub fn bind(&self) {
         unsafe {
             self.context.bind_framebuffer(glow::FRAMEBUFFER, Some(self.fbo));
         }
     }
 
-    pub fn unbind(context: &Rc<glow::Context>) {
+    pub fn unbind(context: &Arc<glow::Context>) {
         unsafe {
             context.bind_framebuffer(glow::FRAMEBUFFER, None);
         }
     }
 
-    // pub fn blit_to_texture(&self, texture: &GlTexture) {
-    //     let dest_fbo = Self::new(texture);
-
-    //     unsafe {
-    //         glow::BindFramebuffer(glow::READ_FRAMEBUFFER, self.fbo);
-    //         glow::BindFramebuffer(glow::DRAW_FRAMEBUFFER, dest_fbo.fbo);
-
-    //         glow::BlitFramebuffer(
-    //             0,
-    //             0,
-    //             self.width as i32,
-    //             self.height as i32,
-    //             0,
-    //             0,
-    //             dest_fbo.width as i32,
-    //             dest_fbo.height as i32,
-    //             glow::COLOR_BUFFER_BIT,
-    //             glow::NEAREST
-    //         );
-
-    //         glow::BindFramebuffer(glow::READ_FRAMEBUFFER, 0);
-    //         glow::BindFramebuffer(glow::DRAW_FRAMEBUFFER, 0);
-    //     }
-    // }
+    /// Copies the drawing of a multisampled framebuffer into its texture and binds the framebuffer
+    /// again, does nothing for other framebuffers.
+    ///
+    /// The scissor test has to be disabled, it applies to the copy.
+    pub fn resolve(&self) {
+        if let Some(resolve_fbo) = self.resolve_fbo {
+            let (width, height) = self.size;
+
+            unsafe {
+                self.context.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.fbo));
+                self.context.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(resolve_fbo));
+                self.context.blit_framebuffer(
+                    0,
+                    0,
+                    width,
+                    height,
+                    0,
+                    0,
+                    width,
+                    height,
+                    glow::COLOR_BUFFER_BIT,
+                    glow::NEAREST,
+                );
+
+                self.context.bind_framebuffer(glow::FRAMEBUFFER, Some(self.fbo));
+            }
+        }
+    }
+
+    /// Binds the framebuffer that holds the pixels of the texture for reading, which is the resolved
+    /// one for multisampled framebuffers, as those can't be read from directly.
+    pub fn bind_read(&self) {
+        unsafe {
+            self.context
+                .bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.resolve_fbo.unwrap_or(self.fbo)));
+        }
+    }
+}
+
+// The error of a framebuffer that isn't complete
+fn incomplete(status: u32) -> ErrorKind {
+    let reason = match status {
+        glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => {
+            format!("({}) Framebuffer incomplete attachment", status)
+        }
+        //glow::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => format!("({}) Framebuffer incomplete draw buffer", status),
+        //glow::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => format!("({}) Framebuffer incomplete layer targets", status),
+        //FIXME: will be in next glow release: glow::FRAMEBUFFER_INCOMPLETE_DIMENSIONS => format!("({}) Framebuffer incomplete dimensions", status),
+        glow::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
+            format!("({}) Framebuffer incomplete missing attachment", status)
+        }
+        glow::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => {
+            format!("({}) Framebuffer incomplete multisample", status)
+        }
+        //glow::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => format!("({}) Framebuffer incomplete read buffer", status),
+        glow::FRAMEBUFFER_UNSUPPORTED => format!("({}) Framebuffer unsupported", status),
+        _ => format!("({}) Framebuffer not complete!", status),
+    };
+
+    ErrorKind::RenderTargetError(reason)
 }
 
 impl Drop for Framebuffer {
@@ -155,6 +295,12 @@ impl Drop for Framebuffer {
             if let Some(depth_stencil_rbo) = self.depth_stencil_rbo {
                 self.context.delete_renderbuffer(depth_stencil_rbo);
             }
+            if let Some(color_rbo) = self.color_rbo {
+                self.context.delete_renderbuffer(color_rbo);
+            }
+            if let Some(resolve_fbo) = self.resolve_fbo {
+                self.context.delete_framebuffer(resolve_fbo);
+            }
         }
     }
 }
@@ -0,0 +1,253 @@
+use glow::HasContext;
+
+use super::{
+    FRAG_BLOCK_BINDING,
+    TEXTURE_UNITS,
+};
+
+/// Snapshot of the GL state that femtovg modifies while rendering.
+///
+/// Used by renderers created with [`OpenGl::new_from_glow_context()`](super::OpenGl::new_from_glow_context)
+/// to hand the context back to the host application exactly as it was found.
+pub(crate) struct GlState {
+    program: i32,
+    vertex_array: i32,
+    array_buffer: i32,
+    element_array_buffer: i32,
+    framebuffer: i32,
+    active_texture: i32,
+    textures: [i32; TEXTURE_UNITS as usize],
+    viewport: [i32; 4],
+    scissor_box: [i32; 4],
+    blend: bool,
+    cull_face: bool,
+    depth_test: bool,
+    scissor_test: bool,
+    stencil_test: bool,
+    depth_func: i32,
+    depth_mask: bool,
+    depth_clear_value: f32,
+    blend_src_rgb: i32,
+    blend_dst_rgb: i32,
+    blend_src_alpha: i32,
+    blend_dst_alpha: i32,
+    blend_equation_rgb: i32,
+    blend_equation_alpha: i32,
+    cull_face_mode: i32,
+    front_face: i32,
+    color_mask: [bool; 4],
+    clear_color: [f32; 4],
+    stencil_mask: i32,
+    stencil_func: i32,
+    stencil_ref: i32,
+    stencil_value_mask: i32,
+    stencil_fail: i32,
+    stencil_pass_depth_fail: i32,
+    stencil_pass_depth_pass: i32,
+    unpack_alignment: i32,
+    // Only saved on contexts with uniform buffers, where the fragment parameters are bound from one
+    uniform_buffers: Option<UniformBufferState>,
+}
+
+struct UniformBufferState {
+    buffer: i32,
+    block_buffer: i32,
+    block_start: i32,
+    block_size: i32,
+}
+
+impl GlState {
+    pub fn save(context: &glow::Context, uniform_buffers: bool) -> Self {
+        unsafe {
+            let active_texture = context.get_parameter_i32(glow::ACTIVE_TEXTURE);
+
+            let mut textures = [0; TEXTURE_UNITS as usize];
+
+            for (unit, texture) in textures.iter_mut().enumerate() {
+                context.active_texture(glow::TEXTURE0 + unit as u32);
+                *texture = context.get_parameter_i32(glow::TEXTURE_BINDING_2D);
+            }
+
+            context.active_texture(active_texture as u32);
+
+            let mut viewport = [0; 4];
+            context.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+
+            let mut scissor_box = [0; 4];
+            context.get_parameter_i32_slice(glow::SCISSOR_BOX, &mut scissor_box);
+
+            let mut color_mask = [0; 4];
+            context.get_parameter_i32_slice(glow::COLOR_WRITEMASK, &mut color_mask);
+
+            let mut clear_color = [0.0; 4];
+            context.get_parameter_f32_slice(glow::COLOR_CLEAR_VALUE, &mut clear_color);
+
+            let uniform_buffers = if uniform_buffers {
+                Some(UniformBufferState {
+                    buffer: context.get_parameter_i32(glow::UNIFORM_BUFFER_BINDING),
+                    block_buffer: context.get_parameter_indexed_i32(glow::UNIFORM_BUFFER_BINDING, FRAG_BLOCK_BINDING),
+                    block_start: context.get_parameter_indexed_i32(glow::UNIFORM_BUFFER_START, FRAG_BLOCK_BINDING),
+                    block_size: context.get_parameter_indexed_i32(glow::UNIFORM_BUFFER_SIZE, FRAG_BLOCK_BINDING),
+                })
+            } else {
+                None
+            };
+
+            Self {
+                program: context.get_parameter_i32(glow::CURRENT_PROGRAM),
+                vertex_array: context.get_parameter_i32(glow::VERTEX_ARRAY_BINDING),
+                array_buffer: context.get_parameter_i32(glow::ARRAY_BUFFER_BINDING),
+                element_array_buffer: context.get_parameter_i32(glow::ELEMENT_ARRAY_BUFFER_BINDING),
+                framebuffer: context.get_parameter_i32(glow::FRAMEBUFFER_BINDING),
+                active_texture,
+                textures,
+                viewport,
+                scissor_box,
+                blend: context.is_enabled(glow::BLEND),
+                cull_face: context.is_enabled(glow::CULL_FACE),
+                depth_test: context.is_enabled(glow::DEPTH_TEST),
+                scissor_test: context.is_enabled(glow::SCISSOR_TEST),
+                stencil_test: context.is_enabled(glow::STENCIL_TEST),
+                depth_func: context.get_parameter_i32(glow::DEPTH_FUNC),
+                depth_mask: context.get_parameter_i32(glow::DEPTH_WRITEMASK) != 0,
+                depth_clear_value: context.get_parameter_f32(glow::DEPTH_CLEAR_VALUE),
+                blend_src_rgb: context.get_parameter_i32(glow::BLEND_SRC_RGB),
+                blend_dst_rgb: context.get_parameter_i32(glow::BLEND_DST_RGB),
+                blend_src_alpha: context.get_parameter_i32(glow::BLEND_SRC_ALPHA),
+                blend_dst_alpha: context.get_parameter_i32(glow::BLEND_DST_ALPHA),
+                blend_equation_rgb: context.get_parameter_i32(glow::BLEND_EQUATION_RGB),
+                blend_equation_alpha: context.get_parameter_i32(glow::BLEND_EQUATION_ALPHA),
+                cull_face_mode: context.get_parameter_i32(glow::CULL_FACE_MODE),
+                front_face: context.get_parameter_i32(glow::FRONT_FACE),
+                color_mask: [
+                    color_mask[0] != 0,
+                    color_mask[1] != 0,
+                    color_mask[2] != 0,
+                    color_mask[3] != 0,
+                ],
+                clear_color,
+                stencil_mask: context.get_parameter_i32(glow::STENCIL_WRITEMASK),
+                stencil_func: context.get_parameter_i32(glow::STENCIL_FUNC),
+                stencil_ref: context.get_parameter_i32(glow::STENCIL_REF),
+                stencil_value_mask: context.get_parameter_i32(glow::STENCIL_VALUE_MASK),
+                stencil_fail: context.get_parameter_i32(glow::STENCIL_FAIL),
+                stencil_pass_depth_fail: context.get_parameter_i32(glow::STENCIL_PASS_DEPTH_FAIL),
+                stencil_pass_depth_pass: context.get_parameter_i32(glow::STENCIL_PASS_DEPTH_PASS),
+                unpack_alignment: context.get_parameter_i32(glow::UNPACK_ALIGNMENT),
+                uniform_buffers,
+            }
+        }
+    }
+
+    pub fn restore(&self, context: &glow::Context) {
+        unsafe {
+            context.use_program(Self::object(self.program));
+            context.bind_vertex_array(Self::object(self.vertex_array));
+            // Part of the vertex array state, so it has to follow the vertex array
+            context.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Self::object(self.element_array_buffer));
+            context.bind_buffer(glow::ARRAY_BUFFER, Self::object(self.array_buffer));
+            context.bind_framebuffer(glow::FRAMEBUFFER, Self::object(self.framebuffer));
+
+            for (unit, texture) in self.textures.iter().enumerate() {
+                context.active_texture(glow::TEXTURE0 + unit as u32);
+                context.bind_texture(glow::TEXTURE_2D, Self::object(*texture));
+            }
+
+            context.active_texture(self.active_texture as u32);
+
+            context.viewport(self.viewport[0], self.viewport[1], self.viewport[2], self.viewport[3]);
+            context.scissor(
+                self.scissor_box[0],
+                self.scissor_box[1],
+                self.scissor_box[2],
+                self.scissor_box[3],
+            );
+
+            Self::set_enabled(context, glow::BLEND, self.blend);
+            Self::set_enabled(context, glow::CULL_FACE, self.cull_face);
+            Self::set_enabled(context, glow::DEPTH_TEST, self.depth_test);
+            Self::set_enabled(context, glow::SCISSOR_TEST, self.scissor_test);
+            Self::set_enabled(context, glow::STENCIL_TEST, self.stencil_test);
+
+            context.blend_func_separate(
+                self.blend_src_rgb as u32,
+                self.blend_dst_rgb as u32,
+                self.blend_src_alpha as u32,
+                self.blend_dst_alpha as u32,
+            );
+            context.blend_equation_separate(self.blend_equation_rgb as u32, self.blend_equation_alpha as u32);
+
+            context.depth_func(self.depth_func as u32);
+            context.depth_mask(self.depth_mask);
+            context.clear_depth_f32(self.depth_clear_value);
+
+            context.cull_face(self.cull_face_mode as u32);
+            context.front_face(self.front_face as u32);
+
+            context.color_mask(
+                self.color_mask[0],
+                self.color_mask[1],
+                self.color_mask[2],
+                self.color_mask[3],
+            );
+            context.clear_color(
+                self.clear_color[0],
+                self.clear_color[1],
+                self.clear_color[2],
+                self.clear_color[3],
+            );
+
+            context.stencil_mask(self.stencil_mask as u32);
+            context.stencil_func(
+                self.stencil_func as u32,
+                self.stencil_ref,
+                self.stencil_value_mask as u32,
+            );
+            context.stencil_op(
+                self.stencil_fail as u32,
+                self.stencil_pass_depth_fail as u32,
+                self.stencil_pass_depth_pass as u32,
+            );
+
+            context.pixel_store_i32(glow::UNPACK_ALIGNMENT, self.unpack_alignment);
+
+            if let Some(state) = &self.uniform_buffers {
+                let block_buffer = Self::object(state.block_buffer);
+
+                // A size of 0 means the whole buffer is bound, or none at all
+                if state.block_size > 0 {
+                    context.bind_buffer_range(
+                        glow::UNIFORM_BUFFER,
+                        FRAG_BLOCK_BINDING,
+                        block_buffer,
+                        state.block_start,
+                        state.block_size,
+                    );
+                } else {
+                    context.bind_buffer_base(glow::UNIFORM_BUFFER, FRAG_BLOCK_BINDING, block_buffer);
+                }
+
+                // Binding the indexed binding point binds the generic one too
+                context.bind_buffer(glow::UNIFORM_BUFFER, Self::object(state.buffer));
+            }
+        }
+    }
+
+    fn object(id: i32) -> Option<u32> {
+        if id == 0 {
+            None
+        } else {
+            Some(id as u32)
+        }
+    }
+
+    fn set_enabled(context: &glow::Context, capability: u32, enabled: bool) {
+        unsafe {
+            if enabled {
+                context.enable(capability);
+            } else {
+                context.disable(capability);
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
     ErrorKind,
@@ -10,24 +10,92 @@ use crate::{
 
 use glow::HasContext;
 
+// OpenGL ES 2.0 and WebGL 1 can't mipmap or repeat textures whose sizes aren't powers of two, such
+// textures would be incomplete and sample as black. They are created without those flags instead.
+// They have no multisampled renderbuffers either.
+fn supported_flags(info: &ImageInfo, opengles_2_0: bool) -> ImageFlags {
+    let power_of_two = info.width().is_power_of_two() && info.height().is_power_of_two();
+
+    if !opengles_2_0 {
+        return info.flags();
+    }
+
+    let mut unsupported = ImageFlags::MULTISAMPLE;
+
+    if !power_of_two {
+        unsupported |= ImageFlags::GENERATE_MIPMAPS | ImageFlags::REPEAT_X | ImageFlags::REPEAT_Y;
+    }
+
+    info.flags() - unsupported
+}
+
+// The minification and magnification filters for the flags of an image
+fn filters(flags: ImageFlags) -> (u32, u32) {
+    let min_filter = match (
+        flags.contains(ImageFlags::GENERATE_MIPMAPS),
+        flags.contains(ImageFlags::NEAREST_MIN),
+    ) {
+        (true, true) => glow::NEAREST_MIPMAP_NEAREST,
+        (true, false) => glow::LINEAR_MIPMAP_LINEAR,
+        (false, true) => glow::NEAREST,
+        (false, false) => glow::LINEAR,
+    };
+
+    let mag_filter = if flags.contains(ImageFlags::NEAREST_MAG) {
+        glow::NEAREST
+    } else {
+        glow::LINEAR
+    };
+
+    (min_filter, mag_filter)
+}
+
+// Sets the filters and wrap modes of the bound texture
+fn set_sampler(context: &glow::Context, flags: ImageFlags) {
+    let (min_filter, mag_filter) = filters(flags);
+
+    let wrap = |repeat: bool| if repeat { glow::REPEAT } else { glow::CLAMP_TO_EDGE };
+
+    unsafe {
+        context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, min_filter as i32);
+        context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, mag_filter as i32);
+        context.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_WRAP_S,
+            wrap(flags.contains(ImageFlags::REPEAT_X)) as i32,
+        );
+        context.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_WRAP_T,
+            wrap(flags.contains(ImageFlags::REPEAT_Y)) as i32,
+        );
+    }
+}
+
 pub struct GlTexture {
-    context: Rc<glow::Context>,
+    context: Arc<glow::Context>,
     id: <glow::Context as glow::HasContext>::Texture,
     info: ImageInfo,
 }
 
 impl GlTexture {
-    pub fn new(context: &Rc<glow::Context>, info: ImageInfo, opengles_2_0: bool) -> Result<Self, ErrorKind> {
+    pub fn new(context: &Arc<glow::Context>, info: ImageInfo, opengles_2_0: bool) -> Result<Self, ErrorKind> {
         //let size = src.dimensions();
 
-        let mut texture = Self {
+        let info = ImageInfo::new(
+            supported_flags(&info, opengles_2_0),
+            info.width(),
+            info.height(),
+            info.format(),
+        );
+
+        let texture = Self {
             context: context.clone(),
-            id: Default::default(),
+            id: unsafe { context.create_texture() }.map_err(ErrorKind::GeneralError)?,
             info: info,
         };
 
         unsafe {
-            texture.id = context.create_texture().unwrap();
             context.bind_texture(glow::TEXTURE_2D, Some(texture.id));
             context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
             if !opengles_2_0 {
@@ -86,65 +154,7 @@ impl GlTexture {
 
         let flags = texture.info.flags();
 
-        if flags.contains(ImageFlags::GENERATE_MIPMAPS) {
-            if flags.contains(ImageFlags::NEAREST) {
-                unsafe {
-                    context.tex_parameter_i32(
-                        glow::TEXTURE_2D,
-                        glow::TEXTURE_MIN_FILTER,
-                        glow::NEAREST_MIPMAP_NEAREST as i32,
-                    );
-                }
-            } else {
-                unsafe {
-                    context.tex_parameter_i32(
-                        glow::TEXTURE_2D,
-                        glow::TEXTURE_MIN_FILTER,
-                        glow::LINEAR_MIPMAP_LINEAR as i32,
-                    );
-                }
-            }
-        } else {
-            if flags.contains(ImageFlags::NEAREST) {
-                unsafe {
-                    context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
-                }
-            } else {
-                unsafe {
-                    context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
-                }
-            }
-        }
-
-        if flags.contains(ImageFlags::NEAREST) {
-            unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
-            }
-        } else {
-            unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
-            }
-        }
-
-        if flags.contains(ImageFlags::REPEAT_X) {
-            unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
-            }
-        } else {
-            unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
-            }
-        }
-
-        if flags.contains(ImageFlags::REPEAT_Y) {
-            unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
-            }
-        } else {
-            unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
-            }
-        }
+        set_sampler(context, flags);
 
         unsafe {
             context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
@@ -272,6 +282,21 @@ impl GlTexture {
         Ok(())
     }
 
+    pub fn set_flags(&mut self, flags: ImageFlags, opengles_2_0: bool) {
+        self.info.set_flags(flags);
+        self.info.set_flags(supported_flags(&self.info, opengles_2_0));
+
+        unsafe {
+            self.context.bind_texture(glow::TEXTURE_2D, Some(self.id));
+        }
+
+        set_sampler(&self.context, self.info.flags());
+
+        unsafe {
+            self.context.bind_texture(glow::TEXTURE_2D, None);
+        }
+    }
+
     pub fn delete(self) {
         unsafe {
             self.context.delete_texture(self.id);
@@ -282,3 +307,37 @@ impl GlTexture {
         self.info
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npot_textures_drop_unsupported_flags_on_gles2() {
+        let flags = ImageFlags::GENERATE_MIPMAPS | ImageFlags::REPEAT_X | ImageFlags::NEAREST;
+
+        let npot = ImageInfo::new(flags, 100, 64, PixelFormat::Rgba8);
+        assert_eq!(supported_flags(&npot, true), ImageFlags::NEAREST);
+        assert_eq!(supported_flags(&npot, false), flags);
+
+        let pot = ImageInfo::new(flags, 128, 64, PixelFormat::Rgba8);
+        assert_eq!(supported_flags(&pot, true), flags);
+
+        let target = ImageInfo::new(ImageFlags::MULTISAMPLE, 128, 64, PixelFormat::Rgba8);
+        assert_eq!(supported_flags(&target, true), ImageFlags::empty());
+        assert_eq!(supported_flags(&target, false), ImageFlags::MULTISAMPLE);
+    }
+
+    #[test]
+    fn minification_and_magnification_filters_are_independent() {
+        let pixel_art = ImageFlags::GENERATE_MIPMAPS | ImageFlags::NEAREST_MAG;
+        assert_eq!(filters(pixel_art), (glow::LINEAR_MIPMAP_LINEAR, glow::NEAREST));
+
+        assert_eq!(filters(ImageFlags::NEAREST_MIN), (glow::NEAREST, glow::LINEAR));
+        assert_eq!(filters(ImageFlags::NEAREST), (glow::NEAREST, glow::NEAREST));
+        assert_eq!(
+            filters(ImageFlags::GENERATE_MIPMAPS | ImageFlags::NEAREST),
+            (glow::NEAREST_MIPMAP_NEAREST, glow::NEAREST)
+        );
+    }
+}
@@ -3,7 +3,13 @@ precision highp float;
 
 #define UNIFORMARRAY_SIZE 14
 
+#ifdef USE_UNIFORM_BUFFER
+layout(std140) uniform FragBlock {
+    vec4 frag[UNIFORMARRAY_SIZE];
+};
+#else
 uniform vec4 frag[UNIFORMARRAY_SIZE];
+#endif
 
 #define scissorMat mat3(frag[0].xyz, frag[1].xyz, frag[2].xyz)
 #define paintMat mat3(frag[3].xyz, frag[4].xyz, frag[5].xyz)
@@ -22,13 +28,25 @@ uniform vec4 frag[UNIFORMARRAY_SIZE];
 #define imageBlurFilterDirection frag[11].yz
 #define imageBlurFilterSigma frag[11].w
 #define imageBlurFilterCoeff frag[12].xyz
+#define scissorRadius frag[12].w
 
 uniform sampler2D tex;
 uniform sampler2D masktex;
+uniform sampler2D luttex;
 uniform vec2 viewSize;
 
+#ifdef USE_UNIFORM_BUFFER
+in vec2 ftcoord;
+in vec2 fpos;
+in vec4 fcolor;
+out vec4 outColor;
+#define texture2D texture
+#else
 varying vec2 ftcoord;
 varying vec2 fpos;
+varying vec4 fcolor;
+#define outColor gl_FragColor
+#endif
 
 float sdroundrect(vec2 pt, vec2 ext, float rad) {
     vec2 ext2 = ext - vec2(rad,rad);
@@ -38,7 +56,14 @@ float sdroundrect(vec2 pt, vec2 ext, float rad) {
 
 // Scissoring
 float scissorMask(vec2 p) {
-    vec2 sc = (abs((scissorMat * vec3(p,1.0)).xy) - scissorExt);
+    vec2 sp = (scissorMat * vec3(p,1.0)).xy;
+
+    if (scissorRadius > 0.0) {
+        float d = sdroundrect(sp, scissorExt, scissorRadius);
+        return clamp(0.5 - d * min(scissorScale.x, scissorScale.y), 0.0, 1.0);
+    }
+
+    vec2 sc = (abs(sp) - scissorExt);
     sc = vec2(0.5,0.5) - sc * scissorScale;
     return clamp(sc.x,0.0,1.0) * clamp(sc.y,0.0,1.0);
 }
@@ -100,6 +125,21 @@ void main(void) {
         // Apply color tint and alpha.
         color *= innerCol;
 
+        result = color;
+    } else if (shaderType == 6) {
+        // Color map; look the value in the red channel of the image up in the colors, radius holds their width.
+
+        vec2 pt = (paintMat * vec3(fpos, 1.0)).xy / extent;
+
+        float value = texture2D(tex, pt).x;
+        vec4 color = texture2D(luttex, vec2((value * (radius - 1.0) + 0.5) / radius, 0.5));
+
+        if (texType == 1) color = vec4(color.xyz * color.w, color.w);
+        if (texType == 2) color = vec4(color.x);
+
+        // Apply global alpha.
+        color *= innerCol;
+
         result = color;
     } else if (shaderType == 2) {
         // Stencil fill
@@ -135,6 +175,19 @@ void main(void) {
         if (texType == 1) color = vec4(color.xyz * color.w, color.w);
         if (texType == 2) color = vec4(color.x);
 
+        result = color;
+    } else if (shaderType == 5) {
+        // Textured mesh; sample the texture at the vertex texture coordinates and multiply with the vertex color.
+
+        vec4 color = vec4(1.0);
+
+        if (texType != 3) color = texture2D(tex, ftcoord);
+        if (texType == 1) color = vec4(color.xyz * color.w, color.w);
+        if (texType == 2) color = vec4(color.x);
+
+        // Apply global alpha.
+        color *= fcolor * innerCol;
+
         result = color;
     }
 
@@ -148,10 +201,13 @@ void main(void) {
 
         mask *= scissor;
         result *= mask;
+    } else if (shaderType == 5) {
+        // Mesh texture coordinates are not stroke coordinates
+        result *= scissor;
     } else if (shaderType != 2 && shaderType != 4) { // Not stencil fill
         // Combine alpha
         result *= strokeAlpha * scissor;
     }
 
-    gl_FragColor = result;
+    outColor = result;
 }
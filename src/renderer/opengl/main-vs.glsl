@@ -1,15 +1,30 @@
 
 uniform vec2 viewSize;
+// Z of the draw, only tested while opaque fills are drawn ahead
+uniform float depth;
 
+#ifdef USE_UNIFORM_BUFFER
+in vec2 vertex;
+in vec2 tcoord;
+in vec4 color;
+
+out vec2 ftcoord;
+out vec2 fpos;
+out vec4 fcolor;
+#else
 attribute vec2 vertex;
 attribute vec2 tcoord;
+attribute vec4 color;
 
 varying vec2 ftcoord;
 varying vec2 fpos;
+varying vec4 fcolor;
+#endif
 
 void main(void) {
     ftcoord = tcoord;
     fpos = vertex;
+    fcolor = color;
 
-    gl_Position = vec4(2.0 * vertex.x / viewSize.x - 1.0, 1.0 - 2.0 * vertex.y / viewSize.y, 0, 1);
+    gl_Position = vec4(2.0 * vertex.x / viewSize.x - 1.0, 1.0 - 2.0 * vertex.y / viewSize.y, depth, 1);
 }
@@ -0,0 +1,189 @@
+use crate::{
+    renderer::{
+        Command,
+        CommandType,
+        ShaderType,
+    },
+    CompositeOperationState,
+};
+
+/// A step of drawing the commands of a flush.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Step {
+    /// Clears the depth buffer, enables the depth test and depth writes and disables blending.
+    BeginDepth,
+    /// Draws the inside of an opaque fill without blending and writes its depth.
+    Opaque { index: usize, depth: f32 },
+    /// Enables blending again and stops writing depth, for the draws behind the opaque fills.
+    EndOpaque,
+    /// Draws the command as usual. `fringe_only` is set for opaque fills whose inside was already
+    /// drawn by a [`Step::Opaque`] step.
+    Draw {
+        index: usize,
+        depth: f32,
+        fringe_only: bool,
+    },
+    /// Disables the depth test again.
+    EndDepth,
+}
+
+// Returns true for a fill that covers the inside of its path with fully opaque pixels, so that
+// everything drawn before it there is hidden
+pub(crate) fn is_opaque(cmd: &Command) -> bool {
+    let params = match &cmd.cmd_type {
+        CommandType::ConvexFill { params } => params,
+        _ => return false,
+    };
+
+    // A scissor fades the pixels along its edges
+    let scissored = params.scissor_mat.iter().any(|value| *value != 0.0);
+
+    params.shader_type == ShaderType::FillGradient.to_f32()
+        && params.inner_col[3] >= 1.0
+        && params.outer_col[3] >= 1.0
+        && !scissored
+        && cmd.image.is_none()
+        && cmd.alpha_mask.is_none()
+        && cmd.vertex_colors.is_none()
+        && cmd.composite_operation == CompositeOperationState::default()
+}
+
+// Commands that the depth buffer can't be kept across
+fn is_barrier(cmd: &Command) -> bool {
+    matches!(
+        cmd.cmd_type,
+        CommandType::ClearRect { .. } | CommandType::SetRenderTarget(_) | CommandType::RenderFilteredImage { .. }
+    )
+}
+
+/// Returns the steps that draw `commands` in order. With `prepass` set, the insides of opaque
+/// fills are drawn first, front to back with depth writes, and the rest of the commands are drawn
+/// back to front behind them, so that pixels hidden by an opaque fill are only drawn once.
+pub(crate) fn plan(commands: &[Command], prepass: bool) -> Vec<Step> {
+    let mut steps = Vec::with_capacity(commands.len());
+    let mut start = 0;
+
+    while start < commands.len() {
+        if is_barrier(&commands[start]) {
+            steps.push(Step::Draw {
+                index: start,
+                depth: 0.0,
+                fringe_only: false,
+            });
+            start += 1;
+            continue;
+        }
+
+        let end = commands[start..]
+            .iter()
+            .position(is_barrier)
+            .map_or(commands.len(), |offset| start + offset);
+
+        plan_segment(commands, start..end, prepass, &mut steps);
+
+        start = end;
+    }
+
+    steps
+}
+
+fn plan_segment(commands: &[Command], range: std::ops::Range<usize>, prepass: bool, steps: &mut Vec<Step>) {
+    // An opaque fill only hides what was drawn before it
+    let occludes = prepass && range.clone().skip(1).any(|index| is_opaque(&commands[index]));
+
+    if !occludes {
+        steps.extend(range.map(|index| Step::Draw {
+            index,
+            depth: 0.0,
+            fringe_only: false,
+        }));
+
+        return;
+    }
+
+    // Later commands are nearer, the depth buffer is cleared to the far plane at 1
+    let count = range.len() as f32;
+    let depth = |index: usize| 1.0 - 2.0 * (index - range.start + 1) as f32 / (count + 1.0);
+
+    steps.push(Step::BeginDepth);
+
+    for index in range.clone().rev().filter(|index| is_opaque(&commands[*index])) {
+        steps.push(Step::Opaque {
+            index,
+            depth: depth(index),
+        });
+    }
+
+    steps.push(Step::EndOpaque);
+
+    for index in range.clone() {
+        steps.push(Step::Draw {
+            index,
+            depth: depth(index),
+            fringe_only: is_opaque(&commands[index]),
+        });
+    }
+
+    steps.push(Step::EndDepth);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Params;
+
+    fn fill(alpha: f32) -> Command {
+        let params = Params {
+            inner_col: [1.0, 0.0, 0.0, alpha],
+            outer_col: [1.0, 0.0, 0.0, alpha],
+            ..Default::default()
+        };
+
+        Command::new(CommandType::ConvexFill { params })
+    }
+
+    #[test]
+    fn opaque_fills_are_drawn_first_front_to_back() {
+        let commands = [fill(0.5), fill(1.0), fill(0.5), fill(1.0)];
+
+        let steps = plan(&commands, true);
+
+        assert_eq!(steps[0], Step::BeginDepth);
+        assert!(matches!(steps[1], Step::Opaque { index: 3, .. }));
+        assert!(matches!(steps[2], Step::Opaque { index: 1, .. }));
+        assert_eq!(steps[3], Step::EndOpaque);
+        assert!(matches!(
+            steps[5],
+            Step::Draw {
+                index: 1,
+                fringe_only: true,
+                ..
+            }
+        ));
+        assert_eq!(steps.last(), Some(&Step::EndDepth));
+
+        let depths: Vec<f32> = steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Draw { depth, .. } => Some(*depth),
+                _ => None,
+            })
+            .collect();
+
+        assert!(depths.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(depths.iter().all(|depth| depth.abs() < 1.0));
+    }
+
+    #[test]
+    fn leading_opaque_fill_is_drawn_in_order() {
+        let commands = [fill(1.0), fill(0.5), fill(0.5)];
+
+        // The only opaque fill is drawn first and hides nothing
+        let steps = plan(&commands, true);
+
+        assert_eq!(steps.len(), 3);
+        assert!(steps
+            .iter()
+            .all(|step| matches!(step, Step::Draw { depth, .. } if *depth == 0.0)));
+    }
+}
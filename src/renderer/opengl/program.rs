@@ -1,19 +1,62 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::ErrorKind;
 
 use glow::HasContext;
 
+use super::program_cache::ProgramCache;
+
 const GLSL_VERSION: &str = "#version 100";
 
+// Binding point of the uniform block the fragment parameters are read from
+pub(crate) const FRAG_BLOCK_BINDING: u32 = 0;
+
+// Texture units the main program samples from, starting at 0: the image, the alpha mask and the
+// color map lookup table
+pub(crate) const TEXTURE_UNITS: u32 = 3;
+
+/// Returns the GLSL version to compile the uniform buffer variant of the shaders with, if the
+/// context supports uniform buffers: OpenGL 3.2, OpenGL ES 3.0, WebGL 2 and later.
+pub(crate) fn uniform_buffer_glsl_version(context: &glow::Context) -> Option<&'static str> {
+    let version = unsafe { context.get_parameter_string(glow::VERSION) };
+
+    let parse = |version: &str| -> Option<(u32, u32)> {
+        let mut numbers = version
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|part| !part.is_empty());
+        let major = numbers.next()?.parse().ok()?;
+        let minor = numbers.next().and_then(|minor| minor.parse().ok()).unwrap_or(0);
+        Some((major, minor))
+    };
+
+    if let Some(rest) = version.strip_prefix("WebGL ") {
+        return match parse(rest) {
+            Some((major, _)) if major >= 2 => Some("#version 300 es"),
+            _ => None,
+        };
+    }
+
+    if let Some(rest) = version.strip_prefix("OpenGL ES ") {
+        return match parse(rest) {
+            Some((major, _)) if major >= 3 => Some("#version 300 es"),
+            _ => None,
+        };
+    }
+
+    match parse(&version) {
+        Some(version) if version >= (3, 2) => Some("#version 150"),
+        _ => None,
+    }
+}
+
 pub(crate) struct Shader {
-    context: Rc<glow::Context>,
+    context: Arc<glow::Context>,
     id: <glow::Context as glow::HasContext>::Shader,
 }
 
 impl Shader {
-    pub fn new(context: &Rc<glow::Context>, src: &str, kind: u32) -> Result<Self, ErrorKind> {
-        let id = unsafe { context.create_shader(kind).unwrap() };
+    pub fn new(context: &Arc<glow::Context>, src: &str, kind: u32) -> Result<Self, ErrorKind> {
+        let id = unsafe { context.create_shader(kind) }.map_err(ErrorKind::ShaderCompileError)?;
 
         // Compile
         unsafe {
@@ -56,16 +99,29 @@ impl Drop for Shader {
 }
 
 pub(crate) struct Program {
-    context: Rc<glow::Context>,
+    context: Arc<glow::Context>,
     id: <glow::Context as glow::HasContext>::Program,
 }
 
 impl Program {
-    pub fn new(context: &Rc<glow::Context>, shaders: &[Shader], attrib_locations: &[&str]) -> Result<Self, ErrorKind> {
-        let program = Self {
+    pub(crate) fn create(context: &Arc<glow::Context>) -> Result<Self, ErrorKind> {
+        Ok(Self {
             context: context.clone(),
-            id: unsafe { context.create_program().unwrap() },
-        };
+            id: unsafe { context.create_program() }.map_err(ErrorKind::ShaderLinkError)?,
+        })
+    }
+
+    pub fn new(
+        context: &Arc<glow::Context>,
+        shaders: &[Shader],
+        attrib_locations: &[&str],
+        cache: Option<&ProgramCache>,
+    ) -> Result<Self, ErrorKind> {
+        let program = Self::create(context)?;
+
+        if let Some(cache) = cache {
+            cache.prepare(&program);
+        }
 
         // Attach stages
         for shader in shaders {
@@ -104,6 +160,11 @@ impl Program {
         Ok(program)
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn id(&self) -> <glow::Context as glow::HasContext>::Program {
+        self.id
+    }
+
     pub(crate) fn bind(&self) {
         unsafe {
             self.context.use_program(Some(self.id));
@@ -117,7 +178,19 @@ impl Program {
     }
 
     fn uniform_location(&self, name: &str) -> Result<<glow::Context as glow::HasContext>::UniformLocation, ErrorKind> {
-        unsafe { Ok(self.context.get_uniform_location(self.id, name).unwrap()) }
+        unsafe { self.context.get_uniform_location(self.id, name) }
+            .ok_or_else(|| ErrorKind::GeneralError(format!("Uniform {} not found", name)))
+    }
+
+    fn bind_uniform_block(&self, name: &str, binding: u32) -> Result<(), ErrorKind> {
+        let index = unsafe { self.context.get_uniform_block_index(self.id, name) }
+            .ok_or_else(|| ErrorKind::GeneralError(format!("Uniform block {} not found", name)))?;
+
+        unsafe {
+            self.context.uniform_block_binding(self.id, index, binding);
+        }
+
+        Ok(())
     }
 }
 
@@ -130,40 +203,115 @@ impl Drop for Program {
 }
 
 pub struct MainProgram {
-    context: Rc<glow::Context>,
+    context: Arc<glow::Context>,
     program: Program,
     loc_viewsize: <glow::Context as glow::HasContext>::UniformLocation,
+    loc_depth: <glow::Context as glow::HasContext>::UniformLocation,
     loc_tex: <glow::Context as glow::HasContext>::UniformLocation,
     loc_masktex: <glow::Context as glow::HasContext>::UniformLocation,
-    loc_frag: <glow::Context as glow::HasContext>::UniformLocation,
+    loc_luttex: <glow::Context as glow::HasContext>::UniformLocation,
+    // None if the fragment parameters are read from a uniform buffer
+    loc_frag: Option<<glow::Context as glow::HasContext>::UniformLocation>,
 }
 
 impl MainProgram {
-    pub(crate) fn new(context: &Rc<glow::Context>, antialias: bool) -> Result<Self, ErrorKind> {
-        let shader_defs = if antialias { "#define EDGE_AA 1" } else { "" };
-        let vert_shader_src = format!("{}\n{}\n{}", GLSL_VERSION, shader_defs, include_str!("main-vs.glsl"));
-        let frag_shader_src = format!("{}\n{}\n{}", GLSL_VERSION, shader_defs, include_str!("main-fs.glsl"));
+    pub(crate) fn new(
+        context: &Arc<glow::Context>,
+        antialias: bool,
+        cache: Option<&ProgramCache>,
+    ) -> Result<Self, ErrorKind> {
+        Self::build(context, antialias, GLSL_VERSION, false, cache)
+    }
 
-        let vert_shader = Shader::new(context, &vert_shader_src, glow::VERTEX_SHADER)?;
-        let frag_shader = Shader::new(context, &frag_shader_src, glow::FRAGMENT_SHADER)?;
+    /// Creates the variant of the program that reads the fragment parameters from the uniform buffer
+    /// bound to [`FRAG_BLOCK_BINDING`] instead of a uniform array.
+    pub(crate) fn with_uniform_buffer(
+        context: &Arc<glow::Context>,
+        antialias: bool,
+        glsl_version: &str,
+        cache: Option<&ProgramCache>,
+    ) -> Result<Self, ErrorKind> {
+        Self::build(context, antialias, glsl_version, true, cache)
+    }
 
-        let program = Program::new(context, &[vert_shader, frag_shader], &["vertex", "tcoord"])?;
+    fn build(
+        context: &Arc<glow::Context>,
+        antialias: bool,
+        glsl_version: &str,
+        uniform_buffer: bool,
+        cache: Option<&ProgramCache>,
+    ) -> Result<Self, ErrorKind> {
+        let shader_defs = if antialias { "#define EDGE_AA 1" } else { "" };
+        let variant_defs = if uniform_buffer {
+            "#define USE_UNIFORM_BUFFER 1"
+        } else {
+            ""
+        };
+        let vert_shader_src = format!(
+            "{}\n{}\n{}\n{}",
+            glsl_version,
+            shader_defs,
+            variant_defs,
+            include_str!("main-vs.glsl")
+        );
+        let frag_shader_src = format!(
+            "{}\n{}\n{}\n{}",
+            glsl_version,
+            shader_defs,
+            variant_defs,
+            include_str!("main-fs.glsl")
+        );
+
+        let attrib_locations = ["vertex", "tcoord", "color"];
+
+        let key = cache.map(|cache| cache.key(&[&vert_shader_src, &frag_shader_src, &attrib_locations.join(",")]));
+        let cached = cache.zip(key).and_then(|(cache, key)| cache.load(context, key));
+
+        let program = match cached {
+            Some(program) => program,
+            None => {
+                let vert_shader = Shader::new(context, &vert_shader_src, glow::VERTEX_SHADER)?;
+                let frag_shader = Shader::new(context, &frag_shader_src, glow::FRAGMENT_SHADER)?;
+
+                let program = Program::new(context, &[vert_shader, frag_shader], &attrib_locations, cache)?;
+
+                if let Some((cache, key)) = cache.zip(key) {
+                    cache.store(&program, key);
+                }
+
+                program
+            }
+        };
 
         let loc_viewsize = program.uniform_location("viewSize")?;
+        let loc_depth = program.uniform_location("depth")?;
         let loc_tex = program.uniform_location("tex")?;
         let loc_masktex = program.uniform_location("masktex")?;
-        let loc_frag = program.uniform_location("frag")?;
+        let loc_luttex = program.uniform_location("luttex")?;
+
+        let loc_frag = if uniform_buffer {
+            program.bind_uniform_block("FragBlock", FRAG_BLOCK_BINDING)?;
+            None
+        } else {
+            Some(program.uniform_location("frag")?)
+        };
 
         Ok(Self {
             context: context.clone(),
             program,
             loc_viewsize,
+            loc_depth,
             loc_tex,
             loc_masktex,
+            loc_luttex,
             loc_frag,
         })
     }
 
+    pub(crate) fn uses_uniform_buffer(&self) -> bool {
+        self.loc_frag.is_none()
+    }
+
     pub(crate) fn set_tex(&self, tex: i32) {
         unsafe {
             self.context.uniform_1_i32(Some(&self.loc_tex), tex);
@@ -176,15 +324,27 @@ impl MainProgram {
         }
     }
 
+    pub(crate) fn set_luttex(&self, tex: i32) {
+        unsafe {
+            self.context.uniform_1_i32(Some(&self.loc_luttex), tex);
+        }
+    }
+
     pub(crate) fn set_view(&self, view: [f32; 2]) {
         unsafe {
             self.context.uniform_2_f32_slice(Some(&self.loc_viewsize), &view);
         }
     }
 
+    pub(crate) fn set_depth(&self, depth: f32) {
+        unsafe {
+            self.context.uniform_1_f32(Some(&self.loc_depth), depth);
+        }
+    }
+
     pub(crate) fn set_config(&self, config: &[f32]) {
         unsafe {
-            self.context.uniform_4_f32_slice(Some(&self.loc_frag), config);
+            self.context.uniform_4_f32_slice(self.loc_frag.as_ref(), config);
         }
     }
 
@@ -0,0 +1,255 @@
+//! Keeps linked programs on disk with `glGetProgramBinary()`, so that later runs skip compiling
+//! the shaders. Some mobile drivers take hundreds of milliseconds for that on the first frame.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::ffi::c_void;
+#[cfg(not(target_arch = "wasm32"))]
+use std::hash::Hasher;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use fnv::FnvHasher;
+#[cfg(not(target_arch = "wasm32"))]
+use glow::HasContext;
+
+use super::program::Program;
+
+#[cfg(not(target_arch = "wasm32"))]
+// Identifies the files written by this module, followed by the binary format and the binary
+const MAGIC: &[u8; 4] = b"FVGB";
+
+#[cfg(not(target_arch = "wasm32"))]
+const PROGRAM_BINARY_LENGTH: u32 = 0x8741;
+#[cfg(not(target_arch = "wasm32"))]
+const PROGRAM_BINARY_RETRIEVABLE_HINT: u32 = 0x8257;
+
+#[cfg(not(target_arch = "wasm32"))]
+type GetProgramIv = unsafe extern "system" fn(u32, u32, *mut i32);
+#[cfg(not(target_arch = "wasm32"))]
+type GetProgramBinary = unsafe extern "system" fn(u32, i32, *mut i32, *mut u32, *mut c_void);
+#[cfg(not(target_arch = "wasm32"))]
+type ProgramBinary = unsafe extern "system" fn(u32, u32, *const c_void, i32);
+#[cfg(not(target_arch = "wasm32"))]
+type ProgramParameterI = unsafe extern "system" fn(u32, u32, i32);
+
+/// The entry points for program binaries, glow doesn't expose them.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct BinaryFns {
+    get_program_iv: GetProgramIv,
+    get_program_binary: GetProgramBinary,
+    program_binary: ProgramBinary,
+    program_parameter_i: ProgramParameterI,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BinaryFns {
+    /// Loads the entry points, returns None if the driver lacks any of them.
+    pub(crate) fn load<F>(load_fn: &mut F) -> Option<Self>
+    where
+        F: FnMut(&str) -> *const c_void,
+    {
+        let mut load = |name: &str| {
+            let ptr = load_fn(name);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(ptr)
+            }
+        };
+
+        let get_program_iv = load("glGetProgramiv")?;
+        let get_program_binary = load("glGetProgramBinary")?;
+        let program_binary = load("glProgramBinary")?;
+        let program_parameter_i = load("glProgramParameteri")?;
+
+        // SAFETY: the pointers were returned by the loader for these names, whose signatures are
+        // fixed by the OpenGL specification
+        unsafe {
+            Some(Self {
+                get_program_iv: std::mem::transmute::<*const c_void, GetProgramIv>(get_program_iv),
+                get_program_binary: std::mem::transmute::<*const c_void, GetProgramBinary>(get_program_binary),
+                program_binary: std::mem::transmute::<*const c_void, ProgramBinary>(program_binary),
+                program_parameter_i: std::mem::transmute::<*const c_void, ProgramParameterI>(program_parameter_i),
+            })
+        }
+    }
+}
+
+/// Stores program binaries in a directory, keyed by the driver and the shader sources.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct ProgramCache {
+    dir: PathBuf,
+    // Binaries only load into the driver that produced them
+    driver: String,
+    fns: BinaryFns,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ProgramCache {
+    /// Returns None if the context supports no binary formats.
+    pub(crate) fn new(context: &glow::Context, fns: BinaryFns, dir: PathBuf) -> Option<Self> {
+        let formats = unsafe { context.get_parameter_i32(glow::NUM_PROGRAM_BINARY_FORMATS) };
+
+        // Clear the error raised by contexts that don't know the parameter
+        unsafe {
+            context.get_error();
+        }
+
+        if formats <= 0 {
+            return None;
+        }
+
+        let driver = unsafe {
+            format!(
+                "{}\n{}\n{}",
+                context.get_parameter_string(glow::VENDOR),
+                context.get_parameter_string(glow::RENDERER),
+                context.get_parameter_string(glow::VERSION)
+            )
+        };
+
+        Some(Self { dir, driver, fns })
+    }
+
+    /// Returns the key of the program linked from `sources`.
+    pub(crate) fn key(&self, sources: &[&str]) -> u64 {
+        let mut hasher = FnvHasher::default();
+        hasher.write(self.driver.as_bytes());
+
+        for source in sources {
+            hasher.write(source.as_bytes());
+        }
+
+        hasher.finish()
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("femtovg-{:016x}.bin", key))
+    }
+
+    /// Returns the cached program, or None if there is none or the driver rejects it.
+    pub(crate) fn load(&self, context: &Arc<glow::Context>, key: u64) -> Option<Program> {
+        let bytes = std::fs::read(self.path(key)).ok()?;
+        let (format, binary) = decode(&bytes)?;
+
+        let program = Program::create(context).ok()?;
+
+        unsafe {
+            (self.fns.program_binary)(program.id(), format, binary.as_ptr().cast(), binary.len() as i32);
+        }
+
+        if unsafe { context.get_program_link_status(program.id()) } {
+            Some(program)
+        } else {
+            None
+        }
+    }
+
+    /// Asks the driver to keep the binary of `program` when it is linked.
+    pub(crate) fn prepare(&self, program: &Program) {
+        unsafe {
+            (self.fns.program_parameter_i)(program.id(), PROGRAM_BINARY_RETRIEVABLE_HINT, 1);
+        }
+    }
+
+    /// Writes the binary of the linked `program` to the cache. Failures only cost the next run the
+    /// compilation, so they are ignored.
+    pub(crate) fn store(&self, program: &Program, key: u64) {
+        let mut length = 0;
+
+        unsafe {
+            (self.fns.get_program_iv)(program.id(), PROGRAM_BINARY_LENGTH, &mut length);
+        }
+
+        if length <= 0 {
+            return;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+
+        unsafe {
+            (self.fns.get_program_binary)(
+                program.id(),
+                length,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr().cast(),
+            );
+        }
+
+        binary.truncate(written.max(0) as usize);
+
+        if binary.is_empty() {
+            return;
+        }
+
+        let _ = std::fs::create_dir_all(&self.dir);
+        let _ = std::fs::write(self.path(key), encode(format, &binary));
+    }
+}
+
+/// Never constructed, there are no program binaries in WebGL.
+#[cfg(target_arch = "wasm32")]
+pub(crate) enum ProgramCache {}
+
+#[cfg(target_arch = "wasm32")]
+impl ProgramCache {
+    pub(crate) fn key(&self, _sources: &[&str]) -> u64 {
+        match *self {}
+    }
+
+    pub(crate) fn load(&self, _context: &Arc<glow::Context>, _key: u64) -> Option<Program> {
+        match *self {}
+    }
+
+    pub(crate) fn prepare(&self, _program: &Program) {
+        match *self {}
+    }
+
+    pub(crate) fn store(&self, _program: &Program, _key: u64) {
+        match *self {}
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn encode(format: u32, binary: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + binary.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(binary);
+    bytes
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decode(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let rest = bytes.strip_prefix(MAGIC.as_slice())?;
+
+    if rest.len() <= 4 {
+        return None;
+    }
+
+    let (format, binary) = rest.split_at(4);
+    let format = u32::from_le_bytes(std::convert::TryInto::try_into(format).ok()?);
+
+    Some((format, binary))
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binaries_round_trip() {
+        let bytes = encode(0x8E21, &[1, 2, 3]);
+
+        assert_eq!(decode(&bytes), Some((0x8E21, [1u8, 2, 3].as_slice())));
+
+        // Truncated and foreign files are ignored
+        assert_eq!(decode(&bytes[..8]), None);
+        assert_eq!(decode(b"PNG\0\0\0\0\0\0"), None);
+    }
+}
@@ -86,6 +86,10 @@ impl UniformArray {
     pub fn set_image_blur_filter_coeff(&mut self, coeff: [f32; 3]) {
         self.0[48..51].copy_from_slice(&coeff);
     }
+
+    pub fn set_scissor_radius(&mut self, radius: f32) {
+        self.0[51] = radius;
+    }
 }
 
 impl From<&Params> for UniformArray {
@@ -109,6 +113,7 @@ impl From<&Params> for UniformArray {
         arr.set_image_blur_filter_direction(params.image_blur_filter_direction);
         arr.set_image_blur_filter_sigma(params.image_blur_filter_sigma);
         arr.set_image_blur_filter_coeff(params.image_blur_filter_coeff);
+        arr.set_scissor_radius(params.scissor_radius);
 
         arr
     }
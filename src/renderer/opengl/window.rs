@@ -0,0 +1,152 @@
+use glutin::{
+    ContextBuilder,
+    ContextError,
+    CreationError,
+    NotCurrent,
+    PossiblyCurrent,
+    RawContext,
+};
+use raw_window_handle::{
+    HasRawWindowHandle,
+    RawWindowHandle,
+};
+
+use super::OpenGl;
+use crate::ErrorKind;
+
+/// The OpenGL context created by [`OpenGl::new_from_window_handle()`].
+///
+/// The context has to be kept alive for as long as the renderer is used. Requires the `window-handle` feature.
+pub struct WindowContext {
+    context: RawContext<PossiblyCurrent>,
+}
+
+impl WindowContext {
+    /// Presents the rendered frame, call this after [`crate::Canvas::flush()`].
+    pub fn swap_buffers(&self) -> Result<(), ErrorKind> {
+        self.context.swap_buffers().map_err(context_error)
+    }
+
+    /// Resizes the surface of the context. Needs to be called when the window is resized on some
+    /// platforms, notably Wayland.
+    pub fn resize(&self, width: u32, height: u32) {
+        self.context.resize(glutin::dpi::PhysicalSize::new(width, height));
+    }
+
+    /// Makes the context current on the calling thread.
+    pub fn make_current(self) -> Result<Self, ErrorKind> {
+        let context = unsafe { self.context.make_current() }.map_err(|(_, err)| context_error(err))?;
+
+        Ok(Self { context })
+    }
+}
+
+impl OpenGl {
+    /// Creates an OpenGL context for the provided window and a renderer that draws into it.
+    ///
+    /// Supported are Xlib and Wayland windows on unix and Win32 windows on Windows. Other window
+    /// handles return an error. `width` and `height` are the size of the window in physical pixels,
+    /// they are only used on Wayland where the surface has no size of its own.
+    ///
+    /// Requires the `window-handle` feature.
+    ///
+    /// # Safety
+    ///
+    /// The window must outlive the returned [`WindowContext`].
+    pub unsafe fn new_from_window_handle(
+        window: &impl HasRawWindowHandle,
+        width: u32,
+        height: u32,
+    ) -> Result<(Self, WindowContext), ErrorKind> {
+        let builder = ContextBuilder::new().with_stencil_buffer(8).with_vsync(false);
+
+        let context = build_raw_context(builder, window.raw_window_handle(), width, height)?;
+        let context = context.make_current().map_err(|(_, err)| context_error(err))?;
+
+        let renderer = Self::new(|s| context.get_proc_address(s) as *const _)?;
+
+        Ok((renderer, WindowContext { context }))
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+unsafe fn build_raw_context(
+    builder: ContextBuilder<NotCurrent>,
+    handle: RawWindowHandle,
+    width: u32,
+    height: u32,
+) -> Result<RawContext<NotCurrent>, ErrorKind> {
+    use glutin::platform::unix::{
+        x11::XConnection,
+        RawContextExt,
+    };
+    use std::sync::Arc;
+
+    match handle {
+        RawWindowHandle::Xlib(handle) => {
+            // Window ids are global to the X server, so a connection of our own can create the
+            // context for a window owned by another connection.
+            let xconn = XConnection::new(None)
+                .map_err(|err| ErrorKind::GeneralError(format!("Cannot connect to the X server: {:?}", err)))?;
+
+            builder
+                .build_raw_x11_context(Arc::new(xconn), handle.window)
+                .map_err(creation_error)
+        }
+        RawWindowHandle::Wayland(handle) => builder
+            .build_raw_wayland_context(handle.display as *const _, handle.surface, width, height)
+            .map_err(creation_error),
+        _ => Err(unsupported_handle()),
+    }
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn build_raw_context(
+    builder: ContextBuilder<NotCurrent>,
+    handle: RawWindowHandle,
+    _width: u32,
+    _height: u32,
+) -> Result<RawContext<NotCurrent>, ErrorKind> {
+    use glutin::platform::windows::RawContextExt;
+
+    match handle {
+        RawWindowHandle::Windows(handle) => builder.build_raw_context(handle.hwnd).map_err(creation_error),
+        _ => Err(unsupported_handle()),
+    }
+}
+
+// glutin can't create raw contexts on the remaining platforms
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "windows",
+)))]
+unsafe fn build_raw_context(
+    _builder: ContextBuilder<NotCurrent>,
+    _handle: RawWindowHandle,
+    _width: u32,
+    _height: u32,
+) -> Result<RawContext<NotCurrent>, ErrorKind> {
+    Err(unsupported_handle())
+}
+
+fn unsupported_handle() -> ErrorKind {
+    ErrorKind::GeneralError("Unsupported window handle".to_owned())
+}
+
+fn creation_error(err: CreationError) -> ErrorKind {
+    ErrorKind::GeneralError(format!("Cannot create OpenGL context: {}", err))
+}
+
+fn context_error(err: ContextError) -> ErrorKind {
+    ErrorKind::GeneralError(format!("OpenGL context error: {}", err))
+}
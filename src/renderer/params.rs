@@ -2,6 +2,7 @@ use crate::{
     paint::GradientColors,
     Color,
     ImageFlags,
+    ImageInfo,
     ImageStore,
     Paint,
     PaintFlavor,
@@ -12,7 +13,7 @@ use crate::{
 
 use super::ShaderType;
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Params {
     pub(crate) scissor_mat: [f32; 12],
     pub(crate) paint_mat: [f32; 12],
@@ -20,6 +21,7 @@ pub struct Params {
     pub(crate) outer_col: [f32; 4],
     pub(crate) scissor_ext: [f32; 2],
     pub(crate) scissor_scale: [f32; 2],
+    pub(crate) scissor_radius: f32,
     pub(crate) extent: [f32; 2],
     pub(crate) radius: f32,
     pub(crate) feather: f32,
@@ -50,6 +52,7 @@ impl Params {
                 ([1.0, 1.0], [1.0, 1.0])
             } else {
                 params.scissor_mat = scissor.transform.inversed().to_mat3x4();
+                params.scissor_radius = scissor.radius;
 
                 let scissor_scale = [
                     (scissor.transform[0] * scissor.transform[0] + scissor.transform[2] * scissor.transform[2]).sqrt()
@@ -104,42 +107,40 @@ impl Params {
                 params.inner_col = color.premultiplied().to_array();
                 params.outer_col = color.premultiplied().to_array();
 
-                let mut transform = Transform2D::identity();
-                transform.rotate(angle);
-                transform.translate(cx, cy);
-                transform.multiply(&paint.transform);
+                inv_transform = image_transform(paint, &image_info, cx, cy, height, angle);
 
-                if image_info.flags().contains(ImageFlags::FLIP_Y) {
-                    let mut m1 = Transform2D::identity();
-                    m1.translate(0.0, height * 0.5);
-                    m1.multiply(&transform);
+                params.shader_type = ShaderType::FillImage.to_f32();
+                params.tex_type = tex_type(&image_info);
+            }
+            PaintFlavor::ColorMap {
+                id,
+                lut,
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+                alpha,
+            } => {
+                let (image_info, lut_info) = match (images.info(id), images.info(lut)) {
+                    (Some(image_info), Some(lut_info)) => (image_info, lut_info),
+                    _ => return params,
+                };
 
-                    let mut m2 = Transform2D::identity();
-                    m2.scale(1.0, -1.0);
-                    m2.multiply(&m1);
+                params.extent[0] = width;
+                params.extent[1] = height;
+                // The shader needs the width of the colors to sample them at the centers of their pixels
+                params.radius = lut_info.width() as f32;
 
-                    let mut m1 = Transform2D::identity();
-                    m1.translate(0.0, -height * 0.5);
-                    m1.multiply(&m2);
+                let color = Color::rgbaf(1.0, 1.0, 1.0, alpha);
 
-                    inv_transform = m1.inversed();
-                } else {
-                    inv_transform = transform.inversed();
-                }
+                params.inner_col = color.premultiplied().to_array();
+                params.outer_col = color.premultiplied().to_array();
 
-                params.shader_type = ShaderType::FillImage.to_f32();
+                inv_transform = image_transform(paint, &image_info, cx, cy, height, angle);
 
-                params.tex_type = match image_info.format() {
-                    PixelFormat::Rgba8 => {
-                        if image_info.flags().contains(ImageFlags::PREMULTIPLIED) {
-                            0.0
-                        } else {
-                            1.0
-                        }
-                    }
-                    PixelFormat::Gray8 => 2.0,
-                    _ => 0.0,
-                };
+                params.shader_type = ShaderType::ColorMap.to_f32();
+                params.tex_type = tex_type(&lut_info);
             }
             PaintFlavor::LinearGradient {
                 start_x,
@@ -198,7 +199,9 @@ impl Params {
                 params.extent[0] = width * 0.5;
                 params.extent[1] = height * 0.5;
                 params.radius = radius;
-                params.feather = feather;
+                // Unlike nanovg, which clamps the feather to at least a pixel, feathers below a pixel
+                // are kept for hard edged boxes. Only the division by a zero feather is avoided.
+                params.feather = feather.max(f32::EPSILON);
                 match colors {
                     GradientColors::TwoStop { start_color, end_color } => {
                         params.inner_col = start_color.premultiplied().to_array();
@@ -246,3 +249,102 @@ impl Params {
         params
     }
 }
+
+// Inverse of the transform that places an image with its top-left corner at `cx, cy`, images with
+// `FLIP_Y` are mirrored around their middle
+fn image_transform(paint: &Paint, image_info: &ImageInfo, cx: f32, cy: f32, height: f32, angle: f32) -> Transform2D {
+    let mut transform = Transform2D::identity();
+    transform.rotate(angle);
+    transform.translate(cx, cy);
+    transform.multiply(&paint.transform);
+
+    if image_info.flags().contains(ImageFlags::FLIP_Y) {
+        let mut m1 = Transform2D::identity();
+        m1.translate(0.0, height * 0.5);
+        m1.multiply(&transform);
+
+        let mut m2 = Transform2D::identity();
+        m2.scale(1.0, -1.0);
+        m2.multiply(&m1);
+
+        let mut m1 = Transform2D::identity();
+        m1.translate(0.0, -height * 0.5);
+        m1.multiply(&m2);
+
+        m1.inversed()
+    } else {
+        transform.inversed()
+    }
+}
+
+// How the shader turns the texels of the image into premultiplied colors
+fn tex_type(image_info: &ImageInfo) -> f32 {
+    match image_info.format() {
+        PixelFormat::Rgba8 => {
+            if image_info.flags().contains(ImageFlags::PREMULTIPLIED) {
+                0.0
+            } else {
+                1.0
+            }
+        }
+        PixelFormat::Gray8 => 2.0,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(paint: Paint) -> Params {
+        let images = ImageStore::<()>::new();
+        Params::new(&images, &paint, &Scissor::default(), 1.0, 1.0, -1.0)
+    }
+
+    // The expected values are the ones nvgLinearGradient(), nvgRadialGradient() and nvgBoxGradient() compute
+    #[test]
+    fn gradients_match_nanovg() {
+        let (inner, outer) = (Color::rgbf(1.0, 0.0, 0.0), Color::rgbf(0.0, 0.0, 1.0));
+
+        let linear = params(Paint::linear_gradient(10.0, 20.0, 10.0, 120.0, inner, outer));
+        assert_eq!(linear.extent, [1e5, 1e5 + 50.0]);
+        assert_eq!(linear.feather, 100.0);
+        assert_eq!(linear.radius, 0.0);
+
+        // Coincident end points fall back to a vertical gradient
+        let point = params(Paint::linear_gradient(10.0, 20.0, 10.0, 20.0, inner, outer));
+        assert_eq!(point.extent, [1e5, 1e5]);
+        assert_eq!(point.feather, 1.0);
+
+        let radial = params(Paint::radial_gradient(50.0, 50.0, 10.0, 30.0, inner, outer));
+        assert_eq!(radial.extent, [20.0, 20.0]);
+        assert_eq!(radial.radius, 20.0);
+        assert_eq!(radial.feather, 20.0);
+
+        let ring = params(Paint::radial_gradient(50.0, 50.0, 20.0, 20.0, inner, outer));
+        assert_eq!(ring.feather, 1.0);
+
+        let shadow = params(Paint::box_gradient(0.0, 0.0, 100.0, 60.0, 8.0, 20.0, inner, outer));
+        assert_eq!(shadow.extent, [50.0, 30.0]);
+        assert_eq!(shadow.radius, 8.0);
+        assert_eq!(shadow.feather, 20.0);
+        assert_eq!(shadow.inner_col, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(shadow.outer_col, [0.0, 0.0, 1.0, 1.0]);
+
+        // The paint space is centered on the box, like in nanovg
+        let m = shadow.paint_mat;
+        let center = Transform2D([m[0], m[1], m[4], m[5], m[8], m[9]]).transform_point(50.0, 30.0);
+        assert_eq!(center, (0.0, 0.0));
+    }
+
+    #[test]
+    fn box_gradient_feathers_below_a_pixel_are_kept() {
+        let (inner, outer) = (Color::black(), Color::white());
+
+        let sharp = params(Paint::box_gradient(0.0, 0.0, 10.0, 10.0, 0.0, 0.25, inner, outer));
+        assert_eq!(sharp.feather, 0.25);
+
+        let hard = params(Paint::box_gradient(0.0, 0.0, 10.0, 10.0, 0.0, 0.0, inner, outer));
+        assert!(hard.feather > 0.0 && hard.feather < 1e-6);
+    }
+}
@@ -5,6 +5,7 @@ use rgb::RGBA8;
 
 use crate::{
     ErrorKind,
+    ImageFlags,
     ImageInfo,
     ImageSource,
     ImageStore,
@@ -53,6 +54,11 @@ impl Renderer for Void {
 
     fn delete_image(&mut self, image: Self::Image, _image_id: ImageId) {}
 
+    fn set_image_flags(&mut self, image: &mut Self::Image, flags: ImageFlags) -> Result<(), ErrorKind> {
+        image.info.set_flags(flags);
+        Ok(())
+    }
+
     fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
         Ok(ImgVec::new(Vec::new(), 0, 0))
     }
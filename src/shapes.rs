@@ -0,0 +1,206 @@
+//! Paths of common shapes, built in one call.
+//!
+//! Angles are in radians and increase clockwise, starting at the positive x axis, like the angles
+//! of [`Path::arc()`]. Shapes that have a vertex on top start drawing there.
+
+use std::f32::consts::PI;
+
+use crate::{
+    Path,
+    Solidity,
+};
+
+/// An arrow from the tail at `x0, y0` to its tip at `x1, y1`.
+///
+/// The head is `head_length` long and `head_width` wide at its base, the shaft is `shaft_width`
+/// wide. Arrows shorter than their head are drawn as a head alone.
+pub fn arrow(x0: f32, y0: f32, x1: f32, y1: f32, shaft_width: f32, head_width: f32, head_length: f32) -> Path {
+    let mut path = Path::new();
+
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let len = (dx * dx + dy * dy).sqrt();
+
+    if len <= f32::EPSILON {
+        return path;
+    }
+
+    let (dx, dy) = (dx / len, dy / len);
+    let (nx, ny) = (-dy, dx);
+
+    let head_length = head_length.min(len);
+    let (bx, by) = (x1 - dx * head_length, y1 - dy * head_length);
+    let (sw, hw) = (shaft_width / 2.0, head_width / 2.0);
+
+    path.move_to(x0 + nx * sw, y0 + ny * sw);
+    path.line_to(bx + nx * sw, by + ny * sw);
+    path.line_to(bx + nx * hw, by + ny * hw);
+    path.line_to(x1, y1);
+    path.line_to(bx - nx * hw, by - ny * hw);
+    path.line_to(bx - nx * sw, by - ny * sw);
+    path.line_to(x0 - nx * sw, y0 - ny * sw);
+    path.close();
+
+    path
+}
+
+/// A pie slice of the circle at `cx, cy` with radius `r`, from angle `a0` clockwise to `a1`.
+pub fn pie(cx: f32, cy: f32, r: f32, a0: f32, a1: f32) -> Path {
+    let mut path = Path::new();
+
+    path.move_to(cx, cy);
+    path.arc(cx, cy, r, a0, a1, Solidity::Hole);
+    path.close();
+
+    path
+}
+
+/// A segment of the ring between the radii `r0` and `r1` around `cx, cy`, from angle `a0`
+/// clockwise to `a1`, like a slice of a donut chart.
+pub fn arc_segment(cx: f32, cy: f32, r0: f32, r1: f32, a0: f32, a1: f32) -> Path {
+    let mut path = Path::new();
+
+    path.arc(cx, cy, r1, a0, a1, Solidity::Hole);
+    path.arc(cx, cy, r0, a1, a0, Solidity::Solid);
+    path.close();
+
+    path
+}
+
+/// A regular polygon with `sides` vertices on the circle at `cx, cy` with radius `r`, nothing for
+/// fewer than three sides.
+pub fn polygon(cx: f32, cy: f32, r: f32, sides: usize) -> Path {
+    let mut path = Path::new();
+
+    if sides < 3 {
+        return path;
+    }
+
+    for i in 0..sides {
+        let (x, y) = vertex(cx, cy, r, i as f32 / sides as f32);
+
+        if i == 0 {
+            path.move_to(x, y);
+        } else {
+            path.line_to(x, y);
+        }
+    }
+
+    path.close();
+
+    path
+}
+
+/// A star with `points` tips on the circle with radius `r1` and the vertices between them on the
+/// circle with radius `r0`, nothing for fewer than two points.
+pub fn star(cx: f32, cy: f32, r0: f32, r1: f32, points: usize) -> Path {
+    let mut path = Path::new();
+
+    if points < 2 {
+        return path;
+    }
+
+    for i in 0..points * 2 {
+        let r = if i % 2 == 0 { r1 } else { r0 };
+        let (x, y) = vertex(cx, cy, r, i as f32 / (points * 2) as f32);
+
+        if i == 0 {
+            path.move_to(x, y);
+        } else {
+            path.line_to(x, y);
+        }
+    }
+
+    path.close();
+
+    path
+}
+
+/// A closed polygon through `points` with its corners rounded by arcs of `radius`, see
+/// [`Path::arc_to()`]. Corners too sharp for the radius get smaller arcs.
+pub fn rounded_polygon(points: &[(f32, f32)], radius: f32) -> Path {
+    let mut path = Path::new();
+
+    if points.len() < 3 {
+        return path;
+    }
+
+    let (first, last) = (points[0], points[points.len() - 1]);
+    path.move_to((first.0 + last.0) / 2.0, (first.1 + last.1) / 2.0);
+
+    for (i, corner) in points.iter().enumerate() {
+        let next = points[(i + 1) % points.len()];
+
+        // Half of the shorter edge at the corner is as far as the arc may reach
+        let prev = if i == 0 { last } else { points[i - 1] };
+        let reach = distance(prev, *corner).min(distance(*corner, next)) / 2.0;
+
+        path.arc_to(corner.0, corner.1, next.0, next.1, radius.min(reach));
+    }
+
+    path.close();
+
+    path
+}
+
+/// A rounded rectangle with a pointed tail from the nearest edge to `tail_x, tail_y`, a plain
+/// rounded rectangle if the tail lies inside it or the edge has no length.
+pub fn speech_bubble(x: f32, y: f32, w: f32, h: f32, radius: f32, tail_x: f32, tail_y: f32) -> Path {
+    let mut path = Path::new();
+
+    let r = radius.min(w / 2.0).min(h / 2.0).max(0.0);
+
+    // Corners and the edges that end in them, clockwise from the top edge
+    let corners = [(x + w, y), (x + w, y + h), (x, y + h), (x, y)];
+
+    let outside_x = (x - tail_x).max(tail_x - (x + w)).max(0.0);
+    let outside_y = (y - tail_y).max(tail_y - (y + h)).max(0.0);
+
+    let tail_edge = if outside_x == 0.0 && outside_y == 0.0 {
+        None
+    } else if outside_y >= outside_x {
+        Some(if tail_y < y { 0 } else { 2 })
+    } else {
+        Some(if tail_x > x + w { 1 } else { 3 })
+    };
+
+    path.move_to(x + r, y);
+
+    for i in 0..4 {
+        let start = corners[(i + 3) % 4];
+        let (end, next) = (corners[i], corners[(i + 1) % 4]);
+
+        let len = distance(start, end);
+
+        // An edge without length has no direction for the tail to leave it in
+        if tail_edge == Some(i) && len > f32::EPSILON {
+            let (dx, dy) = ((end.0 - start.0) / len, (end.1 - start.1) / len);
+
+            // The base of the tail is a quarter of the shorter side, between the rounded corners
+            let half = (w.min(h) / 8.0).min((len - 2.0 * r) / 2.0).max(0.0);
+            let along = ((tail_x - start.0) * dx + (tail_y - start.1) * dy)
+                .max(r + half)
+                .min(len - r - half);
+
+            path.line_to(start.0 + dx * (along - half), start.1 + dy * (along - half));
+            path.line_to(tail_x, tail_y);
+            path.line_to(start.0 + dx * (along + half), start.1 + dy * (along + half));
+        }
+
+        path.arc_to(end.0, end.1, next.0, next.1, r);
+    }
+
+    path.close();
+
+    path
+}
+
+// The point at `turn` of a full turn around the circle, starting on top
+fn vertex(cx: f32, cy: f32, r: f32, turn: f32) -> (f32, f32) {
+    let angle = turn * PI * 2.0 - PI / 2.0;
+
+    (cx + angle.cos() * r, cy + angle.sin() * r)
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
@@ -0,0 +1,301 @@
+use usvg::prelude::*;
+use usvg::{
+    NodeKind,
+    PathSegment,
+    Units,
+};
+
+use crate::{
+    Canvas,
+    Color,
+    ErrorKind,
+    FillRule,
+    LineCap,
+    LineJoin,
+    Paint,
+    Path,
+    Renderer,
+};
+
+#[derive(Clone, Debug)]
+struct Shape {
+    path: Path,
+    fill: Option<Paint>,
+    stroke: Option<Paint>,
+}
+
+/// A parsed SVG document that can be drawn with [`Canvas::draw_picture()`].
+///
+/// The document is converted once into paths with resolved fill and stroke paints, so drawing a picture
+/// is as cheap as drawing the equivalent paths by hand. Requires the `svg` feature.
+///
+/// Supported are paths with solid color, linear and radial gradient fills and strokes. Text, images,
+/// patterns, filters, masks and clip paths are ignored.
+///
+/// # Example
+/// ```
+/// use femtovg::{Canvas, svg::Picture, renderer::Void};
+///
+/// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+///
+/// let data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="red"/></svg>"#;
+/// let mut picture = Picture::from_data(data).expect("Cannot parse svg");
+///
+/// canvas.draw_picture(&mut picture);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Picture {
+    width: f32,
+    height: f32,
+    shapes: Vec<Shape>,
+}
+
+impl Picture {
+    /// Parses an SVG document.
+    pub fn from_data(data: &[u8]) -> Result<Self, ErrorKind> {
+        let tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+
+        Ok(Self::from_tree(&tree))
+    }
+
+    /// Converts an already parsed SVG document.
+    pub fn from_tree(tree: &usvg::Tree) -> Self {
+        let svg = tree.svg_node();
+        let view_box = usvg::utils::view_box_to_transform(svg.view_box.rect, svg.view_box.aspect, svg.size);
+
+        let mut shapes = Vec::new();
+
+        for node in tree.root().descendants() {
+            if tree.is_in_defs(&node) {
+                continue;
+            }
+
+            if let NodeKind::Path(ref svg_path) = *node.borrow() {
+                if svg_path.visibility != usvg::Visibility::Visible {
+                    continue;
+                }
+
+                let mut transform = view_box;
+                transform.append(&node.abs_transform());
+
+                let opacity = group_opacity(&node);
+
+                let path = convert_path(&svg_path.data, &transform);
+                let bbox = svg_path.data.bbox();
+
+                let fill = svg_path.fill.as_ref().and_then(|fill| {
+                    let mut paint = convert_paint(tree, &fill.paint, fill.opacity.value() * opacity, bbox, &transform)?;
+
+                    paint.set_fill_rule(match fill.rule {
+                        usvg::FillRule::NonZero => FillRule::NonZero,
+                        usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+                    });
+
+                    Some(paint)
+                });
+
+                let stroke = svg_path.stroke.as_ref().and_then(|stroke| {
+                    let mut paint =
+                        convert_paint(tree, &stroke.paint, stroke.opacity.value() * opacity, bbox, &transform)?;
+
+                    paint.set_line_width(stroke.width.value() as f32 * average_scale(&transform));
+                    paint.set_miter_limit(stroke.miterlimit.value() as f32);
+
+                    paint.set_line_cap(match stroke.linecap {
+                        usvg::LineCap::Butt => LineCap::Butt,
+                        usvg::LineCap::Round => LineCap::Round,
+                        usvg::LineCap::Square => LineCap::Square,
+                    });
+
+                    paint.set_line_join(match stroke.linejoin {
+                        usvg::LineJoin::Miter => LineJoin::Miter,
+                        usvg::LineJoin::Round => LineJoin::Round,
+                        usvg::LineJoin::Bevel => LineJoin::Bevel,
+                    });
+
+                    Some(paint)
+                });
+
+                if fill.is_some() || stroke.is_some() {
+                    shapes.push(Shape { path, fill, stroke });
+                }
+            }
+        }
+
+        Self {
+            width: svg.size.width() as f32,
+            height: svg.size.height() as f32,
+            shapes,
+        }
+    }
+
+    /// Width of the picture as specified by the document.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Height of the picture as specified by the document.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws the picture with its top left corner at the origin of the current coordinate system.
+    pub fn draw_picture(&mut self, picture: &mut Picture) {
+        for shape in &mut picture.shapes {
+            if let Some(fill) = shape.fill {
+                self.fill_path(&mut shape.path, fill);
+            }
+
+            if let Some(stroke) = shape.stroke {
+                self.stroke_path(&mut shape.path, stroke);
+            }
+        }
+    }
+}
+
+fn convert_path(data: &usvg::PathData, transform: &usvg::Transform) -> Path {
+    let mut path = Path::new();
+
+    // Segments reaching beyond the range of f32 are left out, paths only take finite coordinates
+    let point = |x: f64, y: f64| {
+        let (x, y) = transform.apply(x, y);
+        let (x, y) = (x as f32, y as f32);
+
+        if x.is_finite() && y.is_finite() {
+            Some((x, y))
+        } else {
+            None
+        }
+    };
+
+    for segment in data.iter() {
+        match *segment {
+            PathSegment::MoveTo { x, y } => {
+                if let Some((x, y)) = point(x, y) {
+                    path.move_to(x, y);
+                }
+            }
+            PathSegment::LineTo { x, y } => {
+                if let Some((x, y)) = point(x, y) {
+                    path.line_to(x, y);
+                }
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                if let (Some((x1, y1)), Some((x2, y2)), Some((x, y))) = (point(x1, y1), point(x2, y2), point(x, y)) {
+                    path.bezier_to(x1, y1, x2, y2, x, y);
+                }
+            }
+            PathSegment::ClosePath => path.close(),
+        }
+    }
+
+    // SVG cuts holes out by their winding, femtovg by their solidity
+    path.mark_holes_by_winding();
+
+    path
+}
+
+fn convert_paint(
+    tree: &usvg::Tree,
+    paint: &usvg::Paint,
+    opacity: f64,
+    bbox: Option<usvg::Rect>,
+    transform: &usvg::Transform,
+) -> Option<Paint> {
+    let id = match paint {
+        usvg::Paint::Color(color) => return Some(Paint::color(convert_color(*color, opacity))),
+        usvg::Paint::Link(id) => id,
+    };
+
+    let node = tree.defs_by_id(id)?;
+    let kind = node.borrow();
+
+    let base = match *kind {
+        NodeKind::LinearGradient(ref gradient) => &gradient.base,
+        NodeKind::RadialGradient(ref gradient) => &gradient.base,
+        // Patterns are not supported
+        _ => return None,
+    };
+
+    let mut ts = *transform;
+
+    if base.units == Units::ObjectBoundingBox {
+        ts.append(&usvg::Transform::from_bbox(bbox?));
+    }
+
+    ts.append(&base.transform);
+
+    let stops: Vec<(f32, Color)> = base
+        .stops
+        .iter()
+        .map(|stop| {
+            let color = convert_color(stop.color, stop.opacity.value() * opacity);
+            (stop.offset.value() as f32, color)
+        })
+        .collect();
+
+    match *kind {
+        NodeKind::LinearGradient(ref gradient) => {
+            let (start_x, start_y) = ts.apply(gradient.x1, gradient.y1);
+            let (end_x, end_y) = ts.apply(gradient.x2, gradient.y2);
+
+            Some(Paint::linear_gradient_stops(
+                start_x as f32,
+                start_y as f32,
+                end_x as f32,
+                end_y as f32,
+                &stops,
+            ))
+        }
+        NodeKind::RadialGradient(ref gradient) => {
+            let (cx, cy) = ts.apply(gradient.cx, gradient.cy);
+            let radius = gradient.r.value() as f32 * average_scale(&ts);
+
+            Some(Paint::radial_gradient_stops(cx as f32, cy as f32, 0.0, radius, &stops))
+        }
+        _ => None,
+    }
+}
+
+fn convert_color(color: usvg::Color, opacity: f64) -> Color {
+    let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    Color::rgba(color.red, color.green, color.blue, alpha)
+}
+
+fn group_opacity(node: &usvg::Node) -> f64 {
+    node.ancestors()
+        .map(|ancestor| match *ancestor.borrow() {
+            NodeKind::Group(ref group) => group.opacity.value(),
+            _ => 1.0,
+        })
+        .product()
+}
+
+fn average_scale(transform: &usvg::Transform) -> f32 {
+    let (sx, sy) = transform.get_scale();
+
+    (sx * sy).abs().sqrt() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Picture;
+    use crate::FillRule;
+
+    #[test]
+    fn reversed_contours_are_holes() {
+        // An icon-style donut, the inner square is wound the other way round
+        let data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <path d="M0 0 H100 V100 H0 Z M25 25 V75 H75 V25 Z M40 40 H60 V60 H40 Z"/>
+        </svg>"#;
+
+        let picture = Picture::from_data(data).unwrap();
+        let path = &picture.shapes[0].path;
+
+        // The dot in the middle winds like the outer square and stays filled
+        assert_eq!(path.area(FillRule::NonZero), 10000.0 - 2500.0 + 400.0);
+    }
+}
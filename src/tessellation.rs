@@ -0,0 +1,397 @@
+use std::hash::{
+    Hash,
+    Hasher,
+};
+
+use fnv::{
+    FnvBuildHasher,
+    FnvHasher,
+};
+use lru::LruCache;
+
+use crate::{
+    geometry::Bounds,
+    path::PathCache,
+    Canvas,
+    DrawMode,
+    LineJoin,
+    Paint,
+    Path,
+    Renderer,
+    Transform2D,
+};
+
+// Scales within one bucket share their tessellation. The expanded geometry is tessellated at the
+// scale in the middle of the bucket, a drawn scale that differs from it by up to 1/16 of an octave
+// changes the width of the anti-aliased fringe by about 4%.
+const SCALE_BUCKETS_PER_OCTAVE: f32 = 8.0;
+
+// Relative tolerance for deciding that a transform is a rotation with uniform scale
+const SIMILARITY_TOLERANCE: f32 = 1e-4;
+
+// Batches smaller than this per thread are tessellated on the calling thread, spawning threads for
+// them costs more than it saves
+#[cfg(feature = "parallel")]
+const MIN_PATHS_PER_THREAD: usize = 64;
+
+// The canvas state a path is flattened and expanded with, captured before tessellation so that it
+// can run away from the canvas
+#[derive(Copy, Clone)]
+pub(crate) struct Tessellation {
+    pub(crate) mode: DrawMode,
+    // The paint adjusted for the transform and global alpha, as it is used for the GPU uniforms
+    pub(crate) paint: Paint,
+    pub(crate) transform: Transform2D,
+    pub(crate) snap_offset: Option<f32>,
+    pub(crate) tess_tol: f32,
+    pub(crate) dist_tol: f32,
+    pub(crate) fringe_width: f32,
+    // The part of the render target the scissor lets through
+    pub(crate) clip_bounds: Bounds,
+}
+
+impl Tessellation {
+    // Flattens and expands the path into its cache. Returns false, without expanding it, if the
+    // path is outside of the render target or the scissor.
+    pub(crate) fn run(&self, path: &mut Path) -> bool {
+        // The path cache saves a flattened and transformed version of the path.
+        let path_cache = path.cache_snapped(&self.transform, self.tess_tol, self.dist_tol, self.snap_offset);
+
+        // Early out if path is clipped away
+        if !self.is_visible(&path_cache.bounds) {
+            return false;
+        }
+
+        self.expand(path_cache);
+
+        true
+    }
+
+    // Returns true if the expanded geometry of a path with the flattened bounds can be visible
+    pub(crate) fn is_visible(&self, bounds: &Bounds) -> bool {
+        // Expansion moves the geometry outwards by the fringe, strokes also by their joins and caps.
+        // Miter joins reach the furthest.
+        let margin = match self.mode {
+            DrawMode::Fill => self.fringe_width,
+            DrawMode::Stroke => {
+                self.paint.line_width * 0.5 * self.paint.miter_limit.max(std::f32::consts::SQRT_2) + self.fringe_width
+            }
+        };
+
+        bounds.inflated(margin).overlaps(&self.clip_bounds)
+    }
+
+    // Expands the flattened path, unless it was last expanded with the same parameters. Paths drawn
+    // the same way every frame are then only expanded once.
+    pub(crate) fn expand(&self, path_cache: &mut PathCache) {
+        let key = self.expansion_key();
+
+        if path_cache.expansion_key == Some(key) {
+            return;
+        }
+
+        let paint = &self.paint;
+
+        match self.mode {
+            // expand_fill will fill path_cache.contours[].{stroke, fill} with vertex data for the GPU
+            DrawMode::Fill => path_cache.expand_fill(self.fringe_width, LineJoin::Miter, 2.4),
+            // expand_stroke will fill path_cache.contours[].stroke with vertex data for the GPU
+            DrawMode::Stroke => path_cache.expand_stroke(
+                paint.line_width * 0.5,
+                self.fringe_width,
+                paint.line_cap_start,
+                paint.line_cap_end,
+                paint.line_join,
+                paint.miter_limit,
+                self.tess_tol,
+            ),
+        }
+
+        path_cache.expansion_key = Some(key);
+    }
+
+    // Hash of the parameters expand() depends on
+    fn expansion_key(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+
+        (self.mode as u8).hash(&mut hasher);
+        self.fringe_width.to_bits().hash(&mut hasher);
+
+        if self.mode == DrawMode::Stroke {
+            let paint = &self.paint;
+
+            paint.line_width.to_bits().hash(&mut hasher);
+            (paint.line_cap_start as u8).hash(&mut hasher);
+            (paint.line_cap_end as u8).hash(&mut hasher);
+            (paint.line_join as u8).hash(&mut hasher);
+            paint.miter_limit.to_bits().hash(&mut hasher);
+            self.tess_tol.to_bits().hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Fills or strokes a batch of paths, as if [`Self::fill_path()`] or [`Self::stroke_path()`] was
+    /// called for each of them in order, with the current transform and state.
+    ///
+    /// All paths are flattened and expanded before the first of them is drawn. With the `parallel`
+    /// feature enabled this work is spread over all CPU cores for large batches, which speeds up
+    /// scenes made of thousands of independent shapes such as node graphs or maps. The result is
+    /// identical to drawing the paths one by one, except that the tessellation cache is not used.
+    pub fn draw_paths<'a>(&mut self, paths: impl IntoIterator<Item = (&'a mut Path, Paint, DrawMode)>) {
+        self.frame_budget.check();
+
+        let mut jobs: Vec<(&mut Path, Tessellation, bool)> = paths
+            .into_iter()
+            .filter(|(_, paint, _)| !self.has_dangling_image(paint))
+            .map(|(path, paint, mode)| {
+                let tessellation = match mode {
+                    DrawMode::Fill => self.fill_tessellation(paint, self.state().transform),
+                    DrawMode::Stroke => self.stroke_tessellation(paint, self.state().transform),
+                };
+
+                (path, tessellation, false)
+            })
+            .collect();
+
+        profile!(self, Tessellate, tessellate(&mut jobs));
+
+        for (path, tessellation, visible) in jobs {
+            if !visible {
+                self.culled_draws += 1;
+                continue;
+            }
+
+            match tessellation.mode {
+                DrawMode::Fill => self.emit_fill(&path.cache, &tessellation),
+                DrawMode::Stroke => self.emit_stroke(&path.cache, &tessellation),
+            }
+        }
+    }
+}
+
+fn tessellate(jobs: &mut [(&mut Path, Tessellation, bool)]) {
+    #[cfg(feature = "parallel")]
+    {
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1)
+            .min(jobs.len() / MIN_PATHS_PER_THREAD);
+
+        if threads > 1 {
+            let chunk_size = jobs.len().div_ceil(threads);
+
+            std::thread::scope(|scope| {
+                for chunk in jobs.chunks_mut(chunk_size) {
+                    scope.spawn(move || tessellate_serial(chunk));
+                }
+            });
+
+            return;
+        }
+    }
+
+    tessellate_serial(jobs);
+}
+
+fn tessellate_serial(jobs: &mut [(&mut Path, Tessellation, bool)]) {
+    for (path, tessellation, visible) in jobs {
+        *visible = tessellation.run(path);
+    }
+}
+
+/// Usage statistics of the tessellation cache, see [`Canvas::set_tessellation_cache_capacity()`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TessellationCacheStats {
+    /// Number of draws that reused a cached tessellation.
+    pub hits: usize,
+    /// Number of draws that had to tessellate the path and added it to the cache.
+    pub misses: usize,
+    /// Number of tessellations in the cache.
+    pub entries: usize,
+}
+
+impl TessellationCacheStats {
+    /// Returns the share of cacheable draws that were served from the cache, between 0 and 1.
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+pub(crate) struct TessellationCache {
+    capacity: usize,
+    entries: LruCache<u64, PathCache, FnvBuildHasher>,
+    hits: usize,
+    misses: usize,
+    // Receives the cached geometry mapped to the drawn transform
+    scratch: PathCache,
+}
+
+impl Default for TessellationCache {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            entries: LruCache::unbounded_with_hasher(FnvBuildHasher::default()),
+            hits: 0,
+            misses: 0,
+            scratch: PathCache::default(),
+        }
+    }
+}
+
+impl TessellationCache {
+    // Bytes allocated by the cached tessellations
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let entries: usize = self.entries.iter().map(|(_, cache)| cache.heap_bytes()).sum();
+
+        entries + self.scratch.heap_bytes()
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Sets how many tessellated paths are kept for reuse, 0 disables the cache. Disabled by default.
+    ///
+    /// Filling or stroking a path flattens its curves and expands it into triangles for the current
+    /// transform. A [`Path`] keeps the result of its last draw, but it is recomputed as soon as the
+    /// path is drawn under a different transform, e.g. while a scene is scrolled or its shapes are
+    /// animated. With the cache enabled, the result is tessellated once per path content and scale
+    /// and reused for any translation and rotation of it, also across different [`Path`] instances
+    /// that describe the same shape.
+    ///
+    /// Draws under transforms with skew or non-uniform scaling, draws with pixel snapping enabled
+    /// and [`Self::draw_paths()`] don't use the cache. The least recently used tessellations are
+    /// evicted beyond the capacity.
+    pub fn set_tessellation_cache_capacity(&mut self, capacity: usize) {
+        self.tessellation_cache.capacity = capacity;
+        self.evict_tessellations();
+    }
+
+    /// Returns the statistics of the tessellation cache since it was last cleared.
+    pub fn tessellation_cache_stats(&self) -> TessellationCacheStats {
+        TessellationCacheStats {
+            hits: self.tessellation_cache.hits,
+            misses: self.tessellation_cache.misses,
+            entries: self.tessellation_cache.entries.len(),
+        }
+    }
+
+    /// Removes all cached tessellations and resets the statistics.
+    pub fn clear_tessellation_cache(&mut self) {
+        self.tessellation_cache.entries.clear();
+        self.tessellation_cache.hits = 0;
+        self.tessellation_cache.misses = 0;
+    }
+
+    fn evict_tessellations(&mut self) {
+        let cache = &mut self.tessellation_cache;
+
+        while cache.entries.len() > cache.capacity {
+            cache.entries.pop_lru();
+        }
+    }
+
+    // Draws the path with a cached tessellation, returns false if the draw can't use the cache.
+    // `paint` is the paint as passed to fill_path() or stroke_path().
+    pub(crate) fn draw_cached(&mut self, path: &Path, paint: Paint, tessellation: &Tessellation) -> bool {
+        if self.tessellation_cache.capacity == 0 || tessellation.snap_offset.is_some() || self.deterministic {
+            return false;
+        }
+
+        let transform = tessellation.transform;
+
+        // The tessellation the path keeps for its last transform is cheaper to reuse
+        if path.is_cached_for(&transform, tessellation.tess_tol) {
+            return false;
+        }
+
+        let [a, b, c, d, e, f] = transform.0;
+        let scale = transform.average_scale();
+
+        let is_similarity = scale > 0.0
+            && scale.is_finite()
+            && (a - d).abs() <= scale * SIMILARITY_TOLERANCE
+            && (b + c).abs() <= scale * SIMILARITY_TOLERANCE;
+
+        if !is_similarity {
+            return false;
+        }
+
+        let bucket = (scale.log2() * SCALE_BUCKETS_PER_OCTAVE).round();
+        let bucket_scale = (bucket / SCALE_BUCKETS_PER_OCTAVE).exp2();
+
+        let mut bucket_transform = Transform2D::identity();
+        bucket_transform.scale(bucket_scale, bucket_scale);
+
+        let local = match tessellation.mode {
+            DrawMode::Fill => self.fill_tessellation(paint, bucket_transform),
+            DrawMode::Stroke => self.stroke_tessellation(paint, bucket_transform),
+        };
+
+        let key = {
+            let mut hasher = FnvHasher::default();
+            path.content_hash().hash(&mut hasher);
+            (bucket as i32).hash(&mut hasher);
+            (local.mode == DrawMode::Fill).hash(&mut hasher);
+            local.fringe_width.to_bits().hash(&mut hasher);
+            local.tess_tol.to_bits().hash(&mut hasher);
+            local.dist_tol.to_bits().hash(&mut hasher);
+
+            if local.mode == DrawMode::Stroke {
+                let paint = &local.paint;
+                paint.line_width.to_bits().hash(&mut hasher);
+                (paint.line_cap_start as u8).hash(&mut hasher);
+                (paint.line_cap_end as u8).hash(&mut hasher);
+                (paint.line_join as u8).hash(&mut hasher);
+                paint.miter_limit.to_bits().hash(&mut hasher);
+            }
+
+            hasher.finish()
+        };
+
+        let cache = &mut self.tessellation_cache;
+
+        if cache.entries.contains(&key) {
+            cache.hits += 1;
+        } else {
+            cache.misses += 1;
+
+            let mut path_cache = PathCache::default();
+            path_cache.rebuild(path.verbs(), &bucket_transform, local.tess_tol, local.dist_tol);
+            local.expand(&mut path_cache);
+
+            cache.entries.put(key, path_cache);
+        }
+
+        let mut scratch = std::mem::take(&mut cache.scratch);
+
+        // Maps the geometry tessellated at the bucket scale to the drawn transform
+        let inv_scale = 1.0 / bucket_scale;
+        let mapping = Transform2D([a * inv_scale, b * inv_scale, c * inv_scale, d * inv_scale, e, f]);
+
+        if let Some(path_cache) = cache.entries.get(&key) {
+            path_cache.transform_into(&mapping, &mut scratch);
+        }
+
+        if tessellation.is_visible(&scratch.bounds) {
+            match tessellation.mode {
+                DrawMode::Fill => self.emit_fill(&scratch, tessellation),
+                DrawMode::Stroke => self.emit_stroke(&scratch, tessellation),
+            }
+        } else {
+            self.culled_draws += 1;
+        }
+
+        self.tessellation_cache.scratch = scratch;
+        self.evict_tessellations();
+
+        true
+    }
+}
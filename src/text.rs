@@ -1,18 +1,17 @@
-use std::cell::RefCell;
 use std::ffi::OsStr;
 use std::fs;
-use std::hash::{
-    Hash,
-    Hasher,
-};
 use std::ops::Range;
 use std::path::Path as FilePath;
-use std::rc::Rc;
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+    PoisonError,
+};
 
 use fnv::{
     FnvBuildHasher,
     FnvHashMap,
-    FnvHasher,
 };
 use generational_arena::{
     Arena,
@@ -20,22 +19,27 @@ use generational_arena::{
 };
 use lru::LruCache;
 
-use unicode_bidi::BidiInfo;
-use unicode_segmentation::UnicodeSegmentation;
-
 use crate::{
+    geometry::Bounds,
+    renderer::Vertex,
+    tessellation::Tessellation,
     Canvas,
     Color,
+    DrawMode,
     ErrorKind,
     FillRule,
     ImageFlags,
     ImageId,
     ImageInfo,
+    LineJoin,
     Paint,
     Path,
     PixelFormat,
+    Point,
+    Rect,
     RenderTarget,
     Renderer,
+    Transform2D,
 };
 
 mod atlas;
@@ -45,6 +49,34 @@ mod font;
 use font::Font;
 pub use font::FontMetrics;
 
+mod editing;
+pub use editing::{
+    Preedit,
+    UnderlineStyle,
+};
+
+mod grid;
+pub use grid::{
+    CellStyle,
+    GridCell,
+};
+
+mod icon;
+
+mod rich;
+pub use rich::{
+    PaintOverrides,
+    Span,
+};
+
+mod run;
+pub use run::TextRun;
+
+mod unicode;
+
+mod worker;
+pub(crate) use worker::GlyphWorkers;
+
 // This padding is an empty border around the glyph’s pixels but inside the
 // sampled area (texture coordinates) for the quad in render_atlas().
 const GLYPH_PADDING: u32 = 1;
@@ -52,19 +84,26 @@ const GLYPH_PADDING: u32 = 1;
 // to deal with the linear interpolation of texels at the edge of that area
 // which mixes in the texels just outside of the edge.
 // This manifests as noise around the glyph, outside of the padding.
-const GLYPH_MARGIN: u32 = 1;
+pub(crate) const GLYPH_MARGIN: u32 = 1;
 
-const TEXTURE_SIZE: usize = 512;
-const LRU_CACHE_CAPACITY: usize = 1000;
+pub(crate) const DEFAULT_GLYPH_ATLAS_SIZE: usize = 512;
+pub(crate) const DEFAULT_SHAPING_CACHE_CAPACITY: usize = 1000;
+
+// Interned strings outlive the cache entries they were interned for, they are dropped all at once
+// when there are this many per entry of the shaping cache
+const INTERNED_STRINGS_PER_ENTRY: usize = 4;
 
 /// A font handle.
+///
+/// Fonts are never removed from their text context, so an id stays valid for as long as the
+/// context.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FontId(Index);
 
 /// Text baseline vertical alignment:
 /// `Top`, `Middle`, `Alphabetic` (default), `Bottom`.
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
-#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Baseline {
     /// The text baseline is the top of the em square.
     Top,
@@ -84,7 +123,7 @@ impl Default for Baseline {
 
 /// Text horizontal alignment: `Left` (default), `Center`, `Right`.
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
-#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Align {
     /// The text is left-aligned.
     Left,
@@ -118,6 +157,7 @@ pub(crate) struct RenderedGlyphId {
     font_id: FontId,
     size: u32,
     line_width: u32,
+    stroke_style: StrokeStyle,
     render_mode: RenderMode,
     subpixel_location: u8,
 }
@@ -129,12 +169,50 @@ impl RenderedGlyphId {
             font_id,
             size: (paint.font_size * 10.0).trunc() as u32,
             line_width: (paint.line_width * 10.0).trunc() as u32,
+            stroke_style: StrokeStyle::new(paint, mode),
             render_mode: mode,
             subpixel_location,
         }
     }
 }
 
+// The joins, caps and miter limit glyph outlines are stroked with, all the same for filled glyphs
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub(crate) struct StrokeStyle {
+    line_join: u8,
+    line_caps: (u8, u8),
+    miter_limit: u32,
+}
+
+impl StrokeStyle {
+    pub(crate) fn new(paint: &Paint, mode: RenderMode) -> Self {
+        if mode != RenderMode::Stroke {
+            return Self::default();
+        }
+
+        Self {
+            line_join: paint.line_join as u8,
+            line_caps: (paint.line_cap_start as u8, paint.line_cap_end as u8),
+            miter_limit: (paint.miter_limit * 10.0).trunc() as u32,
+        }
+    }
+}
+
+// How far the stroke of a glyph reaches out of its outline, in pixels. Miter joins reach out up
+// to the miter limit times half of the line width on sharp corners.
+fn stroke_extent(paint: &Paint, mode: RenderMode) -> f32 {
+    if mode != RenderMode::Stroke {
+        return 0.0;
+    }
+
+    let reach = match paint.line_join {
+        LineJoin::Miter => paint.miter_limit.max(1.0),
+        _ => 1.0,
+    };
+
+    (paint.line_width / 2.0 * reach).ceil()
+}
+
 #[derive(Copy, Clone, Debug)]
 pub(crate) struct RenderedGlyph {
     texture_index: usize,
@@ -144,6 +222,15 @@ pub(crate) struct RenderedGlyph {
     atlas_x: u32,
     atlas_y: u32,
     padding: u32,
+    // Value of the canvas flush counter when the glyph was last drawn
+    pub(crate) last_used: u64,
+}
+
+impl RenderedGlyph {
+    // Returns the area the glyph takes up in its atlas, including the margin
+    pub(crate) fn packed_area(&self) -> usize {
+        ((self.width + 2 * GLYPH_MARGIN) * (self.height + 2 * GLYPH_MARGIN)) as usize
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -173,21 +260,18 @@ struct ShapedWord {
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 struct ShapingId {
     size: u32,
-    word_hash: u64,
+    // Interned with TextContextImpl::intern(), equal ids mean equal strings
+    text: u32,
+    max_width: Option<i32>,
     font_ids: [Option<FontId>; 8],
 }
 
 impl ShapingId {
-    fn new(paint: &Paint, word: &str, max_width: Option<f32>) -> Self {
-        let mut hasher = FnvHasher::default();
-        word.hash(&mut hasher);
-        if let Some(max_width) = max_width {
-            (max_width.trunc() as i32).hash(&mut hasher);
-        }
-
+    fn new(paint: &Paint, text: u32, max_width: Option<f32>) -> Self {
         Self {
             size: (paint.font_size * 10.0).trunc() as u32,
-            word_hash: hasher.finish(),
+            text,
+            max_width: max_width.map(|max_width| max_width.trunc() as i32),
             font_ids: paint.font_ids,
         }
     }
@@ -197,7 +281,7 @@ type ShapedWordsCache<H> = LruCache<ShapingId, Result<ShapedWord, ErrorKind>, H>
 type ShapingRunCache<H> = LruCache<ShapingId, TextMetrics, H>;
 
 pub(crate) struct FontTexture {
-    atlas: Atlas,
+    pub(crate) atlas: Atlas,
     pub(crate) image_id: ImageId,
 }
 
@@ -216,8 +300,11 @@ pub(crate) struct FontTexture {
 /// parameter. If you need measurements that take a [`crate::Canvas`]'s transform or dpi into
 /// account (see [`crate::Canvas::set_size()`]), you need to use the measurement functions
 /// on the canvas.
+///
+/// Clones share their fonts and shaping caches. A clone can be sent to another thread, e.g. to lay
+/// out text off the thread that draws, each call holds a lock on the shared state while it runs.
 #[derive(Clone)]
-pub struct TextContext(pub(crate) Rc<RefCell<TextContextImpl>>);
+pub struct TextContext(Arc<Mutex<TextContextImpl>>);
 
 impl Default for TextContext {
     fn default() -> Self {
@@ -226,34 +313,40 @@ impl Default for TextContext {
 }
 
 impl TextContext {
+    // A panic while the context was locked leaves its fonts and caches usable, so the lock isn't
+    // poisoned for the other users
+    pub(crate) fn lock(&self) -> MutexGuard<'_, TextContextImpl> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Registers all .ttf files from a directory with this text context. If successful, the
     /// font ids of all registered fonts are returned.
     pub fn add_font_dir<T: AsRef<FilePath>>(&self, path: T) -> Result<Vec<FontId>, ErrorKind> {
-        self.0.as_ref().borrow_mut().add_font_dir(path)
+        self.lock().add_font_dir(path)
     }
 
     /// Registers the .ttf file from the specified path with this text context. If successful,
     /// the font id is returned.
     pub fn add_font_file<T: AsRef<FilePath>>(&self, path: T) -> Result<FontId, ErrorKind> {
-        self.0.as_ref().borrow_mut().add_font_file(path)
+        self.lock().add_font_file(path)
     }
 
     /// Registers the in-memory representation of a TrueType font pointed to by the data
     /// parameter with this text context. If successful, the font id is returned.
     pub fn add_font_mem(&self, data: &[u8]) -> Result<FontId, ErrorKind> {
-        self.0.as_ref().borrow_mut().add_font_mem(data)
+        self.lock().add_font_mem(data)
     }
 
     /// Returns information on how the provided text will be drawn with the specified paint.
     pub fn measure_text<S: AsRef<str>>(&self, x: f32, y: f32, text: S, paint: Paint) -> Result<TextMetrics, ErrorKind> {
-        self.0.as_ref().borrow_mut().measure_text(x, y, text, paint)
+        self.lock().measure_text(x, y, text, paint)
     }
 
     /// Returns the maximum index-th byte of text that will fit inside max_width.
     ///
     /// The retuned index will always lie at the start and/or end of a UTF-8 code point sequence or at the start or end of the text
     pub fn break_text<S: AsRef<str>>(&self, max_width: f32, text: S, paint: Paint) -> Result<usize, ErrorKind> {
-        self.0.as_ref().borrow_mut().break_text(max_width, text, paint)
+        self.lock().break_text(max_width, text, paint)
     }
 
     /// Returnes a list of ranges representing each line of text that will fit inside max_width
@@ -263,12 +356,25 @@ impl TextContext {
         text: S,
         paint: Paint,
     ) -> Result<Vec<Range<usize>>, ErrorKind> {
-        self.0.as_ref().borrow_mut().break_text_vec(max_width, text, paint)
+        self.lock().break_text_vec(max_width, text, paint)
+    }
+
+    /// Breaks text into lines that fit inside max_width like [`Self::break_text_vec()`], with the
+    /// width of each line and how it ends, see [`TextLine`].
+    ///
+    /// Lines also break at newlines, which are not part of the ranges.
+    pub fn break_text_lines<S: AsRef<str>>(
+        &self,
+        max_width: f32,
+        text: S,
+        paint: Paint,
+    ) -> Result<Vec<TextLine>, ErrorKind> {
+        self.lock().break_text_lines(max_width, text, paint)
     }
 
     /// Returns font metrics for a particular Paint.
     pub fn measure_font(&self, paint: Paint) -> Result<FontMetrics, ErrorKind> {
-        self.0.as_ref().borrow_mut().measure_font(paint)
+        self.lock().measure_font(paint)
     }
 }
 
@@ -276,6 +382,9 @@ pub(crate) struct TextContextImpl {
     fonts: Arena<Font>,
     shaping_run_cache: ShapingRunCache<FnvBuildHasher>,
     shaped_words_cache: ShapedWordsCache<FnvBuildHasher>,
+    interned: FnvHashMap<Box<str>, u32>,
+    // The glyph each font maps an icon character to, see Canvas::fill_icon()
+    icon_glyphs: FnvHashMap<(FontId, char), Option<u16>>,
 }
 
 impl Default for TextContextImpl {
@@ -285,8 +394,10 @@ impl Default for TextContextImpl {
 
         Self {
             fonts: Default::default(),
-            shaping_run_cache: LruCache::with_hasher(LRU_CACHE_CAPACITY, fnv_run),
-            shaped_words_cache: LruCache::with_hasher(LRU_CACHE_CAPACITY, fnv_words),
+            shaping_run_cache: LruCache::with_hasher(DEFAULT_SHAPING_CACHE_CAPACITY, fnv_run),
+            shaped_words_cache: LruCache::with_hasher(DEFAULT_SHAPING_CACHE_CAPACITY, fnv_words),
+            interned: Default::default(),
+            icon_glyphs: Default::default(),
         }
     }
 }
@@ -335,6 +446,7 @@ impl TextContextImpl {
         self.fonts.get_mut(id.0)
     }
 
+    #[cfg_attr(not(feature = "text"), allow(dead_code))]
     pub fn find_font<F, T>(&mut self, _text: &str, paint: &Paint, mut callback: F) -> Result<T, ErrorKind>
     where
         F: FnMut((FontId, &mut Font)) -> (bool, T),
@@ -376,6 +488,51 @@ impl TextContextImpl {
         self.shaped_words_cache.clear();
     }
 
+    pub(crate) fn clear_shaping_caches(&mut self) {
+        self.interned.clear();
+        self.icon_glyphs.clear();
+        self.shaping_run_cache.clear();
+        self.shaped_words_cache.clear();
+    }
+
+    pub(crate) fn shaping_cache_capacity(&self) -> usize {
+        self.shaping_run_cache.cap()
+    }
+
+    // Both caches hold at least one entry, a result is looked up again right after it is added
+    pub(crate) fn set_shaping_cache_capacity(&mut self, capacity: usize) {
+        self.shaping_run_cache.resize(capacity.max(1));
+        self.shaped_words_cache.resize(capacity.max(1));
+    }
+
+    // Number of shaped runs, shaped words and interned strings
+    pub(crate) fn shaping_cache_len(&self) -> (usize, usize, usize) {
+        (
+            self.shaping_run_cache.len(),
+            self.shaped_words_cache.len(),
+            self.interned.len(),
+        )
+    }
+
+    // Returns the id of the string, only strings that weren't seen before are allocated
+    fn intern(&mut self, text: &str) -> u32 {
+        if let Some(id) = self.interned.get(text) {
+            return *id;
+        }
+
+        let id = self.interned.len() as u32;
+        self.interned.insert(text.into(), id);
+        id
+    }
+
+    // Forgets the interned strings once there are too many, together with the shaping results
+    // keyed by them. Must not be called while ids are in use.
+    fn trim_interned(&mut self) {
+        if self.interned.len() > self.shaping_run_cache.cap() * INTERNED_STRINGS_PER_ENTRY {
+            self.clear_shaping_caches();
+        }
+    }
+
     pub fn measure_text<S: AsRef<str>>(
         &mut self,
         x: f32,
@@ -386,6 +543,38 @@ impl TextContextImpl {
         Ok(shape(x, y, self, &paint, text.as_ref(), None)?)
     }
 
+    // Returns the width of the text from the advances of its characters, or None if the text needs
+    // full shaping: it contains characters beyond U+00FF or control characters, or a word no font
+    // covers. Words are given to the first font that covers them, like shape_word() does.
+    pub(crate) fn measure_text_fast(&mut self, text: &str, paint: &Paint) -> Option<f32> {
+        let simple = |c: char| matches!(c, ' '..='~' | '\u{a0}'..='\u{ff}');
+
+        if !text.chars().all(simple) {
+            return None;
+        }
+
+        let mut width = 0.0;
+
+        for word in unicode::words(text) {
+            let paint_fonts = paint.font_ids.iter().map_while(|id| *id);
+            let candidates: Vec<FontId> = paint_fonts.chain(self.fonts.iter().map(|(id, _)| FontId(id))).collect();
+
+            let word_width = candidates.into_iter().find_map(|font_id| {
+                let font = self.fonts.get_mut(font_id.0)?;
+                let scale = font.scale(paint.font_size);
+
+                word.chars().try_fold(0.0, |width, c| {
+                    let advance = font.latin_advance(c)?;
+                    Some(width + advance as f32 * scale + paint.letter_spacing)
+                })
+            })?;
+
+            width += word_width;
+        }
+
+        Some(width)
+    }
+
     pub fn break_text<S: AsRef<str>>(&mut self, max_width: f32, text: S, paint: Paint) -> Result<usize, ErrorKind> {
         let layout = shape(0.0, 0.0, self, &paint, text.as_ref(), Some(max_width))?;
 
@@ -420,6 +609,66 @@ impl TextContextImpl {
         Ok(res)
     }
 
+    pub fn break_text_lines<S: AsRef<str>>(
+        &mut self,
+        max_width: f32,
+        text: S,
+        paint: Paint,
+    ) -> Result<Vec<TextLine>, ErrorKind> {
+        let text = text.as_ref();
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+
+        for paragraph in text.split('\n') {
+            let end = start + paragraph.len();
+            let mut line_start = start;
+
+            loop {
+                let rest = &text[line_start..end];
+                let mut index = self.break_text(max_width, rest, paint)?;
+
+                // A word wider than max_width on its own gets a line by itself
+                if index == 0 {
+                    index = unicode::words(rest).next().map_or(rest.len(), str::len);
+                }
+
+                // The whitespace at a soft break stays on the line it ends
+                let after = &rest[index..];
+                let line_end = line_start + index + after.len() - after.trim_start().len();
+
+                let line = &text[line_start..line_end];
+                let width = self.measure_text(0.0, 0.0, line.trim_end(), paint)?.width();
+                let advance = self.measure_text(0.0, 0.0, line, paint)?.width();
+
+                let line_break = if line_end < end {
+                    LineBreak::Soft
+                } else if end < text.len() {
+                    LineBreak::Newline
+                } else {
+                    LineBreak::End
+                };
+
+                lines.push(TextLine {
+                    range: line_start..line_end,
+                    width,
+                    whitespace_width: (advance - width).max(0.0),
+                    line_break,
+                });
+
+                line_start = line_end;
+
+                if line_start >= end {
+                    break;
+                }
+            }
+
+            start = end + 1;
+        }
+
+        Ok(lines)
+    }
+
     pub fn measure_font(&mut self, paint: Paint) -> Result<FontMetrics, ErrorKind> {
         if let Some(Some(id)) = paint.font_ids.get(0) {
             if let Some(font) = self.font(*id) {
@@ -454,6 +703,12 @@ impl TextMetrics {
             glyph.y *= scale;
             glyph.width *= scale;
             glyph.height *= scale;
+            glyph.advance_x *= scale;
+            glyph.advance_y *= scale;
+            glyph.offset_x *= scale;
+            glyph.offset_y *= scale;
+            glyph.bearing_x *= scale;
+            glyph.bearing_y *= scale;
         }
     }
 
@@ -468,6 +723,114 @@ impl TextMetrics {
     }
 }
 
+/// How a line returned by [`Canvas::break_text_lines()`](crate::Canvas::break_text_lines) ends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineBreak {
+    /// The text wrapped because the next word didn't fit.
+    Soft,
+    /// The line ends at a newline.
+    Newline,
+    /// The line is the last one of the text.
+    End,
+}
+
+/// A line of text broken by [`Canvas::break_text_lines()`](crate::Canvas::break_text_lines).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLine {
+    /// Byte range of the line in the text, including the whitespace at its end but not the
+    /// newline.
+    pub range: Range<usize>,
+    /// Advance width of the line without the whitespace at its end, the width to align and justify.
+    pub width: f32,
+    /// Advance width of the whitespace at the end of the line.
+    pub whitespace_width: f32,
+    pub line_break: LineBreak,
+}
+
+/// Text drawn on the canvas while text recording is enabled with [`Canvas::set_text_recording()`](crate::Canvas::set_text_recording).
+///
+/// Meant for accessibility bridges such as AccessKit that need to know what text is visible and where.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRecord {
+    /// The drawn string.
+    pub text: String,
+    /// Bounding box of the drawn glyphs in the coordinate system the text was drawn in.
+    pub local_bounds: Rect,
+    /// The canvas transform that was active when the text was drawn.
+    pub transform: Transform2D,
+    /// Axis-aligned bounding box of the transformed glyphs in canvas coordinates.
+    pub bounds: Rect,
+}
+
+impl TextRecord {
+    pub(crate) fn new(text: &str, metrics: &TextMetrics, transform: Transform2D) -> Self {
+        let local_bounds = Rect::new(metrics.x, metrics.y, metrics.width(), metrics.height());
+
+        Self {
+            text: text.to_owned(),
+            local_bounds,
+            transform,
+            bounds: quad_bounds(&transformed_quad(&local_bounds, &transform)),
+        }
+    }
+}
+
+/// Metrics of text measured under the current transform, see
+/// [`Canvas::measure_text_transformed()`](crate::Canvas::measure_text_transformed).
+#[derive(Clone, Debug)]
+pub struct TransformedTextMetrics {
+    /// The metrics in the coordinate system the text is drawn in, as [`Canvas::measure_text()`](crate::Canvas::measure_text) returns them.
+    pub metrics: TextMetrics,
+    /// Corners of the box of the glyphs in canvas coordinates, starting at the top left corner of
+    /// the text and going clockwise in the coordinate system of the text.
+    pub quad: [Point; 4],
+    /// Axis-aligned bounding box of the quad in canvas coordinates.
+    pub bounds: Rect,
+}
+
+impl TransformedTextMetrics {
+    /// Returns true if the point in canvas coordinates lies inside the quad, e.g. to hit test a
+    /// rotated label with the mouse position.
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        let side = |a: Point, b: Point| (b.x - a.x) * (y - a.y) - (x - a.x) * (b.y - a.y);
+
+        let sides: Vec<f32> = (0..4).map(|i| side(self.quad[i], self.quad[(i + 1) % 4])).collect();
+
+        // Mirroring transforms reverse the direction of the corners
+        sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+    }
+}
+
+// The corners of the rectangle mapped by the transform, clockwise from its top left corner
+pub(crate) fn transformed_quad(rect: &Rect, transform: &Transform2D) -> [Point; 4] {
+    let corner = |x: f32, y: f32| Point::from(transform.transform_point(x, y));
+
+    [
+        corner(rect.x, rect.y),
+        corner(rect.x + rect.w, rect.y),
+        corner(rect.x + rect.w, rect.y + rect.h),
+        corner(rect.x, rect.y + rect.h),
+    ]
+}
+
+pub(crate) fn quad_bounds(quad: &[Point; 4]) -> Rect {
+    let mut bounds = Bounds::default();
+
+    for point in quad {
+        bounds.minx = bounds.minx.min(point.x);
+        bounds.miny = bounds.miny.min(point.y);
+        bounds.maxx = bounds.maxx.max(point.x);
+        bounds.maxy = bounds.maxy.max(point.y);
+    }
+
+    Rect::new(
+        bounds.minx,
+        bounds.miny,
+        bounds.maxx - bounds.minx,
+        bounds.maxy - bounds.miny,
+    )
+}
+
 // Shaper
 
 pub(crate) fn shape(
@@ -478,7 +841,10 @@ pub(crate) fn shape(
     text: &str,
     max_width: Option<f32>,
 ) -> Result<TextMetrics, ErrorKind> {
-    let id = ShapingId::new(paint, text, max_width);
+    context.trim_interned();
+
+    let text_id = context.intern(text);
+    let id = ShapingId::new(paint, text_id, max_width);
 
     if !context.shaping_run_cache.contains(&id) {
         let metrics = shape_run(context, paint, text, max_width)?;
@@ -509,86 +875,81 @@ fn shape_run(
         final_byte_index: 0,
     };
 
-    let bidi_info = BidiInfo::new(&text, Some(unicode_bidi::Level::ltr()));
+    for (run, rtl) in unicode::visual_runs(text) {
+        let sub_text = &text[run.clone()];
 
-    if let Some(paragraph) = bidi_info.paragraphs.get(0) {
-        let line = paragraph.range.clone();
+        if sub_text.is_empty() {
+            continue;
+        }
 
-        let (levels, runs) = bidi_info.visual_runs(&paragraph, line);
+        let run_start = result.glyphs.len();
+        let mut word_lengths = Vec::new();
+        let mut word_break_reached = false;
+        let mut byte_index = run.start;
 
-        for run in runs.iter() {
-            let sub_text = &text[run.clone()];
+        for word in unicode::words(sub_text) {
+            let word_id = context.intern(word);
+            let id = ShapingId::new(paint, word_id, max_width);
 
-            if sub_text.is_empty() {
-                continue;
+            if !context.shaped_words_cache.contains(&id) {
+                let word = shape_word(word, rtl, context, paint);
+                context.shaped_words_cache.put(id, word);
             }
 
-            let hb_direction = if levels[run.start].is_rtl() {
-                rustybuzz::Direction::RightToLeft
-            } else {
-                rustybuzz::Direction::LeftToRight
-            };
-
-            let mut words = Vec::new();
-            let mut word_break_reached = false;
-            let mut byte_index = run.start;
-
-            for word in sub_text.split_word_bounds() {
-                let id = ShapingId::new(paint, word, max_width);
-
-                if !context.shaped_words_cache.contains(&id) {
-                    let word = shape_word(word, hb_direction, context, paint);
-                    context.shaped_words_cache.put(id, word);
+            if let Some(Ok(word)) = context.shaped_words_cache.get(&id) {
+                if let Some(max_width) = max_width {
+                    if result.width + word.width >= max_width {
+                        word_break_reached = true;
+                        break;
+                    }
                 }
 
-                if let Some(Ok(word)) = context.shaped_words_cache.get(&id) {
-                    let mut word = word.clone();
+                result.width += word.width;
 
-                    if let Some(max_width) = max_width {
-                        if result.width + word.width >= max_width {
-                            word_break_reached = true;
-                            break;
-                        }
-                    }
+                result.glyphs.extend(word.glyphs.iter().map(|glyph| {
+                    let mut glyph = *glyph;
+                    glyph.byte_index += byte_index;
+                    debug_assert!(text.get(glyph.byte_index..).is_some());
+                    glyph
+                }));
 
-                    result.width += word.width;
-
-                    for glyph in &mut word.glyphs {
-                        glyph.byte_index += byte_index;
-                        debug_assert!(text.get(glyph.byte_index..).is_some());
-                    }
+                word_lengths.push(word.glyphs.len());
+            }
 
-                    words.push(word);
-                }
+            byte_index += word.len();
+        }
 
-                byte_index += word.len();
-            }
+        // Right-to-left runs list their words in reverse order, the glyphs of each word stay in
+        // the order they were shaped in
+        if rtl {
+            let run_glyphs = &mut result.glyphs[run_start..];
+            run_glyphs.reverse();
 
-            if levels[run.start].is_rtl() {
-                words.reverse();
-            }
+            let mut start = 0;
 
-            for word in words {
-                result.glyphs.extend(word.glyphs.clone());
+            for len in word_lengths.iter().rev() {
+                run_glyphs[start..start + len].reverse();
+                start += len;
             }
+        }
 
-            result.final_byte_index = byte_index;
+        result.final_byte_index = byte_index;
 
-            if word_break_reached {
-                break;
-            }
+        if word_break_reached {
+            break;
         }
     }
-
     Ok(result)
 }
 
-fn shape_word(
-    word: &str,
-    hb_direction: rustybuzz::Direction,
-    context: &mut TextContextImpl,
-    paint: &Paint,
-) -> Result<ShapedWord, ErrorKind> {
+#[cfg(feature = "text")]
+fn shape_word(word: &str, rtl: bool, context: &mut TextContextImpl, paint: &Paint) -> Result<ShapedWord, ErrorKind> {
+    let hb_direction = if rtl {
+        rustybuzz::Direction::RightToLeft
+    } else {
+        rustybuzz::Direction::LeftToRight
+    };
+
     // find_font will call the closure with each font matching the provided style
     // until a font capable of shaping the word is found
     context.find_font(&word, paint, |(font_id, font)| {
@@ -659,6 +1020,17 @@ fn shape_word(
     })
 }
 
+// Fonts can't be loaded without the `text` feature, there is nothing to shape with
+#[cfg(not(feature = "text"))]
+fn shape_word(
+    _word: &str,
+    _rtl: bool,
+    _context: &mut TextContextImpl,
+    _paint: &Paint,
+) -> Result<ShapedWord, ErrorKind> {
+    Err(ErrorKind::NoFontFound)
+}
+
 // Calculates the x,y coordinates for each glyph based on their advances. Calculates total width and height of the shaped text run
 fn layout(
     x: f32,
@@ -731,6 +1103,23 @@ pub(crate) struct Quad {
     pub t1: f32,
 }
 
+// Appends two triangles per quad, mapped from scaled text coordinates to canvas coordinates
+pub(crate) fn quad_vertices(quads: &[Quad], transform: Transform2D, invscale: f32, verts: &mut Vec<Vertex>) {
+    for quad in quads {
+        let (p0, p1) = transform.transform_point(quad.x0 * invscale, quad.y0 * invscale);
+        let (p2, p3) = transform.transform_point(quad.x1 * invscale, quad.y0 * invscale);
+        let (p4, p5) = transform.transform_point(quad.x1 * invscale, quad.y1 * invscale);
+        let (p6, p7) = transform.transform_point(quad.x0 * invscale, quad.y1 * invscale);
+
+        verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
+        verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
+        verts.push(Vertex::new(p2, p3, quad.s1, quad.t0));
+        verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
+        verts.push(Vertex::new(p6, p7, quad.s0, quad.t1));
+        verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
+    }
+}
+
 pub(crate) fn render_atlas<T: Renderer>(
     canvas: &mut Canvas<T>,
     text_layout: &TextMetrics,
@@ -739,89 +1128,129 @@ pub(crate) fn render_atlas<T: Renderer>(
 ) -> Result<Vec<DrawCmd>, ErrorKind> {
     let mut cmd_map = FnvHashMap::default();
 
-    let line_width_offset = if mode == RenderMode::Stroke {
-        (paint.line_width / 2.0).ceil()
-    } else {
-        0.0
-    };
+    let line_width_offset = stroke_extent(paint, mode);
 
-    let initial_render_target = canvas.current_render_target;
+    canvas.receive_glyphs();
 
     for glyph in &text_layout.glyphs {
-        let subpixel_location = crate::geometry::quantize(glyph.x.fract(), 0.1) * 10.0;
+        if let Some((texture_index, image_id, quad)) = atlas_quad(canvas, glyph, paint, mode, line_width_offset)? {
+            let cmd = cmd_map.entry(texture_index).or_insert_with(|| DrawCmd {
+                image_id,
+                quads: Vec::new(),
+            });
 
-        let id = RenderedGlyphId::new(glyph.codepoint, glyph.font_id, paint, mode, subpixel_location as u8);
+            cmd.quads.push(quad);
+        }
+    }
 
-        if !canvas.rendered_glyphs.contains_key(&id) {
-            let glyph = render_glyph(canvas, paint, mode, &glyph)?;
+    Ok(cmd_map.drain().map(|(_, cmd)| cmd).collect())
+}
 
-            canvas.rendered_glyphs.insert(id, glyph);
-        }
+// Renders the glyph into one of the atlases unless it is cached already. Returns the index of the
+// atlas texture, its image and the quad that samples the glyph from it.
+fn atlas_quad<T: Renderer>(
+    canvas: &mut Canvas<T>,
+    glyph: &ShapedGlyph,
+    paint: &Paint,
+    mode: RenderMode,
+    line_width_offset: f32,
+) -> Result<Option<(usize, ImageId, Quad)>, ErrorKind> {
+    let subpixel_location = crate::geometry::quantize(glyph.x.fract(), 0.1) * 10.0;
 
-        let rendered = canvas.rendered_glyphs.get(&id).unwrap();
+    let id = RenderedGlyphId::new(glyph.codepoint, glyph.font_id, paint, mode, subpixel_location as u8);
 
-        if let Some(texture) = canvas.glyph_textures.get(rendered.texture_index) {
-            let image_id = texture.image_id;
-            let size = texture.atlas.size();
-            let itw = 1.0 / size.0 as f32;
-            let ith = 1.0 / size.1 as f32;
+    if !canvas.rendered_glyphs.contains_key(&id) {
+        if !canvas.frame_budget.allow_glyph() {
+            return Ok(None);
+        }
 
-            let cmd = cmd_map.entry(rendered.texture_index).or_insert_with(|| DrawCmd {
-                image_id,
-                quads: Vec::new(),
-            });
+        // Deterministic frames rasterize their glyphs while drawing
+        let deterministic = canvas.deterministic;
+        let workers = canvas.glyph_workers.as_mut().filter(|_| !deterministic);
 
-            let mut q = Quad::default();
+        if let Some(workers) = workers {
+            workers.skip();
 
-            q.x0 = glyph.x.trunc() - line_width_offset - GLYPH_PADDING as f32;
-            q.y0 = (glyph.y + glyph.bearing_y).round()
-                - rendered.bearing_y as f32
-                - line_width_offset
-                - GLYPH_PADDING as f32;
-            q.x1 = q.x0 + rendered.width as f32;
-            q.y1 = q.y0 + rendered.height as f32;
+            if !workers.is_pending(&id) {
+                let job = prepare_glyph(canvas, id, paint, mode, glyph)?;
 
-            q.s0 = rendered.atlas_x as f32 * itw;
-            q.t0 = rendered.atlas_y as f32 * ith;
-            q.s1 = (rendered.atlas_x + rendered.width) as f32 * itw;
-            q.t1 = (rendered.atlas_y + rendered.height) as f32 * ith;
+                if let Some(workers) = &mut canvas.glyph_workers {
+                    workers.submit(job);
+                }
+            }
 
-            cmd.quads.push(q);
+            return Ok(None);
         }
+
+        let glyph = render_glyph(canvas, id, paint, mode, glyph)?;
+
+        canvas.rendered_glyphs.insert(id, glyph);
     }
 
-    canvas.set_render_target(initial_render_target);
+    let rendered = match canvas.rendered_glyphs.get_mut(&id) {
+        Some(rendered) => rendered,
+        None => return Ok(None),
+    };
+    rendered.last_used = canvas.flushes;
+
+    let texture = match canvas.glyph_textures.get(rendered.texture_index) {
+        Some(texture) => texture,
+        None => return Ok(None),
+    };
+
+    let size = texture.atlas.size();
+    let itw = 1.0 / size.0 as f32;
+    let ith = 1.0 / size.1 as f32;
 
-    Ok(cmd_map.drain().map(|(_, cmd)| cmd).collect())
+    let mut q = Quad::default();
+
+    q.x0 = glyph.x.trunc() - line_width_offset - GLYPH_PADDING as f32;
+    q.y0 = (glyph.y + glyph.bearing_y).round() - rendered.bearing_y as f32 - line_width_offset - GLYPH_PADDING as f32;
+    q.x1 = q.x0 + rendered.width as f32;
+    q.y1 = q.y0 + rendered.height as f32;
+
+    q.s0 = rendered.atlas_x as f32 * itw;
+    q.t0 = rendered.atlas_y as f32 * ith;
+    q.s1 = (rendered.atlas_x + rendered.width) as f32 * itw;
+    q.t1 = (rendered.atlas_y + rendered.height) as f32 * ith;
+
+    Ok(Some((rendered.texture_index, texture.image_id, q)))
 }
 
 fn render_glyph<T: Renderer>(
     canvas: &mut Canvas<T>,
+    id: RenderedGlyphId,
     paint: &Paint,
     mode: RenderMode,
     glyph: &ShapedGlyph,
 ) -> Result<RenderedGlyph, ErrorKind> {
-    let padding = GLYPH_PADDING + GLYPH_MARGIN;
+    let mut job = prepare_glyph(canvas, id, paint, mode, glyph)?;
+    profile!(canvas, Tessellate, job.tessellate());
 
-    let line_width = if mode == RenderMode::Stroke {
-        paint.line_width
-    } else {
-        0.0
-    };
+    Ok(finish_glyph(canvas, job))
+}
+
+// Reserves the place of the glyph in an atlas and captures how each sample of its outline is
+// tessellated, without tessellating it yet
+pub(crate) fn prepare_glyph<T: Renderer>(
+    canvas: &mut Canvas<T>,
+    id: RenderedGlyphId,
+    paint: &Paint,
+    mode: RenderMode,
+    glyph: &ShapedGlyph,
+) -> Result<GlyphJob, ErrorKind> {
+    let padding = GLYPH_PADDING + GLYPH_MARGIN;
 
-    let line_width_offset = (line_width / 2.0).ceil();
+    let line_width_offset = stroke_extent(paint, mode);
 
     let width = glyph.width.ceil() as u32 + (line_width_offset * 2.0) as u32 + padding * 2;
     let height = glyph.height.ceil() as u32 + (line_width_offset * 2.0) as u32 + padding * 2;
 
     let (dst_index, dst_image_id, (dst_x, dst_y)) = find_texture_or_alloc(canvas, width as usize, height as usize)?;
+    let atlas_height = canvas.glyph_textures[dst_index].atlas.size().1;
 
-    // render glyph to image
-    canvas.save();
-    canvas.reset();
-
-    let (mut path, scale) = {
-        let mut text_context = canvas.text_context.as_ref().borrow_mut();
+    let (path, scale) = {
+        let mut text_context = canvas.text_context.lock();
         let font = text_context.font_mut(glyph.font_id).ok_or(ErrorKind::NoFontFound)?;
         let scale = font.scale(paint.font_size);
 
@@ -837,18 +1266,7 @@ fn render_glyph<T: Renderer>(
     let rendered_bearing_y = glyph.bearing_y.round();
     let x_quant = crate::geometry::quantize(glyph.x.fract(), 0.1);
     let x = dst_x as f32 - glyph.bearing_x + line_width_offset + padding as f32 + x_quant;
-    let y = TEXTURE_SIZE as f32 - dst_y as f32 - rendered_bearing_y - line_width_offset - padding as f32;
-
-    canvas.translate(x, y);
-
-    canvas.set_render_target(RenderTarget::Image(dst_image_id));
-    canvas.clear_rect(
-        dst_x as u32,
-        TEXTURE_SIZE as u32 - dst_y as u32 - height as u32,
-        width as u32,
-        height as u32,
-        Color::black(),
-    );
+    let y = atlas_height as f32 - dst_y as f32 - rendered_bearing_y - line_width_offset - padding as f32;
 
     let factor = 1.0 / 8.0;
 
@@ -856,12 +1274,15 @@ fn render_glyph<T: Renderer>(
     mask_paint.set_fill_rule(FillRule::EvenOdd);
     mask_paint.set_anti_alias(false);
 
+    // The outline is stroked like the paint strokes paths, the way large text is drawn
     if mode == RenderMode::Stroke {
-        mask_paint.line_width = line_width / scale;
+        mask_paint.line_width = paint.line_width / scale;
+        mask_paint.line_join = paint.line_join;
+        mask_paint.line_cap_start = paint.line_cap_start;
+        mask_paint.line_cap_end = paint.line_cap_end;
+        mask_paint.miter_limit = paint.miter_limit;
     }
 
-    canvas.global_composite_blend_func(crate::BlendFactor::SrcAlpha, crate::BlendFactor::One);
-
     // 4x
     // let points = [
     //     (-3.0/8.0, 1.0/8.0),
@@ -882,36 +1303,120 @@ fn render_glyph<T: Renderer>(
         (-5.0 / 16.0, 3.0 / 16.0),
     ];
 
-    for point in &points {
+    let samples = canvas.with_glyph_pass(|canvas| {
         canvas.save();
-        canvas.translate(point.0, point.1);
+        canvas.reset();
 
-        canvas.scale(scale, scale);
+        canvas.translate(x, y);
 
-        if mode == RenderMode::Stroke {
-            canvas.stroke_path(&mut path, mask_paint);
-        } else {
-            canvas.fill_path(&mut path, mask_paint);
-        }
+        // The tessellations are clipped to the atlas
+        canvas.set_render_target(RenderTarget::Image(dst_image_id));
+
+        let samples = points
+            .iter()
+            .map(|point| {
+                canvas.save();
+                canvas.translate(point.0, point.1);
+
+                canvas.scale(scale, scale);
+
+                let transform = canvas.state().transform;
+
+                let tessellation = if mode == RenderMode::Stroke {
+                    canvas.stroke_tessellation(mask_paint, transform)
+                } else {
+                    canvas.fill_tessellation(mask_paint, transform)
+                };
+
+                canvas.restore();
+
+                (path.clone(), tessellation, false)
+            })
+            .collect();
 
         canvas.restore();
-    }
 
-    canvas.restore();
+        samples
+    });
 
-    Ok(RenderedGlyph {
-        width: width - 2 * GLYPH_MARGIN,
-        height: height - 2 * GLYPH_MARGIN,
-        bearing_y: rendered_bearing_y as i32,
-        atlas_x: dst_x as u32 + GLYPH_MARGIN,
-        atlas_y: dst_y as u32 + GLYPH_MARGIN,
-        texture_index: dst_index,
-        padding: padding - GLYPH_MARGIN,
+    Ok(GlyphJob {
+        id,
+        atlas_generation: canvas.glyph_atlas_generation,
+        image_id: dst_image_id,
+        clear_rect: (
+            dst_x as u32,
+            atlas_height as u32 - dst_y as u32 - height as u32,
+            width,
+            height,
+        ),
+        samples,
+        rendered: RenderedGlyph {
+            width: width - 2 * GLYPH_MARGIN,
+            height: height - 2 * GLYPH_MARGIN,
+            bearing_y: rendered_bearing_y as i32,
+            atlas_x: dst_x as u32 + GLYPH_MARGIN,
+            atlas_y: dst_y as u32 + GLYPH_MARGIN,
+            texture_index: dst_index,
+            padding: padding - GLYPH_MARGIN,
+            last_used: canvas.flushes,
+        },
     })
 }
 
+// Renders the tessellated samples of the glyph into its place in the atlas
+pub(crate) fn finish_glyph<T: Renderer>(canvas: &mut Canvas<T>, job: GlyphJob) -> RenderedGlyph {
+    canvas.with_glyph_pass(|canvas| {
+        canvas.save();
+        canvas.reset();
+
+        canvas.set_render_target(RenderTarget::Image(job.image_id));
+
+        let (x, y, width, height) = job.clear_rect;
+        canvas.clear_rect(x, y, width, height, Color::black());
+
+        canvas.global_composite_blend_func(crate::BlendFactor::SrcAlpha, crate::BlendFactor::One);
+
+        for (path, tessellation, visible) in &job.samples {
+            if !visible {
+                continue;
+            }
+
+            match tessellation.mode {
+                DrawMode::Fill => canvas.emit_fill(&path.cache, tessellation),
+                DrawMode::Stroke => canvas.emit_stroke(&path.cache, tessellation),
+            }
+        }
+
+        canvas.restore();
+    });
+
+    job.rendered
+}
+
+/// A glyph that is being rendered into an atlas, see [`prepare_glyph()`].
+pub(crate) struct GlyphJob {
+    pub(crate) id: RenderedGlyphId,
+    // The atlases are ignored if they were discarded in the meantime
+    pub(crate) atlas_generation: u64,
+    image_id: ImageId,
+    clear_rect: (u32, u32, u32, u32),
+    // The glyph outline and how it is tessellated for each sample position, and whether the
+    // tessellation is visible
+    samples: Vec<(Path, Tessellation, bool)>,
+    pub(crate) rendered: RenderedGlyph,
+}
+
+impl GlyphJob {
+    // Flattens and expands the glyph outline for each sample, this can run on any thread
+    pub(crate) fn tessellate(&mut self) {
+        for (path, tessellation, visible) in &mut self.samples {
+            *visible = tessellation.run(path);
+        }
+    }
+}
+
 // Returns (texture index, image id, glyph padding box)
-fn find_texture_or_alloc<T: Renderer>(
+pub(crate) fn find_texture_or_alloc<T: Renderer>(
     canvas: &mut Canvas<T>,
     width: usize,
     height: usize,
@@ -927,7 +1432,7 @@ fn find_texture_or_alloc<T: Renderer>(
 
     if texture_search_result.is_none() {
         // All atlases are exausted and a new one must be created
-        let mut atlas = Atlas::new(TEXTURE_SIZE, TEXTURE_SIZE);
+        let mut atlas = Atlas::new(canvas.glyph_atlas_size, canvas.glyph_atlas_size);
 
         let loc = atlas
             .add_rect(width, height)
@@ -944,17 +1449,19 @@ fn find_texture_or_alloc<T: Renderer>(
         if cfg!(debug_assertions) {
             // Fill the texture with red pixels only in debug builds.
             if let Ok(size) = canvas.image_size(image_id) {
-                canvas.save();
-                canvas.reset();
-                canvas.set_render_target(RenderTarget::Image(image_id));
-                canvas.clear_rect(
-                    0,
-                    0,
-                    size.0 as u32,
-                    size.1 as u32,
-                    Color::rgb(255, 0, 0), // Shown as white if using Gray8.
-                );
-                canvas.restore();
+                canvas.with_glyph_pass(|canvas| {
+                    canvas.save();
+                    canvas.reset();
+                    canvas.set_render_target(RenderTarget::Image(image_id));
+                    canvas.clear_rect(
+                        0,
+                        0,
+                        size.0 as u32,
+                        size.1 as u32,
+                        Color::rgb(255, 0, 0), // Shown as white if using Gray8.
+                    );
+                    canvas.restore();
+                });
             }
         }
 
@@ -976,45 +1483,38 @@ pub(crate) fn render_direct<T: Renderer>(
 ) -> Result<(), ErrorKind> {
     let mut paint = *paint;
     paint.set_fill_rule(FillRule::EvenOdd);
-
-    let mut scaled = false;
+    paint.line_width *= invscale;
 
     for glyph in &text_layout.glyphs {
-        let (mut path, scale) = {
-            let mut text_context = canvas.text_context.as_ref().borrow_mut();
+        let mut path = {
+            let mut text_context = canvas.text_context.lock();
             let font = text_context.font_mut(glyph.font_id).ok_or(ErrorKind::NoFontFound)?;
 
-            let scale = font.scale(paint.font_size);
-
-            let path = if let Some(font_glyph) = font.glyph(glyph.codepoint as u16) {
-                font_glyph.path.clone()
+            let scale = font.scale(paint.font_size) * invscale;
+
+            // The outline is moved into the coordinates the text is drawn in instead of drawing it
+            // under a transform of its own, so that gradients and images span the whole text
+            let outline = Transform2D([
+                scale,
+                0.0,
+                0.0,
+                -scale,
+                (glyph.x - glyph.bearing_x) * invscale,
+                (glyph.y + glyph.bearing_y) * invscale,
+            ]);
+
+            if let Some(font_glyph) = font.glyph(glyph.codepoint as u16) {
+                font_glyph.path.transformed(&outline)
             } else {
                 continue;
-            };
-
-            (path, scale)
+            }
         };
 
-        canvas.save();
-
-        if mode == RenderMode::Stroke && !scaled {
-            paint.line_width /= scale;
-            scaled = true;
-        }
-
-        canvas.translate(
-            (glyph.x - glyph.bearing_x) * invscale,
-            (glyph.y + glyph.bearing_y) * invscale,
-        );
-        canvas.scale(scale * invscale, -scale * invscale);
-
         if mode == RenderMode::Stroke {
             canvas.stroke_path(&mut path, paint);
         } else {
             canvas.fill_path(&mut path, paint);
         }
-
-        canvas.restore();
     }
 
     Ok(())
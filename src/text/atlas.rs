@@ -10,6 +10,8 @@ pub struct Atlas {
     width: usize,
     height: usize,
     nodes: Vec<Node>,
+    // Sum of the areas of the packed rectangles
+    used_area: usize,
 }
 
 impl Atlas {
@@ -22,6 +24,7 @@ impl Atlas {
                 y: 0,
                 width: width,
             }],
+            used_area: 0,
         }
     }
 
@@ -29,6 +32,16 @@ impl Atlas {
         (self.width, self.height)
     }
 
+    /// Returns the share of the atlas covered by packed rectangles, between 0 and 1. The rest is
+    /// free or wasted below the skyline.
+    pub fn occupancy(&self) -> f32 {
+        if self.width == 0 || self.height == 0 {
+            return 0.0;
+        }
+
+        self.used_area as f32 / (self.width * self.height) as f32
+    }
+
     pub fn expand(&mut self, width: usize, height: usize) {
         // Insert node for empty space
 
@@ -67,6 +80,7 @@ impl Atlas {
         if let Some(besti) = besti {
             // Perform the actual packing.
             self.add_skyline_level(besti, bestx, besty, rect_width, rect_height);
+            self.used_area += rect_width * rect_height;
             return Some((bestx, besty));
         }
 
@@ -0,0 +1,296 @@
+use std::ops::Range;
+
+use super::{
+    unicode,
+    Baseline,
+    ShapedGlyph,
+    TextMetrics,
+};
+
+use crate::{
+    Canvas,
+    ErrorKind,
+    Paint,
+    Path,
+    Renderer,
+};
+
+// Spans closer than this are merged into one
+const SPAN_MERGE_DISTANCE: f32 = 0.5;
+
+/// Input method composition (preedit) state for [`Canvas::draw_preedit()`].
+///
+/// All ranges and indices are byte offsets into the laid out text, which contains the preedit
+/// string at `range`. Input method events usually report offsets relative to the preedit string,
+/// add `range.start` to them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preedit {
+    /// The composed text.
+    pub range: Range<usize>,
+    /// The clauses the composition is split into. Leave it empty if the input method doesn't
+    /// report clauses.
+    pub clauses: Vec<Range<usize>>,
+    /// Index of the clause that is being converted, it is highlighted.
+    pub target_clause: Option<usize>,
+    /// Position of the caret, if the input method shows one.
+    pub cursor: Option<usize>,
+}
+
+/// How [`Canvas::draw_underlines()`] draws its lines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    Solid,
+    /// Round dots, e.g. for grammar hints.
+    Dotted,
+    /// A wavy line, e.g. for misspelled words.
+    Squiggly,
+}
+
+impl TextMetrics {
+    /// Returns the horizontal position of the caret in front of the character at `byte_index` of
+    /// `text`, which must be the text these metrics were measured for.
+    ///
+    /// Right-to-left characters are preceded by a caret on their right side. An index past the last
+    /// character places the caret after it.
+    pub fn caret_x(&self, text: &str, byte_index: usize) -> f32 {
+        let rtl_bytes = unicode::rtl_bytes(text);
+        let is_rtl = |glyph: &ShapedGlyph| rtl_bytes.get(glyph.byte_index).copied().unwrap_or(false);
+
+        if let Some(glyph) = self.glyphs.iter().find(|glyph| glyph.byte_index == byte_index) {
+            let (left, right) = glyph_span(glyph);
+            return if is_rtl(glyph) { right } else { left };
+        }
+
+        // The caret follows the cluster that contains or precedes the index
+        let preceding = self
+            .glyphs
+            .iter()
+            .filter(|glyph| glyph.byte_index < byte_index)
+            .max_by_key(|glyph| glyph.byte_index);
+
+        match preceding {
+            Some(glyph) => {
+                let (left, right) = glyph_span(glyph);
+                if is_rtl(glyph) {
+                    left
+                } else {
+                    right
+                }
+            }
+            None => self.x,
+        }
+    }
+
+    /// Returns the horizontal spans covered by the characters in `range`, sorted from left to right.
+    ///
+    /// A contiguous range of bidirectional text can map to several disjoint spans, e.g. when a
+    /// selection starts in left-to-right text and ends in an embedded right-to-left run.
+    pub fn range_spans(&self, range: Range<usize>) -> Vec<(f32, f32)> {
+        let mut spans: Vec<(f32, f32)> = self
+            .glyphs
+            .iter()
+            .filter(|glyph| range.contains(&glyph.byte_index))
+            .map(glyph_span)
+            .collect();
+
+        spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut merged: Vec<(f32, f32)> = Vec::with_capacity(spans.len());
+
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if span.0 <= last.1 + SPAN_MERGE_DISTANCE => last.1 = last.1.max(span.1),
+                _ => merged.push(span),
+            }
+        }
+
+        merged
+    }
+}
+
+// Pen position before the glyph and after its advance
+fn glyph_span(glyph: &ShapedGlyph) -> (f32, f32) {
+    let x = glyph.x - glyph.offset_x - glyph.bearing_x;
+    (x, x + glyph.advance_x)
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws a caret in front of the character at `byte_index`.
+    ///
+    /// `y`, `text` and `paint` are the arguments the text was drawn or measured with and `metrics`
+    /// is the result. The caret spans the ascender and descender of the font, its width is the line
+    /// width of the paint.
+    pub fn draw_caret(
+        &mut self,
+        y: f32,
+        text: &str,
+        metrics: &TextMetrics,
+        byte_index: usize,
+        paint: Paint,
+    ) -> Result<(), ErrorKind> {
+        let (baseline, ascender, descender) = self.line_extent(y, paint)?;
+        let x = metrics.caret_x(text, byte_index);
+        let width = paint.line_width();
+
+        let mut path = Path::new();
+        path.rect(x - width / 2.0, baseline - ascender, width, ascender - descender);
+        self.fill_path(&mut path, paint);
+
+        Ok(())
+    }
+
+    /// Draws the decorations of an input method composition: an underline below every clause, a
+    /// thicker underline and a highlight behind the target clause and the caret.
+    ///
+    /// `y`, `text` and `paint` are the arguments the text was drawn or measured with and `metrics`
+    /// is the result. The decorations use the paint's color, thin underlines are as thick as the
+    /// paint's line width. Clauses that split a right-to-left run or a mixed direction run are
+    /// underlined in pieces, the way they appear on screen.
+    pub fn draw_preedit(
+        &mut self,
+        y: f32,
+        text: &str,
+        metrics: &TextMetrics,
+        preedit: &Preedit,
+        paint: Paint,
+    ) -> Result<(), ErrorKind> {
+        let (baseline, ascender, descender) = self.line_extent(y, paint)?;
+        let thickness = paint.line_width();
+        let underline_y = baseline - descender / 2.0;
+
+        let whole = [preedit.range.clone()];
+
+        let clauses = if preedit.clauses.is_empty() {
+            &whole[..]
+        } else {
+            &preedit.clauses[..]
+        };
+
+        for (index, clause) in clauses.iter().enumerate() {
+            let clause = clause.start.max(preedit.range.start)..clause.end.min(preedit.range.end);
+            let is_target = preedit.target_clause == Some(index);
+
+            let spans = metrics.range_spans(clause);
+
+            if is_target {
+                let mut highlight = paint;
+                highlight.mul_alpha(0.25);
+
+                let mut path = Path::new();
+
+                for (left, right) in &spans {
+                    path.rect(*left, baseline - ascender, right - left, ascender - descender);
+                }
+
+                self.fill_path(&mut path, highlight);
+            }
+
+            let height = if is_target { thickness * 2.0 } else { thickness };
+
+            // Leave a gap between neighbouring clauses
+            let mut path = Path::new();
+
+            for (left, right) in &spans {
+                let width = right - left - thickness * 2.0;
+
+                if width > 0.0 {
+                    path.rect(left + thickness, underline_y, width, height);
+                }
+            }
+
+            self.fill_path(&mut path, paint);
+        }
+
+        if let Some(cursor) = preedit.cursor {
+            self.draw_caret(y, text, metrics, cursor, paint)?;
+        }
+
+        Ok(())
+    }
+
+    /// Underlines the characters in each of `ranges`, e.g. the misspelled words found by a spell
+    /// checker.
+    ///
+    /// `y` and `paint` are the arguments the text was drawn or measured with and `metrics` is the
+    /// result, the ranges are byte offsets into its text. The lines follow the extents of the
+    /// shaped clusters, so ligatures and right-to-left runs are underlined the way they appear on
+    /// screen. They use the paint's color and are as thick as its line width.
+    pub fn draw_underlines(
+        &mut self,
+        y: f32,
+        metrics: &TextMetrics,
+        ranges: &[Range<usize>],
+        style: UnderlineStyle,
+        paint: Paint,
+    ) -> Result<(), ErrorKind> {
+        let (baseline, _, descender) = self.line_extent(y, paint)?;
+        let thickness = paint.line_width();
+        let underline_y = baseline - descender / 2.0;
+
+        let mut path = Path::new();
+
+        for range in ranges {
+            for (left, right) in metrics.range_spans(range.clone()) {
+                match style {
+                    UnderlineStyle::Solid => path.rect(left, underline_y, right - left, thickness),
+                    UnderlineStyle::Dotted => {
+                        let radius = thickness / 2.0;
+                        let mut x = left + radius;
+
+                        while x + radius <= right {
+                            path.circle(x, underline_y + radius, radius);
+                            x += thickness * 2.0;
+                        }
+                    }
+                    UnderlineStyle::Squiggly => {
+                        // Half waves as wide as two line widths, the last one is cut short
+                        let half_wave = thickness * 2.0;
+                        let center_y = underline_y + thickness;
+
+                        path.move_to(left, center_y);
+
+                        let mut x = left;
+                        let mut up = true;
+
+                        while x < right {
+                            let end = (x + half_wave).min(right);
+                            // The control point lies twice as far out as the crest of the curve
+                            let crest = if up { -thickness } else { thickness } * (end - x) / half_wave;
+
+                            path.quad_to((x + end) / 2.0, center_y + crest * 2.0, end, center_y);
+
+                            x = end;
+                            up = !up;
+                        }
+                    }
+                }
+            }
+        }
+
+        match style {
+            UnderlineStyle::Squiggly => self.stroke_path(&mut path, paint),
+            _ => self.fill_path(&mut path, paint),
+        }
+
+        Ok(())
+    }
+
+    // Returns the baseline for text drawn at `y` with the paint and the ascender and descender of
+    // its font, in canvas coordinates
+    fn line_extent(&mut self, y: f32, paint: Paint) -> Result<(f32, f32, f32), ErrorKind> {
+        let invscale = 1.0 / (self.font_scale() * self.device_px_ratio);
+        let font_metrics = self.measure_font(paint)?;
+
+        let ascender = font_metrics.ascender() * invscale;
+        let descender = font_metrics.descender() * invscale;
+
+        let baseline = match paint.text_baseline {
+            Baseline::Top => y + ascender,
+            Baseline::Middle => y + (ascender + descender) / 2.0,
+            Baseline::Alphabetic => y,
+            Baseline::Bottom => y + descender,
+        };
+
+        Ok((baseline, ascender, descender))
+    }
+}
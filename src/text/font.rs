@@ -1,4 +1,5 @@
 use fnv::FnvHashMap;
+#[cfg(feature = "text")]
 use owned_ttf_parser::{
     AsFaceRef,
     Face as TtfFont,
@@ -89,15 +90,21 @@ impl FontMetrics {
     }
 }
 
+#[cfg_attr(not(feature = "text"), allow(dead_code))]
 pub(crate) struct Font {
     data: Vec<u8>,
+    #[cfg(feature = "text")]
     owned_ttf_font: OwnedFace,
     units_per_em: u16,
     metrics: FontMetrics,
     glyphs: FnvHashMap<u16, Glyph>,
+    // Horizontal advances of the characters up to U+00FF in font units, None for the ones the font
+    // doesn't cover. Filled on first use.
+    latin_advances: Vec<Option<u16>>,
 }
 
 impl Font {
+    #[cfg(feature = "text")]
     pub fn new(data: &[u8]) -> Result<Self, ErrorKind> {
         let owned_ttf_font = OwnedFace::from_vec(data.to_owned(), 0).map_err(|_| ErrorKind::FontParseError)?;
 
@@ -127,13 +134,22 @@ impl Font {
             units_per_em,
             metrics,
             glyphs: Default::default(),
+            latin_advances: Vec::new(),
         })
     }
 
+    /// Fonts can't be parsed without the `text` feature.
+    #[cfg(not(feature = "text"))]
+    pub fn new(_data: &[u8]) -> Result<Self, ErrorKind> {
+        Err(ErrorKind::FontParseError)
+    }
+
+    #[cfg_attr(not(feature = "text"), allow(dead_code))]
     pub fn data(&self) -> &[u8] {
         self.data.as_ref()
     }
 
+    #[cfg(feature = "text")]
     fn font_ref(&self) -> &TtfFont<'_> {
         self.owned_ttf_font.as_face_ref()
     }
@@ -150,6 +166,31 @@ impl Font {
         size / self.units_per_em as f32
     }
 
+    /// Returns the id of the glyph the font maps `c` to, or `None` if the font doesn't cover it.
+    #[cfg(feature = "text")]
+    pub fn glyph_index(&self, c: char) -> Option<u16> {
+        self.font_ref().glyph_index(c).map(|id| id.0).filter(|id| *id != 0)
+    }
+
+    /// Returns the horizontal advance of `c` in font units, without shaping. Only characters up to
+    /// U+00FF are covered, `None` is returned for the others and for the ones the font lacks.
+    #[cfg(feature = "text")]
+    pub fn latin_advance(&mut self, c: char) -> Option<u16> {
+        if self.latin_advances.is_empty() {
+            let advances = (0..=0xffu8)
+                .map(|code| {
+                    let id = self.glyph_index(char::from(code))?;
+                    self.font_ref().glyph_hor_advance(GlyphId(id))
+                })
+                .collect();
+
+            self.latin_advances = advances;
+        }
+
+        self.latin_advances.get(c as usize).copied().flatten()
+    }
+
+    #[cfg(feature = "text")]
     pub fn glyph(&mut self, codepoint: u16) -> Option<&mut Glyph> {
         if !self.glyphs.contains_key(&codepoint) {
             let mut path = Path::new();
@@ -175,3 +216,19 @@ impl Font {
         self.glyphs.get_mut(&codepoint)
     }
 }
+
+// Without the `text` feature no font is ever created, these only keep the callers compiling
+#[cfg(not(feature = "text"))]
+impl Font {
+    pub fn glyph_index(&self, _c: char) -> Option<u16> {
+        None
+    }
+
+    pub fn latin_advance(&mut self, _c: char) -> Option<u16> {
+        None
+    }
+
+    pub fn glyph(&mut self, _codepoint: u16) -> Option<&mut Glyph> {
+        None
+    }
+}
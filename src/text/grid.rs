@@ -0,0 +1,352 @@
+use bitflags::bitflags;
+use fnv::FnvHashMap;
+use rgb::RGBA8;
+
+use super::{
+    atlas_quad,
+    FontId,
+    RenderMode,
+    ShapedGlyph,
+    TextContextImpl,
+};
+
+use crate::{
+    renderer::{
+        Command,
+        CommandType,
+        Params,
+        ShaderType,
+        Vertex,
+    },
+    Canvas,
+    Color,
+    ErrorKind,
+    ImageId,
+    Paint,
+    Renderer,
+};
+
+// Horizontal shift of the top of the glyph relative to its height for synthesized italics
+const FAKE_ITALIC_SKEW: f32 = 0.2;
+
+bitflags! {
+    /// Text attributes of a [`GridCell`].
+    #[derive(Default)]
+    pub struct CellStyle: u8 {
+        const BOLD = 1;
+        const ITALIC = 1 << 1;
+        const UNDERLINE = 1 << 2;
+        const STRIKETHROUGH = 1 << 3;
+    }
+}
+
+/// A single character cell drawn with [`Canvas::fill_glyph_grid()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridCell {
+    pub ch: char,
+    /// Color of the glyph and its decorations.
+    pub fg: Color,
+    /// Color of the cell background, `None` leaves the background untouched.
+    pub bg: Option<Color>,
+    pub style: CellStyle,
+}
+
+impl GridCell {
+    pub fn new(ch: char, fg: Color) -> Self {
+        Self {
+            ch,
+            fg,
+            bg: None,
+            style: CellStyle::empty(),
+        }
+    }
+}
+
+// A character resolved to a glyph of a font, shared by all cells with the same character and style
+#[derive(Copy, Clone)]
+struct CellGlyph {
+    glyph: Option<ShapedGlyph>,
+    fake_bold: bool,
+    fake_italic: bool,
+}
+
+#[derive(Default)]
+struct Triangles {
+    verts: Vec<Vertex>,
+    colors: Vec<RGBA8>,
+}
+
+impl Triangles {
+    fn push_quad(&mut self, corners: [(f32, f32); 4], tex: [f32; 4], color: RGBA8) {
+        let [p0, p1, p2, p3] = corners;
+        let [s0, t0, s1, t1] = tex;
+
+        self.verts.push(Vertex::new(p0.0, p0.1, s0, t0));
+        self.verts.push(Vertex::new(p2.0, p2.1, s1, t1));
+        self.verts.push(Vertex::new(p1.0, p1.1, s1, t0));
+        self.verts.push(Vertex::new(p0.0, p0.1, s0, t0));
+        self.verts.push(Vertex::new(p3.0, p3.1, s0, t1));
+        self.verts.push(Vertex::new(p2.0, p2.1, s1, t1));
+
+        self.colors.extend_from_slice(&[color; 6]);
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Draws a grid of monospace character cells, as used by terminal emulators and code editors.
+    ///
+    /// `cells` are laid out row by row, `columns` cells per row, starting at `origin` with every cell
+    /// taking `cell_size`. Each cell holds a single character which is drawn at the cell's position
+    /// without any shaping, so there are no ligatures or kerning and characters wider than a cell
+    /// overflow into the next one. Glyphs are vertically centered in their cells.
+    ///
+    /// The font and its size are taken from `paint`, its color is ignored in favor of the colors of
+    /// the cells. Bold and italic cells use the first font of the paint with a matching style,
+    /// falling back to a synthesized style. The whole grid is drawn with a handful of draw calls
+    /// regardless of the number of colors used.
+    pub fn fill_glyph_grid(
+        &mut self,
+        origin: (f32, f32),
+        cell_size: (f32, f32),
+        columns: usize,
+        cells: &[GridCell],
+        mut paint: Paint,
+    ) -> Result<(), ErrorKind> {
+        if columns == 0 || cells.is_empty() {
+            return Ok(());
+        }
+
+        let transform = self.state().transform;
+        let scale = self.font_scale() * self.device_px_ratio;
+        let invscale = 1.0 / scale;
+
+        self.transform_text_paint(&mut paint);
+
+        let (ascender, descender) = {
+            let context = self.text_context.lock();
+
+            let font = paint
+                .font_ids
+                .iter()
+                .flatten()
+                .find_map(|id| context.font(*id))
+                .or_else(|| context.fonts.iter().next().map(|(_, font)| font))
+                .ok_or(ErrorKind::NoFontFound)?;
+
+            let metrics = font.metrics(paint.font_size);
+
+            (metrics.ascender(), metrics.descender())
+        };
+
+        let cell_width = cell_size.0 * scale;
+        let cell_height = cell_size.1 * scale;
+        let baseline = ((cell_height + ascender + descender) / 2.0).round();
+        let line_thickness = (paint.font_size / 14.0).round().max(1.0);
+        let bold_offset = (paint.font_size / 16.0).round().max(1.0);
+
+        let to_canvas = |x: f32, y: f32| transform.transform_point(x * invscale, y * invscale);
+
+        let mut resolved = FnvHashMap::default();
+        let mut backgrounds = Triangles::default();
+        let mut glyphs: FnvHashMap<usize, (ImageId, Triangles)> = FnvHashMap::default();
+        let mut decorations = Triangles::default();
+
+        for (index, cell) in cells.iter().enumerate() {
+            let x = origin.0 * scale + (index % columns) as f32 * cell_width;
+            let y = origin.1 * scale + (index / columns) as f32 * cell_height;
+
+            let corners = |x0: f32, y0: f32, x1: f32, y1: f32| {
+                [
+                    to_canvas(x0, y0),
+                    to_canvas(x1, y0),
+                    to_canvas(x1, y1),
+                    to_canvas(x0, y1),
+                ]
+            };
+
+            if let Some(bg) = cell.bg {
+                backgrounds.push_quad(corners(x, y, x + cell_width, y + cell_height), [0.0; 4], rgba8(bg));
+            }
+
+            let fg = rgba8(cell.fg);
+            let baseline_y = y + baseline;
+
+            if cell.style.contains(CellStyle::UNDERLINE) {
+                let y0 = baseline_y + line_thickness;
+                decorations.push_quad(corners(x, y0, x + cell_width, y0 + line_thickness), [0.0; 4], fg);
+            }
+
+            if cell.style.contains(CellStyle::STRIKETHROUGH) {
+                let y0 = (baseline_y - ascender / 3.0).round();
+                decorations.push_quad(corners(x, y0, x + cell_width, y0 + line_thickness), [0.0; 4], fg);
+            }
+
+            if cell.ch.is_whitespace() || cell.ch.is_control() {
+                continue;
+            }
+
+            let style = cell.style & (CellStyle::BOLD | CellStyle::ITALIC);
+
+            let cell_glyph = *resolved
+                .entry((cell.ch, style))
+                .or_insert_with(|| resolve_glyph(&mut self.text_context.lock(), &paint, cell.ch, style));
+
+            let mut glyph = match cell_glyph.glyph {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+
+            glyph.x = x + glyph.bearing_x;
+            glyph.y = baseline_y - glyph.bearing_y;
+
+            let strikes = if cell_glyph.fake_bold { 2 } else { 1 };
+
+            for strike in 0..strikes {
+                glyph.x += strike as f32 * bold_offset;
+
+                let (texture_index, image_id, quad) = match atlas_quad(self, &glyph, &paint, RenderMode::Fill, 0.0)? {
+                    Some(atlas_quad) => atlas_quad,
+                    None => continue,
+                };
+
+                let skew = |y: f32| {
+                    if cell_glyph.fake_italic {
+                        (baseline_y - y) * FAKE_ITALIC_SKEW
+                    } else {
+                        0.0
+                    }
+                };
+
+                let (top, bottom) = (skew(quad.y0), skew(quad.y1));
+
+                let corners = [
+                    to_canvas(quad.x0 + top, quad.y0),
+                    to_canvas(quad.x1 + top, quad.y0),
+                    to_canvas(quad.x1 + bottom, quad.y1),
+                    to_canvas(quad.x0 + bottom, quad.y1),
+                ];
+
+                glyphs
+                    .entry(texture_index)
+                    .or_insert_with(|| (image_id, Triangles::default()))
+                    .1
+                    .push_quad(corners, [quad.s0, quad.t0, quad.s1, quad.t1], fg);
+            }
+        }
+
+        self.render_colored_triangles(backgrounds, None);
+
+        for (_, (image_id, triangles)) in glyphs {
+            self.render_colored_triangles(triangles, Some(image_id));
+        }
+
+        self.render_colored_triangles(decorations, None);
+
+        Ok(())
+    }
+
+    fn render_colored_triangles(&mut self, triangles: Triangles, alpha_mask: Option<ImageId>) {
+        if triangles.verts.is_empty() {
+            return;
+        }
+
+        let scissor = self.state().scissor;
+
+        let mut paint = Paint::color(Color::rgbaf(1.0, 1.0, 1.0, self.state().alpha));
+        paint.set_alpha_mask(alpha_mask);
+
+        let mut params = Params::new(&self.images, &paint, &scissor, 1.0, 1.0, -1.0);
+        params.shader_type = ShaderType::TexturedMesh.to_f32();
+        // Vertex colors only
+        params.tex_type = 3.0;
+
+        let mut cmd = Command::new(CommandType::Triangles { params });
+        cmd.composite_operation = self.state().composite_operation;
+        cmd.alpha_mask = alpha_mask;
+        cmd.triangles_verts = Some((self.verts.len(), triangles.verts.len()));
+        cmd.vertex_colors = Some(triangles.colors);
+
+        self.append_cmd(cmd);
+
+        self.verts.extend_from_slice(&triangles.verts);
+    }
+}
+
+// Finds the font to draw `c` with. Fonts of the paint with the requested style are preferred, then
+// any font covering the character, with bold and italic synthesized if the font lacks them.
+fn resolve_glyph(context: &mut TextContextImpl, paint: &Paint, c: char, style: CellStyle) -> CellGlyph {
+    let bold = style.contains(CellStyle::BOLD);
+    let italic = style.contains(CellStyle::ITALIC);
+
+    let covers =
+        |context: &TextContextImpl, id: FontId| context.font(id).and_then(|font| font.glyph_index(c)).is_some();
+
+    let has_style = |context: &TextContextImpl, id: FontId| match context.font(id) {
+        Some(font) => {
+            let metrics = font.metrics(paint.font_size);
+            metrics.bold() == bold && (metrics.italic() || metrics.oblique()) == italic
+        }
+        None => false,
+    };
+
+    let paint_fonts = paint.font_ids.iter().flatten().copied();
+
+    let font_id = paint_fonts
+        .clone()
+        .find(|id| has_style(context, *id) && covers(context, *id))
+        .or_else(|| {
+            paint_fonts
+                .chain(context.fonts.iter().map(|(id, _)| FontId(id)))
+                .find(|id| covers(context, *id))
+        });
+
+    let mut cell_glyph = CellGlyph {
+        glyph: None,
+        fake_bold: false,
+        fake_italic: false,
+    };
+
+    let (font_id, font) = match font_id.and_then(|id| context.font_mut(id).map(|font| (id, font))) {
+        Some(found) => found,
+        None => return cell_glyph,
+    };
+
+    let metrics = font.metrics(paint.font_size);
+    cell_glyph.fake_bold = bold && !metrics.bold();
+    cell_glyph.fake_italic = italic && !(metrics.italic() || metrics.oblique());
+
+    let codepoint = font.glyph_index(c).unwrap_or_default();
+    let scale = font.scale(paint.font_size);
+
+    if let Some(glyph) = font.glyph(codepoint) {
+        cell_glyph.glyph = Some(ShapedGlyph {
+            x: 0.0,
+            y: 0.0,
+            c,
+            byte_index: 0,
+            font_id,
+            codepoint: codepoint as u32,
+            width: glyph.metrics.width * scale,
+            height: glyph.metrics.height * scale,
+            advance_x: 0.0,
+            advance_y: 0.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            bearing_x: glyph.metrics.bearing_x * scale,
+            bearing_y: glyph.metrics.bearing_y * scale,
+        });
+    }
+
+    cell_glyph
+}
+
+fn rgba8(color: Color) -> RGBA8 {
+    let color = color.premultiplied();
+
+    RGBA8::new(
+        (color.r * 255.0).round() as u8,
+        (color.g * 255.0).round() as u8,
+        (color.b * 255.0).round() as u8,
+        (color.a * 255.0).round() as u8,
+    )
+}
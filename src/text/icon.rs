@@ -0,0 +1,130 @@
+use super::{
+    FontId,
+    RenderMode,
+    ShapedGlyph,
+    TextContextImpl,
+    TextMetrics,
+    TextRecord,
+};
+
+use crate::{
+    Canvas,
+    ErrorKind,
+    Paint,
+    Renderer,
+};
+
+impl TextContextImpl {
+    // The first font of the paint, or else of the context, that has a glyph for the icon
+    fn icon_glyph(&mut self, icon: char, paint: &Paint) -> Option<(FontId, u16)> {
+        let paint_fonts = paint.font_ids.iter().map_while(|id| *id);
+        let fonts: Vec<FontId> = paint_fonts.chain(self.fonts.iter().map(|(id, _)| FontId(id))).collect();
+
+        for font_id in fonts {
+            let fonts = &self.fonts;
+
+            let glyph = *self
+                .icon_glyphs
+                .entry((font_id, icon))
+                .or_insert_with(|| fonts.get(font_id.0).and_then(|font| font.glyph_index(icon)));
+
+            if let Some(glyph) = glyph {
+                return Some((font_id, glyph));
+            }
+        }
+
+        None
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Fills the glyph of an icon font centered in the square box at `x, y` with sides `size`
+    /// long.
+    ///
+    /// Icon fonts have a glyph for each icon on a codepoint of their own, usually in the private
+    /// use area, so the glyph is looked up directly instead of shaping the text. It is drawn at
+    /// font size `size` with its outline centered in the box, which puts icons of any shape in the
+    /// middle of a button. The alignment, baseline and letter spacing of `paint` don't apply.
+    ///
+    /// The fonts of `paint` are tried first, then all fonts of the text context. Returns
+    /// [`ErrorKind::NoFontFound`] if none of them has a glyph for `icon`.
+    pub fn fill_icon(
+        &mut self,
+        x: f32,
+        y: f32,
+        size: f32,
+        icon: char,
+        mut paint: Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        self.validate_draw("fill_icon", Some((x, y)), &paint);
+
+        let transform = self.state().transform;
+        let scale = self.font_scale() * self.device_px_ratio;
+        let invscale = 1.0 / scale;
+
+        paint.font_size = size;
+        self.transform_text_paint(&mut paint);
+
+        let glyph = {
+            let mut text_context = self.text_context.lock();
+
+            let (font_id, codepoint) = text_context.icon_glyph(icon, &paint).ok_or(ErrorKind::NoFontFound)?;
+            let font = text_context.font_mut(font_id).ok_or(ErrorKind::NoFontFound)?;
+            let font_scale = font.scale(paint.font_size);
+
+            let (width, height, bearing_x, bearing_y) = match font.glyph(codepoint) {
+                Some(glyph) => (
+                    glyph.metrics.width * font_scale,
+                    glyph.metrics.height * font_scale,
+                    glyph.metrics.bearing_x * font_scale,
+                    glyph.metrics.bearing_y * font_scale,
+                ),
+                None => (0.0, 0.0, 0.0, 0.0),
+            };
+
+            let (center_x, center_y) = ((x + size / 2.0) * scale, (y + size / 2.0) * scale);
+
+            ShapedGlyph {
+                x: center_x - width / 2.0,
+                y: center_y - height / 2.0,
+                c: icon,
+                byte_index: 0,
+                font_id,
+                codepoint: codepoint as u32,
+                width,
+                height,
+                advance_x: width,
+                advance_y: 0.0,
+                offset_x: 0.0,
+                offset_y: 0.0,
+                bearing_x,
+                bearing_y,
+            }
+        };
+
+        let mut layout = TextMetrics {
+            x: glyph.x,
+            y: glyph.y,
+            width: glyph.width,
+            height: glyph.height,
+            glyphs: vec![glyph],
+            final_byte_index: icon.len_utf8(),
+        };
+
+        if paint.font_size > 92.0 {
+            super::render_direct(self, &layout, &paint, RenderMode::Fill, invscale)?;
+        } else {
+            let cmds = super::render_atlas(self, &layout, &paint, RenderMode::Fill)?;
+
+            self.render_glyph_quads(&cmds, paint, transform, invscale);
+        }
+
+        layout.scale(invscale);
+
+        if let Some(records) = &mut self.text_records {
+            records.push(TextRecord::new(icon.encode_utf8(&mut [0; 4]), &layout, transform));
+        }
+
+        Ok(layout)
+    }
+}
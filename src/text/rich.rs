@@ -0,0 +1,338 @@
+use std::ops::Range;
+
+use super::{
+    unicode,
+    Align,
+    Baseline,
+    FontId,
+    RenderMode,
+    TextMetrics,
+};
+
+use crate::{
+    Canvas,
+    Color,
+    ErrorKind,
+    Paint,
+    Renderer,
+};
+
+/// Paint properties a [`Span`] draws with instead of those of the paint given to
+/// [`Canvas::fill_rich_text()`], `None` keeps the property of that paint.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PaintOverrides {
+    pub color: Option<Color>,
+    /// Font tried first, e.g. the bold face, the fonts of the paint stay the fallbacks.
+    pub font: Option<FontId>,
+    pub font_size: Option<f32>,
+    pub letter_spacing: Option<f32>,
+}
+
+impl PaintOverrides {
+    fn apply(&self, paint: &mut Paint) {
+        if let Some(color) = self.color {
+            paint.set_color(color);
+        }
+
+        if let Some(font) = self.font {
+            let fallbacks = paint.font_ids.iter().filter_map(|id| *id).filter(|id| *id != font);
+            let fonts: Vec<FontId> = std::iter::once(font).chain(fallbacks).collect();
+            paint.set_font(&fonts);
+        }
+
+        if let Some(size) = self.font_size {
+            paint.set_font_size(size);
+        }
+
+        if let Some(spacing) = self.letter_spacing {
+            paint.set_letter_spacing(spacing);
+        }
+    }
+}
+
+/// A piece of text with its own style, see [`Canvas::fill_rich_text()`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub paint_overrides: PaintOverrides,
+}
+
+impl<'a> Span<'a> {
+    /// A span drawn with the paint of the text.
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            paint_overrides: PaintOverrides::default(),
+        }
+    }
+
+    pub fn styled(text: &'a str, paint_overrides: PaintOverrides) -> Self {
+        Self { text, paint_overrides }
+    }
+}
+
+// A part of a span on one line
+struct Piece {
+    span: usize,
+    range: Range<usize>,
+    width: f32,
+}
+
+#[derive(Default)]
+struct Line {
+    pieces: Vec<Piece>,
+    width: f32,
+    ascender: f32,
+    descender: f32,
+    height: f32,
+}
+
+impl Line {
+    fn push(&mut self, piece: Piece, metrics: &PieceMetrics) {
+        self.width += piece.width;
+        self.ascender = self.ascender.max(metrics.ascender);
+        self.descender = self.descender.min(metrics.descender);
+        self.height = self.height.max(metrics.height);
+        self.pieces.push(piece);
+    }
+}
+
+struct PieceMetrics {
+    width: f32,
+    ascender: f32,
+    descender: f32,
+    height: f32,
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Fills spans of differently styled text one after the other on a shared baseline.
+    ///
+    /// Each span is drawn with `paint` changed by its [`PaintOverrides`], so a label with a bold or
+    /// colored word doesn't need to measure the text before it. The alignment and the baseline of
+    /// `paint` apply to the whole line: [`Baseline::Top`] aligns the highest ascender of the spans
+    /// to `y`, for example. The returned metrics cover all spans, the byte indices of their glyphs
+    /// index the texts of the spans joined together.
+    pub fn fill_rich_text(&mut self, x: f32, y: f32, spans: &[Span], paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        let mut line = Line::default();
+
+        for (i, span) in spans.iter().enumerate() {
+            let span_paint = self.span_paint(span, paint);
+            let metrics = self.measure_piece(span.text, span_paint)?;
+
+            let piece = Piece {
+                span: i,
+                range: 0..span.text.len(),
+                width: metrics.width,
+            };
+
+            line.push(piece, &metrics);
+        }
+
+        self.fill_rich_lines(x, y, &[line], spans, paint)
+    }
+
+    /// Fills spans like [`Self::fill_rich_text()`], but breaks them into lines no wider than
+    /// `max_width`, see [`Self::break_text()`].
+    ///
+    /// Lines break between words and at newlines, a word that is wider than `max_width` on its own
+    /// is put on a line by itself. Each line is as high as the tallest font on it and aligned on
+    /// its own, `y` positions the first line like [`Self::fill_rich_text()`] does.
+    ///
+    /// Gradients and images of the paints are placed in canvas coordinates like for paths, so a
+    /// gradient from the top to the bottom of the block runs across all of its lines and spans.
+    pub fn fill_rich_paragraph(
+        &mut self,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        spans: &[Span],
+        paint: Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        let mut lines = vec![Line::default()];
+
+        for (i, span) in spans.iter().enumerate() {
+            let span_paint = self.span_paint(span, paint);
+            let mut start = 0;
+
+            for (n, paragraph) in span.text.split('\n').enumerate() {
+                if n > 0 {
+                    lines.push(Line::default());
+                }
+
+                let mut rest = start..start + paragraph.len();
+                start = rest.end + 1;
+
+                while !rest.is_empty() {
+                    let text = &span.text[rest.clone()];
+                    let line = lines.last_mut().ok_or(ErrorKind::UnknownError)?;
+
+                    let mut end = self.break_text(max_width - line.width, text, span_paint)?;
+
+                    if end == 0 {
+                        if !line.pieces.is_empty() {
+                            lines.push(Line::default());
+                            rest.start += text.len() - text.trim_start().len();
+                            continue;
+                        }
+
+                        end = unicode::words(text).next().map_or(text.len(), str::len);
+                    }
+
+                    let metrics = self.measure_piece(&text[..end], span_paint)?;
+
+                    let piece = Piece {
+                        span: i,
+                        range: rest.start..rest.start + end,
+                        width: metrics.width,
+                    };
+
+                    lines.last_mut().ok_or(ErrorKind::UnknownError)?.push(piece, &metrics);
+
+                    rest.start += end;
+
+                    if !rest.is_empty() {
+                        lines.push(Line::default());
+                        let text = &span.text[rest.clone()];
+                        rest.start += text.len() - text.trim_start().len();
+                    }
+                }
+            }
+        }
+
+        self.fill_rich_lines(x, y, &lines, spans, paint)
+    }
+
+    /// Fills plain text broken into lines no wider than `max_width`, like
+    /// [`Self::fill_rich_paragraph()`] with a single span.
+    ///
+    /// The paint fills the block as a whole: a gradient or image is continuous across the lines
+    /// instead of starting over on each of them.
+    pub fn fill_paragraph(
+        &mut self,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        text: &str,
+        paint: Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        self.fill_rich_paragraph(x, y, max_width, &[Span::new(text)], paint)
+    }
+
+    fn fill_rich_lines(
+        &mut self,
+        x: f32,
+        y: f32,
+        lines: &[Line],
+        spans: &[Span],
+        paint: Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        let mut result = TextMetrics::default();
+        let (mut minx, mut miny, mut maxx, mut maxy) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+
+        // Byte offset of each span in the joined texts
+        let offsets: Vec<usize> = spans
+            .iter()
+            .scan(0, |offset, span| {
+                let start = *offset;
+                *offset += span.text.len();
+                Some(start)
+            })
+            .collect();
+
+        let mut baseline = match paint.text_baseline {
+            Baseline::Top => y + lines.first().map_or(0.0, |line| line.ascender),
+            _ => y,
+        };
+
+        for (n, line) in lines.iter().enumerate() {
+            if n > 0 {
+                baseline += line.height.max(lines[n - 1].height);
+            }
+
+            let line_baseline = match paint.text_baseline {
+                Baseline::Middle => baseline + (line.ascender + line.descender) / 2.0,
+                Baseline::Bottom => baseline + line.descender,
+                _ => baseline,
+            };
+
+            let mut cursor_x = match paint.text_align {
+                Align::Left => x,
+                Align::Center => x - line.width / 2.0,
+                Align::Right => x - line.width,
+            };
+
+            for piece in &line.pieces {
+                let span = &spans[piece.span];
+
+                let mut span_paint = self.span_paint(span, paint);
+                span_paint.set_text_align(Align::Left);
+                span_paint.set_text_baseline(Baseline::Alphabetic);
+
+                let text = &span.text[piece.range.clone()];
+                let metrics = self.draw_text(cursor_x, line_baseline, text, span_paint, RenderMode::Fill)?;
+
+                if !metrics.glyphs.is_empty() {
+                    minx = minx.min(metrics.x);
+                    miny = miny.min(metrics.y);
+                    maxx = maxx.max(metrics.x + metrics.width());
+                    maxy = maxy.max(metrics.y + metrics.height());
+                }
+
+                let offset = offsets[piece.span] + piece.range.start;
+
+                result.glyphs.extend(metrics.glyphs.into_iter().map(|mut glyph| {
+                    glyph.byte_index += offset;
+                    glyph
+                }));
+
+                result.final_byte_index = offset + metrics.final_byte_index;
+
+                cursor_x += piece.width;
+            }
+        }
+
+        if minx <= maxx {
+            result.x = minx;
+            result.y = miny;
+            result.width = maxx - minx;
+            result.height = maxy - miny;
+        }
+
+        Ok(result)
+    }
+
+    fn span_paint(&self, span: &Span, mut paint: Paint) -> Paint {
+        span.paint_overrides.apply(&mut paint);
+        paint
+    }
+
+    // Width of the text and the vertical metrics of the fonts its glyphs use
+    fn measure_piece(&mut self, text: &str, paint: Paint) -> Result<PieceMetrics, ErrorKind> {
+        let layout = self.measure_text(0.0, 0.0, text, paint)?;
+
+        let mut fonts: Vec<FontId> = layout.glyphs.iter().map(|glyph| glyph.font_id).collect();
+        fonts.extend(paint.font_ids.iter().take(1).filter_map(|id| *id));
+        fonts.dedup();
+
+        let mut metrics = PieceMetrics {
+            width: layout.width(),
+            ascender: 0.0,
+            descender: 0.0,
+            height: 0.0,
+        };
+
+        let text_context = self.text_context.lock();
+
+        for id in fonts {
+            if let Some(font) = text_context.font(id) {
+                let font_metrics = font.metrics(paint.font_size);
+
+                metrics.ascender = metrics.ascender.max(font_metrics.ascender());
+                metrics.descender = metrics.descender.min(font_metrics.descender());
+                metrics.height = metrics.height.max(font_metrics.height());
+            }
+        }
+
+        Ok(metrics)
+    }
+}
@@ -0,0 +1,206 @@
+use std::hash::{
+    Hash,
+    Hasher,
+};
+
+use fnv::FnvHasher;
+
+use super::{
+    quad_vertices,
+    render_atlas,
+    shape,
+    DrawCmd,
+    RenderMode,
+    StrokeStyle,
+    TextMetrics,
+    TextRecord,
+};
+
+use crate::{
+    renderer::Vertex,
+    Canvas,
+    ErrorKind,
+    ImageId,
+    Paint,
+    Renderer,
+};
+
+/// A string that is drawn every frame with the same style, see [`Canvas::fill_text_run()`].
+///
+/// The run keeps the shaped glyphs and the vertices of the last draw. As long as the text, its
+/// position, the font related properties of the paint and the scale of the transform don't change,
+/// drawing it again skips shaping and the glyph atlas lookups. The vertices are only recomputed when
+/// the transform changes.
+#[derive(Clone, Debug, Default)]
+pub struct TextRun {
+    x: f32,
+    y: f32,
+    text: String,
+    cache: Option<RunCache>,
+}
+
+#[derive(Clone, Debug)]
+struct RunCache {
+    key: u64,
+    atlas_generation: u64,
+    // False if glyphs were left out because the frame ran out of budget
+    complete: bool,
+    metrics: TextMetrics,
+    cmds: Vec<DrawCmd>,
+    // Vertices of every atlas draw command for the transform with `transform_key`
+    transform_key: u64,
+    verts: Vec<(ImageId, Vec<Vertex>)>,
+}
+
+impl TextRun {
+    pub fn new<S: Into<String>>(x: f32, y: f32, text: S) -> Self {
+        Self {
+            x,
+            y,
+            text: text.into(),
+            cache: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text, the run is shaped again on its next draw if the text changed.
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text.clear();
+            self.text.push_str(text);
+            self.cache = None;
+        }
+    }
+
+    pub fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        if (self.x, self.y) != (x, y) {
+            self.x = x;
+            self.y = y;
+            self.cache = None;
+        }
+    }
+
+    /// Returns the metrics of the last draw, if the run was drawn since it last changed.
+    pub fn metrics(&self) -> Option<&TextMetrics> {
+        self.cache.as_ref().map(|cache| &cache.metrics)
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Fills a retained text run with the specified Paint, like [`Self::fill_text()`] does for a
+    /// plain string.
+    pub fn fill_text_run(&mut self, run: &mut TextRun, paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        self.draw_text_run(run, paint, RenderMode::Fill)
+    }
+
+    /// Strokes a retained text run with the specified Paint, like [`Self::stroke_text()`] does for a
+    /// plain string.
+    pub fn stroke_text_run(&mut self, run: &mut TextRun, paint: Paint) -> Result<TextMetrics, ErrorKind> {
+        self.draw_text_run(run, paint, RenderMode::Stroke)
+    }
+
+    fn draw_text_run(&mut self, run: &mut TextRun, paint: Paint, mode: RenderMode) -> Result<TextMetrics, ErrorKind> {
+        let transform = self.state().transform;
+        let scale = self.font_scale() * self.device_px_ratio;
+        let invscale = 1.0 / scale;
+
+        let mut text_paint = paint;
+        self.transform_text_paint(&mut text_paint);
+
+        // Large text is drawn as paths, there is nothing to retain
+        if text_paint.font_size > 92.0 {
+            run.cache = None;
+            return self.draw_text(run.x, run.y, &run.text, paint, mode);
+        }
+
+        let key = {
+            let mut hasher = FnvHasher::default();
+            scale.to_bits().hash(&mut hasher);
+            text_paint.font_ids.hash(&mut hasher);
+            text_paint.font_size.to_bits().hash(&mut hasher);
+            text_paint.letter_spacing.to_bits().hash(&mut hasher);
+            text_paint.text_baseline.hash(&mut hasher);
+            text_paint.text_align.hash(&mut hasher);
+            mode.hash(&mut hasher);
+
+            if mode == RenderMode::Stroke {
+                text_paint.line_width.to_bits().hash(&mut hasher);
+                StrokeStyle::new(&text_paint, mode).hash(&mut hasher);
+            }
+
+            hasher.finish()
+        };
+
+        let is_valid = matches!(&run.cache, Some(cache) if cache.complete && cache.key == key && cache.atlas_generation == self.glyph_atlas_generation);
+
+        if !is_valid {
+            let layout = shape(
+                run.x * scale,
+                run.y * scale,
+                &mut self.text_context.lock(),
+                &text_paint,
+                &run.text,
+                None,
+            )?;
+
+            let skipped_glyphs = self.skipped_glyphs();
+            let cmds = render_atlas(self, &layout, &text_paint, mode)?;
+
+            let mut metrics = layout;
+            metrics.scale(invscale);
+
+            run.cache = Some(RunCache {
+                key,
+                atlas_generation: self.glyph_atlas_generation,
+                complete: self.skipped_glyphs() == skipped_glyphs,
+                metrics,
+                cmds,
+                transform_key: 0,
+                verts: Vec::new(),
+            });
+        }
+
+        let cache = match &mut run.cache {
+            Some(cache) => cache,
+            None => return Err(ErrorKind::UnknownError),
+        };
+
+        let transform_key = transform.cache_key();
+
+        if !is_valid || cache.transform_key != transform_key {
+            cache.transform_key = transform_key;
+            cache.verts.clear();
+
+            for cmd in &cache.cmds {
+                let mut verts = Vec::with_capacity(cmd.quads.len() * 6);
+                quad_vertices(&cmd.quads, transform, invscale, &mut verts);
+                cache.verts.push((cmd.image_id, verts));
+            }
+        }
+
+        let mut paint = text_paint;
+        paint.transform = transform;
+
+        for (image_id, verts) in &cache.verts {
+            paint.set_alpha_mask(Some(*image_id));
+
+            // Apply global alpha
+            paint.mul_alpha(self.state().alpha);
+
+            self.render_triangles(verts, &paint);
+        }
+
+        if let Some(records) = &mut self.text_records {
+            records.push(TextRecord::new(&run.text, &cache.metrics, transform));
+        }
+
+        Ok(cache.metrics.clone())
+    }
+}
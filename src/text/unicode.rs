@@ -0,0 +1,64 @@
+// Word segmentation and bidirectional text analysis. Without the `text` feature their tables aren't
+// compiled in: text is one word and left-to-right, which only matters for the layout helpers since
+// no font can be loaded then.
+
+use std::ops::Range;
+
+#[cfg(feature = "text")]
+use unicode_bidi::BidiInfo;
+#[cfg(feature = "text")]
+use unicode_segmentation::UnicodeSegmentation;
+
+// The words of the text and the whitespace and punctuation between them, in logical order
+#[cfg(feature = "text")]
+pub(crate) fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split_word_bounds()
+}
+
+#[cfg(not(feature = "text"))]
+pub(crate) fn words(text: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(text).filter(|text| !text.is_empty())
+}
+
+// The byte ranges of the runs of the first paragraph in visual order, and whether each runs right
+// to left
+#[cfg(feature = "text")]
+pub(crate) fn visual_runs(text: &str) -> Vec<(Range<usize>, bool)> {
+    let bidi_info = BidiInfo::new(text, Some(unicode_bidi::Level::ltr()));
+
+    match bidi_info.paragraphs.get(0) {
+        Some(paragraph) => {
+            let (levels, runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+
+            runs.into_iter()
+                .map(|run| {
+                    let rtl = levels[run.start].is_rtl();
+                    (run, rtl)
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+#[cfg(not(feature = "text"))]
+pub(crate) fn visual_runs(text: &str) -> Vec<(Range<usize>, bool)> {
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        vec![(0..text.len(), false)]
+    }
+}
+
+// Whether the character at each byte of the text is right to left
+#[cfg(feature = "text")]
+pub(crate) fn rtl_bytes(text: &str) -> Vec<bool> {
+    let bidi_info = BidiInfo::new(text, Some(unicode_bidi::Level::ltr()));
+
+    bidi_info.levels.iter().map(|level| level.is_rtl()).collect()
+}
+
+#[cfg(not(feature = "text"))]
+pub(crate) fn rtl_bytes(text: &str) -> Vec<bool> {
+    vec![false; text.len()]
+}
@@ -0,0 +1,170 @@
+use std::sync::mpsc::{
+    self,
+    Receiver,
+    Sender,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::thread::JoinHandle;
+
+use fnv::FnvHashSet;
+
+use super::{
+    finish_glyph,
+    GlyphJob,
+    RenderedGlyphId,
+};
+
+use crate::{
+    Canvas,
+    Renderer,
+};
+
+// Tessellates the outlines of new glyphs on background threads, so that text with many glyphs
+// that weren't drawn before doesn't stall the frame it first appears in
+pub(crate) struct GlyphWorkers {
+    jobs: Option<Sender<GlyphJob>>,
+    done: Receiver<GlyphJob>,
+    threads: Vec<JoinHandle<()>>,
+    pending: FnvHashSet<RenderedGlyphId>,
+    // Glyph draws left out since the last flush because their glyph wasn't ready yet
+    skipped: usize,
+}
+
+impl GlyphWorkers {
+    fn new(threads: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<GlyphJob>();
+        let (done_sender, done) = mpsc::channel();
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let threads = (0..threads)
+            .map(|_| {
+                let job_receiver = job_receiver.clone();
+                let done_sender = done_sender.clone();
+
+                std::thread::spawn(move || loop {
+                    // The lock is released before the job runs
+                    let job = job_receiver.lock().unwrap().recv();
+
+                    match job {
+                        Ok(mut job) => {
+                            job.tessellate();
+
+                            if done_sender.send(job).is_err() {
+                                return;
+                            }
+                        }
+                        // The workers are dropped
+                        Err(_) => return,
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            done,
+            threads,
+            pending: Default::default(),
+            skipped: 0,
+        }
+    }
+
+    pub(crate) fn is_pending(&self, id: &RenderedGlyphId) -> bool {
+        self.pending.contains(id)
+    }
+
+    pub(crate) fn submit(&mut self, job: GlyphJob) {
+        self.pending.insert(job.id);
+
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send(job);
+        }
+    }
+
+    pub(crate) fn skip(&mut self) {
+        self.skipped += 1;
+    }
+
+    pub(crate) fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    pub(crate) fn take_skipped(&mut self) -> usize {
+        std::mem::take(&mut self.skipped)
+    }
+}
+
+impl Drop for GlyphWorkers {
+    fn drop(&mut self) {
+        // Closing the channel ends the threads once they finished their current job
+        self.jobs.take();
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Sets how many background threads rasterize glyphs that weren't drawn before, 0 rasterizes
+    /// them while the text is drawn. Defaults to 0.
+    ///
+    /// Rasterizing a glyph for the first time takes long enough that text with many new glyphs,
+    /// e.g. a page of CJK text, visibly stalls the frame it appears in. With worker threads, the
+    /// glyphs are left out of that frame and show up in a later one, once the text is drawn again
+    /// after they are done. The [`FrameStats`](crate::FrameStats) returned by [`Self::end_frame()`]
+    /// count the glyphs that were left out. Has no effect on wasm32, where no threads are
+    /// available.
+    pub fn set_glyph_worker_threads(&mut self, threads: usize) {
+        let threads = if cfg!(target_arch = "wasm32") { 0 } else { threads };
+
+        if threads == self.glyph_worker_threads() {
+            return;
+        }
+
+        // Glyphs that are in flight are rasterized again by the new workers
+        self.glyph_workers = if threads > 0 {
+            Some(GlyphWorkers::new(threads))
+        } else {
+            None
+        };
+    }
+
+    /// Returns the number of glyph worker threads, see [`Self::set_glyph_worker_threads()`].
+    pub fn glyph_worker_threads(&self) -> usize {
+        self.glyph_workers.as_ref().map_or(0, |workers| workers.threads.len())
+    }
+
+    // Number of glyph draws left out since the last flush, by the frame budget or because the
+    // workers weren't done with them yet
+    pub(crate) fn skipped_glyphs(&self) -> usize {
+        self.frame_budget.deferred_glyphs() + self.glyph_workers.as_ref().map_or(0, GlyphWorkers::skipped)
+    }
+
+    // Renders the glyphs the workers are done with into the atlases
+    pub(crate) fn receive_glyphs(&mut self) {
+        let workers = match &mut self.glyph_workers {
+            Some(workers) => workers,
+            None => return,
+        };
+
+        let jobs: Vec<GlyphJob> = workers.done.try_iter().collect();
+
+        for job in jobs {
+            if let Some(workers) = &mut self.glyph_workers {
+                workers.pending.remove(&job.id);
+            }
+
+            // Deterministic frames don't take glyphs whose rasterization started in another frame
+            if job.atlas_generation == self.glyph_atlas_generation && !self.deterministic {
+                let id = job.id;
+                let rendered = finish_glyph(self, job);
+                self.rendered_glyphs.insert(id, rendered);
+            }
+        }
+    }
+}
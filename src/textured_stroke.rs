@@ -0,0 +1,307 @@
+use rgb::RGBA8;
+
+use crate::{
+    geometry::Transform2D,
+    Canvas,
+    Color,
+    ErrorKind,
+    ImageId,
+    LineCap,
+    LineJoin,
+    MeshVertex,
+    Paint,
+    Path,
+    PathCache,
+    Renderer,
+};
+
+type Vec2 = (f32, f32);
+
+// The outline of the stroke across the path at a distance `u` along it
+#[derive(Copy, Clone)]
+struct Edge {
+    left: Vec2,
+    right: Vec2,
+    u: f32,
+}
+
+impl Edge {
+    fn new(point: Vec2, normal: Vec2, half_width: f32, u: f32) -> Self {
+        Self {
+            left: add(point, normal, half_width),
+            right: add(point, normal, -half_width),
+            u,
+        }
+    }
+}
+
+// Where two segments of the stroke meet: the edge the incoming segment ends at, the one the
+// outgoing segment starts at and the triangle that fills a bevel between them
+struct Join {
+    incoming: Edge,
+    outgoing: Edge,
+    bevel: Option<[(Vec2, f32); 3]>,
+}
+
+struct StrokeMesh {
+    vertices: Vec<MeshVertex>,
+    indices: Vec<u32>,
+    color: RGBA8,
+    half_width: f32,
+    pattern_length: f32,
+    line_join: LineJoin,
+    line_cap_start: LineCap,
+    line_cap_end: LineCap,
+    miter_limit: f32,
+}
+
+impl StrokeMesh {
+    fn vertex(&mut self, (x, y): Vec2, u: f32, v: f32) -> u32 {
+        let index = self.vertices.len() as u32;
+        self.vertices
+            .push(MeshVertex::new(x, y, u / self.pattern_length, v, self.color));
+        index
+    }
+
+    fn quad(&mut self, start: Edge, end: Edge) {
+        let a = self.vertex(start.left, start.u, 0.0);
+        let b = self.vertex(start.right, start.u, 1.0);
+        let c = self.vertex(end.left, end.u, 0.0);
+        let d = self.vertex(end.right, end.u, 1.0);
+
+        self.indices.extend_from_slice(&[a, b, c, b, d, c]);
+    }
+
+    fn triangle(&mut self, corners: [(Vec2, f32); 3], u: f32) {
+        for (point, v) in corners {
+            let index = self.vertex(point, u, v);
+            self.indices.push(index);
+        }
+    }
+
+    fn add_polyline(&mut self, points: &[Vec2], closed: bool) {
+        let mut points: Vec<Vec2> = points.to_vec();
+        points.dedup();
+
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+
+        let count = points.len();
+
+        if count < 2 {
+            return;
+        }
+
+        let segment_count = if closed { count } else { count - 1 };
+
+        // Direction and length of the segment that starts at each point
+        let segments: Vec<(Vec2, f32)> = (0..segment_count)
+            .map(|i| {
+                let (p0, p1) = (points[i], points[(i + 1) % count]);
+                let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+                let length = (dx * dx + dy * dy).sqrt();
+
+                ((dx / length, dy / length), length)
+            })
+            .collect();
+
+        let mut distances = Vec::with_capacity(count + 1);
+        distances.push(0.0);
+
+        for (_, length) in &segments {
+            distances.push(distances[distances.len() - 1] + length);
+        }
+
+        let joins: Vec<Join> = (0..count)
+            .map(|i| {
+                let point = points[i];
+
+                if !closed && i == 0 {
+                    let edge = self.cap(point, segments[0].0, self.line_cap_start, 0.0, -1.0);
+
+                    Join {
+                        incoming: edge,
+                        outgoing: edge,
+                        bevel: None,
+                    }
+                } else if !closed && i == count - 1 {
+                    let edge = self.cap(point, segments[i - 1].0, self.line_cap_end, distances[i], 1.0);
+
+                    Join {
+                        incoming: edge,
+                        outgoing: edge,
+                        bevel: None,
+                    }
+                } else {
+                    let before = segments[(i + count - 1) % count];
+                    let after = segments[i];
+
+                    // The closing point of a contour ends its last segment and starts the first one
+                    let u_in = if i == 0 { distances[count] } else { distances[i] };
+
+                    self.join(point, before, after, u_in, distances[i])
+                }
+            })
+            .collect();
+
+        for i in 0..segment_count {
+            let start = joins[i].outgoing;
+            let end = joins[(i + 1) % count].incoming;
+
+            self.quad(start, end);
+        }
+
+        for join in joins {
+            if let Some(bevel) = join.bevel {
+                self.triangle(bevel, join.outgoing.u);
+            }
+        }
+    }
+
+    // The end of an open contour, extended by half of the line width by square caps. `side` is -1
+    // at the start and 1 at the end.
+    fn cap(&self, point: Vec2, direction: Vec2, cap: LineCap, u: f32, side: f32) -> Edge {
+        let extension = match cap {
+            LineCap::Butt => 0.0,
+            LineCap::Round | LineCap::Square => self.half_width,
+        };
+
+        let point = add(point, direction, extension * side);
+
+        Edge::new(point, left_normal(direction), self.half_width, u + extension * side)
+    }
+
+    fn join(&self, point: Vec2, before: (Vec2, f32), after: (Vec2, f32), u_in: f32, u_out: f32) -> Join {
+        let (normal_in, normal_out) = (left_normal(before.0), left_normal(after.0));
+
+        // Miter direction scaled to reach the corner of the outlines, like in the path stroker
+        let mid = ((normal_in.0 + normal_out.0) * 0.5, (normal_in.1 + normal_out.1) * 0.5);
+        let mid_len2 = mid.0 * mid.0 + mid.1 * mid.1;
+
+        let miter = if mid_len2 > 1e-6 {
+            Some((mid.0 / mid_len2, mid.1 / mid_len2))
+        } else {
+            None
+        };
+
+        if let Some(miter) = miter {
+            if self.line_join == LineJoin::Miter && mid_len2 * self.miter_limit * self.miter_limit >= 1.0 {
+                return Join {
+                    incoming: Edge::new(point, miter, self.half_width, u_in),
+                    outgoing: Edge::new(point, miter, self.half_width, u_out),
+                    bevel: None,
+                };
+            }
+        }
+
+        let mut incoming = Edge::new(point, normal_in, self.half_width, u_in);
+        let mut outgoing = Edge::new(point, normal_out, self.half_width, u_out);
+
+        // Turning right puts the outside of the corner on the left
+        let turn = before.0 .0 * after.0 .1 - before.0 .1 * after.0 .0;
+        let outer_left = turn > 0.0;
+
+        // The insides of the segments meet where their outlines cross, unless that is past their ends
+        if let Some(miter) = miter {
+            let reach = self.half_width / mid_len2.sqrt();
+
+            if reach <= before.1.min(after.1) {
+                if outer_left {
+                    incoming.right = add(point, miter, -self.half_width);
+                    outgoing.right = incoming.right;
+                } else {
+                    incoming.left = add(point, miter, self.half_width);
+                    outgoing.left = incoming.left;
+                }
+            }
+        }
+
+        let bevel = if outer_left {
+            [(point, 0.5), (incoming.left, 0.0), (outgoing.left, 0.0)]
+        } else {
+            [(point, 0.5), (incoming.right, 1.0), (outgoing.right, 1.0)]
+        };
+
+        Join {
+            incoming,
+            outgoing,
+            bevel: Some(bevel),
+        }
+    }
+}
+
+// The normal on the left of a direction, in coordinates that grow downwards
+fn left_normal(direction: Vec2) -> Vec2 {
+    (direction.1, -direction.0)
+}
+
+fn add(point: Vec2, direction: Vec2, distance: f32) -> Vec2 {
+    (point.0 + direction.0 * distance, point.1 + direction.1 * distance)
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Strokes the path with an image laid along it, e.g. for textured brushes, ropes or railway
+    /// lines on a map.
+    ///
+    /// The horizontal axis of the image follows the path and the vertical one spans the line width
+    /// of `paint`, with the top of the image on the left side of the path. One copy of the image
+    /// covers `pattern_length` of the path, so an image created with
+    /// [`ImageFlags::REPEAT_X`](crate::ImageFlags::REPEAT_X) repeats along it, and a length of
+    /// `line_width * image_width / image_height` keeps its aspect ratio. The pattern starts over at
+    /// each sub-path.
+    ///
+    /// The line join, caps and miter limit of `paint` apply, round joins and caps are drawn like
+    /// bevel joins and square caps. A solid color paint tints the image. The stroke is drawn like
+    /// [`Self::draw_mesh()`], so its edges aren't anti-aliased; images with transparent top and
+    /// bottom rows give soft edges.
+    ///
+    /// Returns [`ErrorKind::ImageIdNotFound`] if the image is not valid.
+    pub fn stroke_path_textured(
+        &mut self,
+        path: &Path,
+        image: ImageId,
+        pattern_length: f32,
+        paint: Paint,
+    ) -> Result<(), ErrorKind> {
+        self.image_info(image)?;
+
+        if !(paint.line_width > 0.0 && pattern_length > 0.0) {
+            return Ok(());
+        }
+
+        // The mesh is built in the current coordinates, curves are flattened for the device pixels
+        let scale = self.state().transform.average_scale().max(f32::EPSILON);
+
+        let mut cache = PathCache::default();
+        cache.rebuild(
+            path.verbs(),
+            &Transform2D::identity(),
+            self.tessellation_tolerance() / scale,
+            self.dist_tol / scale,
+        );
+
+        let color = paint.solid_color().unwrap_or_else(Color::white).premultiplied();
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let mut mesh = StrokeMesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            color: RGBA8::new(channel(color.r), channel(color.g), channel(color.b), channel(color.a)),
+            half_width: paint.line_width * 0.5,
+            pattern_length,
+            line_join: paint.line_join,
+            line_cap_start: paint.line_cap_start,
+            line_cap_end: paint.line_cap_end,
+            miter_limit: paint.miter_limit,
+        };
+
+        for (points, closed) in cache.polylines() {
+            mesh.add_polyline(&points, closed);
+        }
+
+        self.draw_mesh(&mesh.vertices, &mesh.indices, Some(image));
+
+        Ok(())
+    }
+}
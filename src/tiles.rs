@@ -0,0 +1,313 @@
+use std::sync::mpsc::{
+    self,
+    Receiver,
+    Sender,
+};
+
+use fnv::{
+    FnvBuildHasher,
+    FnvHashSet,
+};
+use imgref::ImgVec;
+use lru::LruCache;
+use rgb::RGBA8;
+
+use crate::{
+    Canvas,
+    ErrorKind,
+    ImageFlags,
+    ImageId,
+    Paint,
+    Path,
+    Renderer,
+};
+
+/// Address of a tile in a [`TileSet`].
+///
+/// Level `zoom` of the pyramid is a grid of `2^zoom` by `2^zoom` tiles, `x` and `y` count tiles from
+/// the top left corner, as in the usual slippy map tile scheme.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TileKey {
+    pub zoom: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileKey {
+    pub fn new(zoom: u8, x: u32, y: u32) -> Self {
+        Self { zoom, x, y }
+    }
+
+    /// The tile one zoom level up that covers this tile.
+    pub fn parent(self) -> Option<Self> {
+        if self.zoom == 0 {
+            None
+        } else {
+            Some(Self::new(self.zoom - 1, self.x / 2, self.y / 2))
+        }
+    }
+}
+
+/// The part of the map drawn by [`TileSet::draw()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileView {
+    /// Map position shown in the middle of the view, in pixels of zoom level 0.
+    pub center: (f32, f32),
+    /// Fractional zoom level, every whole level doubles the size of the map.
+    pub zoom: f32,
+    /// Size of the view in canvas coordinates, its top left corner is at the origin.
+    pub size: (f32, f32),
+}
+
+/// Sends decoded tiles to a [`TileSet`] from any thread.
+#[derive(Clone)]
+pub struct TileSender(Sender<(TileKey, ImgVec<RGBA8>)>);
+
+impl TileSender {
+    /// Queues the tile for upload, returns false if the tile set was dropped.
+    pub fn send(&self, key: TileKey, image: ImgVec<RGBA8>) -> bool {
+        self.0.send((key, image)).is_ok()
+    }
+}
+
+struct Tile {
+    image: ImageId,
+    opacity: f32,
+}
+
+/// A pyramid of image tiles for slippy map style applications.
+///
+/// The tile set decides which tiles a view needs, keeps the most recently drawn ones uploaded and
+/// cross-fades between zoom levels: until a tile is available, or while it fades in, the closest
+/// loaded tile of a lower zoom level is drawn in its place.
+///
+/// Fetching and decoding tiles is left to the application, typically on background threads that
+/// hand the decoded pixels over through a [`TileSender`]. The pixels are uploaded on the rendering
+/// thread by [`TileSet::upload()`], a few tiles per frame so that uploads don't cause hitches.
+///
+/// # Example
+/// ```no_run
+/// # use femtovg::{renderer::Void, Canvas, TileSet, TileView};
+/// # let mut canvas = Canvas::new(Void).unwrap();
+/// let mut tiles = TileSet::new(256, 512);
+/// let sender = tiles.sender();
+///
+/// // Every frame
+/// tiles.upload(&mut canvas, 4).unwrap();
+///
+/// let view = TileView { center: (128.0, 128.0), zoom: 3.5, size: (800.0, 600.0) };
+///
+/// for key in tiles.draw(&mut canvas, view, 1.0 / 60.0) {
+///     // Fetch and decode the tile, then queue it with `sender.send(key, image)`
+/// }
+/// ```
+pub struct TileSet {
+    tile_size: f32,
+    capacity: usize,
+    max_zoom: u8,
+    fade_duration: f32,
+    tiles: LruCache<TileKey, Tile, FnvBuildHasher>,
+    requested: FnvHashSet<TileKey>,
+    sender: Sender<(TileKey, ImgVec<RGBA8>)>,
+    receiver: Receiver<(TileKey, ImgVec<RGBA8>)>,
+}
+
+impl TileSet {
+    /// Creates a tile set for square tiles of `tile_size` pixels that keeps at most `capacity` tiles
+    /// uploaded.
+    pub fn new(tile_size: usize, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            tile_size: tile_size as f32,
+            capacity: capacity.max(1),
+            max_zoom: 19,
+            fade_duration: 0.25,
+            tiles: LruCache::unbounded_with_hasher(FnvBuildHasher::default()),
+            requested: FnvHashSet::default(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Sets the highest zoom level tiles exist for. Views zoomed in further scale up the tiles of
+    /// that level. Defaults to 19.
+    pub fn set_max_zoom(&mut self, max_zoom: u8) {
+        self.max_zoom = max_zoom.min(31);
+    }
+
+    /// Sets how long newly uploaded tiles take to fade in, in seconds. Defaults to 0.25.
+    pub fn set_fade_duration(&mut self, seconds: f32) {
+        self.fade_duration = seconds.max(0.0);
+    }
+
+    /// Returns a handle that queues decoded tiles for upload from any thread.
+    pub fn sender(&self) -> TileSender {
+        TileSender(self.sender.clone())
+    }
+
+    /// Queues a decoded tile for upload.
+    pub fn insert(&mut self, key: TileKey, image: ImgVec<RGBA8>) {
+        let _ = self.sender.send((key, image));
+    }
+
+    /// Forgets that the tile was requested so that [`TileSet::draw()`] asks for it again, e.g. after
+    /// fetching it failed.
+    pub fn cancel_request(&mut self, key: TileKey) {
+        self.requested.remove(&key);
+    }
+
+    /// Returns true if the tile is uploaded.
+    pub fn contains(&self, key: TileKey) -> bool {
+        self.tiles.contains(&key)
+    }
+
+    /// Uploads up to `max_tiles` queued tiles and evicts the least recently drawn tiles beyond the
+    /// capacity. Returns the number of uploaded tiles.
+    pub fn upload<T: Renderer>(&mut self, canvas: &mut Canvas<T>, max_tiles: usize) -> Result<usize, ErrorKind> {
+        let mut uploaded = 0;
+
+        while uploaded < max_tiles {
+            let (key, image) = match self.receiver.try_recv() {
+                Ok(tile) => tile,
+                Err(_) => break,
+            };
+
+            self.requested.remove(&key);
+
+            let id = canvas.create_image(image.as_ref(), ImageFlags::empty())?;
+
+            if let Some(replaced) = self.tiles.put(
+                key,
+                Tile {
+                    image: id,
+                    opacity: 0.0,
+                },
+            ) {
+                canvas.delete_image(replaced.image);
+            }
+
+            uploaded += 1;
+        }
+
+        while self.tiles.len() > self.capacity {
+            if let Some((_, evicted)) = self.tiles.pop_lru() {
+                canvas.delete_image(evicted.image);
+            }
+        }
+
+        Ok(uploaded)
+    }
+
+    /// Deletes all uploaded tiles.
+    pub fn clear<T: Renderer>(&mut self, canvas: &mut Canvas<T>) {
+        while let Some((_, tile)) = self.tiles.pop_lru() {
+            canvas.delete_image(tile.image);
+        }
+
+        self.requested.clear();
+    }
+
+    /// Draws the view and advances the fade in of new tiles by `dt` seconds.
+    ///
+    /// Returns the visible tiles that aren't uploaded yet, each tile is only returned once until it
+    /// is uploaded or its request is canceled.
+    pub fn draw<T: Renderer>(&mut self, canvas: &mut Canvas<T>, view: TileView, dt: f32) -> Vec<TileKey> {
+        let zoom = view.zoom.round().clamp(0.0, self.max_zoom as f32) as u8;
+        let tiles_per_side = 1u64 << zoom;
+
+        let map_size = self.tile_size * view.zoom.exp2();
+        let tile_size = map_size / tiles_per_side as f32;
+        let origin_x = view.size.0 / 2.0 - view.center.0 / self.tile_size * map_size;
+        let origin_y = view.size.1 / 2.0 - view.center.1 / self.tile_size * map_size;
+
+        let visible = |origin: f32, size: f32| {
+            let first = ((-origin / tile_size).floor().max(0.0) as u64).min(tiles_per_side);
+            let last = (((size - origin) / tile_size).ceil().max(0.0) as u64).min(tiles_per_side);
+            first..last
+        };
+
+        let fade = if self.fade_duration > 0.0 {
+            dt / self.fade_duration
+        } else {
+            1.0
+        };
+
+        let mut missing = Vec::new();
+
+        for y in visible(origin_y, view.size.1) {
+            for x in visible(origin_x, view.size.0) {
+                let key = TileKey::new(zoom, x as u32, y as u32);
+                let rect = (
+                    origin_x + x as f32 * tile_size,
+                    origin_y + y as f32 * tile_size,
+                    tile_size,
+                );
+
+                let opacity = match self.tiles.get_mut(&key) {
+                    Some(tile) => {
+                        tile.opacity = (tile.opacity + fade).min(1.0);
+                        Some((tile.image, tile.opacity))
+                    }
+                    None => None,
+                };
+
+                match opacity {
+                    Some((_, opacity)) if opacity >= 1.0 => (),
+                    _ => self.draw_fallback(canvas, key, rect),
+                }
+
+                match opacity {
+                    Some((image, opacity)) => Self::draw_tile(canvas, image, rect, rect, opacity),
+                    None => {
+                        if self.requested.insert(key) {
+                            missing.push(key);
+                        }
+                    }
+                }
+            }
+        }
+
+        missing
+    }
+
+    // Draws the part of the closest loaded ancestor that covers the tile
+    fn draw_fallback<T: Renderer>(&mut self, canvas: &mut Canvas<T>, key: TileKey, rect: (f32, f32, f32)) {
+        let mut ancestor = key.parent();
+
+        while let Some(parent) = ancestor {
+            if let Some(tile) = self.tiles.get(&parent) {
+                if tile.opacity > 0.0 {
+                    let levels = key.zoom - parent.zoom;
+                    let size = rect.2 * (1u64 << levels) as f32;
+                    let x = rect.0 - (key.x - (parent.x << levels)) as f32 * rect.2;
+                    let y = rect.1 - (key.y - (parent.y << levels)) as f32 * rect.2;
+
+                    Self::draw_tile(canvas, tile.image, rect, (x, y, size), tile.opacity);
+                    return;
+                }
+            }
+
+            ancestor = parent.parent();
+        }
+    }
+
+    // Fills `rect` with the part of the tile image positioned at `image_rect`
+    fn draw_tile<T: Renderer>(
+        canvas: &mut Canvas<T>,
+        image: ImageId,
+        rect: (f32, f32, f32),
+        image_rect: (f32, f32, f32),
+        opacity: f32,
+    ) {
+        let (x, y, size) = image_rect;
+
+        // Neighbouring tiles would show seams along anti-aliased edges
+        let mut paint = Paint::image(image, x, y, size, size, 0.0, opacity);
+        paint.set_anti_alias(false);
+
+        let mut path = Path::new();
+        path.rect(rect.0, rect.1, rect.2, rect.2);
+        canvas.fill_path(&mut path, paint);
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{
+    self,
+    Display,
+    Formatter,
+};
+
+use crate::{
+    paint::PaintFlavor,
+    Canvas,
+    ImageId,
+    Paint,
+    Renderer,
+};
+
+/// A problem found by the validation mode, see [`Canvas::set_validation()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ValidationWarning {
+    /// The canvas call that was validated, e.g. `"fill_path"`.
+    pub call: &'static str,
+    pub issue: ValidationIssue,
+}
+
+/// The kinds of problems that make a draw render nothing or garbage without failing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ValidationIssue {
+    /// The text position has NaN or infinite coordinates. Paths drop them as they are built, see
+    /// [`Path`](crate::Path).
+    NonFiniteCoordinates,
+    /// The current transform has NaN or infinite components, or scales everything to nothing.
+    DegenerateTransform,
+    /// The scissor rectangle has no area, everything is clipped away.
+    EmptyScissor,
+    /// The paint draws with an image that was deleted.
+    DeletedImage(ImageId),
+}
+
+impl Display for ValidationWarning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let issue = match self.issue {
+            ValidationIssue::NonFiniteCoordinates => "NaN or infinite coordinates".to_string(),
+            ValidationIssue::DegenerateTransform => "the transform is not invertible".to_string(),
+            ValidationIssue::EmptyScissor => "the scissor rectangle is empty".to_string(),
+            ValidationIssue::DeletedImage(id) => format!("the paint uses the deleted image {:?}", id),
+        };
+
+        write!(f, "{}: {}", self.call, issue)
+    }
+}
+
+impl<T: Renderer> Canvas<T> {
+    /// Enables or disables the validation mode, disabled by default.
+    ///
+    /// Many mistakes make draws render nothing without failing, e.g. coordinates that became NaN
+    /// after dividing by zero or a scissor that clips everything away. In validation mode, every
+    /// path and text draw checks the current state and its input and keeps a warning for each
+    /// problem it finds until [`Self::take_validation_warnings()`]. With the `tracing` feature, the
+    /// warnings are also emitted as `tracing` events. The mode is meant for debugging.
+    ///
+    /// To see which GL calls belong to which draw in a GPU debugger, see
+    /// [`OpenGl::set_debug_groups()`](crate::renderer::OpenGl::set_debug_groups).
+    pub fn set_validation(&mut self, enabled: bool) {
+        if enabled != self.validation.is_some() {
+            self.validation = if enabled { Some(Vec::new()) } else { None };
+        }
+    }
+
+    /// Returns true if the validation mode is enabled, see [`Self::set_validation()`].
+    pub fn validation(&self) -> bool {
+        self.validation.is_some()
+    }
+
+    /// Returns the warnings found since the last call, in the order of the draws.
+    pub fn take_validation_warnings(&mut self) -> Vec<ValidationWarning> {
+        self.validation.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // Checks a draw with the current state, `position` is where text is drawn and None for paths
+    pub(crate) fn validate_draw(&mut self, call: &'static str, position: Option<(f32, f32)>, paint: &Paint) {
+        if self.validation.is_none() {
+            return;
+        }
+
+        let mut issues = Vec::new();
+
+        if let Some((x, y)) = position {
+            if !(x.is_finite() && y.is_finite()) {
+                issues.push(ValidationIssue::NonFiniteCoordinates);
+            }
+        }
+
+        let transform = self.state().transform;
+        let determinant = transform[0] * transform[3] - transform[1] * transform[2];
+
+        if !transform.0.iter().all(|v| v.is_finite()) || determinant == 0.0 {
+            issues.push(ValidationIssue::DegenerateTransform);
+        }
+
+        if let Some([width, height]) = self.state().scissor.extent {
+            if !(width > 0.0 && height > 0.0) {
+                issues.push(ValidationIssue::EmptyScissor);
+            }
+        }
+
+        let images = match paint.flavor {
+            PaintFlavor::Image { id, .. } => vec![id],
+            PaintFlavor::ColorMap { id, lut, .. } => vec![id, lut],
+            _ => Vec::new(),
+        };
+
+        for id in images {
+            if !self.is_image_valid(id) {
+                issues.push(ValidationIssue::DeletedImage(id));
+            }
+        }
+
+        if let Some(warnings) = &mut self.validation {
+            for issue in issues {
+                let warning = ValidationWarning { call, issue };
+
+                #[cfg(feature = "tracing")]
+                tracing::warn!("{}", warning);
+
+                warnings.push(warning);
+            }
+        }
+    }
+}
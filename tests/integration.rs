@@ -4,6 +4,7 @@ use femtovg::{
     Canvas,
     Color,
     FillRule,
+    LineBreak,
     Paint,
     Path,
     Solidity,
@@ -129,6 +130,27 @@ fn degenerate_arc() {
     canvas.stroke_path(&mut path, Paint::color(Color::rgb(100, 100, 100)));
 }
 
+#[test]
+fn stroke_polyline_decimated() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(200, 100, 1.0);
+
+    let mut points: Vec<[f32; 2]> = (0..100_000)
+        .map(|i| {
+            let t = i as f32 / 100_000.0;
+            [t * 200.0, 50.0 + (t * 3000.0).sin() * 40.0]
+        })
+        .collect();
+    points[500][1] = f32::NAN;
+
+    let paint = Paint::color(Color::black());
+
+    canvas.stroke_polyline_decimated(&points, paint, 1.0);
+    canvas.stroke_polyline_decimated(&points[..1], paint, 1.0);
+    canvas.stroke_polyline_decimated(&[], paint, 0.0);
+    canvas.flush();
+}
+
 #[test]
 fn path_contains_point() {
     let mut canvas = Canvas::new(Void).unwrap();
@@ -153,6 +175,7 @@ fn path_contains_point() {
     assert!(canvas.contains_point(&mut path, 50.0, 5.0, FillRule::NonZero));
 }
 
+#[cfg(feature = "text")]
 #[test]
 fn text_location_respects_scale() {
     let mut canvas = Canvas::new(Void).unwrap();
@@ -171,6 +194,7 @@ fn text_location_respects_scale() {
     assert_eq!(res.y, 100.0);
 }
 
+#[cfg(feature = "text")]
 #[test]
 fn text_measure_without_canvas() {
     let text_context = femtovg::TextContext::default();
@@ -191,6 +215,203 @@ fn text_measure_without_canvas() {
     assert_eq!(metrics.height().ceil(), 13.);
 }
 
+#[cfg(feature = "text")]
+#[test]
+fn text_measure_fast() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(400, 100, 2.0);
+
+    let latin = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let arabic = canvas
+        .add_font("examples/assets/amiri-regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[latin, arabic]);
+    paint.set_font_size(16.0);
+    paint.set_letter_spacing(1.0);
+
+    for text in ["Hello World", "Café »naïve«", ""] {
+        let shaped = canvas.measure_text(0.0, 0.0, text, paint).unwrap().width();
+        let fast = canvas.measure_text_fast(text, paint).unwrap();
+
+        // Only kerning is missing
+        assert!(
+            (fast - shaped).abs() <= shaped * 0.02,
+            "{}: {} vs {}",
+            text,
+            fast,
+            shaped
+        );
+    }
+
+    // Complex scripts are shaped
+    let text = "ab سلام";
+    let shaped = canvas.measure_text(0.0, 0.0, text, paint).unwrap().width();
+    assert_eq!(canvas.measure_text_fast(text, paint).unwrap(), shaped);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn text_records_after_flush() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(200, 200, 1.0);
+
+    let font_id = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font_id]);
+
+    canvas.set_text_recording(true);
+    canvas.translate(10.0, 20.0);
+    canvas.fill_text(0.0, 0.0, "Hello World", paint).unwrap();
+
+    assert!(canvas.text_records().is_empty());
+
+    canvas.flush();
+
+    let records = canvas.text_records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].text, "Hello World");
+    assert_eq!(records[0].bounds.x, records[0].local_bounds.x + 10.0);
+    assert_eq!(records[0].bounds.y, records[0].local_bounds.y + 20.0);
+
+    canvas.flush();
+    assert!(canvas.text_records().is_empty());
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn fill_glyph_grid() {
+    use femtovg::{
+        CellStyle,
+        ErrorKind,
+        GridCell,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(200, 200, 1.0);
+
+    let mut cells: Vec<GridCell> = "ab cd\tef"
+        .chars()
+        .map(|ch| GridCell::new(ch, Color::white()))
+        .collect();
+    cells[1].bg = Some(Color::rgb(0, 0, 128));
+    cells[3].style = CellStyle::BOLD | CellStyle::ITALIC | CellStyle::UNDERLINE;
+
+    let paint = Paint::color(Color::black());
+
+    assert!(matches!(
+        canvas.fill_glyph_grid((0.0, 0.0), (8.0, 16.0), 4, &cells, paint),
+        Err(ErrorKind::NoFontFound)
+    ));
+
+    canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    canvas
+        .fill_glyph_grid((10.0, 10.0), (8.0, 16.0), 4, &cells, paint)
+        .unwrap();
+    canvas
+        .fill_glyph_grid((0.0, 0.0), (8.0, 16.0), 0, &cells, paint)
+        .unwrap();
+    canvas.flush();
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn caret_and_preedit_in_bidi_text() {
+    use femtovg::Preedit;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(400, 100, 1.0);
+
+    let latin = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let arabic = canvas
+        .add_font("examples/assets/amiri-regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[latin, arabic]);
+    paint.set_font_size(20.0);
+
+    let text = "ab سلام";
+    let metrics = canvas.fill_text(10.0, 50.0, text, paint).unwrap();
+
+    assert_eq!(metrics.caret_x(text, 0), 10.0);
+    assert!(metrics.caret_x(text, 1) > 10.0);
+
+    // The first Arabic letter is the rightmost one, its caret is on its right side
+    let first_arabic = text.find('س').unwrap();
+    let last_arabic = text.rfind('م').unwrap();
+    assert!(metrics.caret_x(text, first_arabic) > metrics.caret_x(text, last_arabic));
+
+    // "b" and the first Arabic letter are far apart on screen
+    let spans = metrics.range_spans(1..first_arabic + 2);
+    assert_eq!(spans.len(), 2);
+    assert!(spans[0].1 < spans[1].0);
+
+    let preedit = Preedit {
+        range: first_arabic..text.len(),
+        clauses: vec![first_arabic..last_arabic, last_arabic..text.len()],
+        target_clause: Some(1),
+        cursor: Some(text.len()),
+    };
+
+    canvas.draw_preedit(50.0, text, &metrics, &preedit, paint).unwrap();
+    canvas.flush();
+}
+
+#[test]
+fn dpi_change_callback() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let low_res = canvas
+        .create_image_empty(4, 4, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::empty())
+        .unwrap();
+    let high_res = canvas
+        .create_image_empty(8, 8, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::empty())
+        .unwrap();
+    canvas.set_image_scale(low_res, 1.0);
+    canvas.set_image_scale(high_res, 2.0);
+
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    let recorded = changes.clone();
+    canvas.set_dpi_change_callback(move |old, new, images| recorded.borrow_mut().push((old, new, images.to_vec())));
+
+    canvas.set_size(100, 100, 2.0);
+    canvas.set_size(200, 200, 2.0);
+
+    assert_eq!(*changes.borrow(), vec![(1.0, 2.0, vec![low_res])]);
+}
+
+#[test]
+fn reset_surface_discards_pending_commands() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(100, 100, 1.0);
+
+    let mut path = Path::new();
+    path.rect(10.0, 10.0, 20.0, 20.0);
+    canvas.fill_path(&mut path, Paint::color(Color::black()));
+
+    canvas.reset_surface(200, 100, 1.0);
+
+    assert_eq!(canvas.width(), 200.0);
+    assert_eq!(canvas.end_frame().vertices, 0);
+}
+
+#[cfg(feature = "text")]
 #[test]
 fn font_measure_without_canvas() {
     let text_context = femtovg::TextContext::default();
@@ -210,6 +431,7 @@ fn font_measure_without_canvas() {
     assert_eq!(metrics.ascender().ceil(), 17.);
 }
 
+#[cfg(feature = "text")]
 #[test]
 fn break_text_without_canvas() {
     let text_context = femtovg::TextContext::default();
@@ -236,3 +458,2806 @@ fn break_text_without_canvas() {
         vec!["Multiple", " Lines ", "Broken"]
     );
 }
+
+#[test]
+fn recorder_is_send_and_submits() {
+    fn assert_send<T: Send>() {}
+    assert_send::<femtovg::CanvasRecorder>();
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let recorder = canvas.recorder();
+
+    let recorder = std::thread::spawn(move || {
+        let mut recorder = recorder;
+        let mut path = Path::new();
+        path.rect(10.0, 10.0, 50.0, 50.0);
+        recorder.translate(5.0, 5.0);
+        recorder.fill_path(&path, Paint::color(Color::rgb(100, 100, 100)));
+        recorder.stroke_path(&path, Paint::color(Color::rgb(100, 100, 100)));
+        recorder
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(recorder.len(), 2);
+
+    canvas.submit(recorder).unwrap();
+
+    // Submitting must not leak the recorded state into the canvas
+    assert_eq!(canvas.transform(), femtovg::Transform2D::identity());
+}
+
+#[test]
+fn viewport_maps_world_to_screen() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let world = femtovg::Rect::new(-10.0, -10.0, 20.0, 20.0);
+    let screen = femtovg::Rect::new(0.0, 0.0, 200.0, 100.0);
+
+    canvas.set_viewport(world, screen, true);
+
+    assert_eq!(canvas.world_to_screen(-10.0, -10.0), (0.0, 100.0));
+    assert_eq!(canvas.world_to_screen(10.0, 10.0), (200.0, 0.0));
+    assert_eq!(canvas.screen_to_world(100.0, 50.0), (0.0, 0.0));
+
+    canvas.set_viewport(world, screen, false);
+
+    assert_eq!(canvas.world_to_screen(-10.0, -10.0), (0.0, 0.0));
+    assert_eq!(canvas.screen_to_world(200.0, 100.0), (10.0, 10.0));
+}
+
+#[test]
+fn draw_with_scratch_path() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    for i in 0..3 {
+        let mode = if i % 2 == 0 {
+            femtovg::DrawMode::Fill
+        } else {
+            femtovg::DrawMode::Stroke
+        };
+
+        canvas.draw(
+            |path| {
+                assert!(path.is_empty());
+                path.rect(10.0, 10.0, 20.0, 20.0);
+            },
+            Paint::color(Color::rgb(100, 100, 100)),
+            mode,
+        );
+    }
+}
+
+#[test]
+fn end_frame_reports_unbalanced_state() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.save();
+    canvas.translate(10.0, 10.0);
+    let mut path = Path::new();
+    path.rect(10.0, 10.0, 20.0, 20.0);
+    canvas.fill_path(&mut path, Paint::color(Color::rgb(100, 100, 100)));
+
+    let stats = canvas.end_frame();
+    assert!(stats.commands > 0);
+    assert!(stats.vertices > 0);
+    assert_eq!(stats.unmatched_saves, 1);
+    assert!(!stats.is_balanced());
+
+    // The next frame starts out clean
+    canvas.begin_frame(100, 100, 1.0);
+    assert_eq!(canvas.transform(), femtovg::Transform2D::identity());
+    canvas.restore();
+
+    let stats = canvas.end_frame();
+    assert_eq!(stats.unmatched_saves, 0);
+    assert_eq!(stats.unmatched_restores, 1);
+}
+
+#[test]
+fn end_frame_merges_commands() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let draw = |canvas: &mut Canvas<Void>| {
+        canvas.begin_frame(100, 100, 1.0);
+
+        for i in 0..10 {
+            let mut path = Path::new();
+            path.rect(i as f32 * 10.0, 10.0, 5.0, 5.0);
+            canvas.fill_path(&mut path, Paint::color(Color::rgb(100, 100, 100)));
+        }
+
+        for i in 0..10 {
+            let mut path = Path::new();
+            path.rect(i as f32 * 10.0, 30.0, 5.0, 5.0);
+
+            // Stencil strokes can't be merged
+            let mut paint = Paint::color(Color::rgb(100, 100, 100));
+            paint.set_stencil_strokes(false);
+            canvas.stroke_path(&mut path, paint);
+        }
+
+        let mut path = Path::new();
+        path.rect(10.0, 50.0, 5.0, 5.0);
+        canvas.fill_path(&mut path, Paint::color(Color::rgb(200, 0, 0)));
+
+        canvas.end_frame()
+    };
+
+    // The one pixel wide strokes use the same uniforms as the fills and all twenty are merged
+    let batched = draw(&mut canvas);
+    assert_eq!(batched.merged_commands, 19);
+
+    canvas.set_command_batching(false);
+
+    let unbatched = draw(&mut canvas);
+    assert_eq!(unbatched.merged_commands, 0);
+    assert_eq!(unbatched.commands, batched.commands + 19);
+    // Merged commands index the vertices of the original commands instead of copying them
+    assert_eq!(unbatched.vertices, batched.vertices);
+}
+
+#[test]
+fn image_fit_rects() {
+    use femtovg::{
+        Align2D,
+        Fit,
+        Rect,
+    };
+
+    let dst = Rect::new(0.0, 0.0, 100.0, 50.0);
+
+    assert_eq!(Fit::Fill.rect(10.0, 10.0, dst, Align2D::CENTER), dst);
+    assert_eq!(
+        Fit::Contain.rect(10.0, 10.0, dst, Align2D::CENTER),
+        Rect::new(25.0, 0.0, 50.0, 50.0)
+    );
+    assert_eq!(
+        Fit::Cover.rect(10.0, 10.0, dst, Align2D::TOP_LEFT),
+        Rect::new(0.0, 0.0, 100.0, 100.0)
+    );
+    assert_eq!(
+        Fit::ScaleDown.rect(10.0, 10.0, dst, Align2D::BOTTOM_RIGHT),
+        Rect::new(90.0, 40.0, 10.0, 10.0)
+    );
+}
+
+#[test]
+fn draw_image_fit_with_missing_image() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let id = canvas
+        .create_image_empty(10, 10, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::empty())
+        .unwrap();
+    canvas.delete_image(id);
+
+    let dst = femtovg::Rect::new(0.0, 0.0, 100.0, 50.0);
+    assert!(canvas
+        .draw_image_fit(id, dst, femtovg::Fit::Contain, Default::default())
+        .is_err());
+}
+
+#[test]
+fn clear_rect_with_paint() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    let rect = femtovg::Rect::new(0.0, 0.0, 100.0, 100.0);
+
+    // Solid colors are cleared without generating any geometry
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.clear_rect_with_paint(rect, &Paint::color(Color::rgb(100, 100, 100)));
+    let stats = canvas.end_frame();
+    assert_eq!(stats.vertices, 0);
+
+    let paint = Paint::linear_gradient(0.0, 0.0, 0.0, 100.0, Color::black(), Color::white());
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.translate(50.0, 50.0);
+    canvas.clear_rect_with_paint(rect, &paint);
+    assert_eq!(canvas.transform()[4], 50.0);
+    let stats = canvas.end_frame();
+    assert!(stats.vertices > 0);
+
+    // Overlays start out transparent, on the screen and on images alike
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.clear_transparent();
+    let image = canvas
+        .create_image_empty(16, 16, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::PREMULTIPLIED)
+        .unwrap();
+    canvas.set_render_target(femtovg::RenderTarget::Image(image));
+    canvas.clear_transparent();
+    canvas.set_render_target(femtovg::RenderTarget::Screen);
+    let stats = canvas.end_frame();
+    assert_eq!(stats.vertices, 0);
+}
+
+#[test]
+fn restore_resources_keeps_image_ids() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let kept = canvas
+        .create_image_empty(16, 8, PixelFormat::Rgba8, ImageFlags::REPEAT_X)
+        .unwrap();
+
+    let resources = canvas.serialize_resources();
+    assert_eq!(resources.images().len(), 1);
+
+    let dropped = canvas
+        .create_image_empty(4, 4, PixelFormat::Gray8, ImageFlags::empty())
+        .unwrap();
+
+    canvas.restore_resources(Void, &resources).unwrap();
+
+    let info = canvas.image_info(kept).unwrap();
+    assert_eq!((info.width(), info.height()), (16, 8));
+    assert_eq!(info.flags(), ImageFlags::REPEAT_X);
+    assert!(canvas.image_info(dropped).is_err());
+}
+
+#[test]
+#[cfg(feature = "image-loading")]
+fn load_qoi_image() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut data = b"qoif".to_vec();
+    data.extend_from_slice(&3u32.to_be_bytes());
+    data.extend_from_slice(&2u32.to_be_bytes());
+    data.extend_from_slice(&[4, 0]);
+    data.extend_from_slice(&[0xfe, 255, 0, 0, 0xc4]);
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let image = canvas.load_image_mem(&data, ImageFlags::empty()).unwrap();
+    let info = canvas.image_info(image).unwrap();
+    assert_eq!((info.width(), info.height()), (3, 2));
+    assert_eq!(info.format(), PixelFormat::Rgba8);
+
+    // Stream ends one pixel short
+    let run = data.len() - 9;
+    data[run] = 0xc3;
+    assert!(canvas.load_image_mem(&data, ImageFlags::empty()).is_err());
+}
+
+#[test]
+fn aliased_paint_skips_fringe() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let mut vertices = |anti_alias: bool| {
+        let mut paint = Paint::color(Color::rgb(100, 100, 100));
+        paint.set_anti_alias(anti_alias);
+
+        canvas.begin_frame(100, 100, 1.0);
+        let mut path = Path::new();
+        path.rect(10.0, 10.0, 20.0, 20.0);
+        canvas.fill_path(&mut path, paint);
+        canvas.end_frame().vertices
+    };
+
+    assert!(vertices(false) < vertices(true));
+}
+
+#[test]
+fn deleted_image_is_not_drawn() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let id = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    assert!(canvas.is_image_valid(id));
+
+    canvas.delete_image(id);
+    assert!(!canvas.is_image_valid(id));
+
+    // The slot gets reused with a new generation
+    let new_id = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    assert_ne!(id, new_id);
+    assert!(!canvas.is_image_valid(id));
+
+    canvas.begin_frame(100, 100, 1.0);
+    let mut path = Path::new();
+    path.rect(0.0, 0.0, 16.0, 16.0);
+    canvas.fill_path(&mut path, Paint::image(id, 0.0, 0.0, 16.0, 16.0, 0.0, 1.0));
+    assert_eq!(canvas.end_frame().vertices, 0);
+}
+
+#[test]
+fn color_map_is_not_drawn_without_its_colors() {
+    use femtovg::{
+        ImageFlags,
+        PaintKind,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let values = canvas
+        .create_image_empty(16, 16, PixelFormat::Gray8, ImageFlags::empty())
+        .unwrap();
+    let lut = canvas
+        .create_image_empty(8, 1, PixelFormat::Rgba8, ImageFlags::NEAREST)
+        .unwrap();
+
+    let paint = Paint::color_map(values, lut, 0.0, 0.0, 16.0, 16.0, 0.0, 1.0);
+    assert!(matches!(paint.kind(), PaintKind::ColorMap { id, lut: colors, .. } if id == values && colors == lut));
+
+    let mut path = Path::new();
+    path.rect(0.0, 0.0, 16.0, 16.0);
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.fill_path(&mut path, paint);
+    assert!(canvas.end_frame().vertices > 0);
+
+    canvas.delete_image(lut);
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.fill_path(&mut path, paint);
+    assert_eq!(canvas.end_frame().vertices, 0);
+}
+
+#[test]
+fn draw_mesh() {
+    use femtovg::{
+        ImageFlags,
+        MeshVertex,
+        PixelFormat,
+    };
+    use rgb::RGBA8;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let white = RGBA8::new(255, 255, 255, 255);
+    let vertices = [
+        MeshVertex::new(0.0, 0.0, 0.0, 0.0, white),
+        MeshVertex::new(10.0, 0.0, 1.0, 0.0, white),
+        MeshVertex::new(10.0, 10.0, 1.0, 1.0, white),
+        MeshVertex::new(0.0, 10.0, 0.0, 1.0, white),
+    ];
+
+    // The second triangle refers to a vertex that doesn't exist
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.draw_mesh(&vertices, &[0, 1, 2, 0, 2, 4], None);
+    assert_eq!(canvas.end_frame().vertices, 3);
+
+    let id = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.draw_mesh(&vertices, &[0, 1, 2, 0, 2, 3], Some(id));
+    assert_eq!(canvas.end_frame().vertices, 6);
+
+    canvas.delete_image(id);
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.draw_mesh(&vertices, &[0, 1, 2, 0, 2, 3], Some(id));
+    assert_eq!(canvas.end_frame().vertices, 0);
+}
+
+#[test]
+fn fill_triangles() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let vertices = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.fill_triangles(&vertices, &[0, 1, 2, 0, 2, 3, 0, 3, 9], Paint::color(Color::black()));
+    assert_eq!(canvas.end_frame().vertices, 6);
+}
+
+#[cfg(feature = "lyon")]
+#[test]
+fn path_from_lyon() {
+    use lyon_path::math::point;
+
+    let mut builder = lyon_path::Path::builder();
+    builder.begin(point(0.0, 0.0));
+    builder.line_to(point(10.0, 0.0));
+    builder.quadratic_bezier_to(point(10.0, 10.0), point(0.0, 10.0));
+    builder.end(true);
+
+    let mut path = Path::from(builder.build());
+
+    let verbs: Vec<String> = path.verbs().map(|verb| format!("{:?}", verb)).collect();
+    assert_eq!(verbs.len(), 4);
+    assert_eq!(verbs[3], "Close");
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.fill_path(&mut path, Paint::color(Color::black()));
+}
+
+#[test]
+fn tile_set_falls_back_to_parent() {
+    use femtovg::{
+        TileKey,
+        TileSet,
+        TileView,
+    };
+    use imgref::ImgVec;
+    use rgb::RGBA8;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(256, 256, 1.0);
+
+    let mut tiles = TileSet::new(16, 4);
+    let view = TileView {
+        center: (8.0, 8.0),
+        zoom: 1.0,
+        size: (32.0, 32.0),
+    };
+
+    let tile = || ImgVec::new(vec![RGBA8::new(255, 0, 0, 255); 16 * 16], 16, 16);
+
+    let mut missing = tiles.draw(&mut canvas, view, 0.1);
+    missing.sort_by_key(|key| (key.y, key.x));
+    assert_eq!(
+        missing,
+        vec![
+            TileKey::new(1, 0, 0),
+            TileKey::new(1, 1, 0),
+            TileKey::new(1, 0, 1),
+            TileKey::new(1, 1, 1)
+        ]
+    );
+
+    // Requested tiles are only reported once
+    assert!(tiles.draw(&mut canvas, view, 0.1).is_empty());
+
+    tiles.sender().send(TileKey::new(0, 0, 0), tile());
+    for y in 0..2 {
+        for x in 0..2 {
+            tiles.insert(TileKey::new(1, x, y), tile());
+        }
+    }
+
+    assert_eq!(tiles.upload(&mut canvas, 3).unwrap(), 3);
+    assert_eq!(tiles.upload(&mut canvas, 3).unwrap(), 2);
+
+    // Over capacity, the least recently used tile is evicted
+    assert!(!tiles.contains(TileKey::new(0, 0, 0)));
+    assert!(tiles.contains(TileKey::new(1, 1, 1)));
+
+    tiles.cancel_request(TileKey::new(0, 0, 0));
+    assert!(tiles.draw(&mut canvas, view, 0.1).is_empty());
+    canvas.flush();
+
+    tiles.clear(&mut canvas);
+    assert!(!tiles.contains(TileKey::new(1, 1, 1)));
+}
+
+#[test]
+fn context2d_saves_styles() {
+    use femtovg::Context2D;
+
+    let canvas = Canvas::new(Void).unwrap();
+    let mut ctx = Context2D::new(canvas);
+
+    ctx.save();
+    ctx.set_line_width(5.0);
+    ctx.set_font("bold 16px/1.2 sans-serif");
+    assert_eq!((ctx.line_width(), ctx.font_size()), (5.0, 16.0));
+    ctx.restore();
+    assert_eq!((ctx.line_width(), ctx.font_size()), (1.0, 10.0));
+
+    let mut gradient = ctx.create_linear_gradient(0.0, 0.0, 100.0, 0.0);
+    gradient.add_color_stop(0.0, Color::rgb(255, 0, 0));
+    gradient.add_color_stop(1.0, Color::rgb(0, 0, 255));
+
+    ctx.canvas_mut().begin_frame(100, 100, 1.0);
+    ctx.set_fill_style(gradient);
+    ctx.begin_path();
+    ctx.move_to(10.0, 10.0);
+    ctx.arc(50.0, 50.0, 20.0, 0.0, std::f32::consts::PI, false);
+    ctx.close_path();
+    ctx.fill();
+    ctx.stroke_rect(0.0, 0.0, 10.0, 10.0);
+    assert!(ctx.canvas_mut().end_frame().vertices > 0);
+}
+
+#[test]
+fn context2d_clear_rect_follows_the_transform() {
+    use femtovg::Context2D;
+
+    let mut ctx = Context2D::new(Canvas::new(Void).unwrap());
+    ctx.canvas_mut().begin_frame(100, 100, 2.0);
+
+    // Moved off the render target by the transform, so nothing is left to clear
+    ctx.translate(110.0, 0.0);
+    ctx.clear_rect(0.0, 0.0, 10.0, 10.0);
+
+    let stats = ctx.canvas_mut().end_frame();
+    assert_eq!((stats.vertices, stats.culled_draws), (0, 1));
+
+    // Inside the target the rectangle is filled like any other path, in the current user space
+    ctx.canvas_mut().begin_frame(100, 100, 2.0);
+    ctx.scale(0.5, 0.5);
+    ctx.clear_rect(0.0, 0.0, 10.0, 10.0);
+    assert!(ctx.canvas_mut().end_frame().vertices > 0);
+
+    // The scissor clips the cleared rectangle away too
+    ctx.canvas_mut().begin_frame(100, 100, 2.0);
+    ctx.reset_transform();
+    ctx.canvas_mut().scissor(50.0, 50.0, 10.0, 10.0);
+    ctx.clear_rect(0.0, 0.0, 10.0, 10.0);
+    assert_eq!(ctx.canvas_mut().end_frame().culled_draws, 1);
+}
+
+#[cfg(feature = "svg")]
+#[test]
+fn draw_svg_picture() {
+    let data = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10" viewBox="0 0 40 20">
+        <linearGradient id="g"><stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/></linearGradient>
+        <rect width="40" height="20" fill="url(#g)" stroke="#000" stroke-width="2"/>
+    </svg>"##;
+
+    let mut picture = femtovg::svg::Picture::from_data(data).unwrap();
+    assert_eq!((picture.width(), picture.height()), (20.0, 10.0));
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.draw_picture(&mut picture);
+    assert!(canvas.end_frame().vertices > 0);
+
+    assert!(femtovg::svg::Picture::from_data(b"not svg").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn recorder_json_roundtrip() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    let mut recorder = canvas.recorder();
+
+    let mut path = Path::new();
+    path.rect(10.0, 10.0, 20.0, 20.0);
+
+    recorder.translate(5.0, 5.0);
+    recorder.fill_path(
+        &path,
+        Paint::linear_gradient(0.0, 0.0, 10.0, 0.0, Color::black(), Color::white()),
+    );
+    recorder.set_global_alpha(0.5);
+    recorder.stroke_path(&path, Paint::color(Color::rgb(100, 100, 100)));
+
+    let json = serde_json::to_string(&recorder).unwrap();
+    let decoded: femtovg::CanvasRecorder = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+
+    canvas.submit(decoded).unwrap();
+
+    let mut image_paint_recorder = canvas.recorder();
+    let image = canvas
+        .create_image_empty(4, 4, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::empty())
+        .unwrap();
+    image_paint_recorder.fill_path(&path, Paint::image(image, 0.0, 0.0, 4.0, 4.0, 0.0, 1.0));
+
+    assert!(serde_json::to_string(&image_paint_recorder).is_err());
+}
+
+#[cfg(feature = "lottie")]
+#[test]
+fn draw_lottie_animation() {
+    let data = br#"{
+        "v": "5.7.0", "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 50,
+        "layers": [{
+            "ty": 4, "ind": 1, "ip": 0, "op": 30, "st": 0,
+            "ks": {
+                "p": {"a": 1, "k": [
+                    {"t": 0, "s": [0, 0], "o": {"x": 0, "y": 0}, "i": {"x": 1, "y": 1}},
+                    {"t": 30, "s": [50, 0]}
+                ]}
+            },
+            "shapes": [{
+                "ty": "gr",
+                "it": [
+                    {"ty": "rc", "p": {"a": 0, "k": [10, 10]}, "s": {"a": 0, "k": [20, 20]}, "r": {"a": 0, "k": 4}},
+                    {"ty": "el", "p": {"a": 0, "k": [40, 10]}, "s": {"a": 0, "k": [10, 10]}},
+                    {"ty": "st", "c": {"a": 0, "k": [0, 0, 0, 1]}, "o": {"a": 0, "k": 100}, "w": {"a": 0, "k": 2}},
+                    {"ty": "fl", "c": {"a": 0, "k": [1, 0, 0, 1]}, "o": {"a": 0, "k": 50}},
+                    {"ty": "tr", "p": {"a": 0, "k": [0, 0]}, "s": {"a": 0, "k": [100, 100]}}
+                ]
+            }]
+        }]
+    }"#;
+
+    let animation = femtovg::lottie::Animation::from_json(data).unwrap();
+    assert_eq!((animation.width(), animation.height()), (100.0, 50.0));
+    assert_eq!(animation.duration(), 2.0);
+    assert_eq!(animation.frame_at(2.5), 15.0);
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    canvas.begin_frame(100, 50, 1.0);
+    canvas.draw_animation(&animation, 15.0);
+    assert!(canvas.end_frame().vertices > 0);
+
+    // The layer ends before the animation does
+    canvas.begin_frame(100, 50, 1.0);
+    canvas.draw_animation(&animation, 45.0);
+    assert_eq!(canvas.end_frame().vertices, 0);
+
+    assert!(femtovg::lottie::Animation::from_json(b"{}").is_err());
+}
+
+#[test]
+fn export_recording_as_svg_and_pdf() {
+    let canvas = Canvas::new(Void).unwrap();
+    let mut recorder = canvas.recorder();
+
+    let mut path = Path::new();
+    path.move_to(10.0, 10.0);
+    path.bezier_to(20.0, 0.0, 30.0, 20.0, 40.0, 10.0);
+    path.close();
+
+    recorder.scissor(0.0, 0.0, 50.0, 50.0);
+    recorder.fill_path(&path, Paint::color(Color::rgba(255, 0, 0, 128)));
+    recorder.stroke_path(
+        &path,
+        Paint::linear_gradient(0.0, 0.0, 40.0, 0.0, Color::black(), Color::white()),
+    );
+    recorder.fill_text(5.0, 5.0, "a < b", Paint::color(Color::black()));
+
+    let svg = recorder.to_svg(100.0, 100.0);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains(r#"d="M10 10 C20 0 30 20 40 10 Z""#));
+    assert!(svg.contains("<linearGradient"));
+    assert!(svg.contains("<clipPath"));
+    assert!(svg.contains("a &lt; b"));
+
+    let pdf = recorder.to_pdf(100.0, 100.0);
+    let pdf = String::from_utf8(pdf).unwrap();
+    assert!(pdf.starts_with("%PDF-1.4"));
+    assert!(pdf.contains("10 10 m\n20 0 30 20 40 10 c\nh\nf"));
+    assert!(pdf.contains("/ShadingType 2"));
+    assert!(pdf.trim_end().ends_with("%%EOF"));
+}
+
+#[test]
+fn exported_holes_keep_their_winding() {
+    let canvas = Canvas::new(Void).unwrap();
+    let mut recorder = canvas.recorder();
+
+    let mut path = Path::new();
+    path.rect(0.0, 0.0, 100.0, 100.0);
+    path.rect(25.0, 25.0, 50.0, 50.0);
+    path.solidity(Solidity::Hole);
+    recorder.fill_path(&path, Paint::color(Color::black()));
+
+    // The hole is traced backwards, so the nonzero fill rule leaves it empty like the canvas does
+    let svg = recorder.to_svg(100.0, 100.0);
+    assert!(svg.contains(r#"d="M0 0 L0 100 L100 100 L100 0 Z M75 25 L75 75 L25 75 L25 25 Z""#));
+
+    let pdf = String::from_utf8(recorder.to_pdf(100.0, 100.0)).unwrap();
+    assert!(pdf.contains("h\n75 25 m\n75 75 l\n25 75 l\n25 25 l\nh\nf"));
+
+    // Solid contours are wound the other way round
+    let mut path = Path::new();
+    path.move_to(0.0, 0.0);
+    path.line_to(10.0, 0.0);
+    path.bezier_to(10.0, 5.0, 5.0, 10.0, 0.0, 10.0);
+    path.close();
+
+    let mut recorder = canvas.recorder();
+    recorder.fill_path(&path, Paint::color(Color::black()));
+
+    let svg = recorder.to_svg(100.0, 100.0);
+    assert!(svg.contains(r#"d="M0 10 C5 10 10 5 10 0 L0 0 Z""#));
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn new_glyphs_render_ahead_of_the_frame() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Cannot add font");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    let draw = |canvas: &mut Canvas<Void>| {
+        canvas.begin_frame(100, 100, 1.0);
+        canvas.fill_text(10.0, 20.0, "Hello", paint).unwrap();
+        canvas.fill_text(10.0, 40.0, "World", paint).unwrap();
+        canvas.end_frame()
+    };
+
+    let first = draw(&mut canvas);
+    assert!(first.glyph_commands > 0);
+
+    let second = draw(&mut canvas);
+    assert_eq!(second.glyph_commands, 0);
+    assert!(second.commands < first.commands);
+}
+
+#[test]
+fn draw_paths_matches_individual_draws() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let make_paths = || -> Vec<Path> {
+        (0..300)
+            .map(|i| {
+                let mut path = Path::new();
+                path.rounded_rect((i % 20) as f32 * 10.0, (i / 20) as f32 * 10.0, 8.0, 8.0, 2.0);
+                path
+            })
+            .collect()
+    };
+
+    let mode = |i: usize| {
+        if i % 3 == 0 {
+            femtovg::DrawMode::Stroke
+        } else {
+            femtovg::DrawMode::Fill
+        }
+    };
+    let paint = Paint::color(Color::rgb(100, 100, 100));
+
+    let mut paths = make_paths();
+    canvas.begin_frame(200, 200, 1.0);
+    canvas.draw_paths(paths.iter_mut().enumerate().map(|(i, path)| (path, paint, mode(i))));
+    let batched = canvas.end_frame();
+
+    let mut paths = make_paths();
+    canvas.begin_frame(200, 200, 1.0);
+    for (i, path) in paths.iter_mut().enumerate() {
+        match mode(i) {
+            femtovg::DrawMode::Fill => canvas.fill_path(path, paint),
+            femtovg::DrawMode::Stroke => canvas.stroke_path(path, paint),
+        }
+    }
+    let individual = canvas.end_frame();
+
+    assert_eq!(batched, individual);
+    assert!(batched.commands > 0);
+}
+
+#[test]
+fn tessellation_cache_reuses_moved_paths() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_tessellation_cache_capacity(16);
+
+    let mut path = Path::new();
+    path.rounded_rect(0.0, 0.0, 40.0, 20.0, 5.0);
+
+    let paint = Paint::color(Color::rgb(100, 100, 100));
+
+    let mut vertices = Vec::new();
+
+    for i in 0..4 {
+        canvas.begin_frame(200, 200, 1.0);
+        canvas.translate(10.0 + i as f32 * 7.5, 20.0);
+        canvas.rotate(i as f32 * 0.3);
+        canvas.fill_path(&mut path, paint);
+        canvas.stroke_path(&mut path, paint);
+        vertices.push(canvas.end_frame().vertices);
+    }
+
+    let stats = canvas.tessellation_cache_stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.hits, 6);
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.hit_rate(), 0.75);
+
+    // Every frame draws the same geometry
+    assert!(vertices.iter().all(|count| *count == vertices[0]));
+
+    canvas.clear_tessellation_cache();
+    assert_eq!(canvas.tessellation_cache_stats(), Default::default());
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn text_run_matches_fill_text() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Cannot add font");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    // The second frame finds the glyphs in the atlas
+    let mut expected = None;
+    let mut plain = Default::default();
+
+    for _ in 0..2 {
+        canvas.begin_frame(200, 100, 1.0);
+        expected = Some(canvas.fill_text(10.0, 20.0, "Hello World", paint).unwrap());
+        plain = canvas.end_frame();
+    }
+
+    let expected = expected.unwrap();
+
+    let mut run = femtovg::TextRun::new(10.0, 20.0, "Hello World");
+    assert!(run.metrics().is_none());
+
+    for _ in 0..2 {
+        canvas.begin_frame(200, 100, 1.0);
+        let metrics = canvas.fill_text_run(&mut run, paint).unwrap();
+        assert_eq!(canvas.end_frame(), plain);
+        assert_eq!(metrics.width(), expected.width());
+    }
+
+    // Moving the transform reuses the shaped glyphs
+    canvas.begin_frame(200, 100, 1.0);
+    canvas.translate(5.0, 5.0);
+    let moved = canvas.fill_text_run(&mut run, paint).unwrap();
+    canvas.end_frame();
+    assert_eq!(moved.x, expected.x);
+
+    run.set_text("Hello");
+    assert!(run.metrics().is_none());
+
+    canvas.begin_frame(200, 100, 1.0);
+    let shorter = canvas.fill_text_run(&mut run, paint).unwrap();
+    canvas.end_frame();
+    assert!(shorter.width() < expected.width());
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn shaping_is_stable_across_cache_resets() {
+    let text_context = femtovg::TextContext::default();
+
+    let latin = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let arabic = text_context
+        .add_font_file("examples/assets/amiri-regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::default();
+    paint.set_font(&[latin, arabic]);
+    paint.set_font_size(20.0);
+
+    let glyphs = |metrics: femtovg::TextMetrics| -> Vec<(usize, f32)> {
+        metrics.glyphs.iter().map(|glyph| (glyph.byte_index, glyph.x)).collect()
+    };
+
+    let text = "ab سلام عليكم cd";
+    let first = glyphs(text_context.measure_text(0.0, 0.0, text, paint).unwrap());
+
+    // Enough distinct strings to drop the interned shaping keys
+    for i in 0..5_000 {
+        text_context.measure_text(0.0, 0.0, i.to_string(), paint).unwrap();
+    }
+
+    let second = glyphs(text_context.measure_text(0.0, 0.0, text, paint).unwrap());
+
+    assert_eq!(first, second);
+
+    // The words of the right-to-left run are laid out right to left
+    let salam = text.find('س').unwrap();
+    let alaykum = text.find('ع').unwrap();
+    let x_of = |index: usize| first.iter().find(|glyph| glyph.0 == index).unwrap().1;
+    assert!(x_of(salam) > x_of(alaykum));
+}
+
+#[test]
+fn scissor_culls_hidden_draws() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let draw = |canvas: &mut Canvas<Void>, hidden_draws: bool| {
+        canvas.begin_frame(200, 200, 1.0);
+        canvas.scissor(0.0, 0.0, 50.0, 50.0);
+
+        if hidden_draws {
+            let mut hidden = Path::new();
+            hidden.rect(100.0, 100.0, 40.0, 40.0);
+            canvas.fill_path(&mut hidden, Paint::color(Color::black()));
+
+            canvas.fill_triangles(
+                &[[100.0, 100.0], [140.0, 100.0], [100.0, 140.0]],
+                &[0, 1, 2],
+                Paint::color(Color::black()),
+            );
+        }
+
+        // The stroke's miter reaches into the scissor although the path doesn't
+        let mut corner = Path::new();
+        corner.move_to(60.0, 80.0);
+        corner.line_to(60.0, 60.0);
+        corner.line_to(80.0, 60.0);
+        let mut paint = Paint::color(Color::black());
+        paint.set_line_width(30.0);
+        canvas.stroke_path(&mut corner, paint);
+
+        canvas.end_frame()
+    };
+
+    let with_hidden = draw(&mut canvas, true);
+    let without_hidden = draw(&mut canvas, false);
+
+    assert_eq!(with_hidden.culled_draws, 2);
+    assert_eq!(without_hidden.culled_draws, 0);
+    assert_eq!(with_hidden.commands, without_hidden.commands);
+    assert_eq!(with_hidden.vertices, without_hidden.vertices);
+    assert!(with_hidden.vertices > 0);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn frame_budget_degrades_late_draws() {
+    use std::time::Duration;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut text_paint = Paint::color(Color::black());
+    text_paint.set_font(&[font]);
+
+    let mut draw = |canvas: &mut Canvas<Void>| {
+        canvas.begin_frame(200, 200, 1.0);
+
+        let mut path = Path::new();
+        path.circle(100.0, 100.0, 50.0);
+        canvas.fill_path(&mut path, Paint::color(Color::black()));
+
+        let vertices = canvas.end_frame().vertices;
+
+        canvas.begin_frame(200, 200, 1.0);
+        canvas
+            .fill_text(10.0, 50.0, "abcdefghijklmnopqrstuvwxyz", text_paint)
+            .unwrap();
+
+        (vertices, canvas.end_frame())
+    };
+
+    // Every frame is over a budget this short
+    canvas.set_frame_budget(Duration::from_nanos(1));
+    let (degraded_vertices, degraded) = draw(&mut canvas);
+
+    assert!(degraded.degraded);
+    assert!(degraded.deferred_glyphs > 0);
+
+    canvas.set_frame_budget(Duration::ZERO);
+    let (vertices, stats) = draw(&mut canvas);
+
+    assert!(!stats.degraded);
+    assert_eq!(stats.deferred_glyphs, 0);
+    assert!(degraded_vertices < vertices);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn memory_report_tracks_trims() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    canvas.begin_frame(200, 200, 1.0);
+    canvas.fill_text(10.0, 50.0, "Memory", paint).unwrap();
+    canvas
+        .create_image_empty(16, 8, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.end_frame();
+
+    let report = canvas.memory_report();
+
+    assert!(report.shaped_runs > 0);
+    assert!(report.glyph_atlases > 0);
+    assert!(report.rendered_glyphs > 0);
+    assert!(report.glyph_atlas_bytes > 0);
+    assert_eq!(report.images, 1);
+    assert_eq!(report.image_bytes, 16 * 8 * 4);
+    assert!(report.vertex_buffer_bytes > 0);
+
+    canvas.clear_shaping_caches();
+    canvas.clear_glyph_atlases();
+    canvas.flush();
+    canvas.shrink_buffers();
+
+    let trimmed = canvas.memory_report();
+
+    assert_eq!(trimmed.shaped_runs, 0);
+    assert_eq!(trimmed.interned_strings, 0);
+    assert_eq!(trimmed.glyph_atlases, 0);
+    assert_eq!(trimmed.glyph_atlas_bytes, 0);
+    assert_eq!(trimmed.image_bytes, report.image_bytes);
+    assert_eq!(trimmed.vertex_buffer_bytes, 0);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn configurable_cache_sizes() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    canvas.set_shaping_cache_capacity(2);
+    canvas.set_glyph_atlas_size(128);
+
+    assert_eq!(canvas.shaping_cache_capacity(), 2);
+    assert_eq!(canvas.glyph_atlas_size(), 128);
+
+    canvas.begin_frame(200, 200, 1.0);
+
+    for text in ["one", "two", "three", "four"] {
+        canvas.fill_text(10.0, 50.0, text, paint).unwrap();
+    }
+
+    canvas.end_frame();
+
+    let report = canvas.memory_report();
+
+    assert_eq!(report.shaped_runs, 2);
+    assert!(report.glyph_atlases > 0);
+    assert_eq!(report.glyph_atlas_bytes, report.glyph_atlases * 128 * 128 * 4);
+
+    canvas.clear_caches();
+    canvas.flush();
+
+    let cleared = canvas.memory_report();
+
+    assert_eq!(cleared.shaped_runs, 0);
+    assert_eq!(cleared.glyph_atlases, 0);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn glyph_workers_rasterize_in_later_frames() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    canvas.set_glyph_worker_threads(2);
+    assert_eq!(canvas.glyph_worker_threads(), 2);
+
+    let mut frame = |canvas: &mut Canvas<Void>| {
+        canvas.begin_frame(200, 200, 1.0);
+        canvas.fill_text(10.0, 50.0, "Hello", paint).unwrap();
+        canvas.end_frame()
+    };
+
+    // The glyphs are left out until the workers are done with them
+    let first = frame(&mut canvas);
+    assert_eq!(first.pending_glyphs, 5);
+    assert_eq!(canvas.memory_report().rendered_glyphs, 0);
+
+    let mut stats = first;
+
+    for _ in 0..100 {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        stats = frame(&mut canvas);
+
+        if stats.pending_glyphs == 0 {
+            break;
+        }
+    }
+
+    assert_eq!(stats.pending_glyphs, 0);
+    assert_eq!(canvas.memory_report().rendered_glyphs, 4);
+    assert!(stats.vertices > 0);
+
+    canvas.set_glyph_worker_threads(0);
+    assert_eq!(canvas.glyph_worker_threads(), 0);
+}
+
+#[test]
+fn round_caps_follow_device_size() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let mut stroke_vertices = |line_width: f32, scale: f32, dpi: f32| {
+        canvas.begin_frame(1000, 1000, dpi);
+        canvas.scale(scale, scale);
+
+        let mut path = Path::new();
+        path.move_to(10.0, 10.0);
+        path.line_to(50.0, 50.0);
+
+        let mut paint = Paint::color(Color::black());
+        paint.set_line_width(line_width);
+        paint.set_line_cap(femtovg::LineCap::Round);
+
+        canvas.stroke_path(&mut path, paint);
+        canvas.end_frame().vertices
+    };
+
+    // The same on-screen width gets the same number of cap segments
+    assert_eq!(stroke_vertices(20.0, 0.1, 1.0), stroke_vertices(2.0, 1.0, 1.0));
+
+    // Small strokes get far fewer segments than large ones
+    assert!(stroke_vertices(1.0, 1.0, 1.0) * 4 < stroke_vertices(100.0, 1.0, 1.0));
+
+    // The tolerance shrinks with the device pixel ratio along with the pixels
+    assert_eq!(stroke_vertices(2.0, 1.0, 2.0), stroke_vertices(4.0, 1.0, 1.0));
+}
+
+#[test]
+fn path_changes_invalidate_its_flattening() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    let paint = Paint::color(Color::black());
+
+    let mut path = Path::new();
+    path.move_to(10.0, 10.0);
+    path.line_to(100.0, 10.0);
+    path.line_to(100.0, 100.0);
+
+    let mut fill_vertices = |path: &mut Path| {
+        canvas.begin_frame(200, 200, 1.0);
+        canvas.fill_path(path, paint);
+        canvas.end_frame().vertices
+    };
+
+    let triangle = fill_vertices(&mut path);
+    assert_eq!(fill_vertices(&mut path), triangle);
+
+    // Drawing the path again under the same transform must not reuse the flattened triangle
+    path.line_to(10.0, 100.0);
+
+    assert!(fill_vertices(&mut path) > triangle);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn trim_glyph_atlases_repacks_live_glyphs() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    canvas.set_glyph_atlas_size(128);
+
+    // Glyphs at many sizes spill into several atlases
+    canvas.begin_frame(200, 200, 1.0);
+
+    for size in 10..40 {
+        paint.set_font_size(size as f32);
+        canvas.fill_text(10.0, 50.0, "abcdefgh", paint).unwrap();
+    }
+
+    canvas.end_frame();
+
+    let spilled = canvas.memory_report().glyph_atlases;
+    assert!(spilled > 2);
+
+    // Only a short label stays on screen
+    paint.set_font_size(12.0);
+
+    let mut label = |canvas: &mut Canvas<Void>| {
+        canvas.begin_frame(200, 200, 1.0);
+        canvas.fill_text(10.0, 50.0, "ab", paint).unwrap();
+        canvas.end_frame();
+    };
+
+    label(&mut canvas);
+    label(&mut canvas);
+
+    // Everything drawn recently still needs the atlases
+    assert!(!canvas.trim_glyph_atlases(u64::MAX));
+    assert!(canvas.trim_glyph_atlases(1));
+
+    label(&mut canvas);
+    label(&mut canvas);
+
+    assert_eq!(canvas.memory_report().glyph_atlases, 1);
+    assert!(!canvas.trim_glyph_atlases(1));
+}
+
+#[test]
+fn checked_calls_report_errors() {
+    use femtovg::{
+        ErrorKind,
+        ImageFilter,
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    assert!(matches!(
+        canvas.try_set_size(100, 100, 0.0),
+        Err(ErrorKind::InvalidSize)
+    ));
+    assert!(canvas.try_set_size(0, 0, 2.0).is_ok());
+
+    assert!(matches!(canvas.try_restore(), Err(ErrorKind::UnbalancedSaveRestore)));
+    canvas.save();
+    assert!(canvas.try_restore().is_ok());
+
+    let image = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let target = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let blur = ImageFilter::GaussianBlur { sigma: 2.0 };
+
+    assert!(canvas.filter_image(target, blur, image).is_ok());
+
+    canvas.delete_image(image);
+
+    assert!(matches!(
+        canvas.filter_image(target, blur, image),
+        Err(ErrorKind::ImageIdNotFound)
+    ));
+    assert!(matches!(
+        canvas.filter_image(image, blur, target),
+        Err(ErrorKind::ImageIdNotFound)
+    ));
+    assert_eq!(ErrorKind::ImageIdNotFound.to_string(), "the image id is not valid");
+}
+
+#[test]
+fn validation_reports_draws_that_render_nothing() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+        ValidationIssue,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let paint = Paint::color(Color::black());
+    let mut path = Path::new();
+    path.rect(10.0, 10.0, 20.0, 20.0);
+
+    // Nothing is checked while the mode is disabled
+    canvas.scale(0.0, 0.0);
+    canvas.fill_path(&mut path, paint);
+    canvas.set_validation(true);
+    assert!(canvas.take_validation_warnings().is_empty());
+
+    canvas.fill_path(&mut path, paint);
+    canvas.reset();
+    canvas.fill_path(&mut path, paint);
+
+    canvas.intersect_scissor(0.0, 0.0, 50.0, 50.0);
+    canvas.intersect_scissor(60.0, 60.0, 10.0, 10.0);
+    canvas.stroke_path(&mut path, paint);
+    canvas.reset_scissor();
+
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.delete_image(image);
+    canvas.fill_path(&mut path, Paint::image(image, 0.0, 0.0, 4.0, 4.0, 0.0, 1.0));
+
+    let _ = canvas.fill_text(0.0, f32::INFINITY, "a", paint);
+
+    let warnings: Vec<_> = canvas
+        .take_validation_warnings()
+        .into_iter()
+        .map(|warning| (warning.call, warning.issue))
+        .collect();
+
+    assert_eq!(
+        warnings,
+        [
+            ("fill_path", ValidationIssue::DegenerateTransform),
+            ("stroke_path", ValidationIssue::EmptyScissor),
+            ("fill_path", ValidationIssue::DeletedImage(image)),
+            ("fill_text", ValidationIssue::NonFiniteCoordinates),
+        ]
+    );
+    assert!(canvas.take_validation_warnings().is_empty());
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn deterministic_frames_ignore_history() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    canvas.set_glyph_atlas_size(128);
+    canvas.set_frame_budget(std::time::Duration::from_nanos(1));
+    canvas.set_glyph_worker_threads(2);
+    canvas.set_tessellation_cache_capacity(16);
+    canvas.set_deterministic(true);
+    assert!(canvas.deterministic());
+
+    let mut star = Path::new();
+    star.move_to(50.0, 0.0);
+    star.line_to(80.0, 90.0);
+    star.line_to(0.0, 35.0);
+    star.line_to(100.0, 35.0);
+    star.line_to(20.0, 90.0);
+    star.close();
+
+    let mut label_paint = paint;
+    label_paint.set_font_size(12.0);
+
+    let mut label = |canvas: &mut Canvas<Void>| {
+        canvas.begin_frame(200, 200, 1.0);
+        canvas.translate(10.0, 10.0);
+        canvas.fill_path(&mut star, Paint::color(Color::black()));
+        canvas.fill_text(10.0, 50.0, "ab", label_paint).unwrap();
+        canvas.end_frame()
+    };
+
+    let first = label(&mut canvas);
+
+    // Neither the budget nor the workers leave anything out
+    assert!(!first.degraded);
+    assert_eq!(first.deferred_glyphs, 0);
+    assert_eq!(first.pending_glyphs, 0);
+
+    canvas.begin_frame(200, 200, 1.0);
+
+    for size in 10..40 {
+        paint.set_font_size(size as f32);
+        canvas.fill_text(10.0, 50.0, "abcdefgh", paint).unwrap();
+    }
+
+    canvas.end_frame();
+    assert!(canvas.memory_report().glyph_atlases > 2);
+
+    // The same frame after a different history produces the same work and fresh atlases
+    assert_eq!(label(&mut canvas), first);
+    assert_eq!(canvas.memory_report().glyph_atlases, 1);
+    assert_eq!(canvas.memory_report().rendered_glyphs, 2);
+    assert_eq!(canvas.tessellation_cache_stats().hits, 0);
+}
+
+#[test]
+fn extreme_paths_stay_bounded() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    // Round caps of a stroke wider than any render target
+    canvas.begin_frame(100, 100, 1.0);
+
+    let mut line = Path::new();
+    line.move_to(10.0, 10.0);
+    line.line_to(90.0, 90.0);
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_line_width(f32::MAX);
+    paint.set_line_cap(femtovg::LineCap::Round);
+    canvas.stroke_path(&mut line, paint);
+
+    assert!(canvas.end_frame().vertices < 10_000);
+
+    // Curves reaching far beyond the representable pixel grid
+    canvas.begin_frame(100, 100, 1.0);
+
+    let mut path = Path::new();
+    path.move_to(0.0, 0.0);
+
+    for i in 0..1000 {
+        let x = 1e30 * (i + 1) as f32;
+        path.bezier_to(x, 1e30, x, -1e30, x, 0.0);
+    }
+
+    path.close();
+    canvas.fill_path(&mut path, Paint::color(Color::black()));
+
+    assert!(canvas.end_frame().vertices < 10_000);
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "non-finite path coordinates"))]
+fn non_finite_inputs_are_dropped() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let mut path = Path::new();
+    path.move_to(10.0, 10.0);
+    path.line_to(f32::NAN, 20.0);
+    path.bezier_to(0.0, 0.0, f32::INFINITY, 0.0, 5.0, 5.0);
+    path.line_to(30.0, 30.0);
+
+    assert_eq!(path.verbs().count(), 2);
+
+    canvas.translate(10.0, 20.0);
+    let transform = canvas.transform();
+
+    canvas.scale(f32::INFINITY, 1.0);
+    canvas.rotate(f32::NAN);
+
+    assert_eq!(canvas.transform(), transform);
+}
+
+#[test]
+fn degenerate_inputs_stay_finite() {
+    // Debug builds would trip the assertion on non-finite coordinates
+    let mut path = Path::new();
+    path.move_to(10.0, 10.0);
+    path.arc_to(10.0, 10.0, 80.0, 80.0, 5.0);
+    path.arc(50.0, 50.0, 10.0, 1.0, 1.0, Solidity::Solid);
+
+    assert_eq!(path.verbs().count(), 4);
+
+    let singular = femtovg::Transform2D([0.0, 0.0, 0.0, 0.0, 10.0, 20.0]);
+
+    assert_eq!(singular.inversed(), femtovg::Transform2D::identity());
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.scale(0.0, 0.0);
+
+    assert!(canvas.screen_to_world(50.0, 50.0).0.is_finite());
+}
+
+#[test]
+fn deep_zoom_keeps_geometry_precise() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(400, 400, 1.0);
+
+    // Two steps of the f32 grid around 1000, 122 pixels wide at this zoom level
+    let step = 2.0 / 16384.0;
+    let mut path = Path::new();
+    path.rect(1000.0, 1000.0, step, step);
+
+    let expected = f64::from(step) * 1e6;
+
+    for compose in [false, true] {
+        canvas.reset_transform();
+
+        if compose {
+            canvas.translate(200.0, 200.0);
+            canvas.scale(1e6, 1e6);
+            canvas.translate(step - 1000.0, step - 1000.0);
+        } else {
+            canvas.set_transform(1e6, 0.0, 0.0, 1e6, -1e9, -1e9);
+        }
+
+        let bounds = canvas.path_bbox(&mut path);
+
+        assert!((f64::from(bounds.maxx - bounds.minx) - expected).abs() < 0.01);
+        assert!((f64::from(bounds.maxy - bounds.miny) - expected).abs() < 0.01);
+
+        // The winding is still found, so the rectangle is filled
+        let (x, y) = canvas.world_to_screen(1000.0 + step * 0.5, 1000.0 + step * 0.5);
+        assert!(canvas.contains_point(&mut path, x, y, FillRule::NonZero));
+    }
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn profiler_receives_nested_stages() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use femtovg::{
+        Profiler,
+        Stage,
+    };
+    use imgref::Img;
+    use rgb::RGBA8;
+
+    struct Recorder(Rc<RefCell<Vec<(Stage, bool)>>>);
+
+    impl Profiler for Recorder {
+        fn begin(&mut self, stage: Stage) {
+            self.0.borrow_mut().push((stage, true));
+        }
+
+        fn end(&mut self, stage: Stage) {
+            self.0.borrow_mut().push((stage, false));
+        }
+    }
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+    canvas.set_profiler(Some(Box::new(Recorder(events.clone()))));
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    let mut path = Path::new();
+    path.circle(50.0, 50.0, 20.0);
+    canvas.fill_path(&mut path, paint);
+    canvas.fill_text(10.0, 50.0, "femtovg", paint).unwrap();
+
+    let pixels = [RGBA8::new(0, 0, 0, 255); 4];
+    let image = canvas
+        .create_image(Img::new(&pixels[..], 2, 2), femtovg::ImageFlags::empty())
+        .unwrap();
+    canvas.delete_image(image);
+
+    canvas.flush();
+
+    let events = events.borrow();
+    let mut open = Vec::new();
+
+    for &(stage, begin) in events.iter() {
+        if begin {
+            open.push(stage);
+        } else {
+            assert_eq!(open.pop(), Some(stage));
+        }
+    }
+
+    assert!(open.is_empty());
+
+    for stage in [Stage::Tessellate, Stage::Shape, Stage::TextureUpload, Stage::Flush] {
+        assert!(events.contains(&(stage, true)), "{}", stage.name());
+    }
+}
+
+#[cfg(all(feature = "tracing", feature = "text"))]
+#[test]
+fn stages_are_traced_as_spans() {
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+
+    use tracing::{
+        span,
+        Event,
+        Metadata,
+        Subscriber,
+    };
+
+    #[derive(Default)]
+    struct Names(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for Names {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name());
+            span::Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let names = Names::default();
+    let recorded = names.0.clone();
+
+    tracing::subscriber::with_default(names, || {
+        let mut canvas = Canvas::new(Void).unwrap();
+        canvas.set_size(100, 100, 1.0);
+
+        let font = canvas
+            .add_font("examples/assets/Roboto-Regular.ttf")
+            .expect("Font not found");
+        let mut paint = Paint::color(Color::black());
+        paint.set_font(&[font]);
+
+        let mut path = Path::new();
+        path.circle(50.0, 50.0, 20.0);
+        canvas.fill_path(&mut path, paint);
+        canvas.fill_text(10.0, 50.0, "femtovg", paint).unwrap();
+        canvas.flush();
+    });
+
+    let recorded = recorded.lock().unwrap();
+
+    for name in ["femtovg::tessellate", "femtovg::shape", "femtovg::flush"] {
+        assert!(recorded.contains(&name), "{}", name);
+    }
+}
+
+#[cfg(feature = "debug_inspector")]
+#[test]
+fn debug_overlay_outlines_batches() {
+    use femtovg::DebugOverlay;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    // Returns the number of commands before merging
+    let mut frame = |canvas: &mut Canvas<Void>, flags: Option<DebugOverlay>| {
+        canvas.begin_frame(200, 200, 1.0);
+
+        let mut paint = Paint::color(Color::rgb(200, 0, 0));
+        paint.set_font(&[font]);
+
+        for x in [10.0, 60.0] {
+            let mut path = Path::new();
+            path.rect(x, 10.0, 40.0, 40.0);
+            canvas.fill_path(&mut path, paint);
+        }
+
+        let mut path = Path::new();
+        path.rect(110.0, 10.0, 40.0, 40.0);
+        canvas.fill_path(&mut path, Paint::color(Color::rgb(0, 0, 200)));
+
+        canvas.fill_text(10.0, 100.0, "femtovg", paint).unwrap();
+
+        if let Some(flags) = flags {
+            canvas.draw_debug_overlay(flags);
+        }
+
+        let stats = canvas.end_frame();
+        stats.commands + stats.merged_commands
+    };
+
+    // Renders the glyphs into the atlas
+    frame(&mut canvas, None);
+    let scene = frame(&mut canvas, None);
+
+    // The red rectangles, the blue one and the text are one batch each
+    assert_eq!(frame(&mut canvas, Some(DebugOverlay::BATCHES)), scene + 3);
+    assert_eq!(frame(&mut canvas, Some(DebugOverlay::GLYPH_ATLASES)), scene + 3);
+    assert_eq!(frame(&mut canvas, Some(DebugOverlay::WIREFRAME)), scene + 1);
+    assert_eq!(frame(&mut canvas, Some(DebugOverlay::OVERDRAW)), scene + 1);
+    assert_eq!(frame(&mut canvas, Some(DebugOverlay::all())), scene + 8);
+}
+
+#[test]
+fn end_frame_reports_state_misuse() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    canvas.begin_frame(100, 100, 1.0);
+    // The outer save is the one left unmatched
+    let save_line = line!() + 1;
+    canvas.save();
+    canvas.save();
+    canvas.scissor(10.0, 10.0, 50.0, 50.0);
+    canvas.restore();
+
+    let stats = canvas.end_frame();
+    assert_eq!(stats.unmatched_saves, 1);
+    assert!(!stats.scissor_left_applied);
+
+    if cfg!(debug_assertions) {
+        let location = stats.unmatched_save_location.unwrap();
+        assert_eq!((location.file(), location.line()), (file!(), save_line));
+    } else {
+        assert_eq!(stats.unmatched_save_location, None);
+    }
+
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.scissor(10.0, 10.0, 50.0, 50.0);
+
+    let stats = canvas.end_frame();
+    assert!(stats.is_balanced());
+    assert_eq!(stats.unmatched_save_location, None);
+    assert!(stats.scissor_left_applied);
+}
+
+#[test]
+fn image_ids_and_handles() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let stale = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.delete_image(stale);
+
+    // The new image may reuse the slot, the old id doesn't become valid again
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    assert_ne!(stale, image);
+    assert!(!canvas.is_image_valid(stale));
+
+    let handle = canvas.image_handle(image);
+    let clone = handle.clone();
+    drop(handle);
+    canvas.flush();
+    assert!(canvas.is_image_valid(clone.id()));
+
+    // Dropped on another thread, deleted by the next flush
+    std::thread::spawn(move || drop(clone)).join().unwrap();
+    assert!(canvas.is_image_valid(image));
+    canvas.flush();
+    assert!(!canvas.is_image_valid(image));
+}
+
+// Shapes of the nanovg demo, checked against how nanovg fills them
+#[test]
+fn nanovg_demo_scenes_fill_like_nanovg() {
+    use std::f32::consts::PI;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(400, 400, 1.0);
+
+    // Contours are rewound whichever direction they are drawn in, so a clockwise and a counter
+    // clockwise square nested in each other both stay solid with the nonzero rule
+    let mut nested = Path::new();
+    nested.move_to(10.0, 10.0);
+    nested.line_to(110.0, 10.0);
+    nested.line_to(110.0, 110.0);
+    nested.line_to(10.0, 110.0);
+    nested.close();
+    nested.move_to(40.0, 40.0);
+    nested.line_to(40.0, 80.0);
+    nested.line_to(80.0, 80.0);
+    nested.line_to(80.0, 40.0);
+    nested.close();
+    assert!(canvas.contains_point(&mut nested, 60.0, 60.0, FillRule::NonZero));
+    assert!(!canvas.contains_point(&mut nested, 60.0, 60.0, FillRule::EvenOdd));
+
+    // drawWindow(): the drop shadow is a rectangle with the window cut out as a hole
+    let mut shadow = Path::new();
+    shadow.rect(40.0, 42.0, 320.0, 330.0);
+    shadow.rounded_rect(50.0, 50.0, 300.0, 300.0, 3.0);
+    shadow.solidity(Solidity::Hole);
+    assert!(canvas.contains_point(&mut shadow, 45.0, 200.0, FillRule::NonZero));
+    assert!(!canvas.contains_point(&mut shadow, 200.0, 200.0, FillRule::NonZero));
+
+    // drawColorwheel(): a segment of the ring, swept clockwise on the outside (NVG_CW is a hole)
+    // and back counter clockwise on the inside
+    let (cx, cy, r1, r0) = (200.0, 200.0, 100.0, 80.0);
+    let (a0, a1) = (-0.25 * PI, 0.25 * PI);
+
+    let mut segment = Path::new();
+    segment.arc(cx, cy, r0, a0, a1, Solidity::Hole);
+    segment.arc(cx, cy, r1, a1, a0, Solidity::Solid);
+    segment.close();
+    assert!(canvas.contains_point(&mut segment, cx + 90.0, cy, FillRule::NonZero));
+    assert!(!canvas.contains_point(&mut segment, cx, cy + 90.0, FillRule::NonZero));
+    assert!(!canvas.contains_point(&mut segment, cx + 50.0, cy, FillRule::NonZero));
+
+    // Positive angles run clockwise on screen, through +y, like in nanovg
+    let mut quarter = Path::new();
+    quarter.move_to(cx, cy);
+    quarter.arc(cx, cy, r1, 0.0, 0.5 * PI, Solidity::Hole);
+    quarter.close();
+    assert!(canvas.contains_point(&mut quarter, cx + 50.0, cy + 50.0, FillRule::NonZero));
+    assert!(!canvas.contains_point(&mut quarter, cx + 50.0, cy - 50.0, FillRule::NonZero));
+
+    // drawSpinner(): an arc drawn counter clockwise from a0 to a1 takes the long way round
+    let mut spinner = Path::new();
+    spinner.move_to(cx, cy);
+    spinner.arc(cx, cy, r1, 0.0, 0.5 * PI, Solidity::Solid);
+    spinner.close();
+    assert!(!canvas.contains_point(&mut spinner, cx + 50.0, cy + 50.0, FillRule::NonZero));
+    assert!(canvas.contains_point(&mut spinner, cx - 50.0, cy - 50.0, FillRule::NonZero));
+
+    // drawEyes(), drawLines(): the defaults of a paint are those of a fresh nanovg state
+    let paint = Paint::default();
+    assert_eq!(paint.line_width(), 1.0);
+    assert_eq!(paint.miter_limit(), 10.0);
+    assert_eq!(paint.line_join(), femtovg::LineJoin::Miter);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn text_context_measures_on_other_threads() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(200, 200, 1.0);
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    let text_context = canvas.text_context().clone();
+    let measuring = std::thread::spawn(move || {
+        (0..50)
+            .map(|i| {
+                text_context
+                    .measure_text(0.0, 0.0, format!("line {}", i), paint)
+                    .unwrap()
+                    .width()
+            })
+            .collect::<Vec<f32>>()
+    });
+
+    for i in 0..50 {
+        let _ = canvas.fill_text(10.0, 20.0, format!("line {}", i), paint);
+        canvas.flush();
+    }
+
+    let widths = measuring.join().unwrap();
+
+    for (i, width) in widths.into_iter().enumerate() {
+        let metrics = canvas.measure_text(0.0, 0.0, format!("line {}", i), paint).unwrap();
+        assert_eq!(metrics.width(), width);
+    }
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn builder_applies_options() {
+    use femtovg::prelude::*;
+
+    let text_context = TextContext::default();
+    let font = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let canvas = Canvas::builder(Void)
+        .text_context(text_context)
+        .size(640, 480, 2.0)
+        .antialias(false)
+        .command_batching(false)
+        .shaping_cache_capacity(16)
+        .tessellation_cache_capacity(8)
+        .glyph_atlas_size(256)
+        .validation(true)
+        .build()
+        .unwrap();
+
+    assert_eq!((canvas.width(), canvas.height()), (640.0, 480.0));
+    assert!(!canvas.antialias());
+    assert!(canvas.validation());
+    assert!(canvas.is_font_valid(font));
+    assert_eq!(canvas.shaping_cache_capacity(), 16);
+    assert_eq!(canvas.glyph_atlas_size(), 256);
+
+    assert!(matches!(
+        Canvas::builder(Void).size(10, 10, 0.0).build(),
+        Err(ErrorKind::InvalidSize)
+    ));
+}
+
+#[test]
+fn geometry_converts_from_plain_types() {
+    use femtovg::{
+        Point,
+        Rect,
+        Transform2D,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let mut tuples = Path::new();
+    tuples.move_to_point((10.0, 10.0));
+    tuples.line_to_point([60.0, 10.0]);
+    tuples.quad_to_point(Point::new(60.0, 60.0), (10.0, 60.0));
+    tuples.close();
+    tuples.rect_from((70.0, 70.0, 20.0, 20.0));
+
+    let mut floats = Path::new();
+    floats.move_to(10.0, 10.0);
+    floats.line_to(60.0, 10.0);
+    floats.quad_to(60.0, 60.0, 10.0, 60.0);
+    floats.close();
+    floats.rect(70.0, 70.0, 20.0, 20.0);
+
+    assert_eq!(tuples.verbs().collect::<Vec<_>>(), floats.verbs().collect::<Vec<_>>());
+
+    canvas.apply_transform([2.0, 0.0, 0.0, 2.0, 5.0, 0.0]);
+    assert_eq!(canvas.transform(), Transform2D([2.0, 0.0, 0.0, 2.0, 5.0, 0.0]));
+
+    canvas.reset_transform();
+    canvas.set_viewport((0.0, 0.0, 10.0, 10.0), Rect::new(0.0, 0.0, 100.0, 100.0), false);
+    assert_eq!(canvas.screen_to_world(50.0, 50.0), (5.0, 5.0));
+}
+
+#[cfg(feature = "euclid")]
+#[test]
+fn geometry_converts_from_euclid() {
+    use femtovg::{
+        Point,
+        Rect,
+        Transform2D,
+    };
+
+    let point: Point = euclid::default::Point2D::new(1.0, 2.0).into();
+    assert_eq!(point, Point::new(1.0, 2.0));
+
+    let rect: Rect = euclid::default::Rect::new(euclid::point2(1.0, 2.0), euclid::size2(3.0, 4.0)).into();
+    assert_eq!(rect, Rect::new(1.0, 2.0, 3.0, 4.0));
+
+    let transform: Transform2D = euclid::default::Transform2D::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0).into();
+    assert_eq!(transform, Transform2D([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn rich_text_spans_share_a_baseline() {
+    use femtovg::{
+        PaintOverrides,
+        Span,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(400, 400, 1.0);
+
+    let regular = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let bold = canvas
+        .add_font("examples/assets/Roboto-Bold.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[regular]);
+    paint.set_font_size(16.0);
+
+    let emphasis = PaintOverrides {
+        color: Some(Color::rgb(255, 0, 0)),
+        font: Some(bold),
+        font_size: Some(24.0),
+        ..Default::default()
+    };
+
+    let spans = [Span::new("Total: "), Span::styled("42", emphasis), Span::new(" items")];
+    let metrics = canvas.fill_rich_text(10.0, 100.0, &spans, paint).unwrap();
+
+    let prefix = canvas.measure_text(0.0, 0.0, "Total: ", paint).unwrap();
+    let mut bold_paint = paint;
+    bold_paint.set_font(&[bold]);
+    bold_paint.set_font_size(24.0);
+    let number = canvas.measure_text(0.0, 0.0, "42", bold_paint).unwrap();
+
+    let baselines: Vec<f32> = metrics.glyphs.iter().map(|g| g.y + g.bearing_y).collect();
+    assert!(baselines.iter().all(|y| (y - baselines[0]).abs() < 0.01));
+
+    // The number starts where the prefix ends and the suffix where the number ends
+    let four = &metrics.glyphs[7];
+    assert_eq!(four.font_id, bold);
+    assert!((four.x - four.bearing_x - (10.0 + prefix.width())).abs() < 0.01);
+
+    let space = &metrics.glyphs[9];
+    assert_eq!(space.byte_index, 9);
+    assert!((space.x - space.bearing_x - (10.0 + prefix.width() + number.width())).abs() < 0.01);
+
+    let wrapped = canvas.fill_rich_paragraph(10.0, 100.0, 60.0, &spans, paint).unwrap();
+    let lines = {
+        let mut ys: Vec<i32> = wrapped
+            .glyphs
+            .iter()
+            .map(|g| (g.y + g.bearing_y).round() as i32)
+            .collect();
+        ys.dedup();
+        ys.len()
+    };
+
+    assert!(lines > 1);
+    assert!(wrapped.width() <= 60.0);
+}
+
+#[test]
+fn shapes_cover_what_they_describe() {
+    use femtovg::shapes;
+    use std::f32::consts::PI;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(200, 200, 1.0);
+
+    let mut inside = |path: &Path, x: f32, y: f32| canvas.contains_point(&mut path.clone(), x, y, FillRule::NonZero);
+
+    let arrow = shapes::arrow(10.0, 100.0, 190.0, 100.0, 4.0, 20.0, 30.0);
+    assert!(inside(&arrow, 50.0, 101.0));
+    assert!(inside(&arrow, 165.0, 106.0));
+    assert!(!inside(&arrow, 50.0, 107.0));
+
+    // The quarter below and right of the center
+    let pie = shapes::pie(100.0, 100.0, 50.0, 0.0, PI / 2.0);
+    assert!(inside(&pie, 120.0, 120.0));
+    assert!(!inside(&pie, 80.0, 120.0));
+
+    let segment = shapes::arc_segment(100.0, 100.0, 30.0, 50.0, 0.0, PI / 2.0);
+    assert!(inside(&segment, 128.0, 128.0));
+    assert!(!inside(&segment, 110.0, 110.0));
+
+    assert!(inside(&shapes::polygon(100.0, 100.0, 50.0, 6), 100.0, 52.0));
+    assert!(!inside(&shapes::polygon(100.0, 100.0, 50.0, 4), 140.0, 60.0));
+    assert_eq!(shapes::polygon(100.0, 100.0, 50.0, 2).verbs().count(), 0);
+
+    // Tips on the outer circle, notches between them on the inner one
+    let star = shapes::star(100.0, 100.0, 20.0, 50.0, 5);
+    assert!(inside(&star, 100.0, 55.0));
+    assert!(!inside(&star, 100.0, 125.0));
+    assert_eq!(star.verbs().count(), 11);
+
+    let square = shapes::rounded_polygon(&[(50.0, 50.0), (150.0, 50.0), (150.0, 150.0), (50.0, 150.0)], 20.0);
+    assert!(inside(&square, 100.0, 51.0));
+    assert!(!inside(&square, 52.0, 52.0));
+
+    let bubble = shapes::speech_bubble(50.0, 50.0, 100.0, 60.0, 10.0, 80.0, 140.0);
+    assert!(inside(&bubble, 80.0, 135.0));
+    assert!(!inside(&bubble, 140.0, 135.0));
+    assert!(!inside(&bubble, 51.0, 51.0));
+
+    // Zero sized bubbles lose their tail on the edges without length, instead of NaN coordinates
+    for (w, h) in [(0.0, 20.0), (20.0, 0.0), (0.0, 0.0)] {
+        for (tail_x, tail_y) in [(0.0, -10.0), (30.0, 5.0), (5.0, 30.0), (-10.0, 5.0)] {
+            let bubble = shapes::speech_bubble(0.0, 0.0, w, h, 5.0, tail_x, tail_y);
+            assert!(bubble.verbs().count() > 0);
+        }
+    }
+}
+
+#[test]
+fn colors_parse_from_css() {
+    const ACCENT: Color = Color::rgba(255, 128, 0, 255);
+
+    assert_eq!("#ff8000".parse::<Color>().unwrap(), ACCENT);
+    assert_eq!(" #FF8000FF ".parse::<Color>().unwrap(), ACCENT);
+    assert_eq!("#fff".parse::<Color>().unwrap(), Color::WHITE);
+    assert_eq!("#0000".parse::<Color>().unwrap(), Color::TRANSPARENT);
+    assert_eq!("rgb(255,128,0)".parse::<Color>().unwrap(), ACCENT);
+    assert_eq!(
+        "rgb(100% 0% 0% / 50%)".parse::<Color>().unwrap(),
+        Color::rgbaf(1.0, 0.0, 0.0, 0.5)
+    );
+    assert_eq!(
+        "hsla(0, 100%, 50%, 1)".parse::<Color>().unwrap(),
+        Color::hsla(0.0, 1.0, 0.5, 1.0)
+    );
+    assert_eq!("Orange".parse::<Color>().unwrap(), Color::ORANGE);
+
+    for invalid in &[
+        "",
+        "#",
+        "#12",
+        "#ff00zz",
+        "rgb(1, 2)",
+        "rgb(1, 2, 3, 4, 5)",
+        "hsl(x, 1%, 1%)",
+        "blurple",
+    ] {
+        assert!(
+            matches!(invalid.parse::<Color>(), Err(femtovg::ErrorKind::InvalidColor(_))),
+            "{:?} parsed",
+            invalid
+        );
+    }
+
+    // The lenient constructor keeps falling back to black
+    assert_eq!(Color::hex("ff00zz"), Color::BLACK);
+    assert_eq!(Color::hex("f80"), Color::rgb(255, 136, 0));
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn draw_target_draws_without_the_renderer_type() {
+    use femtovg::DrawTarget;
+
+    fn label(target: &mut dyn DrawTarget, text: &str, paint: Paint) -> f32 {
+        target.save();
+        target.translate(10.0, 0.0);
+
+        let mut path = Path::new();
+        path.rect(0.0, 0.0, 50.0, 20.0);
+        target.fill_path(&mut path, paint);
+
+        let width = target.fill_text(0.0, 10.0, text, paint).map_or(0.0, |m| m.width());
+
+        assert!(target.contains_point(&mut path, 15.0, 5.0, FillRule::NonZero));
+        target.restore();
+
+        width
+    }
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let font = canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+
+    let expected = canvas.measure_text(0.0, 0.0, "Label", paint).unwrap().width();
+    let targets: Vec<&mut dyn DrawTarget> = vec![&mut canvas];
+
+    for target in targets {
+        assert_eq!(label(target, "Label", paint), expected);
+        assert_eq!(target.transform(), femtovg::Transform2D::identity());
+    }
+}
+
+#[cfg(not(feature = "text"))]
+#[test]
+fn fonts_need_the_text_feature() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let font = std::fs::read("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    assert!(matches!(
+        canvas.add_font_mem(&font),
+        Err(femtovg::ErrorKind::FontParseError)
+    ));
+    let metrics = canvas.measure_text(0.0, 0.0, "text", Paint::color(Color::black()));
+    assert!(metrics.map_or(true, |metrics| metrics.glyphs.is_empty()));
+}
+
+#[test]
+fn paint_reports_its_style() {
+    use femtovg::{
+        LineCap,
+        PaintKind,
+    };
+
+    let mut paint = Paint::color(Color::RED);
+    paint.set_line_width(3.0);
+    paint.set_line_cap(LineCap::Round);
+
+    assert_eq!(paint.kind(), PaintKind::Color(Color::RED));
+    assert_eq!(paint.solid_color(), Some(Color::RED));
+    assert_eq!(paint.image_id(), None);
+    assert!(paint.gradient_stops().is_empty());
+    assert!(paint.font_ids().is_empty());
+    assert_eq!((paint.line_width(), paint.line_cap_start()), (3.0, LineCap::Round));
+
+    let stops = [(0.0, Color::RED), (0.25, Color::LIME), (1.0, Color::BLUE)];
+    let mut gradient = Paint::linear_gradient_stops(0.0, 0.0, 10.0, 0.0, &stops);
+
+    assert_eq!(gradient.gradient_stops(), stops.to_vec());
+    assert_eq!(gradient.solid_color(), None);
+
+    // Tweaking the stops keeps the geometry
+    gradient.set_gradient_stops(&[(0.0, Color::WHITE), (1.0, Color::BLACK)]);
+
+    assert_eq!(
+        gradient.gradient_stops(),
+        vec![(0.0, Color::WHITE), (1.0, Color::BLACK)]
+    );
+    assert_eq!(
+        gradient.kind(),
+        PaintKind::LinearGradient {
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 10.0,
+            end_y: 0.0
+        }
+    );
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    let image = canvas
+        .create_image_empty(4, 4, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::empty())
+        .unwrap();
+
+    assert_eq!(
+        Paint::image(image, 0.0, 0.0, 4.0, 4.0, 0.0, 1.0).image_id(),
+        Some(image)
+    );
+}
+
+#[test]
+fn restore_to_unwinds_to_the_named_save() {
+    use femtovg::ErrorKind;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(100, 100, 1.0);
+
+    canvas.translate(10.0, 0.0);
+    canvas.save_named("popup_root");
+
+    canvas.translate(5.0, 0.0);
+    canvas.save();
+    canvas.scale(2.0, 2.0);
+    canvas.save();
+    canvas.rotate(1.0);
+
+    assert!(canvas.restore_to("popup_root").is_ok());
+    assert_eq!(canvas.transform()[4], 10.0);
+
+    // The name is gone with its state, and the saves after it don't need matching restores
+    assert!(matches!(
+        canvas.restore_to("popup_root"),
+        Err(ErrorKind::UnbalancedSaveRestore)
+    ));
+    assert_eq!(canvas.end_frame().unmatched_saves, 0);
+
+    // Plain restores past a named save forget it too
+    canvas.begin_frame(100, 100, 1.0);
+    canvas.save_named("popup_root");
+    canvas.restore();
+
+    assert!(canvas.restore_to("popup_root").is_err());
+}
+
+#[test]
+fn animated_values_ease_towards_their_target() {
+    use femtovg::animation::{
+        Animated,
+        Easing,
+        Lerp,
+    };
+    use std::time::Duration;
+
+    assert_eq!(Easing::EaseInOut.apply(0.0), 0.0);
+    assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-3);
+    assert!(Easing::EaseIn.apply(0.25) < 0.25);
+    assert!(Easing::EaseOut.apply(0.25) > 0.25);
+    assert_eq!(Easing::Ease.apply(1.0), 1.0);
+
+    let mut width = Animated::new(10.0f32);
+    width.animate_to(20.0, Duration::from_millis(100), Easing::Linear);
+    width.advance(Duration::from_millis(25));
+
+    assert!(width.is_running());
+    assert!((width.value() - 12.5).abs() < 1e-3);
+
+    // Retargeting starts from where the value is
+    width.animate_to(0.0, Duration::from_millis(100), Easing::Linear);
+    width.advance(Duration::from_millis(50));
+    assert!((width.value() - 6.25).abs() < 1e-3);
+
+    width.advance(Duration::from_secs(1));
+    assert!(!width.is_running());
+    assert_eq!(width.value(), 0.0);
+
+    let gray = Color::BLACK.lerp(&Color::WHITE, 0.5);
+    assert!((gray.r - 0.5).abs() < 1e-6 && gray.a == 1.0);
+
+    let moved =
+        femtovg::Transform2D::new_translation(0.0, 0.0).lerp(&femtovg::Transform2D::new_translation(10.0, 4.0), 0.5);
+    assert_eq!((moved[4], moved[5]), (5.0, 2.0));
+
+    // Paints blend what they can and switch the rest halfway
+    let mut thin = Paint::color(Color::BLACK);
+    thin.set_line_width(1.0);
+    thin.set_line_cap(femtovg::LineCap::Butt);
+    let mut thick = Paint::color(Color::WHITE);
+    thick.set_line_width(3.0);
+    thick.set_line_cap(femtovg::LineCap::Round);
+
+    let paint = thin.lerp(&thick, 0.25);
+    assert_eq!(paint.line_width(), 1.5);
+    assert_eq!(paint.line_cap_start(), femtovg::LineCap::Butt);
+    assert!((paint.solid_color().unwrap().r - 0.25).abs() < 1e-6);
+
+    let a = Paint::linear_gradient(0.0, 0.0, 10.0, 0.0, Color::BLACK, Color::WHITE);
+    let b = Paint::linear_gradient(0.0, 0.0, 20.0, 0.0, Color::WHITE, Color::BLACK);
+    let mid = a.lerp(&b, 0.5);
+
+    assert_eq!(
+        mid.kind(),
+        femtovg::PaintKind::LinearGradient {
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 15.0,
+            end_y: 0.0
+        }
+    );
+    assert!((mid.gradient_stops()[0].1.r - 0.5).abs() < 1e-6);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn icons_are_centered_in_their_box() {
+    use femtovg::ErrorKind;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let icons = canvas.add_font("examples/assets/entypo.ttf").unwrap();
+
+    let mut paint = Paint::color(Color::BLACK);
+    paint.set_font(&[icons]);
+
+    let metrics = canvas.fill_icon(10.0, 10.0, 40.0, '\u{E740}', paint).unwrap();
+
+    assert_eq!(metrics.glyphs.len(), 1);
+    assert!(metrics.width() > 0.0 && metrics.width() <= 40.0);
+    assert!((metrics.x + metrics.width() / 2.0 - 30.0).abs() < 0.5);
+    assert!((metrics.y + metrics.height() / 2.0 - 30.0).abs() < 0.5);
+
+    assert!(matches!(
+        canvas.fill_icon(10.0, 10.0, 40.0, '\u{10FFFD}', paint),
+        Err(ErrorKind::NoFontFound)
+    ));
+}
+
+#[test]
+fn clip_bounds_are_in_local_coordinates() {
+    use femtovg::Rect;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(200, 100, 1.0);
+
+    assert_eq!(canvas.current_clip_bounds(), Some(Rect::new(0.0, 0.0, 200.0, 100.0)));
+
+    canvas.translate(50.0, 10.0);
+    canvas.scale(2.0, 2.0);
+
+    assert_eq!(canvas.current_clip_bounds(), Some(Rect::new(-25.0, -5.0, 100.0, 50.0)));
+
+    canvas.scissor(0.0, 0.0, 20.0, 10.0);
+    assert_eq!(canvas.current_clip_bounds(), Some(Rect::new(0.0, 0.0, 20.0, 10.0)));
+
+    // A scissor outside of the render target leaves nothing to draw
+    canvas.reset_transform();
+    canvas.scissor(300.0, 0.0, 10.0, 10.0);
+    assert_eq!(canvas.current_clip_bounds(), None);
+
+    canvas.end_frame();
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn paragraphs_fill_as_one_block() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(400, 400, 1.0);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    let mut paint = Paint::linear_gradient(0.0, 0.0, 0.0, 100.0, Color::RED, Color::BLUE);
+    paint.set_font(&[font]);
+    paint.set_font_size(20.0);
+
+    let text = "A gradient that runs across every line of the block";
+    let metrics = canvas.fill_paragraph(10.0, 20.0, 120.0, text, paint).unwrap();
+
+    let lines = {
+        let mut ys: Vec<i32> = metrics.glyphs.iter().map(|glyph| glyph.y.round() as i32).collect();
+        ys.sort_unstable();
+        ys.dedup_by(|a, b| (*a - *b).abs() < 10);
+        ys.len()
+    };
+
+    assert!(lines > 1);
+    assert!(metrics.width() <= 120.0);
+
+    // Text large enough to be drawn as paths has its outlines moved into place instead of being
+    // drawn under a transform of their own
+    paint.set_font_size(120.0);
+    canvas.fill_text(10.0, 200.0, "Ag", paint).unwrap();
+    assert_eq!(canvas.transform(), femtovg::Transform2D::identity());
+
+    let stats = canvas.end_frame();
+    assert!(stats.vertices > 0);
+}
+
+#[test]
+fn bitmaps_pack_into_the_glyph_atlas() {
+    use femtovg::ErrorKind;
+    use imgref::Img;
+    use rgb::RGBA8;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    assert!(canvas.glyph_atlas_pages().is_empty());
+
+    let pixels = vec![RGBA8::new(255, 0, 0, 255); 16];
+    let region = canvas.pack_into_glyph_atlas(Img::new(pixels.as_slice(), 4, 4)).unwrap();
+
+    let pages = canvas.glyph_atlas_pages();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].image_id, region.image_id);
+    assert!(pages[0].occupancy > 0.0);
+    assert_eq!((region.width, region.height), (4, 4));
+    assert!(canvas.is_atlas_region_valid(&region));
+
+    // A second bitmap goes next to the first one
+    let other = canvas.pack_into_glyph_atlas(Img::new(pixels.as_slice(), 4, 4)).unwrap();
+    assert_eq!(other.image_id, region.image_id);
+    assert_ne!((other.x, other.y), (region.x, region.y));
+
+    let mut path = Path::new();
+    path.rect(10.0, 10.0, 4.0, 4.0);
+    canvas.fill_path(&mut path, region.paint(10.0, 10.0));
+
+    let gray = vec![rgb::alt::Gray(0u8); 16];
+    assert!(matches!(
+        canvas.pack_into_glyph_atlas(Img::new(gray.as_slice(), 4, 4)),
+        Err(ErrorKind::UnsuportedImageFromat)
+    ));
+
+    // The atlases are released by the next flush
+    canvas.clear_glyph_atlases();
+    canvas.flush();
+    assert!(!canvas.is_atlas_region_valid(&region));
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn text_lines_report_their_width_and_break() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(400, 400, 2.0);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    let mut paint = Paint::default();
+    paint.set_font(&[font]);
+    paint.set_font_size(16.0);
+
+    let text = "Multiple Lines Broken\nand a newline";
+    let lines = canvas.break_text_lines(60.0, text, paint).unwrap();
+
+    let texts: Vec<&str> = lines.iter().map(|line| &text[line.range.clone()]).collect();
+    assert_eq!(texts, vec!["Multiple ", "Lines ", "Broken", "and a ", "newline"]);
+
+    let breaks: Vec<LineBreak> = lines.iter().map(|line| line.line_break).collect();
+    assert_eq!(
+        breaks,
+        vec![
+            LineBreak::Soft,
+            LineBreak::Soft,
+            LineBreak::Newline,
+            LineBreak::Soft,
+            LineBreak::End
+        ]
+    );
+
+    // Widths are in canvas units and leave out the trailing space
+    let width = canvas.measure_text(0.0, 0.0, "Multiple", paint).unwrap().width();
+    assert!((lines[0].width - width).abs() < 0.01);
+    assert!(lines[0].whitespace_width > 0.0);
+    assert_eq!(lines[2].whitespace_width, 0.0);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn underlines_follow_the_shaped_text() {
+    use femtovg::UnderlineStyle;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(400, 100, 1.0);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+    paint.set_font_size(20.0);
+
+    let text = "Teh quick brwn fox";
+    let metrics = canvas.fill_text(10.0, 50.0, text, paint).unwrap();
+    let text_vertices = canvas.end_frame().vertices;
+
+    let mut underline = Paint::color(Color::rgb(255, 0, 0));
+    underline.set_font(&[font]);
+    underline.set_font_size(20.0);
+    underline.set_line_width(1.5);
+
+    let misspelled = [0..3, 10..14];
+
+    for style in [UnderlineStyle::Solid, UnderlineStyle::Dotted, UnderlineStyle::Squiggly] {
+        canvas.begin_frame(400, 100, 1.0);
+        canvas
+            .draw_underlines(50.0, &metrics, &misspelled, style, underline)
+            .unwrap();
+        assert!(canvas.end_frame().vertices > 0);
+    }
+
+    // Ranges without glyphs draw nothing
+    canvas.begin_frame(400, 100, 1.0);
+    canvas
+        .draw_underlines(
+            50.0,
+            &metrics,
+            &[text.len()..text.len()],
+            UnderlineStyle::Squiggly,
+            underline,
+        )
+        .unwrap();
+    assert_eq!(canvas.end_frame().vertices, 0);
+    assert!(text_vertices > 0);
+}
+
+#[test]
+fn path_area_and_centroid_follow_the_fill_rule() {
+    let mut square = Path::new();
+    square.rect(10.0, 20.0, 10.0, 10.0);
+    assert!((square.area(FillRule::NonZero) - 100.0).abs() < 1e-3);
+
+    let centroid = square.centroid(FillRule::NonZero).unwrap();
+    assert!((centroid.x - 15.0).abs() < 1e-3 && (centroid.y - 25.0).abs() < 1e-3);
+
+    // Two overlapping squares are counted once by NonZero, EvenOdd cuts out the overlap
+    let mut overlap = Path::new();
+    overlap.rect(0.0, 0.0, 10.0, 10.0);
+    overlap.rect(5.0, 0.0, 10.0, 10.0);
+    assert!((overlap.area(FillRule::NonZero) - 150.0).abs() < 1e-3);
+    assert!((overlap.area(FillRule::EvenOdd) - 100.0).abs() < 1e-3);
+
+    // Crossing edges of a bow tie
+    let mut bow_tie = Path::new();
+    bow_tie.move_to(0.0, 0.0);
+    bow_tie.line_to(10.0, 10.0);
+    bow_tie.line_to(10.0, 0.0);
+    bow_tie.line_to(0.0, 10.0);
+    bow_tie.close();
+    assert!((bow_tie.area(FillRule::NonZero) - 50.0).abs() < 1e-3);
+
+    // A ring, the hole is subtracted and the centroid stays in the middle
+    let mut ring = Path::new();
+    ring.circle(50.0, 50.0, 20.0);
+    ring.circle(50.0, 50.0, 10.0);
+    ring.solidity(Solidity::Hole);
+
+    let expected = std::f32::consts::PI * (20.0 * 20.0 - 10.0 * 10.0);
+    assert!((ring.area(FillRule::NonZero) - expected).abs() / expected < 0.01);
+
+    let centroid = ring.centroid(FillRule::EvenOdd).unwrap();
+    assert!((centroid.x - 50.0).abs() < 0.01 && (centroid.y - 50.0).abs() < 0.01);
+
+    assert_eq!(Path::new().area(FillRule::NonZero), 0.0);
+    assert_eq!(Path::new().centroid(FillRule::NonZero), None);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn stroked_glyphs_follow_the_line_join() {
+    use femtovg::LineJoin;
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(400, 100, 1.0);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    let mut paint = Paint::color(Color::black());
+    paint.set_font(&[font]);
+    paint.set_font_size(30.0);
+    paint.set_line_width(3.0);
+
+    let occupancy = |canvas: &Canvas<Void>| {
+        canvas
+            .glyph_atlas_pages()
+            .iter()
+            .map(|page| page.occupancy)
+            .sum::<f32>()
+    };
+
+    paint.set_line_join(LineJoin::Bevel);
+    canvas.stroke_text(10.0, 50.0, "VAW", paint).unwrap();
+    let bevel = occupancy(&canvas);
+
+    // Miters need room for the spikes on sharp corners and are rendered separately
+    paint.set_line_join(LineJoin::Miter);
+    paint.set_miter_limit(4.0);
+    canvas.stroke_text(10.0, 50.0, "VAW", paint).unwrap();
+    let miter = occupancy(&canvas) - bevel;
+
+    assert!(bevel > 0.0);
+    assert!(miter > bevel);
+
+    canvas.end_frame();
+}
+
+#[test]
+fn effects_draw_over_their_region() {
+    use femtovg::{
+        Effect,
+        ImageFlags,
+        PixelFormat,
+        Rect,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    let region = Rect::new(0.0, 0.0, 200.0, 100.0);
+
+    let draws = |stats: femtovg::FrameStats| stats.commands + stats.merged_commands;
+
+    canvas.begin_frame(200, 100, 1.0);
+
+    let vignette = Effect::Vignette {
+        color: Color::black(),
+        radius: 0.6,
+        feather: 0.8,
+    };
+    canvas.apply_effect(region, vignette).unwrap();
+    assert_eq!(canvas.transform(), femtovg::Transform2D::identity());
+
+    let vignette_draws = draws(canvas.end_frame());
+
+    let image = canvas
+        .create_image_empty(200, 100, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    let blur = Effect::RadialBlur {
+        image,
+        center: (100.0, 50.0).into(),
+        strength: 0.1,
+        samples: 8,
+    };
+
+    // The blur draws a copy of the image for each sample
+    canvas.begin_frame(200, 100, 1.0);
+    canvas.apply_effect(region, blur).unwrap();
+    assert_eq!(draws(canvas.end_frame()), vignette_draws + 7);
+
+    canvas.delete_image(image);
+    assert!(canvas.apply_effect(region, blur).is_err());
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn rotated_text_is_hit_tested_in_canvas_coordinates() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.begin_frame(400, 400, 1.0);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    let mut paint = Paint::default();
+    paint.set_font(&[font]);
+    paint.set_font_size(20.0);
+
+    // A label running down from 100, 100 like a rotated chart axis label
+    canvas.translate(100.0, 100.0);
+    canvas.rotate(std::f32::consts::FRAC_PI_2);
+
+    let label = canvas.measure_text_transformed(0.0, 0.0, "Revenue", paint).unwrap();
+    let width = label.metrics.width();
+
+    assert!((label.bounds.h - width).abs() < 0.01);
+    assert!(label.bounds.w < width);
+
+    // The glyphs rise above the baseline, which turns to the right
+    assert!(label.contains_point(105.0, 100.0 + width / 2.0));
+    assert!(!label.contains_point(95.0, 100.0 + width / 2.0));
+    assert!(!label.contains_point(100.0 + width / 2.0, 105.0));
+
+    canvas.end_frame();
+}
+
+#[test]
+fn image_flags_change_without_recreating_the_image() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let image = canvas
+        .create_image_empty(64, 64, PixelFormat::Rgba8, ImageFlags::GENERATE_MIPMAPS)
+        .unwrap();
+
+    canvas
+        .set_image_flags(
+            image,
+            ImageFlags::REPEAT_X | ImageFlags::REPEAT_Y | ImageFlags::NEAREST_MAG,
+        )
+        .unwrap();
+
+    // The mipmaps of the image stay
+    assert_eq!(
+        canvas.image_info(image).unwrap().flags(),
+        ImageFlags::GENERATE_MIPMAPS | ImageFlags::REPEAT_X | ImageFlags::REPEAT_Y | ImageFlags::NEAREST_MAG
+    );
+    assert!(canvas.is_image_valid(image));
+
+    canvas.delete_image(image);
+    assert!(canvas.set_image_flags(image, ImageFlags::empty()).is_err());
+}
+
+#[test]
+fn multisampled_targets_stay_multisampled() {
+    use femtovg::{
+        ImageFlags,
+        PixelFormat,
+        RenderTarget,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let target = canvas
+        .create_image_empty(64, 64, PixelFormat::Rgba8, ImageFlags::MULTISAMPLE)
+        .unwrap();
+
+    canvas.set_render_target(RenderTarget::Image(target));
+    let mut path = Path::new();
+    path.circle(32.0, 32.0, 20.0);
+    canvas.fill_path(&mut path, Paint::color(Color::white()));
+    canvas.set_render_target(RenderTarget::Screen);
+    canvas.flush();
+
+    // The render target is created once, turning multisampling on or off would need a new one
+    canvas.set_image_flags(target, ImageFlags::NEAREST).unwrap();
+    assert_eq!(
+        canvas.image_info(target).unwrap().flags(),
+        ImageFlags::MULTISAMPLE | ImageFlags::NEAREST
+    );
+}
+
+#[test]
+fn textured_strokes_follow_the_joins() {
+    use femtovg::{
+        ImageFlags,
+        LineJoin,
+        PixelFormat,
+    };
+
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let image = canvas
+        .create_image_empty(32, 8, PixelFormat::Rgba8, ImageFlags::REPEAT_X)
+        .unwrap();
+
+    let mut path = Path::new();
+    path.move_to(10.0, 10.0);
+    path.line_to(60.0, 10.0);
+    path.line_to(60.0, 60.0);
+
+    let mut paint = Paint::color(Color::white());
+    paint.set_line_width(8.0);
+
+    let mut vertices = |line_join| {
+        paint.set_line_join(line_join);
+
+        canvas.begin_frame(100, 100, 1.0);
+        canvas.stroke_path_textured(&path, image, 32.0, paint).unwrap();
+        canvas.end_frame().vertices
+    };
+
+    // Two quads, and the triangle that fills the bevel
+    assert_eq!(vertices(LineJoin::Miter), 12);
+    assert_eq!(vertices(LineJoin::Bevel), 15);
+
+    canvas.delete_image(image);
+    assert!(canvas.stroke_path_textured(&path, image, 32.0, paint).is_err());
+}
+
+#[test]
+fn nested_scissors_keep_the_rounded_corners() {
+    let canvas = Canvas::new(Void).unwrap();
+    let mut recorder = canvas.recorder();
+
+    let mut path = Path::new();
+    path.rect(0.0, 0.0, 100.0, 100.0);
+
+    // A rounded panel with a child that clips to its top half
+    recorder.intersect_scissor_rounded(0.0, 0.0, 100.0, 100.0, 8.0);
+    recorder.intersect_scissor(0.0, 0.0, 100.0, 50.0);
+    recorder.fill_path(&path, Paint::color(Color::black()));
+
+    // A clip radius above zero cuts the corner pixels away
+    let svg = recorder.to_svg(100.0, 100.0);
+    assert!(svg.contains(r#"width="100" height="50" rx="8""#));
+
+    // A new scissor starts out square again
+    recorder.reset_scissor();
+    recorder.intersect_scissor_rounded(0.0, 0.0, 100.0, 100.0, 8.0);
+    recorder.scissor(0.0, 0.0, 100.0, 50.0);
+    recorder.fill_path(&path, Paint::color(Color::black()));
+
+    let svg = recorder.to_svg(100.0, 100.0);
+    assert!(svg.contains(r#"width="100" height="50" rx="0""#));
+}